@@ -0,0 +1,534 @@
+//! Content-addressed cache of decompressed (and downloaded) images.
+//!
+//! Each entry is keyed by the SHA-256 of its source (the `.xz` file or download as
+//! received) and stores the raw image as `<source_sha256>.img` next to a
+//! `<source_sha256>.json` record with the raw image's SHA-256, so repeat flashes of
+//! the same image skip decompression and the verify pre-pass. The origin (canonical
+//! path or URL) and its stamp (size and mtime, or HTTP validator) let a repeat flash
+//! find its entry without hashing the source again.
+//!
+//! The cache is bounded by a total size; the least recently used entries are evicted
+//! after each insert.
+
+use crate::progress::{
+    check_cancel, emit_progress, OperationControl, OperationPhase, OperationProgress,
+};
+use crate::range::parse_byte_size;
+use anyhow::{Context, Result};
+use log::{debug, info, warn};
+use lzma::reader::LzmaReader;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Size limit when `LITHO_CACHE_MAX` is not set.
+pub const DEFAULT_MAX_BYTES: u64 = 16 * 1024 * 1024 * 1024;
+
+/// Environment variable overriding the size limit (e.g. `64G`; `0` disables the cache).
+pub const MAX_BYTES_ENV: &str = "LITHO_CACHE_MAX";
+
+const COPY_BUFFER: usize = 4 * 1024 * 1024;
+
+/// One cached image.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheEntry {
+    /// SHA-256 of the source as read (compressed file or download).
+    pub source_sha256: String,
+    /// SHA-256 of the raw image.
+    pub image_sha256: String,
+    /// Raw image size in bytes.
+    pub size: u64,
+    /// Canonical path or URL the entry was last used for.
+    pub origin: String,
+    /// Size and mtime of a file origin, or the HTTP validator of a URL origin.
+    pub origin_stamp: String,
+    /// Last use, in seconds since the Unix epoch.
+    pub last_used: u64,
+}
+
+/// Image cache rooted at one directory.
+#[derive(Debug, Clone)]
+pub struct ImageCache {
+    dir: PathBuf,
+    max_bytes: u64,
+}
+
+impl ImageCache {
+    pub fn new(dir: impl Into<PathBuf>, max_bytes: u64) -> Self {
+        Self {
+            dir: dir.into(),
+            max_bytes,
+        }
+    }
+
+    /// `~/.cache/litho/images` (or under `$XDG_CACHE_HOME`).
+    pub fn default_dir() -> Result<PathBuf> {
+        let base = std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+            .context("Neither XDG_CACHE_HOME nor HOME is set")?;
+        Ok(base.join("litho").join("images"))
+    }
+
+    /// The default cache, sized by `LITHO_CACHE_MAX`. `None` when the limit is 0.
+    pub fn open_default() -> Result<Option<Self>> {
+        let max_bytes = match std::env::var(MAX_BYTES_ENV) {
+            Ok(value) => parse_byte_size(&value)
+                .map_err(|e| anyhow::anyhow!("Invalid {}: {}", MAX_BYTES_ENV, e))?,
+            Err(_) => DEFAULT_MAX_BYTES,
+        };
+        if max_bytes == 0 {
+            return Ok(None);
+        }
+        Ok(Some(Self::new(Self::default_dir()?, max_bytes)))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn max_bytes(&self) -> u64 {
+        self.max_bytes
+    }
+
+    /// Path of the raw image for `entry`.
+    pub fn image_path(&self, entry: &CacheEntry) -> PathBuf {
+        self.dir.join(format!("{}.img", entry.source_sha256))
+    }
+
+    fn record_path(&self, source_sha256: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", source_sha256))
+    }
+
+    /// Entries whose image is present, most recently used first.
+    pub fn entries(&self) -> Result<Vec<CacheEntry>> {
+        let listing = match fs::read_dir(&self.dir) {
+            Ok(listing) => listing,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).context(format!("Failed to read {}", self.dir.display())),
+        };
+        let mut entries = Vec::new();
+        for item in listing {
+            let path = item?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            let entry = match fs::read(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|bytes| Ok(serde_json::from_slice::<CacheEntry>(&bytes)?))
+            {
+                Ok(entry) => entry,
+                Err(e) => {
+                    warn!(
+                        "Ignoring unreadable cache record {}: {:#}",
+                        path.display(),
+                        e
+                    );
+                    continue;
+                }
+            };
+            if self.image_path(&entry).is_file() {
+                entries.push(entry);
+            }
+        }
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.last_used));
+        Ok(entries)
+    }
+
+    /// Total size of the cached images.
+    pub fn total_size(&self) -> Result<u64> {
+        Ok(self.entries()?.iter().map(|entry| entry.size).sum())
+    }
+
+    /// The entry last used for `origin` with the same stamp, marked as used.
+    pub fn lookup_origin(&self, origin: &str, stamp: &str) -> Result<Option<CacheEntry>> {
+        let found = self
+            .entries()?
+            .into_iter()
+            .find(|entry| entry.origin == origin && entry.origin_stamp == stamp);
+        found
+            .map(|entry| self.mark_used(entry, origin, stamp))
+            .transpose()
+    }
+
+    /// The entry for a source hash, marked as used by `origin`.
+    pub fn lookup_source(
+        &self,
+        source_sha256: &str,
+        origin: &str,
+        stamp: &str,
+    ) -> Result<Option<CacheEntry>> {
+        let found = self
+            .entries()?
+            .into_iter()
+            .find(|entry| entry.source_sha256 == source_sha256);
+        found
+            .map(|entry| self.mark_used(entry, origin, stamp))
+            .transpose()
+    }
+
+    fn mark_used(&self, mut entry: CacheEntry, origin: &str, stamp: &str) -> Result<CacheEntry> {
+        entry.origin = origin.to_string();
+        entry.origin_stamp = stamp.to_string();
+        entry.last_used = now_secs();
+        self.write_record(&entry)?;
+        Ok(entry)
+    }
+
+    fn write_record(&self, entry: &CacheEntry) -> Result<()> {
+        let path = self.record_path(&entry.source_sha256);
+        let partial = path.with_extension("json.part");
+        fs::write(&partial, serde_json::to_vec_pretty(entry)?)
+            .context(format!("Failed to write {}", partial.display()))?;
+        fs::rename(&partial, &path).context(format!("Failed to write {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Read `source` (xz-decompressing it when `compressed`) into a new entry, hashing
+    /// the source and the raw image on the way, then evict down to the size limit.
+    /// A cancelled or failed fill leaves nothing behind.
+    #[allow(clippy::too_many_arguments)]
+    pub fn insert<R, F>(
+        &self,
+        source: R,
+        compressed: bool,
+        origin: &str,
+        stamp: &str,
+        silent: bool,
+        progress: &mut Option<F>,
+        control: Option<&OperationControl>,
+    ) -> Result<CacheEntry>
+    where
+        R: Read,
+        F: FnMut(OperationProgress),
+    {
+        fs::create_dir_all(&self.dir).context(format!(
+            "Failed to create cache directory {}",
+            self.dir.display()
+        ))?;
+        let partial = self.dir.join(format!(
+            "{}-{}.part",
+            std::process::id(),
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_nanos())
+                .unwrap_or(0)
+        ));
+
+        let result = self.fill(&partial, source, compressed, silent, progress, control);
+        let (source_sha256, image_sha256, size) = match result {
+            Ok(filled) => filled,
+            Err(e) => {
+                if let Err(remove_error) = fs::remove_file(&partial) {
+                    debug!("Failed to remove {}: {}", partial.display(), remove_error);
+                }
+                return Err(e);
+            }
+        };
+
+        let entry = CacheEntry {
+            source_sha256,
+            image_sha256,
+            size,
+            origin: origin.to_string(),
+            origin_stamp: stamp.to_string(),
+            last_used: now_secs(),
+        };
+        fs::rename(&partial, self.image_path(&entry))
+            .context(format!("Failed to store {}", partial.display()))?;
+        self.write_record(&entry)?;
+        if !silent {
+            info!(
+                "Cached {} as {} ({} bytes)",
+                origin,
+                self.image_path(&entry).display(),
+                size
+            );
+        }
+        self.evict(&entry.source_sha256)?;
+        Ok(entry)
+    }
+
+    fn fill<R, F>(
+        &self,
+        partial: &Path,
+        source: R,
+        compressed: bool,
+        silent: bool,
+        progress: &mut Option<F>,
+        control: Option<&OperationControl>,
+    ) -> Result<(String, String, u64)>
+    where
+        R: Read,
+        F: FnMut(OperationProgress),
+    {
+        let mut source = HashingReader::new(BufReader::new(source));
+        let mut output = BufWriter::new(
+            File::create(partial).context(format!("Failed to create {}", partial.display()))?,
+        );
+        let mut image_hasher = Sha256::new();
+        let mut buffer = vec![0u8; COPY_BUFFER];
+        let mut size: u64 = 0;
+        {
+            let mut raw: Box<dyn Read + '_> = if compressed {
+                Box::new(
+                    LzmaReader::new_decompressor(&mut source)
+                        .context("Failed to create LZMA decompressor")?,
+                )
+            } else {
+                Box::new(&mut source)
+            };
+            loop {
+                check_cancel(control)?;
+                let read = raw
+                    .read(&mut buffer)
+                    .context("Failed to read image source")?;
+                if read == 0 {
+                    break;
+                }
+                output
+                    .write_all(&buffer[..read])
+                    .context("Failed to write cached image")?;
+                image_hasher.update(&buffer[..read]);
+                size += read as u64;
+                emit_progress(
+                    silent,
+                    progress,
+                    OperationProgress::new(OperationPhase::Decompressing)
+                        .with_bytes(size, None)
+                        .with_message(format!("{} bytes cached", size)),
+                );
+            }
+        }
+        // Hash any bytes the decompressor left unread (e.g. xz padding).
+        std::io::copy(&mut source, &mut std::io::sink()).context("Failed to read image source")?;
+        output
+            .into_inner()
+            .map_err(|e| e.into_error())
+            .and_then(|file| file.sync_all())
+            .context("Failed to flush cached image")?;
+
+        Ok((
+            format!("{:x}", source.hasher.finalize()),
+            format!("{:x}", image_hasher.finalize()),
+            size,
+        ))
+    }
+
+    /// Remove least recently used entries (never `keep`) until the total fits the limit.
+    fn evict(&self, keep: &str) -> Result<()> {
+        let mut entries = self.entries()?;
+        let mut total: u64 = entries.iter().map(|entry| entry.size).sum();
+        while total > self.max_bytes {
+            let Some(index) = entries
+                .iter()
+                .rposition(|entry| entry.source_sha256 != keep)
+            else {
+                break;
+            };
+            let entry = entries.remove(index);
+            info!(
+                "Evicting cached image {} ({} bytes, last used for {})",
+                entry.source_sha256, entry.size, entry.origin
+            );
+            self.remove(&entry)?;
+            total -= entry.size;
+        }
+        Ok(())
+    }
+
+    fn remove(&self, entry: &CacheEntry) -> Result<()> {
+        for path in [
+            self.record_path(&entry.source_sha256),
+            self.image_path(entry),
+        ] {
+            match fs::remove_file(&path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e).context(format!("Failed to remove {}", path.display())),
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove every entry and leftover partial file. Returns the entries removed and
+    /// the bytes freed.
+    pub fn clear(&self) -> Result<(usize, u64)> {
+        let entries = self.entries()?;
+        for entry in &entries {
+            self.remove(entry)?;
+        }
+        if let Ok(listing) = fs::read_dir(&self.dir) {
+            for item in listing.flatten() {
+                let path = item.path();
+                if path.extension().and_then(|ext| ext.to_str()) == Some("part") {
+                    let _ = fs::remove_file(&path);
+                }
+            }
+        }
+        Ok((entries.len(), entries.iter().map(|entry| entry.size).sum()))
+    }
+}
+
+/// Stamp identifying an unchanged local file: size and modification time.
+pub fn file_stamp(path: &Path) -> Result<String> {
+    let metadata =
+        fs::metadata(path).context(format!("Image file not found: {}", path.display()))?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|since| format!("{}.{:09}", since.as_secs(), since.subsec_nanos()))
+        .unwrap_or_default();
+    Ok(format!("{}:{}", metadata.len(), modified))
+}
+
+/// SHA-256 of a file, checking for cancel between blocks.
+pub(crate) fn sha256_file(path: &Path, control: Option<&OperationControl>) -> Result<String> {
+    let mut file = File::open(path).context(format!("Failed to open {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; COPY_BUFFER];
+    loop {
+        check_cancel(control)?;
+        let read = file
+            .read(&mut buffer)
+            .context(format!("Failed to read {}", path.display()))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+struct HashingReader<R> {
+    inner: R,
+    hasher: Sha256,
+}
+
+impl<R> HashingReader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            hasher: Sha256::new(),
+        }
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.hasher.update(&buf[..read]);
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insert(cache: &ImageCache, source: &[u8], compressed: bool, origin: &str) -> CacheEntry {
+        cache
+            .insert::<_, fn(OperationProgress)>(
+                source, compressed, origin, "stamp", true, &mut None, None,
+            )
+            .unwrap()
+    }
+
+    fn sha256(bytes: &[u8]) -> String {
+        format!("{:x}", Sha256::digest(bytes))
+    }
+
+    #[test]
+    fn insert_decompresses_and_keys_by_source_hash() {
+        let root = tempfile::tempdir().unwrap();
+        let cache = ImageCache::new(root.path(), DEFAULT_MAX_BYTES);
+        let image: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+        let compressed = lzma::compress(&image, 6).unwrap();
+
+        let entry = insert(&cache, &compressed, true, "/images/os.img.xz");
+        assert_eq!(entry.source_sha256, sha256(&compressed));
+        assert_eq!(entry.image_sha256, sha256(&image));
+        assert_eq!(entry.size, image.len() as u64);
+        assert_eq!(fs::read(cache.image_path(&entry)).unwrap(), image);
+
+        let by_origin = cache.lookup_origin("/images/os.img.xz", "stamp").unwrap();
+        assert_eq!(by_origin.unwrap().source_sha256, entry.source_sha256);
+        assert!(cache
+            .lookup_origin("/images/os.img.xz", "other")
+            .unwrap()
+            .is_none());
+
+        let by_source = cache
+            .lookup_source(&entry.source_sha256, "/copy/os.img.xz", "copy")
+            .unwrap()
+            .unwrap();
+        assert_eq!(by_source.origin, "/copy/os.img.xz");
+        assert!(cache
+            .lookup_origin("/copy/os.img.xz", "copy")
+            .unwrap()
+            .is_some());
+
+        assert_eq!(cache.clear().unwrap(), (1, image.len() as u64));
+        assert!(cache.entries().unwrap().is_empty());
+    }
+
+    #[test]
+    fn insert_evicts_least_recently_used_entries() {
+        let root = tempfile::tempdir().unwrap();
+        let cache = ImageCache::new(root.path(), 2500);
+        let old = insert(&cache, &[1u8; 1000], false, "old");
+        let recent = insert(&cache, &[2u8; 1000], false, "recent");
+        cache
+            .write_record(&CacheEntry {
+                last_used: 1,
+                ..old.clone()
+            })
+            .unwrap();
+        cache
+            .write_record(&CacheEntry {
+                last_used: 2,
+                ..recent.clone()
+            })
+            .unwrap();
+
+        let newest = insert(&cache, &[3u8; 1000], false, "newest");
+        let kept: Vec<String> = cache
+            .entries()
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.origin)
+            .collect();
+        assert_eq!(kept, vec!["newest".to_string(), "recent".to_string()]);
+        assert!(!cache.image_path(&old).exists());
+        assert_eq!(cache.total_size().unwrap(), 2000);
+        assert!(cache.image_path(&newest).exists());
+    }
+
+    #[test]
+    fn failed_insert_leaves_nothing_behind() {
+        let root = tempfile::tempdir().unwrap();
+        let cache = ImageCache::new(root.path(), DEFAULT_MAX_BYTES);
+        let result = cache.insert::<_, fn(OperationProgress)>(
+            &b"not xz"[..],
+            true,
+            "broken.img.xz",
+            "stamp",
+            true,
+            &mut None,
+            None,
+        );
+        assert!(result.is_err());
+        assert_eq!(fs::read_dir(root.path()).unwrap().count(), 0);
+    }
+}
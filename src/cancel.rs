@@ -2,9 +2,13 @@
 //!
 //! pkexec elevates litho to root and does not reliably forward stdin, so Lithographer
 //! passes `--cancel-file` and writes `cancel` into a file under the user's cache dir
-//! (root can read it; the unprivileged parent can write it).
+//! (root can read it; the unprivileged parent can write it). The same file carries
+//! `pause` / `resume`; the last control line in the file wins.
 
-use crate::progress::{is_stdin_cancel_line, STDIN_CANCEL_LINE};
+use crate::progress::{
+    is_stdin_cancel_line, parse_control_line, ControlCommand, STDIN_CANCEL_LINE,
+    STDIN_PAUSE_LINE, STDIN_RESUME_LINE,
+};
 use std::io;
 use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
@@ -45,6 +49,25 @@ pub fn request_cancel_via_file(path: &Path) -> io::Result<()> {
     std::fs::write(path, format!("{STDIN_CANCEL_LINE}\n"))
 }
 
+/// Ask the elevated litho child to pause at its next safe point.
+pub fn request_pause_via_file(path: &Path) -> io::Result<()> {
+    std::fs::write(path, format!("{STDIN_PAUSE_LINE}\n"))
+}
+
+/// Ask a paused litho child to continue.
+pub fn request_resume_via_file(path: &Path) -> io::Result<()> {
+    std::fs::write(path, format!("{STDIN_RESUME_LINE}\n"))
+}
+
+/// Last recognised control line in the file (`cancel`, `pause` or `resume`).
+pub fn control_command_in_file(path: &Path) -> Option<ControlCommand> {
+    std::fs::read_to_string(path)
+        .ok()?
+        .lines()
+        .rev()
+        .find_map(parse_control_line)
+}
+
 /// Returns true when the cancel file contains the marker line.
 pub fn cancel_requested_in_file(path: &Path) -> bool {
     std::fs::read_to_string(path)
@@ -77,4 +100,24 @@ mod tests {
         assert!(cancel_requested_in_file(&path));
         remove_cancel_file(&path);
     }
+
+    #[test]
+    fn pause_resume_file_round_trip() {
+        let path = std::env::temp_dir().join(format!(
+            "litho-pause-lib-test-{}-{}.flag",
+            std::process::id(),
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        init_cancel_file(&path).unwrap();
+        assert_eq!(control_command_in_file(&path), None);
+        request_pause_via_file(&path).unwrap();
+        assert_eq!(control_command_in_file(&path), Some(ControlCommand::Pause));
+        assert!(!cancel_requested_in_file(&path));
+        request_resume_via_file(&path).unwrap();
+        assert_eq!(control_command_in_file(&path), Some(ControlCommand::Resume));
+        remove_cancel_file(&path);
+    }
 }
\ No newline at end of file
@@ -1,35 +1,36 @@
-//! Cooperative cancel for litho CLI subprocesses (e.g. Lithographer sidecar).
+//! Cooperative cancel and pause for litho CLI subprocesses (e.g. Lithographer sidecar).
 //!
-//! - **Cancel file** — primary for pkexec (root child reads user cache file).
-//! - **Stdin** — secondary when stdin is a pipe and forwarded.
-//! - **SIGTERM / SIGINT** — fallback at the same privilege level.
+//! - **Cancel file** — primary for pkexec (root child reads user cache file);
+//!   also carries `pause` / `resume`.
+//! - **Stdin** — secondary when stdin is a pipe and forwarded (`cancel`, `pause`, `resume`);
+//!   off when stdin carries the image (`flash -f -`).
+//! - **SIGTERM / SIGINT** — cancel fallback at the same privilege level.
 
-use liblitho::cancel::cancel_requested_in_file;
-use liblitho::progress::is_stdin_cancel_line;
+use liblitho::cancel::control_command_in_file;
+use liblitho::progress::{parse_control_line, ControlCommand, OperationControl};
 use std::io::{self, BufRead, IsTerminal};
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, OnceLock};
 use std::thread;
 use std::time::Duration;
 
-static OPERATION_CANCEL: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+static OPERATION_CONTROL: OnceLock<Arc<OperationControl>> = OnceLock::new();
 static HANDLERS_INSTALLED: std::sync::Once = std::sync::Once::new();
 
 const CANCEL_FILE_POLL_MS: u64 = 50;
 
 extern "C" fn on_cancel_signal(_: libc::c_int) {
-    if let Some(flag) = OPERATION_CANCEL.get() {
-        flag.store(true, Ordering::Relaxed);
+    if let Some(control) = OPERATION_CONTROL.get() {
+        control.cancel();
     }
 }
 
-/// Reset and return the cancel flag for one flash/clone invocation.
-pub fn prepare_operation_cancel() -> Arc<AtomicBool> {
-    let flag = OPERATION_CANCEL
-        .get_or_init(|| Arc::new(AtomicBool::new(false)))
+/// Reset and return the cancel/pause token for one flash/clone invocation.
+pub fn prepare_operation_control() -> Arc<OperationControl> {
+    let control = OPERATION_CONTROL
+        .get_or_init(|| Arc::new(OperationControl::new()))
         .clone();
-    flag.store(false, Ordering::Relaxed);
+    control.reset();
     HANDLERS_INSTALLED.call_once(|| {
         #[cfg(unix)]
         unsafe {
@@ -37,31 +38,41 @@ pub fn prepare_operation_cancel() -> Arc<AtomicBool> {
             install_handler(libc::SIGINT);
         }
     });
-    flag
+    control
 }
 
-/// Start background listeners for cancel requests from GUI hosts.
-pub fn spawn_cancel_watchers(cancel: Arc<AtomicBool>, cancel_file: Option<PathBuf>) {
+/// Start background listeners for cancel/pause requests from GUI hosts. `listen_stdin`
+/// is false when stdin is the image being flashed.
+pub fn spawn_control_watchers(
+    control: Arc<OperationControl>,
+    cancel_file: Option<PathBuf>,
+    listen_stdin: bool,
+) {
     if let Some(path) = cancel_file {
-        spawn_cancel_file_watcher(cancel.clone(), path);
+        spawn_cancel_file_watcher(control.clone(), path);
+    }
+    if listen_stdin {
+        spawn_stdin_control_listener(control);
     }
-    spawn_stdin_cancel_listener(cancel);
 }
 
-fn spawn_cancel_file_watcher(cancel: Arc<AtomicBool>, path: PathBuf) {
+fn spawn_cancel_file_watcher(control: Arc<OperationControl>, path: PathBuf) {
     thread::spawn(move || {
-        while !cancel.load(Ordering::Relaxed) {
-            if cancel_requested_in_file(&path) {
-                cancel.store(true, Ordering::Relaxed);
-                break;
+        let mut last_applied = None;
+        while !control.is_cancelled() {
+            if let Some(command) = control_command_in_file(&path) {
+                if last_applied != Some(command) {
+                    command.apply(&control);
+                    last_applied = Some(command);
+                }
             }
             thread::sleep(Duration::from_millis(CANCEL_FILE_POLL_MS));
         }
     });
 }
 
-/// Listen on stdin for `cancel` when stdin is a pipe.
-fn spawn_stdin_cancel_listener(cancel: Arc<AtomicBool>) {
+/// Listen on stdin for `cancel` / `pause` / `resume` when stdin is a pipe.
+fn spawn_stdin_control_listener(control: Arc<OperationControl>) {
     if io::stdin().is_terminal() {
         return;
     }
@@ -69,9 +80,11 @@ fn spawn_stdin_cancel_listener(cancel: Arc<AtomicBool>) {
     thread::spawn(move || {
         let reader = io::BufReader::new(io::stdin());
         for line in reader.lines().map_while(Result::ok) {
-            if is_stdin_cancel_line(&line) {
-                cancel.store(true, Ordering::Relaxed);
-                break;
+            if let Some(command) = parse_control_line(&line) {
+                command.apply(&control);
+                if command == ControlCommand::Cancel {
+                    break;
+                }
             }
         }
     });
@@ -83,7 +96,7 @@ pub const CANCEL_EXIT_CODE: u8 = 3;
 #[cfg(unix)]
 unsafe fn install_handler(sig: libc::c_int) {
     let mut action: libc::sigaction = std::mem::zeroed();
-    action.sa_sigaction = on_cancel_signal as usize;
+    action.sa_sigaction = on_cancel_signal as *const () as usize;
     action.sa_flags = 0;
     libc::sigemptyset(&mut action.sa_mask);
     libc::sigaction(sig, &action, std::ptr::null_mut());
@@ -91,10 +104,8 @@ unsafe fn install_handler(sig: libc::c_int) {
 
 #[cfg(test)]
 mod tests {
-    use super::*;
     use liblitho::cancel::{cancel_requested_in_file, init_cancel_file, request_cancel_via_file};
     use liblitho::progress::STDIN_CANCEL_LINE;
-    use std::path::PathBuf;
 
     #[test]
     fn cancel_file_round_trip() {
@@ -120,4 +131,14 @@ mod tests {
         assert!(is_stdin_cancel_line("CANCEL\n"));
         assert!(!is_stdin_cancel_line("stop"));
     }
+
+    #[test]
+    fn prepare_clears_previous_pause() {
+        let control = super::prepare_operation_control();
+        control.pause();
+        control.cancel();
+        let control = super::prepare_operation_control();
+        assert!(!control.is_paused());
+        assert!(!control.is_cancelled());
+    }
 }
\ No newline at end of file
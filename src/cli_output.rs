@@ -1,5 +1,6 @@
 use liblitho::progress::{OperationPhase, OperationProgress};
-use std::io::{stdout, IsTerminal, Write};
+use std::fmt::Display;
+use std::io::{stderr, stdout, IsTerminal, Write};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
 pub enum OutputMode {
@@ -11,9 +12,19 @@ pub enum OutputMode {
 
 const BAR_WIDTH: usize = 40;
 
+/// Byte-only progress (unknown total) is logged at most once per this many bytes
+/// when the output is not a terminal.
+const BYTES_LINE_STEP: u64 = 64 * 1024 * 1024;
+
 pub struct CliOutput {
     mode: OutputMode,
     is_tty: bool,
+    /// Partitions reported by the Complete event, printed with the final summary.
+    partitions: Option<Vec<String>>,
+    /// Print to stderr because stdout carries image data (`clone -f -`).
+    to_stderr: bool,
+    /// Bytes at the last byte-only progress line, for throttling non-terminal output.
+    last_bytes_line: u64,
 }
 
 impl CliOutput {
@@ -21,65 +32,124 @@ impl CliOutput {
         Self {
             mode,
             is_tty: stdout().is_terminal(),
+            partitions: None,
+            to_stderr: false,
+            last_bytes_line: 0,
+        }
+    }
+
+    /// Send all output to stderr, leaving stdout free for image data.
+    pub fn on_stderr(mut self) -> Self {
+        self.to_stderr = true;
+        self.is_tty = stderr().is_terminal();
+        self
+    }
+
+    /// Print one line on the output stream.
+    fn say(&self, line: impl Display) {
+        if self.to_stderr {
+            eprintln!("{line}");
+        } else {
+            println!("{line}");
+        }
+    }
+
+    /// Redraw the current terminal line in place.
+    fn say_inline(&self, line: &str) {
+        if self.to_stderr {
+            eprint!("\r{line}");
+            let _ = stderr().flush();
+        } else {
+            print!("\r{line}");
+            let _ = stdout().flush();
         }
     }
 
     pub fn operation_start(&self, verb: &str, source: &str, target: &str, block_size: usize) {
         match self.mode {
             OutputMode::Terminal => {
-                println!("{verb} {source} → {target} (block_size={block_size})");
+                self.say(format_args!(
+                    "{verb} {source} → {target} (block_size={block_size})"
+                ));
             }
             OutputMode::Gui => {
-                println!(
+                self.say(format_args!(
                     "@status phase=preparing msg={}",
                     quote_gui(format!(
                         "{verb} {source} → {target} (block_size={block_size})"
                     ))
-                );
+                ));
             }
         }
     }
 
     pub fn on_progress(&mut self, progress: &OperationProgress) {
+        if progress.partitions.is_some() {
+            self.partitions = progress.partitions.clone();
+        }
         if self.mode == OutputMode::Gui {
-            print_gui_progress(progress);
+            self.say(format_gui_progress(progress));
             return;
         }
 
         if let Some(pct) = progress.percentage {
             let bar = progress_bar(pct, BAR_WIDTH);
             let phase = phase_label(progress.phase);
-            let line = format!("{phase:<14} [{bar}] {pct:5.1}%");
+            let mut line = format!("{phase:<14} [{bar}] {pct:5.1}%");
+            if let Some(errors) = progress.read_errors.filter(|n| *n > 0) {
+                line.push_str(&format!("  {errors} read errors"));
+            }
+            if let Some(downloaded) = progress.bytes_downloaded {
+                line.push_str(&format!(
+                    "  {} written, {downloaded} downloaded",
+                    progress.bytes_processed
+                ));
+            }
             if self.is_tty {
-                print!("\r{line}");
-                let _ = stdout().flush();
+                self.say_inline(&line);
             } else {
-                println!("{line}");
+                self.say(line);
             }
             return;
         }
 
         let phase = phase_label(progress.phase);
+        if progress.phase == OperationPhase::Writing {
+            let line = format!("{phase:<14} {} bytes", progress.bytes_processed);
+            if self.is_tty {
+                self.say_inline(&line);
+            } else if progress.bytes_processed >= self.last_bytes_line + BYTES_LINE_STEP {
+                self.last_bytes_line = progress.bytes_processed;
+                self.say(line);
+            }
+            return;
+        }
         if let Some(ref msg) = progress.message {
-            println!("{phase}: {msg} ({})", progress.bytes_processed);
+            self.say(format_args!(
+                "{phase}: {msg} ({})",
+                progress.bytes_processed
+            ));
         } else {
-            println!("{phase}: {} bytes", progress.bytes_processed);
+            self.say(format_args!("{phase}: {} bytes", progress.bytes_processed));
         }
     }
 
     pub fn finish_progress_line(&mut self) {
         if self.mode == OutputMode::Terminal && self.is_tty {
-            println!();
+            self.say("");
         }
     }
 
     pub fn done_ok(&self, operation: &str) {
         match self.mode {
             OutputMode::Terminal => {
-                println!("Done: {operation} completed successfully");
+                if let Some(partitions) = self.partitions.as_ref().filter(|p| !p.is_empty()) {
+                    self.say(format_args!("Partitions: {}", partitions.join(", ")));
+                }
+                self.say(format_args!("Done: {operation} completed successfully"));
             }
             OutputMode::Gui => {
-                println!("@done ok");
+                self.say("@done ok");
             }
         }
     }
@@ -87,14 +157,14 @@ impl CliOutput {
     pub fn cancelled(&self, message: &str) {
         match self.mode {
             OutputMode::Terminal => {
-                println!("Cancelled: {message}");
+                self.say(format_args!("Cancelled: {message}"));
             }
             OutputMode::Gui => {
-                println!(
+                self.say(format_args!(
                     "@progress phase=cancelled msg={}",
                     quote_gui(message)
-                );
-                println!("@done cancelled");
+                ));
+                self.say("@done cancelled");
             }
         }
     }
@@ -102,18 +172,18 @@ impl CliOutput {
     pub fn dry_run_ok(&self, operation: &str, source: &str, target: &str, block_size: usize) {
         match self.mode {
             OutputMode::Terminal => {
-                println!(
+                self.say(format_args!(
                     "Dry run: would {operation} {source} → {target} (block_size={block_size})"
-                );
+                ));
             }
             OutputMode::Gui => {
-                println!(
+                self.say(format_args!(
                     "@status phase=preparing msg={}",
                     quote_gui(format!(
                         "Dry run: would {operation} {source} → {target} (block_size={block_size})"
                     ))
-                );
-                println!("@done ok");
+                ));
+                self.say("@done ok");
             }
         }
     }
@@ -129,17 +199,167 @@ impl CliOutput {
         }
     }
 
+    pub fn warning(&self, msg: &str) {
+        match self.mode {
+            OutputMode::Terminal => {
+                eprintln!("Warning: {msg}");
+            }
+            OutputMode::Gui => {
+                eprintln!("@warning msg={}", quote_gui(msg));
+            }
+        }
+    }
+
     pub fn query_device(&self, device: &liblitho::devices::DeviceInfo) {
         match self.mode {
-            OutputMode::Terminal => println!("{device}"),
-            OutputMode::Gui => println!("{}", format_device_line(device)),
+            OutputMode::Terminal => self.say(device),
+            OutputMode::Gui => self.say(format_device_line(device)),
+        }
+    }
+
+    /// Full single-device report for `litho query --device`.
+    pub fn device_report(&self, report: &liblitho::devices::DeviceReport) {
+        match self.mode {
+            OutputMode::Terminal => {
+                for line in format_device_report(report) {
+                    self.say(line);
+                }
+            }
+            OutputMode::Gui => {
+                self.say(format_device_line(&report.disk));
+                for partition in &report.disk.partitions {
+                    self.say(format_partition_line(partition));
+                }
+                for holder in &report.holders {
+                    self.say(format_args!("@holder path={}", quote_gui(holder)));
+                }
+                for action in &report.in_use {
+                    self.say(format_in_use_line(action));
+                }
+                self.say(format_args!(
+                    "@query requested={} path={} safe_for_io={} write_protected={} reason={}",
+                    quote_gui(&report.requested),
+                    quote_gui(&report.path),
+                    report.safe_for_io,
+                    report.write_protected,
+                    quote_gui(report.refusal.as_deref().unwrap_or_default()),
+                ));
+            }
+        }
+    }
+
+    /// Partition table, filesystems and OS of a disk for `litho inspect --device`;
+    /// table warnings go to [`Self::warning`].
+    pub fn device_contents(&self, contents: &liblitho::devices::DeviceContents) {
+        let table = contents.partition_table.as_ref();
+        match self.mode {
+            OutputMode::Terminal => {
+                self.say(&contents.path);
+                for line in format_table_lines(table) {
+                    self.say(line);
+                }
+                if let Some(os) = &contents.os_release {
+                    self.say(format_os_line(os));
+                }
+            }
+            OutputMode::Gui => {
+                for line in gui_table_lines(&contents.path, table) {
+                    self.say(line);
+                }
+                if let Some(os) = &contents.os_release {
+                    self.say(gui_os_line(os));
+                }
+            }
+        }
+        for warning in table.iter().flat_map(|table| &table.warnings) {
+            self.warning(warning);
+        }
+    }
+
+    /// What `litho inspect --file` found in an image; table warnings go to
+    /// [`Self::warning`].
+    pub fn image_report(&self, report: &liblitho::inspect::ImageReport) {
+        let table = report.partition_table.as_ref();
+        match self.mode {
+            OutputMode::Terminal => {
+                for line in format_image_report(report) {
+                    self.say(line);
+                }
+            }
+            OutputMode::Gui => {
+                self.say(format_args!(
+                    "@image path={} format={} file_size={} image_size={}",
+                    quote_gui(&report.path),
+                    report.format,
+                    report.file_size,
+                    report
+                        .image_size
+                        .map(|size| size.to_string())
+                        .unwrap_or_default(),
+                ));
+                for line in gui_table_lines(&report.path, table) {
+                    self.say(line);
+                }
+                if let Some(filesystem) = &report.filesystem {
+                    self.say(format_args!(
+                        "@filesystem kind={} label={}",
+                        quote_gui(&filesystem.kind),
+                        quote_gui(filesystem.label.as_deref().unwrap_or_default()),
+                    ));
+                }
+                if let Some(iso) = &report.iso9660 {
+                    self.say(format_args!(
+                        "@iso9660 volume_id={} el_torito={} hybrid={}",
+                        quote_gui(iso.volume_id.as_deref().unwrap_or_default()),
+                        iso.el_torito,
+                        iso.hybrid,
+                    ));
+                }
+                if let Some(os) = &report.os_release {
+                    self.say(gui_os_line(os));
+                }
+                for note in &report.notes {
+                    self.say(format_args!("@note msg={}", quote_gui(note)));
+                }
+            }
+        }
+        for warning in table.iter().flat_map(|table| &table.warnings) {
+            self.warning(warning);
+        }
+    }
+
+    /// Pretty-printed JSON on the output stream, regardless of mode (`--json`).
+    pub fn json(&self, value: &impl serde::Serialize) {
+        match serde_json::to_string_pretty(value) {
+            Ok(json) => self.say(json),
+            Err(e) => self.error(&format!("Failed to encode JSON: {e}")),
+        }
+    }
+
+    pub fn device_event(&self, event: &liblitho::devices::DeviceEvent) {
+        match self.mode {
+            OutputMode::Terminal => self.say(event),
+            OutputMode::Gui => self.say(format_device_event_line(event)),
+        }
+    }
+
+    pub fn cache_entry(&self, entry: &liblitho::cache::CacheEntry) {
+        match self.mode {
+            OutputMode::Terminal => self.say(format_args!(
+                "{}  {:>12} bytes  used {}  {}",
+                &entry.source_sha256[..entry.source_sha256.len().min(12)],
+                entry.size,
+                format_age(now_secs().saturating_sub(entry.last_used)),
+                entry.origin
+            )),
+            OutputMode::Gui => self.say(format_cache_line(entry)),
         }
     }
 
     pub fn query_status(&self, msg: &str) {
         match self.mode {
-            OutputMode::Terminal => println!("{msg}"),
-            OutputMode::Gui => println!("@status msg={}", quote_gui(msg)),
+            OutputMode::Terminal => self.say(msg),
+            OutputMode::Gui => self.say(format_args!("@status msg={}", quote_gui(msg))),
         }
     }
 }
@@ -169,7 +389,7 @@ fn quote_gui(value: impl AsRef<str>) -> String {
     format!("\"{escaped}\"")
 }
 
-fn print_gui_progress(progress: &OperationProgress) {
+fn format_gui_progress(progress: &OperationProgress) -> String {
     let phase = phase_snake(progress.phase);
     let mut parts = vec![format!("@progress phase={phase}")];
 
@@ -180,11 +400,23 @@ fn print_gui_progress(progress: &OperationProgress) {
     if let Some(total) = progress.bytes_total {
         parts.push(format!("total={total}"));
     }
+    if let Some(errors) = progress.read_errors {
+        parts.push(format!("errors={errors}"));
+    }
+    if let Some(downloaded) = progress.bytes_downloaded {
+        parts.push(format!("downloaded={downloaded}"));
+    }
+    if let Some(total) = progress.download_total {
+        parts.push(format!("download_total={total}"));
+    }
+    if let Some(ref partitions) = progress.partitions {
+        parts.push(format!("partitions={}", quote_gui(partitions.join(","))));
+    }
     if let Some(ref msg) = progress.message {
         parts.push(format!("msg={}", quote_gui(msg)));
     }
 
-    println!("{}", parts.join(" "));
+    parts.join(" ")
 }
 
 fn phase_snake(phase: OperationPhase) -> &'static str {
@@ -200,16 +432,365 @@ fn phase_snake(phase: OperationPhase) -> &'static str {
 }
 
 fn format_device_line(device: &liblitho::devices::DeviceInfo) -> String {
+    format!("@device {}", format_device_fields(device))
+}
+
+fn format_device_event_line(event: &liblitho::devices::DeviceEvent) -> String {
+    format!(
+        "@device_event event={} {}",
+        event.kind(),
+        format_device_fields(event.device())
+    )
+}
+
+fn format_device_fields(device: &liblitho::devices::DeviceInfo) -> String {
     format!(
-        "@device name={} vendor={} model={} removable={} size={}",
+        "name={} vendor={} model={} removable={} size={} size_bytes={} serial={} transport={} read_only={} contents={}",
         quote_gui(&device.device_name),
         quote_gui(&device.vendor_name),
         quote_gui(&device.model_name),
         device.removable,
         device.size,
+        device.size_bytes,
+        quote_gui(device.serial.as_deref().unwrap_or_default()),
+        device
+            .transport
+            .map(|transport| transport.to_string())
+            .unwrap_or_default(),
+        device.read_only,
+        quote_gui(
+            device
+                .partition_table
+                .as_ref()
+                .map(|table| table.summary())
+                .unwrap_or_default()
+        ),
+    )
+}
+
+fn format_partition_line(partition: &liblitho::devices::PartitionInfo) -> String {
+    format!(
+        "@partition path={} number={} size_bytes={} fs_type={} label={} uuid={} mountpoint={}",
+        quote_gui(&partition.path),
+        partition.number,
+        partition.size_bytes,
+        quote_gui(partition.fs_type.as_deref().unwrap_or_default()),
+        quote_gui(partition.label.as_deref().unwrap_or_default()),
+        quote_gui(partition.uuid.as_deref().unwrap_or_default()),
+        quote_gui(partition.mountpoint.as_deref().unwrap_or_default()),
     )
 }
 
+fn format_in_use_line(action: &liblitho::devices::ReleaseAction) -> String {
+    match action {
+        liblitho::devices::ReleaseAction::Unmount {
+            source,
+            mount_point,
+        } => format!(
+            "@in_use action=unmount source={} mount_point={}",
+            quote_gui(source),
+            quote_gui(mount_point)
+        ),
+        liblitho::devices::ReleaseAction::Swapoff { source } => {
+            format!("@in_use action=swapoff source={}", quote_gui(source))
+        }
+    }
+}
+
+fn format_table_partition_line(
+    table: &liblitho::partitions::PartitionTable,
+    partition: &liblitho::partitions::Partition,
+) -> String {
+    let filesystem = partition.filesystem.as_ref();
+    format!(
+        "@table_partition number={} first_lba={} last_lba={} size_bytes={} type_id={} type_name={} name={} fs_type={} label={}",
+        partition.number,
+        partition.first_lba,
+        partition.last_lba,
+        table.partition_bytes(partition),
+        quote_gui(&partition.type_id),
+        quote_gui(partition.type_name().unwrap_or_default()),
+        quote_gui(partition.name.as_deref().unwrap_or_default()),
+        quote_gui(filesystem.map(|fs| fs.kind.as_str()).unwrap_or_default()),
+        quote_gui(filesystem.and_then(|fs| fs.label.as_deref()).unwrap_or_default()),
+    )
+}
+
+/// `@table` line (empty fields without a table) and one `@table_partition` per partition.
+fn gui_table_lines(
+    path: &str,
+    table: Option<&liblitho::partitions::PartitionTable>,
+) -> Vec<String> {
+    let Some(table) = table else {
+        return vec![format!(
+            "@table path={} kind= sector_size=0 summary=\"\"",
+            quote_gui(path)
+        )];
+    };
+    std::iter::once(format!(
+        "@table path={} kind={} sector_size={} summary={}",
+        quote_gui(path),
+        table.kind.to_string().to_lowercase(),
+        table.sector_size,
+        quote_gui(table.summary()),
+    ))
+    .chain(
+        table
+            .partitions
+            .iter()
+            .map(|partition| format_table_partition_line(table, partition)),
+    )
+    .collect()
+}
+
+fn gui_os_line(os: &liblitho::inspect::OsRelease) -> String {
+    format!(
+        "@os_release pretty_name={} id={} version_id={} source={}",
+        quote_gui(&os.pretty_name),
+        quote_gui(os.id.as_deref().unwrap_or_default()),
+        quote_gui(os.version_id.as_deref().unwrap_or_default()),
+        quote_gui(&os.source),
+    )
+}
+
+fn format_os_line(os: &liblitho::inspect::OsRelease) -> String {
+    format!("  OS:         {} ({})", os.pretty_name, os.source)
+}
+
+/// Table, summary and partition lines of `litho inspect`.
+fn format_table_lines(table: Option<&liblitho::partitions::PartitionTable>) -> Vec<String> {
+    let Some(table) = table else {
+        return vec!["  Table:      none".to_string()];
+    };
+    let mut lines = vec![
+        format!(
+            "  Table:      {} ({}-byte sectors)",
+            table.kind, table.sector_size
+        ),
+        format!("  Summary:    {}", table.summary()),
+    ];
+    if !table.partitions.is_empty() {
+        lines.push("  Partitions:".to_string());
+    }
+    for partition in &table.partitions {
+        let mut line = format!(
+            "    {:>3}  {:>10}  sectors {}-{}  {}",
+            partition.number,
+            format_bytes(table.partition_bytes(partition)),
+            partition.first_lba,
+            partition.last_lba,
+            partition.type_name().unwrap_or(&partition.type_id)
+        );
+        if let Some(filesystem) = &partition.filesystem {
+            line.push_str(&format!("  {}", filesystem.kind));
+            if let Some(label) = &filesystem.label {
+                line.push_str(&format!(" \"{label}\""));
+            }
+        }
+        if let Some(name) = &partition.name {
+            line.push_str(&format!("  name \"{name}\""));
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// Terminal lines for [`CliOutput::image_report`].
+fn format_image_report(report: &liblitho::inspect::ImageReport) -> Vec<String> {
+    use liblitho::inspect::ImageFormat;
+
+    let mut format = format!("{}, {}", report.format, format_bytes(report.file_size));
+    match report.image_size {
+        Some(size) if report.format != ImageFormat::Raw => {
+            format.push_str(&format!(" ({} uncompressed)", format_bytes(size)))
+        }
+        _ => {}
+    }
+    let mut lines = vec![report.path.clone(), format!("  Format:     {format}")];
+    match &report.filesystem {
+        Some(filesystem) => {
+            let mut line = format!("  Filesystem: {}", filesystem.kind);
+            if let Some(label) = &filesystem.label {
+                line.push_str(&format!(" \"{label}\""));
+            }
+            lines.push(line);
+        }
+        None if report.format.is_readable() => {
+            lines.extend(format_table_lines(report.partition_table.as_ref()))
+        }
+        None => {}
+    }
+    if let Some(iso) = &report.iso9660 {
+        let mut line = format!(
+            "  ISO 9660:   \"{}\"",
+            iso.volume_id.as_deref().unwrap_or_default()
+        );
+        if iso.el_torito {
+            line.push_str(", El Torito boot");
+        }
+        if iso.hybrid {
+            line.push_str(", hybrid (boots from USB)");
+        }
+        lines.push(line);
+    }
+    if let Some(os) = &report.os_release {
+        lines.push(format_os_line(os));
+    }
+    for note in &report.notes {
+        lines.push(format!("  Note:       {note}"));
+    }
+    lines
+}
+
+/// Terminal lines for [`CliOutput::device_report`].
+fn format_device_report(report: &liblitho::devices::DeviceReport) -> Vec<String> {
+    let disk = &report.disk;
+    let or_dash = |value: Option<&str>| value.unwrap_or("-").to_string();
+    let yes_no = |value: bool| if value { "yes" } else { "no" };
+
+    let mut lines = Vec::new();
+    if report.requested == report.path {
+        lines.push(report.path.clone());
+    } else {
+        lines.push(format!("{} -> {}", report.requested, report.path));
+    }
+    if let Some(partition) = &report.partition {
+        lines.push(format!(
+            "  Partition:  {} of {}, {}",
+            partition.number,
+            disk.device_name,
+            format_bytes(partition.size_bytes)
+        ));
+    }
+    let name = format!("{} {}", disk.vendor_name.trim(), disk.model_name.trim());
+    let name = Some(name.trim()).filter(|name| !name.is_empty());
+    lines.push(format!("  Model:      {}", or_dash(name)));
+    lines.push(format!(
+        "  Size:       {} ({} bytes)",
+        format_bytes(disk.size_bytes),
+        disk.size_bytes
+    ));
+    let mut transport = disk
+        .transport
+        .map(|transport| transport.to_string())
+        .unwrap_or_else(|| "-".to_string());
+    if let Some(usb) = &disk.usb {
+        transport.push_str(&format!(" ({}:{}", usb.vendor_id, usb.product_id));
+        if let Some(speed) = usb.speed_mbps {
+            transport.push_str(&format!(", {speed} Mbit/s"));
+        }
+        transport.push(')');
+    }
+    lines.push(format!("  Transport:  {transport}"));
+    lines.push(format!("  Serial:     {}", or_dash(disk.serial.as_deref())));
+    lines.push(format!("  WWN:        {}", or_dash(disk.wwn.as_deref())));
+    lines.push(format!(
+        "  Removable:  {}    Read-only: {}",
+        yes_no(disk.removable == 1),
+        yes_no(disk.read_only)
+    ));
+    lines.push(format!(
+        "  Sectors:    {} logical / {} physical",
+        disk.logical_sector_size, disk.physical_sector_size
+    ));
+    for link in &disk.by_id {
+        lines.push(format!("  By-id:      {link}"));
+    }
+    if let Some(table) = &disk.partition_table {
+        lines.push(format!(
+            "  Contents:   {} — {}",
+            table.kind,
+            table.summary()
+        ));
+    }
+    if disk.partitions.is_empty() {
+        lines.push("  Partitions: none".to_string());
+    } else {
+        lines.push("  Partitions:".to_string());
+        for partition in &disk.partitions {
+            let mut line = format!(
+                "    {:<16} {:>10}  {}",
+                partition.path,
+                format_bytes(partition.size_bytes),
+                or_dash(partition.fs_type.as_deref())
+            );
+            if let Some(label) = &partition.label {
+                line.push_str(&format!(" \"{label}\""));
+            }
+            if let Some(mountpoint) = &partition.mountpoint {
+                line.push_str(&format!("  on {mountpoint}"));
+            }
+            lines.push(line);
+        }
+    }
+    lines.push(format!(
+        "  Holders:    {}",
+        if report.holders.is_empty() {
+            "none".to_string()
+        } else {
+            report.holders.join(", ")
+        }
+    ));
+    if report.in_use.is_empty() {
+        lines.push("  In use:     no".to_string());
+    }
+    for action in &report.in_use {
+        lines.push(format!("  In use:     {action}"));
+    }
+    match &report.refusal {
+        None if report.write_protected => {
+            lines.push("  Flash/clone: clone only — device is write-protected".to_string())
+        }
+        None => lines.push("  Flash/clone: allowed".to_string()),
+        Some(reason) => lines.push(format!("  Flash/clone: refused — {reason}")),
+    }
+    lines
+}
+
+/// Size in binary units with one decimal (`29.3 GiB`).
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
+fn format_cache_line(entry: &liblitho::cache::CacheEntry) -> String {
+    format!(
+        "@cache source_sha256={} image_sha256={} size={} last_used={} origin={}",
+        entry.source_sha256,
+        entry.image_sha256,
+        entry.size,
+        entry.last_used,
+        quote_gui(&entry.origin),
+    )
+}
+
+/// Coarse "how long ago" for cache listings.
+fn format_age(seconds: u64) -> String {
+    match seconds {
+        0..=59 => "just now".to_string(),
+        60..=3599 => format!("{}m ago", seconds / 60),
+        3600..=86399 => format!("{}h ago", seconds / 3600),
+        _ => format!("{}d ago", seconds / 86400),
+    }
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -233,4 +814,70 @@ mod tests {
     fn phase_snake_labels() {
         assert_eq!(phase_snake(OperationPhase::Writing), "writing");
     }
+
+    #[test]
+    fn partition_table_lines() {
+        let table: liblitho::partitions::PartitionTable = serde_json::from_value(serde_json::json!({
+            "kind": "mbr",
+            "sector_size": 512,
+            "partitions": [
+                {"number": 1, "first_lba": 2048, "last_lba": 526335, "type_id": "0x0c", "name": null,
+                 "filesystem": {"kind": "FAT32", "label": "boot"}},
+                {"number": 2, "first_lba": 526336, "last_lba": 15624191, "type_id": "0x83", "name": null,
+                 "filesystem": {"kind": "ext4", "label": "rootfs"}},
+            ],
+        }))
+        .unwrap();
+        assert_eq!(
+            format_table_lines(Some(&table)),
+            [
+                "  Table:      MBR (512-byte sectors)",
+                "  Summary:    2 partitions: FAT32 boot 256M, ext4 rootfs 7.2G",
+                "  Partitions:",
+                "      1   256.0 MiB  sectors 2048-526335  FAT32  FAT32 \"boot\"",
+                "      2     7.2 GiB  sectors 526336-15624191  Linux  ext4 \"rootfs\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn image_report_lines() {
+        let report: liblitho::inspect::ImageReport = serde_json::from_value(serde_json::json!({
+            "path": "debian.iso",
+            "format": "raw",
+            "file_size": 660602880u64,
+            "image_size": 660602880u64,
+            "partition_table": null,
+            "filesystem": {"kind": "ISO9660", "label": "Debian 12.5.0 amd64 n"},
+            "iso9660": {"volume_id": "Debian 12.5.0 amd64 n", "el_torito": true, "hybrid": true},
+            "os_release": null,
+            "notes": ["gzip records the size modulo 4 GiB"],
+        }))
+        .unwrap();
+        assert_eq!(
+            format_image_report(&report),
+            [
+                "debian.iso",
+                "  Format:     raw, 630.0 MiB",
+                "  Filesystem: ISO9660 \"Debian 12.5.0 amd64 n\"",
+                "  ISO 9660:   \"Debian 12.5.0 amd64 n\", El Torito boot, hybrid (boots from USB)",
+                "  Note:       gzip records the size modulo 4 GiB",
+            ]
+        );
+    }
+
+    #[test]
+    fn format_bytes_binary_units() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(60063744 * 512), "28.6 GiB");
+    }
+
+    #[test]
+    fn format_age_buckets() {
+        assert_eq!(format_age(5), "just now");
+        assert_eq!(format_age(600), "10m ago");
+        assert_eq!(format_age(7200), "2h ago");
+        assert_eq!(format_age(3 * 86400), "3d ago");
+    }
 }
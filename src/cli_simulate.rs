@@ -1,21 +1,31 @@
 use anyhow::Result;
-use crate::progress::{check_cancel, OperationPhase, OperationProgress};
-use std::sync::atomic::AtomicBool;
+use crate::cache::ImageCache;
+use crate::platform::DiscardMethod;
+use crate::progress::{check_cancel, OperationControl, OperationPhase, OperationProgress};
+use crate::range::IoRange;
+use crate::rescue::RescueSummary;
+use crate::wipe::WipeMethod;
+use std::io::{self, Read, Write};
 use std::thread;
 use std::time::Duration;
 
 const SIMULATED_TOTAL_BYTES: u64 = 1_024 * 1_024 * 512; // 512 MiB
+const SIMULATED_USED_BYTES: u64 = 1_024 * 1_024 * 256; // last partition ends at 256 MiB
 const SIMULATED_STEPS: u64 = 20;
 
 /// Simulated flash for CLI output-mode testing (no real block I/O).
+#[allow(clippy::too_many_arguments)]
 pub fn simulate_flash<F>(
     image: &str,
     device: &str,
     block_size: usize,
+    range: IoRange,
     silent: bool,
     verify: bool,
+    discard: Option<DiscardMethod>,
+    cache: Option<&ImageCache>,
     mut progress: Option<F>,
-    cancel: Option<&AtomicBool>,
+    control: Option<&OperationControl>,
 ) -> Result<()>
 where
     F: FnMut(OperationProgress),
@@ -26,31 +36,49 @@ where
         OperationProgress::new(OperationPhase::Preparing)
             .with_message(format!("Opening image {image} (simulated)")),
     );
+    if let Some(cache) = cache.filter(|_| image.ends_with(".xz") || crate::http::is_url(image)) {
+        emit(
+            silent,
+            &mut progress,
+            OperationProgress::new(OperationPhase::Preparing)
+                .with_message(format!("Image cache {} (simulated)", cache.dir().display())),
+        );
+    }
 
-    check_cancel(cancel)?;
+    let total = range.copy_len(range.skip + range.count.unwrap_or(SIMULATED_TOTAL_BYTES))?;
+    check_cancel(control)?;
     thread::sleep(Duration::from_millis(80));
-    check_cancel(cancel)?;
+    check_cancel(control)?;
+
+    if let Some(method) = discard {
+        emit(
+            silent,
+            &mut progress,
+            OperationProgress::new(OperationPhase::Preparing)
+                .with_message(format!("{method} {device} (simulated)")),
+        );
+    }
 
     for step in 1..=SIMULATED_STEPS {
-        check_cancel(cancel)?;
-        let bytes = SIMULATED_TOTAL_BYTES * step / SIMULATED_STEPS;
+        check_cancel(control)?;
+        let bytes = total * step / SIMULATED_STEPS;
         let pct = if verify {
-            (bytes as f64 / SIMULATED_TOTAL_BYTES as f64) * 90.0
+            (bytes as f64 / total as f64) * 90.0
         } else {
-            (bytes as f64 / SIMULATED_TOTAL_BYTES as f64) * 100.0
+            (bytes as f64 / total as f64) * 100.0
         };
         emit(
             silent,
             &mut progress,
             OperationProgress::new(OperationPhase::Writing)
-                .with_bytes(bytes, Some(SIMULATED_TOTAL_BYTES))
+                .with_bytes(bytes, Some(total))
                 .with_percentage(pct),
         );
         thread::sleep(Duration::from_millis(40));
     }
 
     if verify {
-        check_cancel(cancel)?;
+        check_cancel(control)?;
         emit(
             silent,
             &mut progress,
@@ -60,29 +88,102 @@ where
         );
 
         for step in 1..=5 {
-            check_cancel(cancel)?;
-            let verified = SIMULATED_TOTAL_BYTES * step / 5;
-            let pct = 90.0 + (verified as f64 / SIMULATED_TOTAL_BYTES as f64) * 10.0;
+            check_cancel(control)?;
+            let verified = total * step / 5;
+            let pct = 90.0 + (verified as f64 / total as f64) * 10.0;
             emit(
                 silent,
                 &mut progress,
                 OperationProgress::new(OperationPhase::Verifying)
-                    .with_bytes(verified, Some(SIMULATED_TOTAL_BYTES))
+                    .with_bytes(verified, Some(total))
                     .with_percentage(pct.min(99.9)),
             );
             thread::sleep(Duration::from_millis(40));
         }
     }
 
-    check_cancel(cancel)?;
+    check_cancel(control)?;
     emit(
         silent,
         &mut progress,
         OperationProgress::new(OperationPhase::Complete)
-            .with_bytes(SIMULATED_TOTAL_BYTES, Some(SIMULATED_TOTAL_BYTES))
+            .with_bytes(total, Some(total))
             .with_percentage(100.0)
             .with_message(format!(
-                "Simulated flash of {image} to {device} (block_size={block_size}, verify={verify})"
+                "Simulated flash of {image} to {device} (block_size={block_size}, {range}, verify={verify})"
+            )),
+    );
+
+    Ok(())
+}
+
+/// Simulated stream flash: consumes the input and reports bytes read, without
+/// writing to the device.
+#[allow(clippy::too_many_arguments)]
+pub fn simulate_flash_reader<R, F>(
+    mut reader: R,
+    size: Option<u64>,
+    device: &str,
+    block_size: usize,
+    range: IoRange,
+    silent: bool,
+    verify: bool,
+    discard: Option<DiscardMethod>,
+    mut progress: Option<F>,
+    control: Option<&OperationControl>,
+) -> Result<()>
+where
+    R: Read,
+    F: FnMut(OperationProgress),
+{
+    emit(
+        silent,
+        &mut progress,
+        OperationProgress::new(OperationPhase::Preparing)
+            .with_message("Reading image from stream (simulated)"),
+    );
+
+    let write_size = match size {
+        Some(size) => Some(range.copy_len(size)?),
+        None => range.count,
+    };
+    if let Some(method) = discard {
+        emit(
+            silent,
+            &mut progress,
+            OperationProgress::new(OperationPhase::Preparing)
+                .with_message(format!("{method} {device} (simulated)")),
+        );
+    }
+
+    io::copy(&mut (&mut reader).take(range.skip), &mut io::sink())?;
+    let mut reader = reader.take(write_size.unwrap_or(u64::MAX));
+    let mut buffer = vec![0u8; block_size];
+    let mut count: u64 = 0;
+    loop {
+        check_cancel(control)?;
+        let bytes_read = reader.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        count += bytes_read as u64;
+        let mut event =
+            OperationProgress::new(OperationPhase::Writing).with_bytes(count, write_size);
+        if write_size.is_none() {
+            event = event.with_message(format!("{count} bytes written (simulated)"));
+        }
+        emit(silent, &mut progress, event);
+    }
+
+    check_cancel(control)?;
+    emit(
+        silent,
+        &mut progress,
+        OperationProgress::new(OperationPhase::Complete)
+            .with_bytes(count, Some(count))
+            .with_percentage(100.0)
+            .with_message(format!(
+                "Simulated flash of stream to {device} (block_size={block_size}, {range}, verify={verify})"
             )),
     );
 
@@ -94,9 +195,10 @@ pub fn simulate_clone<F>(
     device: &str,
     file: &str,
     block_size: usize,
+    range: IoRange,
     silent: bool,
     mut progress: Option<F>,
-    cancel: Option<&AtomicBool>,
+    control: Option<&OperationControl>,
 ) -> Result<()>
 where
     F: FnMut(OperationProgress),
@@ -108,39 +210,191 @@ where
             .with_message(format!("Opening {device} (simulated)")),
     );
 
-    check_cancel(cancel)?;
+    let total = range.copy_len(range.skip + range.count.unwrap_or(SIMULATED_TOTAL_BYTES))?;
+    check_cancel(control)?;
     thread::sleep(Duration::from_millis(80));
-    check_cancel(cancel)?;
+    check_cancel(control)?;
 
     for step in 1..=SIMULATED_STEPS {
-        check_cancel(cancel)?;
-        let bytes = SIMULATED_TOTAL_BYTES * step / SIMULATED_STEPS;
-        let pct = (bytes as f64 / SIMULATED_TOTAL_BYTES as f64) * 100.0;
+        check_cancel(control)?;
+        let bytes = total * step / SIMULATED_STEPS;
+        let pct = (bytes as f64 / total as f64) * 100.0;
         emit(
             silent,
             &mut progress,
             OperationProgress::new(OperationPhase::Writing)
-                .with_bytes(bytes, Some(SIMULATED_TOTAL_BYTES))
+                .with_bytes(bytes, Some(total))
                 .with_percentage(pct),
         );
         thread::sleep(Duration::from_millis(40));
     }
 
-    check_cancel(cancel)?;
+    check_cancel(control)?;
+    emit(
+        silent,
+        &mut progress,
+        OperationProgress::new(OperationPhase::Complete)
+            .with_bytes(total, Some(total))
+            .with_percentage(100.0)
+            .with_message(format!(
+                "Simulated clone of {device} to {file} (block_size={block_size}, {range})"
+            )),
+    );
+
+    Ok(())
+}
+
+/// Simulated stream clone: reports progress without writing anything to `_writer`.
+pub fn simulate_clone_writer<W, F>(
+    device: &str,
+    _writer: W,
+    block_size: usize,
+    range: IoRange,
+    silent: bool,
+    progress: Option<F>,
+    control: Option<&OperationControl>,
+) -> Result<()>
+where
+    W: Write,
+    F: FnMut(OperationProgress),
+{
+    if range.seek > 0 {
+        anyhow::bail!("An output offset (seek) is not supported when cloning to a stream");
+    }
+    simulate_clone(device, "stream", block_size, range, silent, progress, control)
+}
+
+/// Simulated used-extent clone: a simulated clone that stops at `SIMULATED_USED_BYTES`.
+pub fn simulate_clone_used<F>(
+    device: &str,
+    file: &str,
+    block_size: usize,
+    silent: bool,
+    progress: Option<F>,
+    control: Option<&OperationControl>,
+) -> Result<()>
+where
+    F: FnMut(OperationProgress),
+{
+    let range = IoRange {
+        count: Some(SIMULATED_USED_BYTES),
+        ..Default::default()
+    };
+    simulate_clone(device, file, block_size, range, silent, progress, control)
+}
+
+/// Simulated rescue clone: a clean simulated clone with no unreadable areas.
+pub fn simulate_rescue<F>(
+    device: &str,
+    file: &str,
+    mapfile: &str,
+    block_size: usize,
+    silent: bool,
+    progress: Option<F>,
+    control: Option<&OperationControl>,
+) -> Result<RescueSummary>
+where
+    F: FnMut(OperationProgress),
+{
+    simulate_clone(
+        device,
+        file,
+        block_size,
+        IoRange::default(),
+        silent,
+        progress,
+        control,
+    )?;
+    log::info!("Simulated rescue would record its mapfile at {mapfile}");
+    Ok(RescueSummary {
+        bytes_total: SIMULATED_TOTAL_BYTES,
+        bytes_rescued: SIMULATED_TOTAL_BYTES,
+        ..RescueSummary::default()
+    })
+}
+
+/// Simulated wipe for CLI output-mode testing (no real block I/O).
+pub fn simulate_wipe<F>(
+    device: &str,
+    method: WipeMethod,
+    block_size: usize,
+    silent: bool,
+    verify: bool,
+    mut progress: Option<F>,
+    control: Option<&OperationControl>,
+) -> Result<()>
+where
+    F: FnMut(OperationProgress),
+{
+    if verify && !method.supports_verify() {
+        anyhow::bail!("A {method} wipe cannot be verified; use the zero method instead");
+    }
+    emit(
+        silent,
+        &mut progress,
+        OperationProgress::new(OperationPhase::Preparing)
+            .with_message(format!("Opening {device} for a {method} wipe (simulated)")),
+    );
+
+    check_cancel(control)?;
+    thread::sleep(Duration::from_millis(80));
+
+    let total = match method {
+        WipeMethod::Quick => 2 * crate::wipe::QUICK_WIPE_BYTES,
+        _ => SIMULATED_TOTAL_BYTES,
+    };
+    let write_share = if verify { 90.0 } else { 100.0 };
+    for step in 1..=SIMULATED_STEPS {
+        check_cancel(control)?;
+        let bytes = total * step / SIMULATED_STEPS;
+        emit(
+            silent,
+            &mut progress,
+            OperationProgress::new(OperationPhase::Writing)
+                .with_bytes(bytes, Some(total))
+                .with_percentage(bytes as f64 / total as f64 * write_share),
+        );
+        thread::sleep(Duration::from_millis(40));
+    }
+
+    if verify {
+        for step in 1..=5 {
+            check_cancel(control)?;
+            let verified = total * step / 5;
+            emit(
+                silent,
+                &mut progress,
+                OperationProgress::new(OperationPhase::Verifying)
+                    .with_bytes(verified, Some(total))
+                    .with_percentage((90.0 + verified as f64 / total as f64 * 10.0).min(99.9)),
+            );
+            thread::sleep(Duration::from_millis(40));
+        }
+    }
+
+    check_cancel(control)?;
     emit(
         silent,
         &mut progress,
         OperationProgress::new(OperationPhase::Complete)
-            .with_bytes(SIMULATED_TOTAL_BYTES, Some(SIMULATED_TOTAL_BYTES))
+            .with_bytes(total, Some(total))
             .with_percentage(100.0)
             .with_message(format!(
-                "Simulated clone of {device} to {file} (block_size={block_size})"
+                "Simulated {method} wipe of {device} (block_size={block_size}, verify={verify})"
             )),
     );
 
     Ok(())
 }
 
+/// Simulated eject: reports the steps without touching the device.
+pub fn simulate_eject(device: &str) -> Vec<String> {
+    vec![
+        format!("sync {device} (simulated)"),
+        format!("power off {device} (simulated)"),
+    ]
+}
+
 fn emit<F>(silent: bool, progress: &mut Option<F>, event: OperationProgress)
 where
     F: FnMut(OperationProgress),
@@ -0,0 +1,600 @@
+//! Device-independent flash, verify and clone loops.
+//!
+//! These work on [`DeviceReader`] / [`DeviceWriter`] trait objects instead of paths,
+//! so the code that drives a block device also runs against a
+//! [`MemoryDevice`](crate::platform::MemoryDevice) or a plain file
+//! ([`FileDeviceWriter`](crate::platform::FileDeviceWriter)) in tests. The path-based
+//! functions in the crate root open the platform device, add the size fallbacks,
+//! discard and partition refresh that need sysfs, and delegate here.
+
+use crate::platform::{DeviceReader, DeviceWriter};
+use crate::progress::{
+    check_cancel, emit_progress, OperationControl, OperationPhase, OperationProgress,
+};
+use crate::range::IoRange;
+use anyhow::{Context, Result};
+use log::{debug, info};
+use sha2::{Digest, Sha256};
+use std::io::{BufReader, Read, SeekFrom, Write};
+
+/// Flash `source` to an opened device and, with a `verifier` (a reader of the same
+/// device), read the written range back and compare SHA-256 checksums.
+///
+/// `size` is the length of `source` when known; `range` selects the part of it to
+/// write and the device offset, as for [`crate::flash`]. Returns the bytes written.
+#[allow(clippy::too_many_arguments)]
+pub fn flash_device<R, F>(
+    mut source: R,
+    size: Option<u64>,
+    writer: &mut dyn DeviceWriter,
+    verifier: Option<&mut dyn DeviceReader>,
+    block_size: usize,
+    range: IoRange,
+    silent: bool,
+    mut progress: Option<F>,
+    control: Option<&OperationControl>,
+) -> Result<u64>
+where
+    R: Read,
+    F: FnMut(OperationProgress),
+{
+    check_cancel(control)?;
+    let write_size = match size {
+        Some(size) => Some(range.copy_len(size)?),
+        None => range.count,
+    };
+    let device_size = writer.device_size().ok().filter(|s| *s > 0);
+    if let (Some(write_size), Some(device_size)) = (write_size, device_size) {
+        range.check_fits(write_size, device_size)?;
+    }
+    skip_input(&mut source, range.skip)?;
+
+    let verify = verifier.is_some();
+    let (written, checksum) = write_image(
+        &mut source,
+        writer,
+        block_size,
+        range.seek,
+        write_size,
+        verify,
+        verify,
+        silent,
+        &mut progress,
+        control,
+    )?;
+    finish_flash(
+        verifier.zip(checksum),
+        range.seek,
+        written,
+        None,
+        silent,
+        &mut progress,
+        control,
+    )?;
+    Ok(written)
+}
+
+/// Clone `range` of an opened device into `output`, returning the bytes copied.
+/// The total for progress comes from the reader's size. `range.seek` is ignored:
+/// position `output` before calling.
+#[allow(clippy::too_many_arguments)]
+pub fn clone_device<W, F>(
+    reader: &mut dyn DeviceReader,
+    mut output: W,
+    block_size: usize,
+    range: IoRange,
+    silent: bool,
+    mut progress: Option<F>,
+    control: Option<&OperationControl>,
+) -> Result<u64>
+where
+    W: Write,
+    F: FnMut(OperationProgress),
+{
+    let total_bytes = match reader.device_size().ok().filter(|s| *s > 0) {
+        Some(size) => Some(range.copy_len(size)?),
+        None => range.count,
+    };
+    let copied = read_device(
+        reader,
+        &mut output,
+        block_size,
+        range,
+        total_bytes,
+        silent,
+        &mut progress,
+        control,
+    )?;
+    output.flush().context("Failed to flush clone output")?;
+
+    emit_progress(
+        silent,
+        &mut progress,
+        OperationProgress::new(OperationPhase::Complete)
+            .with_bytes(copied, total_bytes)
+            .with_percentage(100.0)
+            .with_message("Clone completed"),
+    );
+    Ok(copied)
+}
+
+/// Write `source` to the device from offset `seek`, up to `write_size` bytes when
+/// known, then flush and sync. Input shorter than `write_size` is an error.
+///
+/// Returns the bytes written and, with `hash`, their SHA-256. With `verify` the
+/// write phase reports 0–90% and leaves the rest to [`finish_flash`].
+#[allow(clippy::too_many_arguments)]
+pub fn write_image<F>(
+    source: &mut dyn Read,
+    writer: &mut dyn DeviceWriter,
+    block_size: usize,
+    seek: u64,
+    write_size: Option<u64>,
+    hash: bool,
+    verify: bool,
+    silent: bool,
+    progress: &mut Option<F>,
+    control: Option<&OperationControl>,
+) -> Result<(u64, Option<String>)>
+where
+    F: FnMut(OperationProgress),
+{
+    if seek > 0 {
+        writer
+            .seek(SeekFrom::Start(seek))
+            .context(format!("Failed to seek device to offset {}", seek))?;
+    }
+
+    let mut reader = source.take(write_size.unwrap_or(u64::MAX));
+    let mut buffer = vec![0u8; block_size];
+    let mut hasher = hash.then(Sha256::new);
+    let scale = if verify { 90.0 } else { 100.0 };
+    let mut count: u64 = 0;
+    loop {
+        check_cancel(control)?;
+        let bytes_read = reader.read(&mut buffer).context("Failed to read image")?;
+        if bytes_read == 0 {
+            break;
+        }
+        writer
+            .write_all(&buffer[..bytes_read])
+            .context("Failed to write to device")?;
+        if let Some(hasher) = hasher.as_mut() {
+            hasher.update(&buffer[..bytes_read]);
+        }
+        count += bytes_read as u64;
+
+        let event = OperationProgress::new(OperationPhase::Writing).with_bytes(count, write_size);
+        let event = match write_size {
+            Some(write_size) => event.with_percentage((count as f64 / write_size as f64) * scale),
+            None => event.with_message(format!("{} bytes written", count)),
+        };
+        emit_progress(silent, progress, event);
+
+        if !silent {
+            match write_size {
+                Some(write_size) => debug!("Written {}/{}", count, write_size),
+                None => debug!("Written {} bytes", count),
+            }
+        }
+    }
+
+    if let Some(write_size) = write_size.filter(|expected| count < *expected) {
+        anyhow::bail!("Input ended after {} of {} bytes", count, write_size);
+    }
+
+    writer
+        .flush_and_sync()
+        .context("Failed to flush and sync device")?;
+    Ok((
+        count,
+        hasher.map(|hasher| format!("{:x}", hasher.finalize())),
+    ))
+}
+
+/// Report completion of a flash of `written` bytes at `seek`. With a verifier and the
+/// source checksum, the written range is read back and compared first; a mismatch
+/// reports the Failed phase and errors.
+#[allow(clippy::too_many_arguments)]
+pub fn finish_flash<F>(
+    verifier: Option<(&mut dyn DeviceReader, String)>,
+    seek: u64,
+    written: u64,
+    partitions: Option<Vec<String>>,
+    silent: bool,
+    progress: &mut Option<F>,
+    control: Option<&OperationControl>,
+) -> Result<()>
+where
+    F: FnMut(OperationProgress),
+{
+    let Some((reader, source_checksum)) = verifier else {
+        let mut complete = OperationProgress::new(OperationPhase::Complete)
+            .with_bytes(written, Some(written))
+            .with_percentage(100.0)
+            .with_message("Flash completed");
+        if let Some(partitions) = partitions {
+            complete = complete.with_partitions(partitions);
+        }
+        emit_progress(silent, progress, complete);
+        if !silent {
+            info!("Flash completed successfully");
+        }
+        return Ok(());
+    };
+
+    emit_progress(
+        silent,
+        progress,
+        OperationProgress::new(OperationPhase::Verifying)
+            .with_percentage(90.0)
+            .with_message("Verifying checksum"),
+    );
+
+    if seek > 0 {
+        reader
+            .seek(SeekFrom::Start(seek))
+            .context("Failed to seek device for verification")?;
+    }
+    let mut buffered_reader = BufReader::with_capacity(1024 * 1024, reader);
+    let device_checksum =
+        verify_checksum_with_progress(&mut buffered_reader, written, silent, progress, control)?;
+
+    if !silent {
+        info!("Device checksum: {}", device_checksum);
+    }
+
+    if source_checksum == device_checksum {
+        let mut complete = OperationProgress::new(OperationPhase::Complete)
+            .with_percentage(100.0)
+            .with_message("Checksums match");
+        if let Some(partitions) = partitions {
+            complete = complete.with_partitions(partitions);
+        }
+        emit_progress(silent, progress, complete);
+        if !silent {
+            info!("Checksums match. Write operation successful.");
+        }
+        Ok(())
+    } else {
+        emit_progress(
+            silent,
+            progress,
+            OperationProgress::new(OperationPhase::Failed).with_message("Checksums do not match"),
+        );
+        log::error!("Checksums do not match. Write operation may have failed.");
+        anyhow::bail!("Checksums do not match");
+    }
+}
+
+/// Copy from `range.skip` of the device into `output` until `range.count` bytes or the
+/// end of the device, returning the bytes copied. `total_bytes` is the progress total.
+#[allow(clippy::too_many_arguments)]
+pub fn read_device<F>(
+    reader: &mut dyn DeviceReader,
+    output: &mut dyn Write,
+    block_size: usize,
+    range: IoRange,
+    total_bytes: Option<u64>,
+    silent: bool,
+    progress: &mut Option<F>,
+    control: Option<&OperationControl>,
+) -> Result<u64>
+where
+    F: FnMut(OperationProgress),
+{
+    if range.skip > 0 {
+        reader
+            .seek(SeekFrom::Start(range.skip))
+            .context(format!("Failed to seek device to offset {}", range.skip))?;
+    }
+
+    let mut buffer = vec![0u8; block_size];
+    let mut total_bytes_read: u64 = 0;
+
+    loop {
+        check_cancel(control)?;
+        let to_read = match range.count {
+            Some(count) => {
+                let remaining = count - total_bytes_read;
+                if remaining == 0 {
+                    break;
+                }
+                buffer
+                    .len()
+                    .min(usize::try_from(remaining).unwrap_or(usize::MAX))
+            }
+            None => buffer.len(),
+        };
+        let bytes_read = reader
+            .read(&mut buffer[..to_read])
+            .context("Failed to read from device")?;
+        if bytes_read == 0 {
+            if let Some(count) = range.count {
+                anyhow::bail!(
+                    "Device ended after {} of {} requested bytes",
+                    total_bytes_read,
+                    count
+                );
+            }
+            break;
+        }
+        output
+            .write_all(&buffer[..bytes_read])
+            .context("Failed to write to output file")?;
+        total_bytes_read += bytes_read as u64;
+
+        let mut event = OperationProgress::new(OperationPhase::Writing)
+            .with_bytes(total_bytes_read, total_bytes);
+        if total_bytes.is_none() {
+            event = event.with_message(format!("{} bytes copied", total_bytes_read));
+        }
+        emit_progress(silent, progress, event);
+
+        if !silent {
+            debug!("Read and written {} bytes", total_bytes_read);
+        }
+    }
+
+    Ok(total_bytes_read)
+}
+
+/// Read and drop the first `skip` bytes of a stream.
+pub(crate) fn skip_input(source: &mut dyn Read, skip: u64) -> Result<()> {
+    if skip == 0 {
+        return Ok(());
+    }
+    let skipped = std::io::copy(&mut source.take(skip), &mut std::io::sink())
+        .context("Failed to read input stream")?;
+    if skipped < skip {
+        anyhow::bail!(
+            "Input ended after {} bytes, before the skip offset {}",
+            skipped,
+            skip
+        );
+    }
+    Ok(())
+}
+
+fn verify_checksum_with_progress<F>(
+    reader: &mut dyn Read,
+    size: u64,
+    silent: bool,
+    progress: &mut Option<F>,
+    control: Option<&OperationControl>,
+) -> Result<String>
+where
+    F: FnMut(OperationProgress),
+{
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 65536];
+    let mut verified: u64 = 0;
+
+    while verified < size {
+        check_cancel(control)?;
+        let remaining = size - verified;
+        let to_read = buffer
+            .len()
+            .min(usize::try_from(remaining).unwrap_or(usize::MAX));
+        let bytes_read = reader.read(&mut buffer[..to_read]).with_context(|| {
+            format!(
+                "Failed to read from device during verification ({} bytes remaining)",
+                remaining
+            )
+        })?;
+        if bytes_read == 0 {
+            anyhow::bail!(
+                "Unexpected end of device read during verification ({} bytes short)",
+                remaining
+            );
+        }
+        hasher.update(&buffer[..bytes_read]);
+        verified += bytes_read as u64;
+        let verify_pct = 90.0 + (verified as f64 / size as f64) * 10.0;
+        emit_progress(
+            silent,
+            progress,
+            OperationProgress::new(OperationPhase::Verifying)
+                .with_bytes(verified, Some(size))
+                .with_percentage(verify_pct.min(99.9)),
+        );
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::{FileDeviceReader, FileDeviceWriter, MemoryDevice};
+
+    fn pattern(len: u32, modulus: u32) -> Vec<u8> {
+        (0..len).map(|i| (i % modulus) as u8).collect()
+    }
+
+    #[test]
+    fn flash_device_writes_the_range_and_verifies() {
+        let image = pattern(4096, 251);
+        let device = MemoryDevice::new(16 * 1024);
+        let range = IoRange {
+            skip: 1024,
+            seek: 8 * 1024,
+            count: Some(2048),
+        };
+        let mut phases = Vec::new();
+
+        let written = flash_device(
+            image.as_slice(),
+            Some(image.len() as u64),
+            &mut device.writer(),
+            Some(&mut device.reader()),
+            512,
+            range,
+            false,
+            Some(|event: OperationProgress| phases.push(event.phase)),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(written, 2048);
+        let contents = device.contents();
+        assert!(contents[..8 * 1024].iter().all(|b| *b == 0));
+        assert_eq!(&contents[8 * 1024..10 * 1024], &image[1024..3072]);
+        assert!(contents[10 * 1024..].iter().all(|b| *b == 0));
+        assert_eq!(device.syncs(), 1);
+        assert!(phases.contains(&OperationPhase::Verifying));
+        assert_eq!(phases.last(), Some(&OperationPhase::Complete));
+    }
+
+    #[test]
+    fn flash_device_reports_a_verify_mismatch() {
+        let image = pattern(2048, 13);
+        let device = MemoryDevice::new(4096);
+        // Reading back a different device stands in for a write that did not stick.
+        let stale = MemoryDevice::new(4096);
+        let mut last_phase = None;
+
+        let error = flash_device(
+            image.as_slice(),
+            Some(image.len() as u64),
+            &mut device.writer(),
+            Some(&mut stale.reader()),
+            512,
+            IoRange::default(),
+            false,
+            Some(|event: OperationProgress| last_phase = Some(event.phase)),
+            None,
+        )
+        .unwrap_err();
+
+        assert!(error.to_string().contains("Checksums do not match"));
+        assert_eq!(last_phase, Some(OperationPhase::Failed));
+    }
+
+    #[test]
+    fn flash_device_refuses_images_past_the_device_end() {
+        let device = MemoryDevice::new(8192);
+        let range = IoRange {
+            seek: 6144,
+            ..Default::default()
+        };
+        let error = flash_device::<_, fn(OperationProgress)>(
+            &[1u8; 4096][..],
+            Some(4096),
+            &mut device.writer(),
+            None,
+            512,
+            range,
+            true,
+            None,
+            None,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("do not fit"));
+        assert!(device.contents().iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn write_image_rejects_short_input_and_full_devices() {
+        let device = MemoryDevice::new(1024);
+        let error = write_image::<fn(OperationProgress)>(
+            &mut &[7u8; 100][..],
+            &mut device.writer(),
+            64,
+            0,
+            Some(200),
+            false,
+            false,
+            true,
+            &mut None,
+            None,
+        )
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Input ended after 100 of 200 bytes"));
+
+        let error = write_image::<fn(OperationProgress)>(
+            &mut &[7u8; 2000][..],
+            &mut device.writer(),
+            512,
+            0,
+            None,
+            false,
+            false,
+            true,
+            &mut None,
+            None,
+        )
+        .unwrap_err();
+        assert!(format!("{:#}", error).contains("Failed to write to device"));
+    }
+
+    #[test]
+    fn clone_device_copies_a_file_backed_range() {
+        let disk = pattern(8192, 253);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("disk.img");
+        std::fs::write(&path, &disk).unwrap();
+        let mut reader = FileDeviceReader::open(path.to_str().unwrap()).unwrap();
+        let range = IoRange {
+            skip: 512,
+            seek: 0,
+            count: Some(3000),
+        };
+        let mut output = Vec::new();
+        let mut last_total = None;
+
+        let copied = clone_device(
+            &mut reader,
+            &mut output,
+            1024,
+            range,
+            false,
+            Some(|event: OperationProgress| last_total = event.bytes_total),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(copied, 3000);
+        assert_eq!(output, &disk[512..3512]);
+        assert_eq!(last_total, Some(3000));
+    }
+
+    #[test]
+    fn file_device_round_trip_flash_then_clone() {
+        let image = pattern(5000, 249);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("device.img");
+        let path = path.to_str().unwrap();
+        let mut writer = FileDeviceWriter::create(path, 8192).unwrap();
+
+        flash_device::<_, fn(OperationProgress)>(
+            image.as_slice(),
+            None,
+            &mut writer,
+            Some(&mut FileDeviceReader::open(path).unwrap()),
+            1000,
+            IoRange::default(),
+            true,
+            None,
+            None,
+        )
+        .unwrap();
+
+        let mut output = Vec::new();
+        clone_device::<_, fn(OperationProgress)>(
+            &mut FileDeviceReader::open(path).unwrap(),
+            &mut output,
+            4096,
+            IoRange::default(),
+            true,
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(output.len(), 8192);
+        assert_eq!(&output[..5000], image.as_slice());
+        assert!(output[5000..].iter().all(|b| *b == 0));
+    }
+}
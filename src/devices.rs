@@ -1,25 +1,118 @@
+use crate::inspect::{guess_os, OsRelease};
+use crate::partitions::{read_partition_table, PartitionTable};
+use crate::platform::PlatformDevice;
 use anyhow::{Context, Result};
 use log::{debug, warn};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use std::collections::HashMap;
 use std::fmt;
 use std::fs;
 use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg(test)]
+mod fixtures;
+mod policy;
+mod system;
+mod watch;
+pub use policy::{Policy, PolicyRule};
+pub use system::{FakeSystem, RealSystem, SystemView};
+pub use watch::{watch, DeviceEvent, DeviceWatcher, WatchBackend, DEFAULT_POLL_INTERVAL};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct DeviceInfo {
     pub device_name: String,
     pub vendor_name: String,
     pub model_name: String,
     pub removable: u8,
+    /// Size in 512-byte sectors.
     pub size: u64,
+    /// Size in bytes.
+    #[serde(default)]
+    pub size_bytes: u64,
+    /// Serial number (udev `ID_SERIAL_SHORT`, or the sysfs / USB `serial` attribute).
+    #[serde(default)]
+    pub serial: Option<String>,
+    /// World Wide Name (`ID_WWN_WITH_EXTENSION`, or the sysfs `wwid`).
+    #[serde(default)]
+    pub wwn: Option<String>,
+    #[serde(default)]
+    pub transport: Option<Transport>,
+    /// The USB device the disk sits on, for USB transports.
+    #[serde(default)]
+    pub usb: Option<UsbInfo>,
+    /// The kernel marks the disk read-only (`/sys/block/<disk>/ro`, e.g. an SD lock switch).
+    #[serde(default)]
+    pub read_only: bool,
+    #[serde(default)]
+    pub logical_sector_size: u64,
+    #[serde(default)]
+    pub physical_sector_size: u64,
+    /// `/dev/disk/by-id` links to the whole disk.
+    #[serde(default)]
+    pub by_id: Vec<String>,
+    #[serde(default)]
+    pub partitions: Vec<PartitionInfo>,
+    /// Partition table and filesystems read from the disk itself; `None` when the
+    /// disk has no table, its node cannot be read (e.g. without root), or it was
+    /// listed without opening disks ([`get_storage_devices`], the hotplug watcher).
+    #[serde(default)]
+    pub partition_table: Option<PartitionTable>,
+}
+
+/// Bus the disk is attached through, from its sysfs device path.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    Usb,
+    Nvme,
+    Sata,
+    Mmc,
+    Virtio,
+    Scsi,
+}
+
+impl fmt::Display for Transport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Usb => "usb",
+            Self::Nvme => "nvme",
+            Self::Sata => "sata",
+            Self::Mmc => "mmc",
+            Self::Virtio => "virtio",
+            Self::Scsi => "scsi",
+        })
+    }
+}
+
+/// USB identity and negotiated link speed of a USB disk.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct UsbInfo {
+    /// `idVendor`, four hex digits.
+    pub vendor_id: String,
+    /// `idProduct`, four hex digits.
+    pub product_id: String,
+    /// Link speed in Mbit/s (`1.5`, `12`, `480`, `5000`, …).
+    pub speed_mbps: Option<f64>,
+}
+
+/// A partition of a listed disk, with udev's filesystem probe and its mount point.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PartitionInfo {
+    pub path: String,
+    pub number: u32,
+    pub size_bytes: u64,
+    pub fs_type: Option<String>,
+    pub label: Option<String>,
+    pub uuid: Option<String>,
+    pub mountpoint: Option<String>,
 }
 
 impl fmt::Display for DeviceInfo {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", json!(self).to_string())
+        write!(f, "{}", json!(self))
     }
 }
 
@@ -30,9 +123,75 @@ fn get_file_content(input_file: String) -> Result<String> {
     Ok(content)
 }
 
-/// Require an exact whole-block device path such as `/dev/sdb` (no normalization).
-pub fn validate_block_device_path(path: &str) -> Result<(), String> {
+/// `/dev/disk` directories whose links name one disk or partition.
+const STABLE_LINK_DIRS: [&str; 7] = [
+    "by-id",
+    "by-path",
+    "by-uuid",
+    "by-label",
+    "by-partuuid",
+    "by-partlabel",
+    "by-diskseq",
+];
+
+/// Resolve a stable `/dev/disk/by-id/…` (`by-path`, `by-uuid`, …) link to the
+/// `/dev/<name>` node it points at now; any other path comes back trimmed but
+/// otherwise unchanged. Links are only followed from those directories and must end
+/// on a node directly under `/dev`.
+pub fn resolve_device_link(path: &str) -> Result<String, String> {
+    resolve_device_link_in(Path::new("/dev"), path)
+}
+
+fn resolve_device_link_in(dev: &Path, path: &str) -> Result<String, String> {
     let trimmed = path.trim();
+    let Some(link) = trimmed.strip_prefix("/dev/disk/") else {
+        return Ok(trimmed.to_string());
+    };
+    let kind = link.split('/').next().unwrap_or_default();
+    if !STABLE_LINK_DIRS.contains(&kind) {
+        return Err(format!(
+            "Unsupported device link directory /dev/disk/{kind} (use by-id, by-path or by-uuid): {trimmed}"
+        ));
+    }
+    let resolved = fs::canonicalize(dev.join("disk").join(link))
+        .map_err(|e| format!("Device link {trimmed} does not resolve: {e}"))?;
+    let dev = fs::canonicalize(dev).unwrap_or_else(|_| dev.to_path_buf());
+    let name = resolved
+        .strip_prefix(&dev)
+        .ok()
+        .and_then(|name| name.to_str())
+        .filter(|name| !name.is_empty() && !name.contains('/'))
+        .ok_or_else(|| {
+            format!(
+                "Device link {trimmed} does not point at a device node: {}",
+                resolved.display()
+            )
+        })?;
+    debug!("{} -> /dev/{}", trimmed, name);
+    Ok(format!("/dev/{name}"))
+}
+
+/// Mention the link an error was reached through, if any.
+fn via_link(error: String, path: &str) -> String {
+    if path.trim().starts_with("/dev/disk/") {
+        format!("{error} (via {})", path.trim())
+    } else {
+        error
+    }
+}
+
+/// Require a whole-block device path such as `/dev/sdb`, or a stable `/dev/disk/by-*`
+/// link to one ([`resolve_device_link`]). Other paths are not normalized.
+pub fn validate_block_device_path(path: &str) -> Result<(), String> {
+    block_device_path_in(&RealSystem, path)
+}
+
+fn block_device_path_in(view: &dyn SystemView, path: &str) -> Result<(), String> {
+    let resolved = resolve_device_link_in(&view.dev(), path)?;
+    validate_block_device_node(view, &resolved).map_err(|e| via_link(e, path))
+}
+
+fn validate_block_device_node(view: &dyn SystemView, trimmed: &str) -> Result<(), String> {
     if trimmed.is_empty() {
         return Err("Device path is empty.".to_string());
     }
@@ -58,16 +217,76 @@ pub fn validate_block_device_path(path: &str) -> Result<(), String> {
     if !is_whole_block_device_name(name) {
         return Err(format!("Not a recognized whole block device: {trimmed}"));
     }
-    if !Path::new(trimmed).exists() {
+    if !view.dev().join(name).exists() {
         return Err(format!("Device path does not exist: {trimmed}"));
     }
     Ok(())
 }
 
+/// Validate that `path` names an existing partition (`/dev/sdb2`, `/dev/mmcblk0p1`, …)
+/// directly or through a `/dev/disk/by-*` link.
+pub fn validate_partition_path(path: &str) -> Result<(), String> {
+    partition_path_in(&RealSystem, path)
+}
+
+fn partition_path_in(view: &dyn SystemView, path: &str) -> Result<(), String> {
+    let resolved = resolve_device_link_in(&view.dev(), path)?;
+    validate_partition_node(view, &resolved).map_err(|e| via_link(e, path))
+}
+
+fn validate_partition_node(view: &dyn SystemView, trimmed: &str) -> Result<(), String> {
+    let name = trimmed
+        .strip_prefix("/dev/")
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| {
+            format!("Partition must be a full device path (e.g. /dev/sdb2), got: {trimmed}")
+        })?;
+    if is_rejected_block_name(name) {
+        return Err(format!("Device type is not allowed for flash/clone: {trimmed}"));
+    }
+    if !is_partition_block_name(name) {
+        return Err(format!("Not a recognized partition: {trimmed}"));
+    }
+    if !is_whole_block_device_name(&whole_disk_name_from_block(name)) {
+        return Err(format!("Not a partition of a supported disk: {trimmed}"));
+    }
+    if !view.dev().join(name).exists() {
+        return Err(format!("Partition does not exist: {trimmed}"));
+    }
+    Ok(())
+}
+
+/// Partition `number` of the whole disk at `disk_path` (`/dev/sdb` + 2 → `/dev/sdb2`,
+/// `/dev/mmcblk0` + 1 → `/dev/mmcblk0p1`).
+pub fn partition_path(disk_path: &str, number: u32) -> Result<String, String> {
+    validate_block_device_path(disk_path)?;
+    Ok(partition_path_unchecked(
+        &resolve_device_link(disk_path)?,
+        number,
+    ))
+}
+
+fn partition_path_unchecked(disk: &str, number: u32) -> String {
+    if disk.ends_with(|c: char| c.is_ascii_digit()) {
+        format!("{disk}p{number}")
+    } else {
+        format!("{disk}{number}")
+    }
+}
+
+/// True when `path` names a partition rather than a whole disk.
+pub fn is_partition_path(path: &str) -> bool {
+    resolve_device_link(path).is_ok_and(|path| {
+        path.strip_prefix("/dev/")
+            .is_some_and(is_partition_block_name)
+    })
+}
+
 /// Require that `path` is a valid block device and appears in `known` (picker flows).
 pub fn validate_listed_block_device(path: &str, known: &[impl AsRef<str>]) -> Result<(), String> {
     validate_device_safe_for_io(path)?;
-    if !known.iter().any(|entry| entry.as_ref() == path) {
+    let resolved = resolve_device_link(path)?;
+    if !known.iter().any(|entry| entry.as_ref() == resolved) {
         return Err(format!(
             "Device {path} is not in the current device list. Refresh devices and select again."
         ));
@@ -75,18 +294,85 @@ pub fn validate_listed_block_device(path: &str, known: &[impl AsRef<str>]) -> Re
     Ok(())
 }
 
-/// Validate path format and refuse the system disk or mounted targets.
+/// Validate path format and refuse the system disk, devices the site policy
+/// (`/etc/litho/policy.toml`) rules out, or mounted targets.
 pub fn validate_device_safe_for_io(path: &str) -> Result<(), String> {
-    validate_block_device_path(path)?;
-    validate_device_not_system_disk(path)?;
-    validate_device_not_busy(path)?;
+    device_safe_for_io_in(&RealSystem, path)
+}
+
+fn device_safe_for_io_in(view: &dyn SystemView, path: &str) -> Result<(), String> {
+    block_device_path_in(view, path)?;
+    not_system_disk_in(view, path)?;
+    policy_allows_in(view, path)?;
+    not_busy_in(view, path)?;
+    Ok(())
+}
+
+/// Partition-level counterpart of [`validate_device_safe_for_io`]: refuses
+/// partitions on the system disk or on a disk the policy rules out, and partitions
+/// that (or whose whole disk) are mounted.
+pub fn validate_partition_safe_for_io(path: &str) -> Result<(), String> {
+    partition_safe_for_io_in(&RealSystem, path)
+}
+
+fn partition_safe_for_io_in(view: &dyn SystemView, path: &str) -> Result<(), String> {
+    partition_path_in(view, path)?;
+    not_system_disk_in(view, path)?;
+    policy_allows_in(view, path)?;
+    not_busy_in(view, path)?;
+    Ok(())
+}
+
+/// [`validate_partition_safe_for_io`] for partition paths, else [`validate_device_safe_for_io`].
+pub fn validate_target_safe_for_io(path: &str) -> Result<(), String> {
+    target_safe_for_io_in(&RealSystem, path)
+}
+
+fn target_safe_for_io_in(view: &dyn SystemView, path: &str) -> Result<(), String> {
+    let is_partition = resolve_device_link_in(&view.dev(), path).is_ok_and(|node| {
+        node.strip_prefix("/dev/")
+            .is_some_and(is_partition_block_name)
+    });
+    if is_partition {
+        partition_safe_for_io_in(view, path)
+    } else {
+        device_safe_for_io_in(view, path)
+    }
+}
+
+/// Refuse disks and partitions the kernel marks read-only: the `ro` flag of the node
+/// or its whole disk in sysfs, or `BLKROGET` on the node (SD card lock switch,
+/// `blockdev --setro`, write-protected eMMC boot areas). Flash and wipe check this
+/// before touching the target; clone only reads and does not.
+pub fn validate_device_writable(path: &str) -> Result<(), String> {
+    writable_in(&RealSystem, path)
+}
+
+fn writable_in(view: &dyn SystemView, path: &str) -> Result<(), String> {
+    let node = resolve_device_link_in(&view.dev(), path)?;
+    let name = node.strip_prefix("/dev/").unwrap_or(&node);
+    let disk = whole_disk_name_from_block(name);
+    let sysfs_read_only = [sysfs_block_dir(view, name), view.sys_block().join(&disk)]
+        .iter()
+        .any(|dir| read_sysfs_number(&dir.join("ro")) == Some(1));
+    let node_path = view.dev().join(name);
+    if sysfs_read_only || PlatformDevice::is_read_only(&node_path.to_string_lossy()) == Some(true) {
+        return Err(format!(
+            "Refusing {path}: device is write-protected (lock switch on the card or adapter, \
+             or the kernel's read-only flag)"
+        ));
+    }
     Ok(())
 }
 
 /// Refuse the whole block device that hosts the root filesystem.
 pub fn validate_device_not_system_disk(path: &str) -> Result<(), String> {
-    let target_whole = whole_disk_path(path)?;
-    let root_sources = root_filesystem_sources()?;
+    not_system_disk_in(&RealSystem, path)
+}
+
+fn not_system_disk_in(view: &dyn SystemView, path: &str) -> Result<(), String> {
+    let target_whole = whole_disk_path(&resolve_device_link_in(&view.dev(), path)?)?;
+    let root_sources = root_filesystem_sources(view)?;
     for source in &root_sources {
         let source_whole = whole_disk_path_from_source(source)?;
         if source_whole == target_whole {
@@ -98,22 +384,184 @@ pub fn validate_device_not_system_disk(path: &str) -> Result<(), String> {
     Ok(())
 }
 
-/// Refuse devices with partitions or the whole disk currently mounted.
+/// Refuse disks (and partitions of disks) that the device policy in
+/// `/etc/litho/policy.toml` rules out, naming the rule; allow everything without one.
+pub fn validate_device_policy(path: &str) -> Result<(), String> {
+    policy_allows_in(&RealSystem, path)
+}
+
+fn policy_allows_in(view: &dyn SystemView, path: &str) -> Result<(), String> {
+    let policy = Policy::load(&view.policy_file())
+        .map_err(|e| format!("Refusing {path}: invalid device policy: {e:#}"))?;
+    let Some(policy) = policy else {
+        return Ok(());
+    };
+    let disk = whole_disk_path(&resolve_device_link_in(&view.dev(), path)?)?;
+    let name = disk.strip_prefix("/dev/").unwrap_or(&disk);
+    let device = device_info_in(view, "", name).ok_or_else(|| {
+        format!(
+            "Refusing {path}: cannot read the details of {disk} that the device policy {} needs",
+            policy.source().display()
+        )
+    })?;
+    policy
+        .check(&device)
+        .map_err(|reason| format!("Refusing {path}: {reason}"))
+}
+
+/// Refuse devices with partitions or the whole disk currently mounted or used as swap.
+///
+/// For a partition path only that partition and its whole disk count as busy.
 pub fn validate_device_not_busy(path: &str) -> Result<(), String> {
-    let mounts = busy_mounts_for_device(path)?;
-    if mounts.is_empty() {
+    not_busy_in(&RealSystem, path)
+}
+
+fn not_busy_in(view: &dyn SystemView, path: &str) -> Result<(), String> {
+    let holders = busy_holders(view, path)?;
+    if holders.is_empty() {
         return Ok(());
     }
-    let details: Vec<String> = mounts
+    let details: Vec<String> = holders
         .iter()
-        .map(|(src, mp)| format!("{src} on {mp}"))
+        .map(|holder| match holder {
+            ReleaseAction::Unmount {
+                source,
+                mount_point,
+            } => format!("{source} on {mount_point}"),
+            ReleaseAction::Swapoff { source } => format!("{source} as swap"),
+        })
         .collect();
     Err(format!(
-        "Refusing {path}: device is mounted ({})",
+        "Refusing {path}: device is in use ({})",
         details.join(", ")
     ))
 }
 
+/// One step needed to release a busy target: a mount to unmount or a swap area to turn off.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum ReleaseAction {
+    Unmount { source: String, mount_point: String },
+    Swapoff { source: String },
+}
+
+impl fmt::Display for ReleaseAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReleaseAction::Unmount {
+                source,
+                mount_point,
+            } => write!(f, "unmount {source} from {mount_point}"),
+            ReleaseAction::Swapoff { source } => write!(f, "swapoff {source}"),
+        }
+    }
+}
+
+/// What [`release_target`] would do to free `path`: every mount (deepest first) and
+/// active swap on the target. Refuses invalid paths, the system disk and devices the
+/// policy rules out up front, so callers never unmount a disk they cannot write.
+pub fn release_plan(path: &str) -> Result<Vec<ReleaseAction>, String> {
+    if is_partition_path(path) {
+        validate_partition_path(path)?;
+    } else {
+        validate_block_device_path(path)?;
+    }
+    validate_device_not_system_disk(path)?;
+    validate_device_policy(path)?;
+    busy_holders(&RealSystem, path)
+}
+
+/// Unmount and swapoff everything in `plan`, stopping at the first failure.
+pub fn release_target(plan: &[ReleaseAction]) -> Result<(), String> {
+    for action in plan {
+        let output = match action {
+            ReleaseAction::Unmount { mount_point, .. } => {
+                Command::new("umount").arg(mount_point).output()
+            }
+            ReleaseAction::Swapoff { source } => Command::new("swapoff").arg(source).output(),
+        }
+        .map_err(|e| format!("Failed to {action}: {e}"))?;
+        if !output.status.success() {
+            return Err(format!(
+                "Failed to {action}: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        debug!("{action}");
+    }
+    Ok(())
+}
+
+fn busy_holders(view: &dyn SystemView, path: &str) -> Result<Vec<ReleaseAction>, String> {
+    let path = &resolve_device_link_in(&view.dev(), path)?;
+    busy_holders_in(&view.mounts()?, &view.swaps(), path)
+}
+
+/// Mounts (deepest mount point first) then swap areas that make `device_path` busy.
+fn busy_holders_in(
+    mounts: &str,
+    swaps: &str,
+    device_path: &str,
+) -> Result<Vec<ReleaseAction>, String> {
+    let mut busy_mounts = busy_mounts_in(mounts, device_path)?;
+    busy_mounts.sort_by_key(|(_, mount_point)| {
+        std::cmp::Reverse(mount_point.matches('/').count())
+    });
+    let mut holders: Vec<ReleaseAction> = busy_mounts
+        .into_iter()
+        .map(|(source, mount_point)| ReleaseAction::Unmount {
+            source,
+            mount_point: unescape_proc_field(&mount_point),
+        })
+        .collect();
+
+    let target_whole = whole_disk_path(device_path)?;
+    let partition = is_partition_path(device_path).then(|| device_path.trim());
+    for line in swaps.lines().skip(1) {
+        let Some(source) = line.split_whitespace().next() else {
+            continue;
+        };
+        let source = unescape_proc_field(source);
+        if !source.starts_with("/dev/") {
+            continue;
+        }
+        let busy = match partition {
+            Some(partition) => source == partition || source == target_whole,
+            None => whole_disk_path(&source)? == target_whole,
+        };
+        if busy {
+            holders.push(ReleaseAction::Swapoff { source });
+        }
+    }
+    Ok(holders)
+}
+
+/// Undo the octal escapes (`\040` for space, …) used in `/proc/mounts` and `/proc/swaps`.
+fn unescape_proc_field(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let octal = match bytes.get(i..i + 4) {
+            Some([b'\\', digits @ ..]) if digits.iter().all(|d| (b'0'..=b'7').contains(d)) => {
+                Some(digits.iter().fold(0u32, |acc, d| acc * 8 + u32::from(d - b'0')))
+            }
+            _ => None,
+        };
+        match octal.and_then(|value| u8::try_from(value).ok()) {
+            Some(byte) => {
+                out.push(byte);
+                i += 4;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
 fn scsi_disk_stem(name: &str) -> Option<&str> {
     name.strip_prefix("sd")
         .or_else(|| name.strip_prefix("vd"))
@@ -145,6 +593,7 @@ fn is_rejected_block_name(name: &str) -> bool {
 
 /// Map a block device name or path to its parent whole-disk path (`/dev/sdb`, …).
 pub fn whole_disk_path(path_or_name: &str) -> Result<String, String> {
+    let path_or_name = &resolve_device_link(path_or_name)?;
     let name = if path_or_name.starts_with("/dev/") {
         path_or_name
             .strip_prefix("/dev/")
@@ -189,110 +638,67 @@ fn normalize_mount_source(source: &str) -> String {
         .to_string()
 }
 
-fn root_filesystem_sources() -> Result<Vec<String>, String> {
-    match root_filesystem_source_from_findmnt() {
-        Ok(source) => expand_block_sources(&normalize_mount_source(&source)),
-        Err(findmnt_err) => {
-            let source = root_filesystem_source_from_proc_mounts()
-                .map_err(|proc_err| format!("{findmnt_err}; fallback: {proc_err}"))?;
-            expand_block_sources(&normalize_mount_source(&source))
-        }
-    }
-}
-
-fn root_filesystem_source_from_findmnt() -> Result<String, String> {
-    let output = Command::new("findmnt")
-        .args(["-n", "-o", "SOURCE", "--target", "/"])
-        .output()
-        .map_err(|e| format!("Failed to run findmnt: {e}"))?;
-    if !output.status.success() {
-        return Err(format!(
-            "findmnt failed: {}",
-            String::from_utf8_lossy(&output.stderr).trim()
-        ));
-    }
-    let source = String::from_utf8_lossy(&output.stdout).trim().to_string();
-    if source.is_empty() {
-        return Err("findmnt returned an empty root source".to_string());
-    }
-    Ok(source)
+fn root_filesystem_sources(view: &dyn SystemView) -> Result<Vec<String>, String> {
+    expand_block_sources(view, &normalize_mount_source(&view.root_source()?))
 }
 
-fn root_filesystem_source_from_proc_mounts() -> Result<String, String> {
-    let contents = fs::read_to_string("/proc/mounts")
-        .map_err(|e| format!("Failed to read /proc/mounts: {e}"))?;
-    for line in contents.lines() {
-        let mut parts = line.split_whitespace();
-        let Some(source) = parts.next() else {
-            continue;
-        };
-        let Some(mount_point) = parts.next() else {
-            continue;
-        };
-        if mount_point == "/" {
-            return Ok(source.to_string());
-        }
-    }
-    Err("Root mount not found in /proc/mounts".to_string())
-}
+/// How many stacked devices (dm-crypt on LVM on md, …) are followed below the root.
+const MAX_STACK_DEPTH: usize = 8;
 
-fn expand_block_sources(source: &str) -> Result<Vec<String>, String> {
-    if !source.starts_with("/dev/") {
-        return Err(format!(
-            "Root filesystem source {source} is not a block device path"
-        ));
+/// The partitions or disks under `source`: device-mapper (LVM, dm-crypt) and md
+/// devices are followed down their sysfs `slaves`, however deeply they are stacked.
+fn expand_block_sources(view: &dyn SystemView, source: &str) -> Result<Vec<String>, String> {
+    let name = source
+        .strip_prefix("/dev/")
+        .ok_or_else(|| format!("Root filesystem source {source} is not a block device path"))?;
+    if name.is_empty() {
+        return Err(format!("Invalid root device source: {source}"));
     }
-
-    let block_name = Path::new(source)
-        .file_name()
-        .and_then(|n| n.to_str())
-        .ok_or_else(|| format!("Invalid root device source: {source}"))?;
-
-    if block_name.starts_with("dm-") || source.contains("/mapper/") {
-        if let Some(dm_name) = dm_sysfs_name(source).or_else(|| {
-            fs::read_link(source)
-                .ok()
-                .and_then(|link| link.file_name().and_then(|n| n.to_str()).map(str::to_string))
-                .filter(|name| name.starts_with("dm-"))
-        }) {
-            let slaves_dir = format!("/sys/block/{dm_name}/slaves");
-            if let Ok(entries) = fs::read_dir(&slaves_dir) {
-                let slaves: Vec<String> = entries
-                    .filter_map(|entry| entry.ok())
-                    .filter_map(|entry| entry.file_name().into_string().ok())
-                    .map(|name| format!("/dev/{name}"))
-                    .collect();
-                if !slaves.is_empty() {
-                    return Ok(slaves);
-                }
-            }
-        }
+    // `/dev/mapper/vg-root` and `/dev/vg/root` are links to the `dm-N` node.
+    let name = fs::canonicalize(view.dev().join(name))
+        .ok()
+        .and_then(|node| Some(node.file_name()?.to_str()?.to_string()))
+        .filter(|node| node.starts_with("dm-"))
+        .unwrap_or_else(|| name.to_string());
+    let slaves = stacked_slaves(view, &name, 0);
+    if slaves.is_empty() {
+        Ok(vec![source.to_string()])
+    } else {
+        Ok(slaves)
     }
-
-    Ok(vec![source.to_string()])
 }
 
-fn dm_sysfs_name(source: &str) -> Option<String> {
-    let path = Path::new(source);
-    if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-        if name.starts_with("dm-") {
-            return Some(name.to_string());
+/// `/dev` paths of the devices at the bottom of the stack under block device `name`;
+/// empty when it sits directly on hardware.
+fn stacked_slaves(view: &dyn SystemView, name: &str, depth: usize) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(view.sys_block().join(name).join("slaves")) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect();
+    names.sort();
+    let mut sources = Vec::new();
+    for slave in names {
+        let below = if depth < MAX_STACK_DEPTH {
+            stacked_slaves(view, &slave, depth + 1)
+        } else {
+            Vec::new()
+        };
+        if below.is_empty() {
+            sources.push(format!("/dev/{slave}"));
+        } else {
+            sources.extend(below);
         }
     }
-
-    let link = fs::read_link(source).ok()?;
-    let link_name = link.file_name().and_then(|n| n.to_str())?;
-    if link_name.starts_with("dm-") {
-        return Some(link_name.to_string());
-    }
-
-    None
+    sources
 }
 
-fn busy_mounts_for_device(device_path: &str) -> Result<Vec<(String, String)>, String> {
+/// Mounts from `/proc/mounts`-style `contents` that make `device_path` busy.
+fn busy_mounts_in(contents: &str, device_path: &str) -> Result<Vec<(String, String)>, String> {
     let target_whole = whole_disk_path(device_path)?;
-    let contents = fs::read_to_string("/proc/mounts")
-        .map_err(|e| format!("Failed to read /proc/mounts: {e}"))?;
+    let partition = is_partition_path(device_path).then(|| device_path.trim());
 
     let mut mounts = Vec::new();
     for line in contents.lines() {
@@ -306,8 +712,11 @@ fn busy_mounts_for_device(device_path: &str) -> Result<Vec<(String, String)>, St
         if !source.starts_with("/dev/") {
             continue;
         }
-        let source_whole = whole_disk_path_from_source(source)?;
-        if source_whole == target_whole {
+        let busy = match partition {
+            Some(partition) => source == partition || source == target_whole,
+            None => whole_disk_path_from_source(source)? == target_whole,
+        };
+        if busy {
             mounts.push((source.to_string(), mount_point.to_string()));
         }
     }
@@ -323,18 +732,642 @@ fn is_whole_block_device_name(name: &str) -> bool {
             && stem.chars().all(|c| c.is_ascii_lowercase())
             && !stem.chars().any(|c| c.is_ascii_digit());
     }
-    if name.starts_with("mmcblk") {
-        return name["mmcblk".len()..]
-            .chars()
-            .all(|c| c.is_ascii_digit());
+    if let Some(rest) = name.strip_prefix("mmcblk") {
+        return rest.chars().all(|c| c.is_ascii_digit());
     }
-    if name.starts_with("nvme") {
-        let rest = &name[4..];
+    if let Some(rest) = name.strip_prefix("nvme") {
         return !rest.is_empty() && rest.chars().all(|c| c.is_ascii_digit() || c == 'n');
     }
     false
 }
 
+/// I/O buffer sizes (bytes) used by Lithographer legacy logic — pick based on device capacity.
+const IO_BLOCK_SIZES: [usize; 14] = [
+    4096, 8192, 16384, 32768, 65536, 131072, 262144, 524288, 1048576, 2097152, 4194304, 8388608,
+    16777216, 33554432,
+];
+
+const DEFAULT_IO_BLOCK_SIZE: usize = IO_BLOCK_SIZES[0];
+
+/// Pick I/O buffer size from device capacity (`DeviceInfo.size` — 512-byte sectors).
+///
+/// Matches the historical Lithographer `execute` logic: find the smallest table entry
+/// that is >= `size_sectors`, clamped to the table range.
+pub fn optimal_io_block_size_from_sectors(size_sectors: u64) -> usize {
+    if size_sectors > IO_BLOCK_SIZES[13] as u64 {
+        return IO_BLOCK_SIZES[13];
+    }
+    if size_sectors < IO_BLOCK_SIZES[0] as u64 {
+        return IO_BLOCK_SIZES[0];
+    }
+
+    for &block in &IO_BLOCK_SIZES {
+        if size_sectors <= block as u64 {
+            return block;
+        }
+    }
+
+    IO_BLOCK_SIZES[13]
+}
+
+/// Resolve block size for a device path via sysfs sector count.
+pub fn optimal_io_block_size(device_path: &str) -> usize {
+    device_size_sectors(device_path)
+        .map(optimal_io_block_size_from_sectors)
+        .unwrap_or(DEFAULT_IO_BLOCK_SIZE)
+}
+
+/// Returns device or partition size in 512-byte sectors from sysfs
+/// (`/sys/block/<disk>/size`, `/sys/block/<disk>/<partition>/size`).
+pub fn device_size_sectors(device_path: &str) -> Option<u64> {
+    device_size_sectors_in(&RealSystem, device_path)
+}
+
+fn device_size_sectors_in(view: &dyn SystemView, device_path: &str) -> Option<u64> {
+    let device_path = resolve_device_link_in(&view.dev(), device_path).ok()?;
+    let block_name = device_path.strip_prefix("/dev/")?;
+    read_sysfs_number(&sysfs_block_dir(view, block_name).join("size"))
+}
+
+/// sysfs directory of a disk (`/sys/block/sdb`) or partition (`/sys/block/sdb/sdb1`).
+fn sysfs_block_dir(view: &dyn SystemView, name: &str) -> PathBuf {
+    let disk = whole_disk_name_from_block(name);
+    if disk == name {
+        view.sys_block().join(name)
+    } else {
+        view.sys_block().join(disk).join(name)
+    }
+}
+
+/// Returns device or partition size in bytes from sysfs (512-byte sectors).
+pub fn device_size_bytes(device_path: &str) -> Option<u64> {
+    device_size_sectors(device_path)
+        .map(|sectors| sectors.saturating_mul(512))
+}
+
+/// Logical sector size in bytes from `/sys/block/<disk>/queue/logical_block_size`
+/// (partitions use their parent disk).
+pub fn logical_sector_size(device_path: &str) -> Option<u64> {
+    logical_sector_size_in(&RealSystem, device_path)
+}
+
+fn logical_sector_size_in(view: &dyn SystemView, device_path: &str) -> Option<u64> {
+    let disk = whole_disk_path(&resolve_device_link_in(&view.dev(), device_path).ok()?).ok()?;
+    read_sysfs_number(
+        &view
+            .sys_block()
+            .join(disk.strip_prefix("/dev/")?)
+            .join("queue/logical_block_size"),
+    )
+}
+
+/// Discard capabilities from `/sys/block/<disk>/queue` (partitions use their parent disk).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiscardSupport {
+    /// `discard_max_bytes`; zero means the device does not support discard.
+    pub max_bytes: u64,
+    /// `discard_granularity` in bytes.
+    pub granularity: u64,
+    /// `write_zeroes_max_bytes`; non-zero when `BLKZEROOUT` is offloaded to the device.
+    pub write_zeroes_max_bytes: u64,
+}
+
+impl DiscardSupport {
+    pub fn supports_discard(&self) -> bool {
+        self.max_bytes > 0
+    }
+}
+
+/// Read discard support for a disk or partition; `None` if sysfs has no queue attributes.
+pub fn discard_support(device_path: &str) -> Option<DiscardSupport> {
+    discard_support_in(&RealSystem, device_path)
+}
+
+fn discard_support_in(view: &dyn SystemView, device_path: &str) -> Option<DiscardSupport> {
+    let disk = whole_disk_path(&resolve_device_link_in(&view.dev(), device_path).ok()?).ok()?;
+    let queue = view
+        .sys_block()
+        .join(disk.strip_prefix("/dev/")?)
+        .join("queue");
+    let read = |attr: &str| read_sysfs_number(&queue.join(attr));
+    Some(DiscardSupport {
+        max_bytes: read("discard_max_bytes")?,
+        granularity: read("discard_granularity").unwrap_or(0),
+        write_zeroes_max_bytes: read("write_zeroes_max_bytes").unwrap_or(0),
+    })
+}
+
+/// Partition paths the kernel currently knows for the disk at `disk_path`, in partition
+/// order, from `/sys/block/<disk>/<partition>/partition`.
+pub fn disk_partitions(disk_path: &str) -> Vec<String> {
+    disk_partitions_in(&RealSystem, disk_path)
+}
+
+fn disk_partitions_in(view: &dyn SystemView, disk_path: &str) -> Vec<String> {
+    let Ok(disk_path) = resolve_device_link_in(&view.dev(), disk_path) else {
+        return Vec::new();
+    };
+    let Some(name) = disk_path.strip_prefix("/dev/") else {
+        return Vec::new();
+    };
+    partitions_in_sysfs(&view.sys_block().join(name))
+}
+
+fn partitions_in_sysfs(disk_dir: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(disk_dir) else {
+        return Vec::new();
+    };
+    let mut partitions: Vec<(u32, String)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let number = fs::read_to_string(entry.path().join("partition"))
+                .ok()?
+                .trim()
+                .parse()
+                .ok()?;
+            Some((number, format!("/dev/{}", entry.file_name().to_str()?)))
+        })
+        .collect();
+    partitions.sort();
+    partitions.into_iter().map(|(_, path)| path).collect()
+}
+
+/// Wait for udev to create device nodes for new partitions (best effort; no-op without udev).
+pub fn settle_udev() {
+    match Command::new("udevadm")
+        .args(["settle", "--timeout=10"])
+        .status()
+    {
+        Ok(status) if status.success() => debug!("udevadm settle finished"),
+        Ok(status) => warn!("udevadm settle exited with {status}"),
+        Err(e) => debug!("udevadm not available: {e}"),
+    }
+}
+
+/// Sync the disk behind `device_path`, release any (auto-)mounts and swap, then power
+/// it off so it can be unplugged: the SCSI device is removed (`device/delete`) and the
+/// USB device de-authorized. Disks that cannot be powered off (SD slots, NVMe) are only
+/// synced and released. Returns a description of each step taken.
+pub fn eject(device_path: &str) -> Result<Vec<String>, String> {
+    let disk = whole_disk_path(device_path)?;
+    validate_block_device_path(&disk)?;
+    let name = disk.trim_start_matches("/dev/");
+    let mut steps = Vec::new();
+
+    fs::File::open(&disk)
+        .and_then(|file| file.sync_all())
+        .map_err(|e| format!("Failed to sync {disk}: {e}"))?;
+    steps.push(format!("sync {disk}"));
+
+    let plan = release_plan(&disk)?;
+    release_target(&plan)?;
+    steps.extend(plan.iter().map(ToString::to_string));
+
+    // Resolve the USB device before the SCSI removal makes the sysfs node disappear.
+    let usb_device = fs::canonicalize(format!("/sys/block/{name}"))
+        .ok()
+        .and_then(|path| usb_device_dir(&path));
+
+    let mut powered_off = false;
+    let scsi_delete = format!("/sys/block/{name}/device/delete");
+    if Path::new(&scsi_delete).exists() {
+        fs::write(&scsi_delete, "1")
+            .map_err(|e| format!("Failed to remove SCSI device {name}: {e}"))?;
+        steps.push(format!("remove SCSI device {name}"));
+        powered_off = true;
+    }
+    if let Some(usb_device) = usb_device {
+        let usb_name = usb_device
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        fs::write(usb_device.join("authorized"), "0")
+            .map_err(|e| format!("Failed to power off USB device {usb_name}: {e}"))?;
+        steps.push(format!("power off USB device {usb_name}"));
+        powered_off = true;
+    }
+    if !powered_off {
+        steps.push(format!("{disk} cannot be powered off; it is safe to remove"));
+    }
+    debug!("Ejected {disk}: {steps:?}");
+    Ok(steps)
+}
+
+/// Nearest USB device (not interface) above a resolved `/sys/devices/...` block path.
+fn usb_device_dir(block_sysfs_path: &Path) -> Option<PathBuf> {
+    block_sysfs_path
+        .ancestors()
+        .find(|dir| dir.join("authorized").is_file() && dir.join("idVendor").is_file())
+        .map(Path::to_path_buf)
+}
+
+pub fn is_removable_device(device_path: &str) -> Result<bool> {
+    removable_in(&RealSystem, device_path)
+}
+
+fn removable_in(view: &dyn SystemView, device_path: &str) -> Result<bool> {
+    let device_path =
+        resolve_device_link_in(&view.dev(), device_path).map_err(anyhow::Error::msg)?;
+    // Extract device name from the device path
+    let device_name = Path::new(&device_path)
+        .file_name()
+        .context("Invalid device path")?
+        .to_str()
+        .context("Non UTF-8 device name")?;
+
+    // Construct the path to the removable file
+    let removable_path = view.sys_block().join(device_name).join("removable");
+    debug!("Checking removable path: {}", removable_path.display());
+
+    // Read the contents of the removable file
+    let mut file = fs::File::open(&removable_path)
+        .context(format!("Failed to open {}", removable_path.display()))?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)
+        .context("Failed to read removable file")?;
+
+    // Check if the device is removable
+    Ok(contents.trim() == "1")
+}
+
+/// Storage devices from sysfs and the udev database. Disks are not opened, so
+/// `partition_table` is `None`; see [`get_storage_devices_with_tables`].
+pub fn get_storage_devices() -> Result<Vec<DeviceInfo>> {
+    get_storage_devices_in(&RealSystem)
+}
+
+/// [`get_storage_devices`] with each disk's partition table read from the disk, for
+/// one-off listings. Reading wakes idle disks, so pollers should not use it.
+pub fn get_storage_devices_with_tables() -> Result<Vec<DeviceInfo>> {
+    storage_devices_in(&RealSystem, true)
+}
+
+/// Fill in `device.partition_table` from the disk, leaving `None` when it cannot
+/// be read; for refreshing single entries of a [`get_storage_devices`] listing.
+pub fn read_device_table(device: &mut DeviceInfo) {
+    read_table_in(&RealSystem, device);
+}
+
+/// [`get_storage_devices`] as `view` sees them (a [`FakeSystem`] fixture tree, …).
+pub fn get_storage_devices_in(view: &dyn SystemView) -> Result<Vec<DeviceInfo>> {
+    storage_devices_in(view, false)
+}
+
+fn storage_devices_in(view: &dyn SystemView, with_tables: bool) -> Result<Vec<DeviceInfo>> {
+    let sys_block = view.sys_block();
+    let entries = fs::read_dir(&sys_block)
+        .context(format!("Failed to read {} directory", sys_block.display()))?;
+    let mut names = Vec::new();
+    for entry in entries {
+        let entry = entry.context("Failed to read directory entry")?;
+        match entry.file_name().into_string() {
+            Ok(name) => names.push(name),
+            Err(_) => warn!("Could not convert device name to string"),
+        }
+    }
+    names.sort();
+
+    let mounts = view.mounts().unwrap_or_else(|e| {
+        warn!("{}", e);
+        String::new()
+    });
+    let mut devices: Vec<DeviceInfo> = names
+        .iter()
+        .filter_map(|name| device_info_in(view, &mounts, name))
+        .collect();
+    if with_tables {
+        for device in &mut devices {
+            read_table_in(view, device);
+        }
+    }
+    debug!("Found {} devices", devices.len());
+    Ok(devices)
+}
+
+/// Everything [`query_device`] found out about one device.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DeviceReport {
+    /// The path as given; may be a `/dev/disk/by-*` link.
+    pub requested: String,
+    /// `requested` with symlinks resolved (`/dev/sdb`, `/dev/sdb1`).
+    pub path: String,
+    /// The whole disk holding `path` (the device itself for disks).
+    pub disk: DeviceInfo,
+    /// The partition, when `path` is one.
+    pub partition: Option<PartitionInfo>,
+    /// Devices stacked on top (device-mapper, md, LVM), from sysfs `holders`.
+    pub holders: Vec<String>,
+    /// Mounts and swap areas that make `path` busy.
+    pub in_use: Vec<ReleaseAction>,
+    /// Whether flash/clone would accept `path` ([`validate_target_safe_for_io`]).
+    pub safe_for_io: bool,
+    /// Why it would be refused, when it would.
+    pub refusal: Option<String>,
+    /// Whether the kernel marks `path` read-only, so flash and wipe would refuse it
+    /// ([`validate_device_writable`]).
+    pub write_protected: bool,
+}
+
+/// Look up one disk or partition by path, following `/dev/disk/by-*` links, with its
+/// partitions, holders, mounts, and the outcome of the flash/clone safety checks.
+pub fn query_device(path: &str) -> Result<DeviceReport> {
+    device_report_in(&RealSystem, path)
+}
+
+/// Partition table and filesystems found on one whole disk, for `litho inspect`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DeviceContents {
+    /// The `/dev` node that was read (after following `/dev/disk/by-*` links).
+    pub path: String,
+    /// `None` when the disk has no MBR or GPT.
+    pub partition_table: Option<PartitionTable>,
+    /// Operating system found on the disk's filesystems ([`crate::inspect::guess_os`]).
+    pub os_release: Option<OsRelease>,
+}
+
+/// Read the partition table of a whole disk (or a `/dev/disk/by-*` link to one),
+/// with the filesystem type and label of each partition and the operating system.
+pub fn inspect_device(path: &str) -> Result<DeviceContents> {
+    validate_block_device_path(path).map_err(anyhow::Error::msg)?;
+    let node = resolve_device_link(path).map_err(anyhow::Error::msg)?;
+    let mut reader = PlatformDevice::new_verify_reader(&node)?;
+    let partition_table = read_partition_table(&mut reader)
+        .with_context(|| format!("Failed to read the partition table of {node}"))?;
+    let os_release = guess_os(&mut reader, partition_table.as_ref(), None);
+    Ok(DeviceContents {
+        path: node,
+        partition_table,
+        os_release,
+    })
+}
+
+fn device_report_in(view: &dyn SystemView, requested: &str) -> Result<DeviceReport> {
+    let relative = Path::new(requested.trim())
+        .strip_prefix("/dev")
+        .map_err(|_| anyhow::anyhow!("Not a device path (expected /dev/...): {requested}"))?;
+    // Resolve under `view.dev()`, so links behave the same in a fixture tree.
+    let dev = view.dev();
+    let resolved =
+        fs::canonicalize(dev.join(relative)).context(format!("Device not found: {requested}"))?;
+    let dev = fs::canonicalize(&dev).unwrap_or(dev);
+    let name = resolved
+        .strip_prefix(&dev)
+        .ok()
+        .and_then(|name| name.to_str())
+        .filter(|name| !name.is_empty() && !name.contains('/'))
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "{requested} resolves to {}, not a block device node",
+                resolved.display()
+            )
+        })?;
+    let path = format!("/dev/{name}");
+
+    let disk_name = whole_disk_name_from_block(name);
+    let mounts = view.mounts().map_err(anyhow::Error::msg)?;
+    let mut disk = device_info_in(view, &mounts, &disk_name)
+        .ok_or_else(|| anyhow::anyhow!("{path} is not a storage device"))?;
+    read_table_in(view, &mut disk);
+    let partition = if disk_name == name {
+        None
+    } else {
+        Some(
+            disk.partitions
+                .iter()
+                .find(|partition| partition.path == path)
+                .cloned()
+                .ok_or_else(|| {
+                    anyhow::anyhow!("{path} is not a partition of {}", disk.device_name)
+                })?,
+        )
+    };
+
+    // A disk is held by whatever sits on it or on any of its partitions.
+    let disk_dir = view.sys_block().join(&disk_name);
+    let holder_dirs: Vec<PathBuf> = if partition.is_some() {
+        vec![disk_dir.join(name)]
+    } else {
+        let part_dirs = disk
+            .partitions
+            .iter()
+            .filter_map(|partition| partition.path.strip_prefix("/dev/"))
+            .map(|part_name| disk_dir.join(part_name));
+        std::iter::once(disk_dir.clone()).chain(part_dirs).collect()
+    };
+    let mut holders: Vec<String> = holder_dirs
+        .iter()
+        .filter_map(|dir| fs::read_dir(dir.join("holders")).ok())
+        .flat_map(|entries| entries.flatten())
+        .filter_map(|entry| Some(format!("/dev/{}", entry.file_name().to_str()?)))
+        .collect();
+    holders.sort();
+    holders.dedup();
+
+    let in_use = busy_holders_in(&mounts, &view.swaps(), &path).map_err(anyhow::Error::msg)?;
+    let refusal = target_safe_for_io_in(view, &path).err();
+    let write_protected = writable_in(view, &path).is_err();
+    Ok(DeviceReport {
+        requested: requested.trim().to_string(),
+        path,
+        disk,
+        partition,
+        holders,
+        in_use,
+        safe_for_io: refusal.is_none(),
+        refusal,
+        write_protected,
+    })
+}
+
+/// Details of `/sys/block/<name>`; `None` for entries without a backing device
+/// (loop, zram, dm), without a `/dev` node, or with an unreadable `removable` flag.
+fn device_info_in(view: &dyn SystemView, mounts: &str, name: &str) -> Option<DeviceInfo> {
+    let dir = view.sys_block().join(name);
+    let node = view.dev().join(name);
+    if !dir.join("device").exists() || !node.exists() {
+        return None;
+    }
+    let removable = match read_sysfs_attr(&dir.join("removable")).as_deref() {
+        Some("1") => 1,
+        Some("0") => 0,
+        _ => return None,
+    };
+
+    let read_name =
+        |attr: &str| match get_file_content(dir.join("device").join(attr).display().to_string()) {
+            Ok(value) => value.replace('\n', ""),
+            Err(e) => {
+                warn!("Failed to read {} name: {}", attr, e);
+                String::new()
+            }
+        };
+    let size = read_sysfs_number(&dir.join("size")).unwrap_or_else(|| {
+        warn!("Failed to read size of {}", name);
+        0
+    });
+    let udev = udev_properties(&view.udev_data(), &dir);
+    let resolved = fs::canonicalize(&dir).unwrap_or_else(|_| dir.clone());
+    let usb_dir = usb_device_dir(&resolved);
+
+    let serial = udev
+        .get("ID_SERIAL_SHORT")
+        .cloned()
+        .or_else(|| read_sysfs_attr(&dir.join("serial")))
+        .or_else(|| read_sysfs_attr(&dir.join("device/serial")))
+        .or_else(|| {
+            usb_dir
+                .as_ref()
+                .and_then(|usb| read_sysfs_attr(&usb.join("serial")))
+        });
+    let wwn = udev
+        .get("ID_WWN_WITH_EXTENSION")
+        .or_else(|| udev.get("ID_WWN"))
+        .cloned()
+        .or_else(|| read_sysfs_attr(&dir.join("wwid")))
+        .or_else(|| read_sysfs_attr(&dir.join("device/wwid")));
+    let usb = usb_dir.as_ref().and_then(|usb| {
+        Some(UsbInfo {
+            vendor_id: read_sysfs_attr(&usb.join("idVendor"))?,
+            product_id: read_sysfs_attr(&usb.join("idProduct"))?,
+            speed_mbps: read_sysfs_attr(&usb.join("speed")).and_then(|speed| speed.parse().ok()),
+        })
+    });
+
+    Some(DeviceInfo {
+        device_name: format!("/dev/{name}"),
+        vendor_name: read_name("vendor"),
+        model_name: read_name("model"),
+        removable,
+        size,
+        size_bytes: size.saturating_mul(512),
+        serial,
+        wwn,
+        transport: transport_from_sysfs_path(&resolved.to_string_lossy()),
+        usb,
+        read_only: read_sysfs_attr(&dir.join("ro")).as_deref() == Some("1"),
+        logical_sector_size: read_sysfs_number(&dir.join("queue/logical_block_size"))
+            .unwrap_or(512),
+        physical_sector_size: read_sysfs_number(&dir.join("queue/physical_block_size"))
+            .unwrap_or(512),
+        by_id: links_to(&view.dev().join("disk/by-id"), &node),
+        partitions: partition_infos_in(view, mounts, &dir),
+        partition_table: None,
+    })
+}
+
+/// Fill in `device.partition_table` from its `/dev` node; left `None` when the node
+/// cannot be read (e.g. without root).
+fn read_table_in(view: &dyn SystemView, device: &mut DeviceInfo) {
+    let Some(name) = device.device_name.strip_prefix("/dev/") else {
+        return;
+    };
+    device.partition_table = read_table_best_effort(&view.dev().join(name));
+}
+
+fn read_table_best_effort(node: &Path) -> Option<PartitionTable> {
+    let table = fs::File::open(node)
+        .map_err(anyhow::Error::from)
+        .and_then(|mut file| read_partition_table(&mut file));
+    match table {
+        Ok(table) => table,
+        Err(e) => {
+            debug!("No partition table read from {}: {:#}", node.display(), e);
+            None
+        }
+    }
+}
+
+fn partition_infos_in(view: &dyn SystemView, mounts: &str, disk_dir: &Path) -> Vec<PartitionInfo> {
+    let Ok(entries) = fs::read_dir(disk_dir) else {
+        return Vec::new();
+    };
+    let mut partitions: Vec<PartitionInfo> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let number = read_sysfs_number(&entry.path().join("partition"))?;
+            let path = format!("/dev/{}", entry.file_name().to_str()?);
+            let udev = udev_properties(&view.udev_data(), &entry.path());
+            let mountpoint = mounts.lines().find_map(|line| {
+                let mut fields = line.split_whitespace();
+                let source = unescape_proc_field(fields.next()?);
+                (source == path).then(|| unescape_proc_field(fields.next().unwrap_or_default()))
+            });
+            Some(PartitionInfo {
+                number: u32::try_from(number).ok()?,
+                size_bytes: read_sysfs_number(&entry.path().join("size"))
+                    .unwrap_or(0)
+                    .saturating_mul(512),
+                fs_type: udev.get("ID_FS_TYPE").cloned(),
+                label: udev.get("ID_FS_LABEL").cloned(),
+                uuid: udev.get("ID_FS_UUID").cloned(),
+                mountpoint,
+                path,
+            })
+        })
+        .collect();
+    partitions.sort_by_key(|partition| partition.number);
+    partitions
+}
+
+/// Transport from a resolved `/sys/devices/...` block path. USB comes first so a
+/// USB–SATA bridge counts as USB; virtio-scsi counts as virtio.
+fn transport_from_sysfs_path(path: &str) -> Option<Transport> {
+    let has = |prefix: &str| path.split('/').any(|segment| segment.starts_with(prefix));
+    if has("usb") {
+        Some(Transport::Usb)
+    } else if has("nvme") {
+        Some(Transport::Nvme)
+    } else if has("mmc") {
+        Some(Transport::Mmc)
+    } else if has("virtio") {
+        Some(Transport::Virtio)
+    } else if has("ata") {
+        Some(Transport::Sata)
+    } else if has("host") && has("target") {
+        Some(Transport::Scsi)
+    } else {
+        None
+    }
+}
+
+/// `E:` properties from udev's database entry for the block device in `sysfs_dir`.
+fn udev_properties(udev_data: &Path, sysfs_dir: &Path) -> HashMap<String, String> {
+    let Some(dev) = read_sysfs_attr(&sysfs_dir.join("dev")) else {
+        return HashMap::new();
+    };
+    fs::read_to_string(udev_data.join(format!("b{dev}")))
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| line.strip_prefix("E:")?.split_once('='))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+/// Links in `dir` that resolve to `target`, sorted.
+fn links_to(dir: &Path, target: &Path) -> Vec<String> {
+    let (Ok(entries), Ok(target)) = (fs::read_dir(dir), fs::canonicalize(target)) else {
+        return Vec::new();
+    };
+    let mut links: Vec<String> = entries
+        .flatten()
+        .filter(|entry| fs::canonicalize(entry.path()).is_ok_and(|resolved| resolved == target))
+        .map(|entry| entry.path().display().to_string())
+        .collect();
+    links.sort();
+    links
+}
+
+/// Trimmed sysfs attribute; `None` when missing or empty.
+fn read_sysfs_attr(path: &Path) -> Option<String> {
+    let value = fs::read_to_string(path).ok()?;
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+fn read_sysfs_number(path: &Path) -> Option<u64> {
+    read_sysfs_attr(path)?.parse().ok()
+}
+
 #[cfg(test)]
 mod validation_tests {
     use super::*;
@@ -361,6 +1394,7 @@ mod validation_tests {
     fn validate_requires_dev_prefix() {
         assert!(validate_block_device_path("sdb").is_err());
         assert!(validate_block_device_path("/dev/sdb1").is_err());
+        assert!(validate_block_device_path("/dev/disk/by-id/usb-Missing_Stick-0:0").is_err());
     }
 
     #[test]
@@ -406,222 +1440,249 @@ mod validation_tests {
         assert!(mounts.is_empty());
     }
 
-    fn busy_mounts_from_lines(contents: &str, device_path: &str) -> Result<Vec<(String, String)>, String> {
-        let target_whole = whole_disk_path(device_path)?;
-        let mut mounts = Vec::new();
-        for line in contents.lines() {
-            let mut parts = line.split_whitespace();
-            let Some(source) = parts.next() else {
-                continue;
-            };
-            let Some(mount_point) = parts.next() else {
-                continue;
-            };
-            if !source.starts_with("/dev/") {
-                continue;
-            }
-            let source_whole = whole_disk_path_from_source(source)?;
-            if source_whole == target_whole {
-                mounts.push((source.to_string(), mount_point.to_string()));
-            }
-        }
-        Ok(mounts)
+    #[test]
+    fn partition_busy_ignores_sibling_partitions() {
+        let proc_mounts = "/dev/sdb1 /mnt/boot vfat rw 0 0\n/dev/nvme0n1p2 / ext4 rw 0 0\n";
+        assert!(busy_mounts_in(proc_mounts, "/dev/sdb2").unwrap().is_empty());
+        assert_eq!(busy_mounts_in(proc_mounts, "/dev/sdb1").unwrap().len(), 1);
+        assert_eq!(busy_mounts_in(proc_mounts, "/dev/sdb").unwrap().len(), 1);
     }
-}
 
-/// I/O buffer sizes (bytes) used by Lithographer legacy logic — pick based on device capacity.
-const IO_BLOCK_SIZES: [usize; 14] = [
-    4096, 8192, 16384, 32768, 65536, 131072, 262144, 524288, 1048576, 2097152, 4194304, 8388608,
-    16777216, 33554432,
-];
+    #[test]
+    fn busy_holders_lists_nested_mounts_first_then_swap() {
+        let proc_mounts = "/dev/sdb2 /mnt/root ext4 rw 0 0\n\
+                           /dev/sdb1 /mnt/root/boot vfat rw 0 0\n\
+                           /dev/sdc1 /mnt/other\\040disk ext4 rw 0 0\n";
+        let proc_swaps = "Filename\tType\tSize\tUsed\tPriority\n\
+                          /dev/sdb3 partition 1048572 0 -2\n\
+                          /dev/nvme0n1p4 partition 8388604 0 -3\n";
+        let holders = busy_holders_in(proc_mounts, proc_swaps, "/dev/sdb").unwrap();
+        assert_eq!(
+            holders.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            [
+                "unmount /dev/sdb1 from /mnt/root/boot",
+                "unmount /dev/sdb2 from /mnt/root",
+                "swapoff /dev/sdb3",
+            ]
+        );
+        assert_eq!(
+            busy_holders_in(proc_mounts, proc_swaps, "/dev/sdb3").unwrap(),
+            [ReleaseAction::Swapoff {
+                source: "/dev/sdb3".to_string()
+            }]
+        );
+        assert_eq!(
+            busy_holders_in(proc_mounts, "", "/dev/sdc").unwrap(),
+            [ReleaseAction::Unmount {
+                source: "/dev/sdc1".to_string(),
+                mount_point: "/mnt/other disk".to_string()
+            }]
+        );
+    }
 
-const DEFAULT_IO_BLOCK_SIZE: usize = IO_BLOCK_SIZES[0];
+    #[test]
+    fn usb_device_dir_skips_interfaces_and_root_hub() {
+        let root = tempfile::tempdir().unwrap();
+        let hub = root.path().join("usb1");
+        let device = hub.join("1-2");
+        let interface = device.join("1-2:1.0");
+        let block = interface.join("host3/target3:0:0/3:0:0:0/block/sdb");
+        fs::create_dir_all(&block).unwrap();
+        for dir in [&hub, &device] {
+            fs::write(dir.join("authorized"), "1\n").unwrap();
+            fs::write(dir.join("idVendor"), "0781\n").unwrap();
+        }
+        fs::write(interface.join("authorized"), "1\n").unwrap();
 
-/// Pick I/O buffer size from device capacity (`DeviceInfo.size` — 512-byte sectors).
-///
-/// Matches the historical Lithographer `execute` logic: find the smallest table entry
-/// that is >= `size_sectors`, clamped to the table range.
-pub fn optimal_io_block_size_from_sectors(size_sectors: u64) -> usize {
-    if size_sectors > IO_BLOCK_SIZES[13] as u64 {
-        return IO_BLOCK_SIZES[13];
-    }
-    if size_sectors < IO_BLOCK_SIZES[0] as u64 {
-        return IO_BLOCK_SIZES[0];
+        assert_eq!(usb_device_dir(&block), Some(device));
+        assert_eq!(usb_device_dir(&root.path().join("pci0000:00/nvme0n1")), None);
     }
 
-    for &block in &IO_BLOCK_SIZES {
-        if size_sectors <= block as u64 {
-            return block;
+    #[test]
+    fn partitions_in_sysfs_sorts_by_partition_number() {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, number) in [("sdb10", "10"), ("sdb2", "2"), ("sdb1", "1")] {
+            fs::create_dir(dir.path().join(name)).unwrap();
+            fs::write(dir.path().join(name).join("partition"), format!("{number}\n")).unwrap();
         }
+        fs::create_dir(dir.path().join("queue")).unwrap();
+        assert_eq!(
+            partitions_in_sysfs(dir.path()),
+            ["/dev/sdb1", "/dev/sdb2", "/dev/sdb10"]
+        );
+        assert!(partitions_in_sysfs(&dir.path().join("missing")).is_empty());
     }
 
-    IO_BLOCK_SIZES[13]
-}
-
-/// Resolve block size for a device path via sysfs sector count.
-pub fn optimal_io_block_size(device_path: &str) -> usize {
-    device_size_sectors(device_path)
-        .map(optimal_io_block_size_from_sectors)
-        .unwrap_or(DEFAULT_IO_BLOCK_SIZE)
-}
+    #[test]
+    fn device_info_reads_sysfs_udev_and_mounts() {
+        let fixture = fixtures::usb_stick();
+        let mut image = crate::partitions::test_images::mbr_disk(4096, &[(0x0C, 2048, 2048)]);
+        image[2048 * 512..][..4096]
+            .copy_from_slice(&crate::filesystems::test_images::fat32("BOOT"));
+        fs::write(fixture.view.dev().join("sdb"), image).unwrap();
+        assert!(get_storage_devices_in(&fixture.view)
+            .unwrap()
+            .iter()
+            .all(|device| device.partition_table.is_none()));
+        let devices = storage_devices_in(&fixture.view, true).unwrap();
+        assert_eq!(devices.len(), 2);
+        let device = &devices[0];
+        assert_eq!(device.device_name, "/dev/sdb");
+        assert_eq!(device.vendor_name.trim(), "SanDisk");
+        assert_eq!(device.size_bytes, 60063744 * 512);
+        assert_eq!(device.serial.as_deref(), Some("4C530001"));
+        assert_eq!(device.wwn, None);
+        assert_eq!(device.transport, Some(Transport::Usb));
+        assert_eq!(
+            device.usb,
+            Some(UsbInfo {
+                vendor_id: "0781".to_string(),
+                product_id: "5581".to_string(),
+                speed_mbps: Some(5000.0),
+            })
+        );
+        assert!(!device.read_only);
+        assert_eq!(
+            (device.logical_sector_size, device.physical_sector_size),
+            (512, 4096)
+        );
+        assert_eq!(device.by_id.len(), 1);
+        assert!(device.by_id[0].ends_with("by-id/usb-SanDisk_Ultra_4C53-0:0"));
+        assert_eq!(
+            device.partitions,
+            [PartitionInfo {
+                path: "/dev/sdb1".to_string(),
+                number: 1,
+                size_bytes: 2048 * 512,
+                fs_type: Some("vfat".to_string()),
+                label: Some("BOOT".to_string()),
+                uuid: Some("1234-ABCD".to_string()),
+                mountpoint: Some("/media/me/BOOT DISK".to_string()),
+            }]
+        );
+        assert_eq!(
+            device.partition_table.as_ref().map(|table| table.summary()),
+            Some("1 partition: FAT32 BOOT 1M".to_string())
+        );
+    }
 
-/// Returns device size in 512-byte sectors from `/sys/block/<name>/size`.
-pub fn device_size_sectors(device_path: &str) -> Option<u64> {
-    let block_name = Path::new(device_path)
-        .file_name()
-        .and_then(|n| n.to_str())?;
-    let size_path = format!("/sys/block/{block_name}/size");
-    fs::read_to_string(size_path)
-        .ok()?
-        .trim()
-        .parse()
-        .ok()
-}
+    #[test]
+    fn stable_links_resolve_to_dev_nodes_only() {
+        let fixture = fixtures::usb_stick();
+        let dev = fixture.view.dev();
+        let link = |target: &str, name: &str| {
+            fixture.symlink(target, &format!("dev/disk/{name}"));
+        };
+        link("../../sdb1", "by-uuid/1234-ABCD");
+        link(
+            "../../sdc",
+            "by-path/pci-0000:00:14.0-usb-0:2:1.0-scsi-0:0:0:0",
+        );
+        link(fixture.root().to_str().unwrap(), "by-id/escape");
+        link("../../sdb", "by-foo/sdb");
+        let resolve = |path: &str| resolve_device_link_in(&dev, path);
 
-/// Returns device size in bytes from `/sys/block/<name>/size` (512-byte sectors).
-pub fn device_size_bytes(device_path: &str) -> Option<u64> {
-    device_size_sectors(device_path)
-        .map(|sectors| sectors.saturating_mul(512))
-}
+        assert_eq!(
+            resolve("/dev/disk/by-id/usb-SanDisk_Ultra_4C53-0:0").as_deref(),
+            Ok("/dev/sdb")
+        );
+        assert_eq!(
+            resolve(" /dev/disk/by-uuid/1234-ABCD ").as_deref(),
+            Ok("/dev/sdb1")
+        );
+        assert_eq!(resolve(" /dev/sdb ").as_deref(), Ok("/dev/sdb"));
+        // Dangling (device unplugged), outside /dev, or not a stable-name directory.
+        assert!(resolve("/dev/disk/by-path/pci-0000:00:14.0-usb-0:2:1.0-scsi-0:0:0:0").is_err());
+        assert!(resolve("/dev/disk/by-id/escape").is_err());
+        assert!(resolve("/dev/disk/by-id/../../../etc").is_err());
+        assert!(resolve("/dev/disk/by-foo/sdb").is_err());
+    }
 
-pub fn is_removable_device(device_path: &str) -> Result<bool> {
-    // Extract device name from the device path
-    let device_name = Path::new(device_path)
-        .file_name()
-        .context("Invalid device path")?
-        .to_str()
-        .context("Non UTF-8 device name")?;
+    #[test]
+    fn device_report_follows_links_and_collects_holders_and_mounts() {
+        let fixture = fixtures::usb_stick();
+        let view = &fixture.view;
+        fs::create_dir_all(view.sys_block().join("sdb/sdb1/holders/dm-0")).unwrap();
+        let by_id = "/dev/disk/by-id/usb-SanDisk_Ultra_4C53-0:0";
 
-    // Construct the path to the removable file
-    let removable_path = format!("/sys/block/{}/removable", device_name);
-    debug!("Checking removable path: {}", removable_path);
+        let report = device_report_in(view, by_id).unwrap();
+        assert_eq!(report.requested, by_id);
+        assert_eq!(report.path, "/dev/sdb");
+        assert_eq!(report.disk.serial.as_deref(), Some("4C530001"));
+        assert_eq!(report.partition, None);
+        assert_eq!(report.holders, ["/dev/dm-0"]);
+        assert_eq!(
+            report.in_use,
+            [ReleaseAction::Unmount {
+                source: "/dev/sdb1".to_string(),
+                mount_point: "/media/me/BOOT DISK".to_string(),
+            }]
+        );
+        assert!(!report.safe_for_io);
+        assert_eq!(
+            report.refusal.as_deref(),
+            Some("Refusing /dev/sdb: device is in use (/dev/sdb1 on /media/me/BOOT DISK)")
+        );
 
-    // Read the contents of the removable file
-    let mut file =
-        fs::File::open(&removable_path).context(format!("Failed to open {}", removable_path))?;
-    let mut contents = String::new();
-    file.read_to_string(&mut contents)
-        .context("Failed to read removable file")?;
+        fixture.mounts("/dev/vda1 / ext4 rw 0 0\n");
+        let report = device_report_in(view, &format!("{by_id}-part1")).unwrap();
+        assert_eq!(report.path, "/dev/sdb1");
+        assert_eq!(report.disk.device_name, "/dev/sdb");
+        assert_eq!(report.partition.unwrap().label.as_deref(), Some("BOOT"));
+        assert!(report.safe_for_io);
 
-    // Check if the device is removable
-    Ok(contents.trim() == "1")
-}
+        let report = device_report_in(view, "/dev/vda1").unwrap();
+        assert!(report.refusal.unwrap().contains("system disk"));
 
-pub fn get_storage_devices() -> Result<Vec<DeviceInfo>> {
-    let paths = fs::read_dir("/sys/block/").context("Failed to read /sys/block/ directory")?;
-    let mut devices: Vec<DeviceInfo> = Vec::new();
-
-    for path in paths {
-        let p = path.context("Failed to read directory entry")?;
-        let mut dev = p.path().clone();
-        let device_end_name = match p.path().clone().file_name() {
-            Some(device) => match device.to_str() {
-                Some(dev) => dev.to_string(),
-                None => {
-                    warn!("Could not convert device name to string");
-                    continue;
-                }
-            },
-            None => {
-                warn!("Could not get device name");
-                continue;
-            }
-        };
+        assert!(device_report_in(view, "/dev/sdz").is_err());
+        assert!(device_report_in(view, "sdb").is_err());
+    }
 
-        dev.push("device");
-        if dev.exists() {
-            dev.push("vendor");
-            let vendor_name_file = match dev.clone().into_os_string().to_str() {
-                Some(name) => name.to_string(),
-                None => {
-                    warn!("Could not convert vendor path to string");
-                    String::new()
-                }
-            };
+    #[test]
+    fn transport_follows_the_sysfs_device_path() {
+        let cases = [
+            ("/sys/devices/pci0000:00/0000:00:14.0/usb2/2-1/2-1:1.0/host6/target6:0:0/6:0:0:0/block/sdb", Some(Transport::Usb)),
+            ("/sys/devices/pci0000:00/0000:00:1d.0/0000:3d:00.0/nvme/nvme0/nvme0n1", Some(Transport::Nvme)),
+            ("/sys/devices/platform/soc/fe340000.mmc/mmc_host/mmc0/mmc0:0001/block/mmcblk0", Some(Transport::Mmc)),
+            ("/sys/devices/pci0000:00/0000:00:02.0/virtio1/block/vda", Some(Transport::Virtio)),
+            ("/sys/devices/pci0000:00/0000:00:17.0/ata1/host0/target0:0:0/0:0:0:0/block/sda", Some(Transport::Sata)),
+            ("/sys/devices/virtual/block/loop0", None),
+        ];
+        for (path, transport) in cases {
+            assert_eq!(transport_from_sysfs_path(path), transport, "{path}");
+        }
+    }
 
-            let mut dev_vendor_name = String::new();
-            if !vendor_name_file.is_empty() {
-                dev_vendor_name = match get_file_content(vendor_name_file) {
-                    Ok(name) => name.replace('\n', ""),
-                    Err(e) => {
-                        warn!("Failed to read vendor name: {}", e);
-                        String::new()
-                    }
-                };
-            }
+    #[test]
+    fn partition_path_appends_number() {
+        assert!(validate_partition_path("/dev/sdb").is_err());
+        assert!(validate_partition_path("/dev/loop0p1").is_err());
+        assert!(is_partition_path("/dev/mmcblk0p2"));
+        assert!(!is_partition_path("/dev/mmcblk0"));
+        assert_eq!(partition_path_unchecked("/dev/sdb", 2), "/dev/sdb2");
+        assert_eq!(partition_path_unchecked("/dev/mmcblk0", 1), "/dev/mmcblk0p1");
+        assert_eq!(partition_path_unchecked("/dev/nvme0n1", 3), "/dev/nvme0n1p3");
+    }
 
-            dev.pop();
-            dev.push("model");
-            let model_name_file = match dev.clone().into_os_string().to_str() {
-                Some(name) => name.to_string(),
-                None => String::new(),
+    fn busy_mounts_from_lines(contents: &str, device_path: &str) -> Result<Vec<(String, String)>, String> {
+        let target_whole = whole_disk_path(device_path)?;
+        let mut mounts = Vec::new();
+        for line in contents.lines() {
+            let mut parts = line.split_whitespace();
+            let Some(source) = parts.next() else {
+                continue;
             };
-
-            let model_name = match get_file_content(model_name_file) {
-                Ok(model) => model.replace('\n', ""),
-                Err(e) => {
-                    warn!("Failed to read model name: {}", e);
-                    String::new()
-                }
+            let Some(mount_point) = parts.next() else {
+                continue;
             };
-
-            dev.pop();
-            dev.pop();
-            dev.push("removable");
-            let mut removable = String::new();
-            if dev.exists() {
-                removable = match fs::read_to_string(dev.clone()) {
-                    Ok(removable) => removable,
-                    Err(e) => {
-                        warn!("Failed to read removable status: {}", e);
-                        String::new()
-                    }
-                }
+            if !source.starts_with("/dev/") {
+                continue;
             }
-
-            dev.pop();
-            dev.push("size");
-            let size: u64 = match fs::read_to_string(dev.clone()) {
-                Ok(size_str) => match size_str.trim().parse::<u64>() {
-                    Ok(s) => s,
-                    Err(e) => {
-                        warn!("Failed to parse size: {}", e);
-                        0
-                    }
-                },
-                Err(e) => {
-                    warn!("Failed to read size: {}", e);
-                    0
-                }
-            };
-
-            if !device_end_name.is_empty() {
-                let mut dev_path = PathBuf::from("/dev/");
-                dev_path.push(device_end_name);
-                if dev_path.exists() {
-                    if removable == "1\n" {
-                        let dev_info = DeviceInfo {
-                            device_name: dev_path.display().to_string(),
-                            vendor_name: dev_vendor_name,
-                            model_name,
-                            removable: 1,
-                            size,
-                        };
-                        devices.push(dev_info);
-                    } else if removable == "0\n" {
-                        let dev_info = DeviceInfo {
-                            device_name: dev_path.display().to_string(),
-                            vendor_name: dev_vendor_name,
-                            model_name,
-                            removable: 0,
-                            size,
-                        };
-                        devices.push(dev_info);
-                    }
-                }
+            let source_whole = whole_disk_path_from_source(source)?;
+            if source_whole == target_whole {
+                mounts.push((source.to_string(), mount_point.to_string()));
             }
         }
+        Ok(mounts)
     }
-    debug!("Found {} devices", devices.len());
-    Ok(devices)
 }
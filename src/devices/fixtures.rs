@@ -0,0 +1,446 @@
+//! Fixture trees for [`FakeSystem`]: sysfs, the udev database, `/dev` and the mount
+//! tables of typical machines, built in a temporary directory.
+
+use super::FakeSystem;
+use std::fs;
+use std::os::unix::fs::symlink;
+use std::path::Path;
+
+pub struct Fixture {
+    dir: tempfile::TempDir,
+    pub view: FakeSystem,
+}
+
+impl Fixture {
+    pub fn new() -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        for path in ["sys/block", "dev/disk/by-id", "run/udev/data", "proc"] {
+            fs::create_dir_all(dir.path().join(path)).unwrap();
+        }
+        fs::write(dir.path().join("proc/mounts"), "").unwrap();
+        let view = FakeSystem::new(dir.path());
+        Self { dir, view }
+    }
+
+    pub fn root(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Write `contents` to `path` (relative to the tree), creating its directories.
+    pub fn write(&self, path: &str, contents: &str) -> &Self {
+        let path = self.root().join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+        self
+    }
+
+    /// Link `link` (relative to the tree) to `target`, taken as is.
+    pub fn symlink(&self, target: &str, link: &str) -> &Self {
+        let link = self.root().join(link);
+        fs::create_dir_all(link.parent().unwrap()).unwrap();
+        symlink(target, link).unwrap();
+        self
+    }
+
+    /// Whole disk `name` with device number `dev` at `sys/devices/{parent}/{name}`,
+    /// linked from `sys/block` and with a `/dev` node. Returns its sysfs directory.
+    pub fn disk(
+        &self,
+        parent: &str,
+        name: &str,
+        dev: &str,
+        sectors: u64,
+        removable: bool,
+    ) -> String {
+        let dir = format!("sys/devices/{parent}/{name}");
+        self.write(&format!("{dir}/dev"), &format!("{dev}\n"))
+            .write(&format!("{dir}/size"), &format!("{sectors}\n"))
+            .write(
+                &format!("{dir}/removable"),
+                if removable { "1\n" } else { "0\n" },
+            )
+            .write(&format!("{dir}/ro"), "0\n")
+            .write(&format!("{dir}/queue/logical_block_size"), "512\n")
+            .write(&format!("{dir}/queue/physical_block_size"), "512\n")
+            .symlink(
+                &format!("../devices/{parent}/{name}"),
+                &format!("sys/block/{name}"),
+            )
+            .write(&format!("dev/{name}"), "");
+        fs::create_dir_all(self.root().join(&dir).join("device")).unwrap();
+        dir
+    }
+
+    /// Partition `number` of the disk whose sysfs directory is `disk_dir`.
+    pub fn partition(
+        &self,
+        disk_dir: &str,
+        name: &str,
+        number: u32,
+        dev: &str,
+        sectors: u64,
+    ) -> &Self {
+        self.write(
+            &format!("{disk_dir}/{name}/partition"),
+            &format!("{number}\n"),
+        )
+        .write(&format!("{disk_dir}/{name}/size"), &format!("{sectors}\n"))
+        .write(&format!("{disk_dir}/{name}/dev"), &format!("{dev}\n"))
+        .write(&format!("dev/{name}"), "")
+    }
+
+    /// Device-mapper device `name` (`dm-N`), known as `/dev/mapper/{mapper}`, on `slaves`.
+    pub fn dm(&self, name: &str, mapper: &str, dev: &str, slaves: &[&str]) -> &Self {
+        let dir = format!("sys/devices/virtual/block/{name}");
+        self.write(&format!("{dir}/dev"), &format!("{dev}\n"))
+            .write(&format!("{dir}/removable"), "0\n")
+            .write(&format!("{dir}/dm/name"), &format!("{mapper}\n"))
+            .symlink(
+                &format!("../devices/virtual/block/{name}"),
+                &format!("sys/block/{name}"),
+            )
+            .write(&format!("dev/{name}"), "")
+            .symlink(&format!("../{name}"), &format!("dev/mapper/{mapper}"));
+        for slave in slaves {
+            fs::create_dir_all(self.root().join(&dir).join("slaves").join(slave)).unwrap();
+        }
+        self
+    }
+
+    pub fn mounts(&self, mounts: &str) -> &Self {
+        self.write("proc/mounts", mounts)
+    }
+
+    pub fn swaps(&self, swaps: &str) -> &Self {
+        self.write(
+            "proc/swaps",
+            &format!("Filename\tType\tSize\tUsed\tPriority\n{swaps}"),
+        )
+    }
+
+    /// udev database entry of the block device numbered `dev`.
+    pub fn udev(&self, dev: &str, entry: &str) -> &Self {
+        self.write(&format!("run/udev/data/b{dev}"), entry)
+    }
+}
+
+const VIRTIO_ROOT: &str = "pci0000:00/0000:00:02.0/virtio1/block";
+
+/// A VM booted from `vda1` with a SanDisk USB stick at `sdb`, its vfat partition mounted.
+pub fn usb_stick() -> Fixture {
+    let fixture = Fixture::new();
+    let vda = fixture.disk(VIRTIO_ROOT, "vda", "253:0", 41943040, false);
+    fixture.partition(&vda, "vda1", 1, "253:1", 41940992);
+
+    let usb = "pci0000:00/0000:00:14.0/usb2/2-1";
+    fixture
+        .write(&format!("sys/devices/{usb}/idVendor"), "0781\n")
+        .write(&format!("sys/devices/{usb}/idProduct"), "5581\n")
+        .write(&format!("sys/devices/{usb}/speed"), "5000\n")
+        .write(&format!("sys/devices/{usb}/authorized"), "1\n");
+    let sdb = fixture.disk(
+        &format!("{usb}/2-1:1.0/host6/target6:0:0/6:0:0:0/block"),
+        "sdb",
+        "8:16",
+        60063744,
+        true,
+    );
+    fixture
+        .write(&format!("{sdb}/device/vendor"), "SanDisk \n")
+        .write(&format!("{sdb}/device/model"), "Ultra           \n")
+        .write(&format!("{sdb}/queue/physical_block_size"), "4096\n")
+        .partition(&sdb, "sdb1", 1, "8:17", 2048)
+        .udev(
+            "8:16",
+            "S:disk/by-id/usb-SanDisk_Ultra_4C53-0:0\nE:ID_SERIAL_SHORT=4C530001\nE:ID_BUS=usb\n",
+        )
+        .udev(
+            "8:17",
+            "E:ID_FS_TYPE=vfat\nE:ID_FS_LABEL=BOOT\nE:ID_FS_UUID=1234-ABCD\n",
+        )
+        .symlink("../../sdb", "dev/disk/by-id/usb-SanDisk_Ultra_4C53-0:0")
+        .symlink(
+            "../../sdb1",
+            "dev/disk/by-id/usb-SanDisk_Ultra_4C53-0:0-part1",
+        )
+        .mounts(
+            "/dev/vda1 / ext4 rw,relatime 0 0\n\
+             /dev/sdb1 /media/me/BOOT\\040DISK vfat rw 0 0\n",
+        );
+    fixture
+}
+
+const NVME: &str = "pci0000:00/0000:00:1d.0/0000:3d:00.0/nvme/nvme0";
+
+/// A laptop with its system on an NVMe SSD: EFI, root and swap partitions.
+pub fn nvme_laptop() -> Fixture {
+    let fixture = Fixture::new();
+    let nvme = fixture.disk(NVME, "nvme0n1", "259:0", 1953525168, false);
+    fixture
+        .write(&format!("{nvme}/device/model"), "Samsung SSD 980 PRO 1TB\n")
+        .write(&format!("{nvme}/wwid"), "eui.002538b211b0e5a1\n")
+        .partition(&nvme, "nvme0n1p1", 1, "259:1", 1048576)
+        .partition(&nvme, "nvme0n1p2", 2, "259:2", 1919969280)
+        .partition(&nvme, "nvme0n1p3", 3, "259:3", 32505856)
+        .mounts(
+            "/dev/nvme0n1p2 / btrfs rw,subvol=/@ 0 0\n\
+             /dev/nvme0n1p1 /boot/efi vfat rw 0 0\n",
+        )
+        .swaps("/dev/nvme0n1p3 partition 16252924 0 -2\n");
+    fixture
+}
+
+/// A desktop booted from SATA `sda2`, with an SD card in a PCIe card reader.
+pub fn sd_card_reader() -> Fixture {
+    let fixture = Fixture::new();
+    let sda = fixture.disk(
+        "pci0000:00/0000:00:17.0/ata1/host0/target0:0:0/0:0:0:0/block",
+        "sda",
+        "8:0",
+        500118192,
+        false,
+    );
+    fixture
+        .write(&format!("{sda}/device/vendor"), "ATA     \n")
+        .write(&format!("{sda}/device/model"), "WDC WDS500G2B0A\n")
+        .partition(&sda, "sda1", 1, "8:1", 1048576)
+        .partition(&sda, "sda2", 2, "8:2", 499067904);
+    let mmc = fixture.disk(
+        "pci0000:00/0000:00:1c.0/0000:02:00.0/rtsx_pci_sdmmc.0/mmc_host/mmc0/mmc0:aaaa/block",
+        "mmcblk0",
+        "179:0",
+        62333952,
+        false,
+    );
+    fixture
+        .write(&format!("{mmc}/device/name"), "SD32G\n")
+        .write(&format!("{mmc}/queue/discard_max_bytes"), "2199023255040\n")
+        .write(&format!("{mmc}/queue/discard_granularity"), "4194304\n")
+        .partition(&mmc, "mmcblk0p1", 1, "179:1", 524288)
+        .partition(&mmc, "mmcblk0p2", 2, "179:2", 61800448)
+        .udev("179:1", "E:ID_FS_TYPE=vfat\nE:ID_FS_LABEL=bootfs\n")
+        .udev("179:2", "E:ID_FS_TYPE=ext4\nE:ID_FS_LABEL=rootfs\n")
+        .mounts(
+            "/dev/sda2 / ext4 rw,relatime 0 0\n\
+             /dev/sda1 /boot/efi vfat rw 0 0\n",
+        );
+    fixture
+}
+
+/// A laptop whose root is an LVM volume inside LUKS on `nvme0n1p3`
+/// (`nvme0n1p3` → `dm-0` luks → `dm-1` vg-root, `dm-2` vg-swap), plus a USB stick.
+pub fn lvm_on_luks() -> Fixture {
+    let fixture = Fixture::new();
+    let nvme = fixture.disk(NVME, "nvme0n1", "259:0", 1000215216, false);
+    fixture
+        .write(&format!("{nvme}/device/model"), "WD PC SN740 512GB\n")
+        .partition(&nvme, "nvme0n1p1", 1, "259:1", 1228800)
+        .partition(&nvme, "nvme0n1p2", 2, "259:2", 4194304)
+        .partition(&nvme, "nvme0n1p3", 3, "259:3", 994789376)
+        .dm("dm-0", "luks-6c4b0d37", "253:0", &["nvme0n1p3"])
+        .dm("dm-1", "vg-root", "253:1", &["dm-0"])
+        .dm("dm-2", "vg-swap", "253:2", &["dm-0"]);
+    let sdb = fixture.disk(
+        "pci0000:00/0000:00:14.0/usb3/3-2/3-2:1.0/host1/target1:0:0/1:0:0:0/block",
+        "sdb",
+        "8:16",
+        30310400,
+        true,
+    );
+    fixture
+        .write(&format!("{sdb}/device/vendor"), "Kingston\n")
+        .write(&format!("{sdb}/device/model"), "DataTraveler 3.0\n")
+        .partition(&sdb, "sdb1", 1, "8:17", 30308352)
+        .mounts(
+            "/dev/mapper/vg-root / ext4 rw,relatime 0 0\n\
+             /dev/nvme0n1p2 /boot ext4 rw,relatime 0 0\n\
+             /dev/nvme0n1p1 /boot/efi vfat rw 0 0\n",
+        )
+        .swaps("/dev/dm-2 partition 8388604 0 -2\n");
+    fixture
+}
+
+mod tests {
+    use super::*;
+    use crate::devices::*;
+
+    fn names(devices: &[DeviceInfo]) -> Vec<&str> {
+        devices.iter().map(|d| d.device_name.as_str()).collect()
+    }
+
+    #[test]
+    fn nvme_system_disk_is_listed_but_refused() {
+        let fixture = nvme_laptop();
+        let view = &fixture.view;
+        let devices = get_storage_devices_in(view).unwrap();
+        assert_eq!(names(&devices), ["/dev/nvme0n1"]);
+        let nvme = &devices[0];
+        assert_eq!(nvme.transport, Some(Transport::Nvme));
+        assert_eq!(nvme.model_name, "Samsung SSD 980 PRO 1TB");
+        assert_eq!(nvme.wwn.as_deref(), Some("eui.002538b211b0e5a1"));
+        assert_eq!(nvme.removable, 0);
+        assert_eq!(
+            nvme.partitions
+                .iter()
+                .map(|p| p.mountpoint.as_deref())
+                .collect::<Vec<_>>(),
+            [Some("/boot/efi"), Some("/"), None]
+        );
+
+        assert_eq!(
+            device_safe_for_io_in(view, "/dev/nvme0n1"),
+            Err("Refusing /dev/nvme0n1: it is the system disk (root filesystem is on /dev/nvme0n1p2)".to_string())
+        );
+        assert!(target_safe_for_io_in(view, "/dev/nvme0n1p3").is_err());
+        assert_eq!(
+            busy_holders(view, "/dev/nvme0n1p3").unwrap(),
+            [ReleaseAction::Swapoff {
+                source: "/dev/nvme0n1p3".to_string()
+            }]
+        );
+        assert_eq!(
+            device_size_sectors_in(view, "/dev/nvme0n1p1"),
+            Some(1048576)
+        );
+        assert_eq!(
+            disk_partitions_in(view, "/dev/nvme0n1"),
+            ["/dev/nvme0n1p1", "/dev/nvme0n1p2", "/dev/nvme0n1p3"]
+        );
+    }
+
+    #[test]
+    fn sd_card_in_reader_is_accepted() {
+        let fixture = sd_card_reader();
+        let view = &fixture.view;
+        let devices = get_storage_devices_in(view).unwrap();
+        assert_eq!(names(&devices), ["/dev/mmcblk0", "/dev/sda"]);
+        assert_eq!(devices[0].transport, Some(Transport::Mmc));
+        assert_eq!(devices[1].transport, Some(Transport::Sata));
+        assert_eq!(
+            devices[0]
+                .partitions
+                .iter()
+                .map(|p| p.label.as_deref())
+                .collect::<Vec<_>>(),
+            [Some("bootfs"), Some("rootfs")]
+        );
+
+        assert_eq!(device_safe_for_io_in(view, "/dev/mmcblk0"), Ok(()));
+        assert_eq!(target_safe_for_io_in(view, "/dev/mmcblk0p2"), Ok(()));
+        assert!(target_safe_for_io_in(view, "/dev/mmcblk0p3").is_err());
+        assert!(device_safe_for_io_in(view, "/dev/sda")
+            .unwrap_err()
+            .contains("system disk"));
+        assert!(!removable_in(view, "/dev/mmcblk0").unwrap());
+        assert_eq!(
+            discard_support_in(view, "/dev/mmcblk0p1"),
+            Some(DiscardSupport {
+                max_bytes: 2199023255040,
+                granularity: 4194304,
+                write_zeroes_max_bytes: 0,
+            })
+        );
+        assert_eq!(discard_support_in(view, "/dev/sda"), None);
+        assert_eq!(logical_sector_size_in(view, "/dev/mmcblk0p2"), Some(512));
+    }
+
+    #[test]
+    fn usb_stick_is_busy_until_unmounted() {
+        let fixture = usb_stick();
+        let view = &fixture.view;
+        assert!(removable_in(view, "/dev/disk/by-id/usb-SanDisk_Ultra_4C53-0:0").unwrap());
+        assert_eq!(
+            device_safe_for_io_in(view, "/dev/sdb"),
+            Err(
+                "Refusing /dev/sdb: device is in use (/dev/sdb1 on /media/me/BOOT DISK)"
+                    .to_string()
+            )
+        );
+        fixture.mounts("/dev/vda1 / ext4 rw,relatime 0 0\n");
+        assert_eq!(device_safe_for_io_in(view, "/dev/sdb"), Ok(()));
+        assert!(device_safe_for_io_in(view, "/dev/vda")
+            .unwrap_err()
+            .contains("system disk"));
+        assert_eq!(
+            device_safe_for_io_in(view, "/dev/sdc"),
+            Err("Device path does not exist: /dev/sdc".to_string())
+        );
+    }
+
+    #[test]
+    fn lvm_on_luks_root_refuses_the_disk_below() {
+        let fixture = lvm_on_luks();
+        let view = &fixture.view;
+        assert_eq!(root_filesystem_sources(view).unwrap(), ["/dev/nvme0n1p3"]);
+
+        let devices = get_storage_devices_in(view).unwrap();
+        assert_eq!(names(&devices), ["/dev/nvme0n1", "/dev/sdb"]);
+        assert_eq!(devices[1].transport, Some(Transport::Usb));
+        assert_eq!(devices[1].vendor_name, "Kingston");
+
+        assert_eq!(
+            device_safe_for_io_in(view, "/dev/nvme0n1"),
+            Err("Refusing /dev/nvme0n1: it is the system disk (root filesystem is on /dev/nvme0n1p3)".to_string())
+        );
+        assert_eq!(device_safe_for_io_in(view, "/dev/sdb"), Ok(()));
+        assert_eq!(
+            device_safe_for_io_in(view, "/dev/dm-1"),
+            Err("Device type is not allowed for flash/clone: /dev/dm-1".to_string())
+        );
+    }
+
+    #[test]
+    fn policy_file_is_enforced_with_the_rule_named() {
+        let fixture = sd_card_reader();
+        let view = &fixture.view;
+        fixture.write(
+            "etc/litho/policy.toml",
+            "[[allow]]\nname = \"removable USB\"\ntransport = \"usb\"\n",
+        );
+        assert_eq!(
+            device_safe_for_io_in(view, "/dev/mmcblk0"),
+            Err(format!(
+                "Refusing /dev/mmcblk0: no allow rule in {} matches it (rule \"removable USB\")",
+                view.policy_file().display()
+            ))
+        );
+        assert!(target_safe_for_io_in(view, "/dev/mmcblk0p1")
+            .unwrap_err()
+            .starts_with("Refusing /dev/mmcblk0p1: no allow rule"));
+
+        fixture.write(
+            "etc/litho/policy.toml",
+            "[[deny]]\ntransport = \"nvme\"\n[[allow]]\ntransport = [\"usb\", \"mmc\"]\n",
+        );
+        assert_eq!(device_safe_for_io_in(view, "/dev/mmcblk0"), Ok(()));
+
+        fixture.write("etc/litho/policy.toml", "[[allow]]\nmodle = \"SD32G\"\n");
+        assert!(device_safe_for_io_in(view, "/dev/mmcblk0")
+            .unwrap_err()
+            .starts_with("Refusing /dev/mmcblk0: invalid device policy:"));
+    }
+
+    #[test]
+    fn write_protected_card_is_refused_for_writing_only() {
+        let fixture = sd_card_reader();
+        let view = &fixture.view;
+        assert_eq!(writable_in(view, "/dev/mmcblk0"), Ok(()));
+
+        fixture.write("sys/block/mmcblk0/ro", "1\n");
+        for path in ["/dev/mmcblk0", "/dev/mmcblk0p2"] {
+            assert_eq!(
+                writable_in(view, path),
+                Err(format!(
+                    "Refusing {path}: device is write-protected (lock switch on the card or \
+                     adapter, or the kernel's read-only flag)"
+                ))
+            );
+            assert_eq!(target_safe_for_io_in(view, path), Ok(()));
+            assert!(device_report_in(view, path).unwrap().write_protected);
+        }
+        assert!(get_storage_devices_in(view)
+            .unwrap()
+            .iter()
+            .any(|device| device.device_name == "/dev/mmcblk0" && device.read_only));
+    }
+}
@@ -0,0 +1,429 @@
+//! Site policy for the devices flash, clone and wipe may write to
+//! (`/etc/litho/policy.toml`):
+//!
+//! ```toml
+//! max_size = "256G"
+//!
+//! [[deny]]
+//! name = "never NVMe"
+//! transport = "nvme"
+//!
+//! [[allow]]
+//! name = "removable USB"
+//! transport = "usb"
+//! removable = true
+//! ```
+//!
+//! A device is refused when it is larger than `max_size`, when any `[[deny]]` rule
+//! matches it, or when there are `[[allow]]` rules and none of them matches. A rule
+//! matches when all of its conditions hold; a list (`vendor = ["SanDisk", "Kingston"]`)
+//! matches any of its entries. Unknown keys are an error, so a typo cannot quietly
+//! widen the policy.
+
+use super::{DeviceInfo, Transport};
+use crate::range::parse_byte_size;
+use anyhow::{anyhow, bail, Context, Result};
+use serde::de::{self, IntoDeserializer, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+use std::fmt;
+use std::fs;
+use std::io::ErrorKind;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+/// Rules loaded from a policy file.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Policy {
+    #[serde(skip)]
+    source: PathBuf,
+    max_size: Option<Size>,
+    #[serde(default)]
+    deny: Vec<PolicyRule>,
+    #[serde(default)]
+    allow: Vec<PolicyRule>,
+}
+
+/// One `[[allow]]` or `[[deny]]` table. List conditions also accept a single value.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyRule {
+    /// Shown when the rule decides; defaults to its conditions.
+    pub name: Option<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub transport: Vec<Transport>,
+    /// Compared case-insensitively with the sysfs vendor, ignoring padding.
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub vendor: Vec<String>,
+    /// Compared case-insensitively with the sysfs model, ignoring padding.
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub model: Vec<String>,
+    /// Compared with the disk serial, ignoring padding.
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub serial: Vec<String>,
+    pub removable: Option<bool>,
+    pub min_size: Option<Size>,
+    pub max_size: Option<Size>,
+}
+
+/// A size setting: bytes, or a string such as `"256G"`, kept as written for messages.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "SizeSetting")]
+pub struct Size {
+    pub bytes: u64,
+    text: String,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SizeSetting {
+    Bytes(u64),
+    Text(String),
+}
+
+impl TryFrom<SizeSetting> for Size {
+    type Error = String;
+
+    fn try_from(setting: SizeSetting) -> Result<Self, String> {
+        Ok(match setting {
+            SizeSetting::Bytes(bytes) => Self {
+                bytes,
+                text: bytes.to_string(),
+            },
+            SizeSetting::Text(text) => Self {
+                bytes: parse_byte_size(&text)?,
+                text: format!("{text:?}"),
+            },
+        })
+    }
+}
+
+impl fmt::Display for Size {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// A string or a non-empty list of strings, as a list of `T`.
+fn one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    struct OneOrMany<T>(PhantomData<T>);
+
+    impl<'de, T: Deserialize<'de>> Visitor<'de> for OneOrMany<T> {
+        type Value = Vec<T>;
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("a string or a list of strings")
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<Vec<T>, E> {
+            T::deserialize(value.into_deserializer()).map(|item| vec![item])
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<T>, A::Error> {
+            let mut items = Vec::new();
+            while let Some(item) = seq.next_element()? {
+                items.push(item);
+            }
+            if items.is_empty() {
+                return Err(de::Error::custom("empty list"));
+            }
+            Ok(items)
+        }
+    }
+
+    deserializer.deserialize_any(OneOrMany(PhantomData))
+}
+
+impl Policy {
+    /// The policy in `path`; `None` when the file does not exist.
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        match fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text, path).map(Some),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => {
+                Err(e).with_context(|| format!("Failed to read device policy {}", path.display()))
+            }
+        }
+    }
+
+    /// Parse policy `text`; `source` names the file in messages.
+    pub fn parse(text: &str, source: &Path) -> Result<Self> {
+        let mut policy: Self = toml::from_str(text).map_err(|e| match e.span() {
+            Some(span) => {
+                let line = text[..span.start].matches('\n').count() + 1;
+                anyhow!("{}:{}: {}", source.display(), line, e.message())
+            }
+            None => anyhow!("{}: {}", source.display(), e.message()),
+        })?;
+        policy.source = source.to_path_buf();
+        for (kind, rules) in [("deny", &policy.deny), ("allow", &policy.allow)] {
+            if let Some(index) = rules.iter().position(|rule| rule.conditions().is_empty()) {
+                bail!(
+                    "{}: [[{}]] rule {} has no conditions",
+                    source.display(),
+                    kind,
+                    index + 1
+                );
+            }
+        }
+        Ok(policy)
+    }
+
+    /// The file the policy was read from.
+    pub fn source(&self) -> &Path {
+        &self.source
+    }
+
+    /// Whether `device` may be written to; otherwise which rule refuses it.
+    pub fn check(&self, device: &DeviceInfo) -> Result<(), String> {
+        let source = self.source.display();
+        if let Some(max) = &self.max_size {
+            if device.size_bytes > max.bytes {
+                return Err(format!(
+                    "it is larger than max_size = {} in {source} ({} bytes)",
+                    max, device.size_bytes
+                ));
+            }
+        }
+        if let Some((index, rule)) = self
+            .deny
+            .iter()
+            .enumerate()
+            .find(|(_, rule)| rule.matches(device))
+        {
+            return Err(format!(
+                "denied by {} in {source}",
+                rule.label("deny", index)
+            ));
+        }
+        if !self.allow.is_empty() && !self.allow.iter().any(|rule| rule.matches(device)) {
+            let allowed: Vec<String> = self
+                .allow
+                .iter()
+                .enumerate()
+                .map(|(index, rule)| rule.label("allow", index))
+                .collect();
+            return Err(format!(
+                "no allow rule in {source} matches it ({})",
+                allowed.join("; ")
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl PolicyRule {
+    pub fn matches(&self, device: &DeviceInfo) -> bool {
+        let any_text = |wanted: &[String], actual: &str| {
+            wanted.is_empty()
+                || wanted
+                    .iter()
+                    .any(|entry| entry.trim().eq_ignore_ascii_case(actual.trim()))
+        };
+        (self.transport.is_empty()
+            || device
+                .transport
+                .is_some_and(|transport| self.transport.contains(&transport)))
+            && any_text(&self.vendor, &device.vendor_name)
+            && any_text(&self.model, &device.model_name)
+            && (self.serial.is_empty()
+                || device.serial.as_ref().is_some_and(|serial| {
+                    self.serial
+                        .iter()
+                        .any(|entry| entry.trim() == serial.trim())
+                }))
+            && self
+                .removable
+                .is_none_or(|removable| removable == (device.removable == 1))
+            && self
+                .min_size
+                .as_ref()
+                .is_none_or(|min| device.size_bytes >= min.bytes)
+            && self
+                .max_size
+                .as_ref()
+                .is_none_or(|max| device.size_bytes <= max.bytes)
+    }
+
+    /// `rule "never NVMe"`, or `[[deny]] rule 2 (serial = "4C530001")` without a name.
+    fn label(&self, kind: &str, index: usize) -> String {
+        match &self.name {
+            Some(name) => format!("rule {name:?}"),
+            None => format!(
+                "[[{kind}]] rule {} ({})",
+                index + 1,
+                self.conditions().join(", ")
+            ),
+        }
+    }
+
+    /// The conditions as `key = value`, in the order of the fields.
+    fn conditions(&self) -> Vec<String> {
+        let list = |key: &str, values: Vec<String>| match values.as_slice() {
+            [value] => format!("{key} = {value}"),
+            _ => format!("{key} = [{}]", values.join(", ")),
+        };
+        let quoted = |values: &[String]| values.iter().map(|value| format!("{value:?}")).collect();
+        let mut conditions = Vec::new();
+        if !self.transport.is_empty() {
+            let transports: Vec<String> = self.transport.iter().map(ToString::to_string).collect();
+            conditions.push(list("transport", quoted(&transports)));
+        }
+        for (key, values) in [
+            ("vendor", &self.vendor),
+            ("model", &self.model),
+            ("serial", &self.serial),
+        ] {
+            if !values.is_empty() {
+                conditions.push(list(key, quoted(values)));
+            }
+        }
+        if let Some(removable) = self.removable {
+            conditions.push(format!("removable = {removable}"));
+        }
+        for (key, size) in [("min_size", &self.min_size), ("max_size", &self.max_size)] {
+            if let Some(size) = size {
+                conditions.push(format!("{key} = {size}"));
+            }
+        }
+        conditions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::devices::Transport;
+
+    const FLEET: &str = r#"
+# Fleet imaging stations.
+max_size = "256G"
+
+[[deny]]
+name = "never NVMe"
+transport = "nvme"
+
+[[deny]]
+serial = ["4C530001", 'AA00#1']   # known-bad sticks
+
+[[allow]]
+name = "removable USB"
+transport = "usb"
+removable = true
+
+[[allow]]
+vendor = "SanDisk"
+"#;
+
+    fn device(
+        transport: Transport,
+        vendor: &str,
+        serial: &str,
+        removable: u8,
+        gib: u64,
+    ) -> DeviceInfo {
+        DeviceInfo {
+            device_name: "/dev/sdb".to_string(),
+            vendor_name: vendor.to_string(),
+            model_name: "Ultra".to_string(),
+            removable,
+            size: gib << 21,
+            size_bytes: gib << 30,
+            serial: Some(serial.to_string()),
+            wwn: None,
+            transport: Some(transport),
+            usb: None,
+            read_only: false,
+            logical_sector_size: 512,
+            physical_sector_size: 512,
+            by_id: Vec::new(),
+            partitions: Vec::new(),
+            partition_table: None,
+        }
+    }
+
+    #[test]
+    fn fleet_policy_explains_each_refusal() {
+        let policy = Policy::parse(FLEET, Path::new("/etc/litho/policy.toml")).unwrap();
+        assert_eq!(policy.deny[1].serial, ["4C530001", "AA00#1"]);
+
+        assert_eq!(
+            policy.check(&device(Transport::Usb, "Kingston", "1", 1, 32)),
+            Ok(())
+        );
+        // Fixed, but allowed by vendor.
+        assert_eq!(
+            policy.check(&device(Transport::Sata, "SanDisk ", "2", 0, 128)),
+            Ok(())
+        );
+        assert_eq!(
+            policy.check(&device(Transport::Usb, "SanDisk", "3", 1, 512)),
+            Err("it is larger than max_size = \"256G\" in /etc/litho/policy.toml (549755813888 bytes)".to_string())
+        );
+        assert_eq!(
+            policy.check(&device(Transport::Nvme, "SanDisk", "4", 0, 128)),
+            Err("denied by rule \"never NVMe\" in /etc/litho/policy.toml".to_string())
+        );
+        assert_eq!(
+            policy.check(&device(Transport::Usb, "SanDisk", "4C530001", 1, 32)),
+            Err("denied by [[deny]] rule 2 (serial = [\"4C530001\", \"AA00#1\"]) in /etc/litho/policy.toml".to_string())
+        );
+        assert_eq!(
+            policy.check(&device(Transport::Sata, "ATA", "5", 0, 128)),
+            Err("no allow rule in /etc/litho/policy.toml matches it (rule \"removable USB\"; [[allow]] rule 2 (vendor = \"SanDisk\"))".to_string())
+        );
+    }
+
+    #[test]
+    fn accepts_full_toml_and_trims_serials() {
+        let text = r#"
+allow = [
+    { name = """padded "serial" sticks""", serial = ["4C530001 "] },
+    { transport = ["usb",
+                   "mmc"], min_size = 1_073_741_824 },
+]
+"#;
+        let policy = Policy::parse(text, Path::new("policy.toml")).unwrap();
+        assert_eq!(
+            policy.allow[0].name.as_deref(),
+            Some("padded \"serial\" sticks")
+        );
+        assert_eq!(
+            policy.check(&device(Transport::Sata, "ATA", " 4C530001", 0, 128)),
+            Ok(())
+        );
+        assert_eq!(
+            policy.check(&device(Transport::Mmc, "SD", "6", 1, 32)),
+            Ok(())
+        );
+        assert_eq!(
+            policy.check(&device(Transport::Usb, "SanDisk", "7", 1, 0)),
+            Err("no allow rule in policy.toml matches it (rule \"padded \\\"serial\\\" sticks\"; [[allow]] rule 2 (transport = [\"usb\", \"mmc\"], min_size = 1073741824))".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_what_it_does_not_understand() {
+        let path = Path::new("policy.toml");
+        let error = |text: &str| format!("{:#}", Policy::parse(text, path).unwrap_err());
+        assert!(error("[[deny]]\ntransprot = \"nvme\"\n")
+            .starts_with("policy.toml:2: unknown field `transprot`"));
+        assert!(error("[[deny]]\ntransport = \"firewire\"").contains("unknown variant `firewire`"));
+        assert!(error("[rules]").contains("unknown field `rules`"));
+        assert!(error("removable = true").contains("unknown field `removable`"));
+        assert!(error("max_size = \"12x\"").contains("Unknown size suffix"));
+        assert!(error("[[allow]]\nvendor = []").contains("empty list"));
+        assert!(error("[[allow]]\nvendor = \"SanDisk").starts_with("policy.toml:2: "));
+        assert_eq!(
+            error("[[allow]]\nname = \"any\""),
+            "policy.toml: [[allow]] rule 1 has no conditions"
+        );
+        assert!(Policy::load(Path::new("/nonexistent/policy.toml"))
+            .unwrap()
+            .is_none());
+    }
+}
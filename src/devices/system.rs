@@ -0,0 +1,155 @@
+//! Where device enumeration and the safety checks read the system state from: the
+//! running system ([`RealSystem`]) or a directory tree laid out like it ([`FakeSystem`]).
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The parts of the system that describe block devices and their use.
+///
+/// Paths handed to and returned by the `devices` functions stay `/dev/<name>`
+/// whatever the view; only file access goes through [`SystemView::dev`] and friends.
+pub trait SystemView {
+    /// One entry per block device, linked into the sysfs device tree (`/sys/block`).
+    fn sys_block(&self) -> PathBuf;
+
+    /// Device nodes and the `disk/by-*` links (`/dev`).
+    fn dev(&self) -> PathBuf;
+
+    /// udev database, one `b<major>:<minor>` file per block device (`/run/udev/data`).
+    fn udev_data(&self) -> PathBuf;
+
+    /// Mount table in `/proc/mounts` format.
+    fn mounts(&self) -> Result<String, String>;
+
+    /// Swap areas in `/proc/swaps` format; empty without swap support.
+    fn swaps(&self) -> String;
+
+    /// Device safety policy ([`super::Policy`]); there is none when the file is missing.
+    fn policy_file(&self) -> PathBuf;
+
+    /// Source of the root filesystem (`/dev/nvme0n1p2`, `/dev/mapper/vg-root`, …,
+    /// possibly with a btrfs `[/subvolume]` suffix). Defaults to the `/` entry of
+    /// [`SystemView::mounts`].
+    fn root_source(&self) -> Result<String, String> {
+        root_source_from_mounts(&self.mounts()?)
+    }
+}
+
+/// The running system: `/sys/block`, `/dev`, `/run/udev/data`, `/proc/mounts`,
+/// `/proc/swaps` and `/etc/litho/policy.toml`, with the root source from `findmnt`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RealSystem;
+
+impl SystemView for RealSystem {
+    fn sys_block(&self) -> PathBuf {
+        PathBuf::from("/sys/block")
+    }
+
+    fn dev(&self) -> PathBuf {
+        PathBuf::from("/dev")
+    }
+
+    fn udev_data(&self) -> PathBuf {
+        PathBuf::from("/run/udev/data")
+    }
+
+    fn mounts(&self) -> Result<String, String> {
+        fs::read_to_string("/proc/mounts").map_err(|e| format!("Failed to read /proc/mounts: {e}"))
+    }
+
+    fn swaps(&self) -> String {
+        // No /proc/swaps means no swap support.
+        fs::read_to_string("/proc/swaps").unwrap_or_default()
+    }
+
+    fn policy_file(&self) -> PathBuf {
+        PathBuf::from("/etc/litho/policy.toml")
+    }
+
+    fn root_source(&self) -> Result<String, String> {
+        root_source_from_findmnt().or_else(|findmnt_err| {
+            self.mounts()
+                .and_then(|mounts| root_source_from_mounts(&mounts))
+                .map_err(|proc_err| format!("{findmnt_err}; fallback: {proc_err}"))
+        })
+    }
+}
+
+/// A system laid out under one directory: `sys/block`, `dev`, `run/udev/data`,
+/// `proc/mounts`, `proc/swaps` and `etc/litho/policy.toml`, as in a fixture tree.
+#[derive(Debug, Clone)]
+pub struct FakeSystem {
+    root: PathBuf,
+}
+
+impl FakeSystem {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+}
+
+impl SystemView for FakeSystem {
+    fn sys_block(&self) -> PathBuf {
+        self.root.join("sys/block")
+    }
+
+    fn dev(&self) -> PathBuf {
+        self.root.join("dev")
+    }
+
+    fn udev_data(&self) -> PathBuf {
+        self.root.join("run/udev/data")
+    }
+
+    fn mounts(&self) -> Result<String, String> {
+        let path = self.root.join("proc/mounts");
+        fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {e}", path.display()))
+    }
+
+    fn swaps(&self) -> String {
+        fs::read_to_string(self.root.join("proc/swaps")).unwrap_or_default()
+    }
+
+    fn policy_file(&self) -> PathBuf {
+        self.root.join("etc/litho/policy.toml")
+    }
+}
+
+fn root_source_from_findmnt() -> Result<String, String> {
+    let output = Command::new("findmnt")
+        .args(["-n", "-o", "SOURCE", "--target", "/"])
+        .output()
+        .map_err(|e| format!("Failed to run findmnt: {e}"))?;
+    if !output.status.success() {
+        return Err(format!(
+            "findmnt failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let source = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if source.is_empty() {
+        return Err("findmnt returned an empty root source".to_string());
+    }
+    Ok(source)
+}
+
+fn root_source_from_mounts(mounts: &str) -> Result<String, String> {
+    for line in mounts.lines() {
+        let mut parts = line.split_whitespace();
+        let Some(source) = parts.next() else {
+            continue;
+        };
+        let Some(mount_point) = parts.next() else {
+            continue;
+        };
+        if mount_point == "/" {
+            return Ok(source.to_string());
+        }
+    }
+    Err("Root mount not found in /proc/mounts".to_string())
+}
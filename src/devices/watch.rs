@@ -0,0 +1,449 @@
+//! Hotplug monitoring: [`watch`] reports block devices as they come and go.
+//!
+//! On Linux the watcher listens to kernel uevents (`NETLINK_KOBJECT_UEVENT`); any
+//! `block` event triggers a rescan of `/sys/block` once the burst has settled, and the
+//! difference to the previous scan becomes [`DeviceEvent`]s. Where the socket is not
+//! available (other platforms, restricted containers) it polls `/sys/block` instead.
+
+use super::{get_storage_devices, DeviceInfo};
+use anyhow::Result;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// How often `/sys/block` is rescanned without uevents.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Quiet time after a uevent before rescanning, so a disk and its partitions are
+/// reported once and the new device's sysfs entries are complete.
+const SETTLE: Duration = Duration::from_millis(300);
+/// Upper bound on settling when events keep arriving.
+const MAX_SETTLE: Duration = Duration::from_secs(2);
+
+/// A change between two scans, with the device as it is now (or was, for `Removed`).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "event", content = "device", rename_all = "lowercase")]
+pub enum DeviceEvent {
+    Added(DeviceInfo),
+    Removed(DeviceInfo),
+    /// Same device name, different details: media inserted into a card reader, a new
+    /// partition table, a partition mounted, …
+    Changed(DeviceInfo),
+}
+
+impl DeviceEvent {
+    pub fn device(&self) -> &DeviceInfo {
+        match self {
+            DeviceEvent::Added(device)
+            | DeviceEvent::Removed(device)
+            | DeviceEvent::Changed(device) => device,
+        }
+    }
+
+    /// `added`, `removed` or `changed`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            DeviceEvent::Added(_) => "added",
+            DeviceEvent::Removed(_) => "removed",
+            DeviceEvent::Changed(_) => "changed",
+        }
+    }
+}
+
+impl fmt::Display for DeviceEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.kind(), self.device())
+    }
+}
+
+/// What wakes a [`DeviceWatcher`] up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchBackend {
+    /// Kernel uevents over netlink.
+    Uevents,
+    /// Periodic rescans of `/sys/block`.
+    Polling(Duration),
+}
+
+impl fmt::Display for WatchBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WatchBackend::Uevents => write!(f, "kernel uevents"),
+            WatchBackend::Polling(interval) => {
+                write!(f, "polling /sys/block every {:?}", interval)
+            }
+        }
+    }
+}
+
+type Scan = Box<dyn FnMut() -> Result<Vec<DeviceInfo>> + Send>;
+
+/// Current device list plus a stream of changes to it.
+///
+/// [`wait`](Self::wait) returns the changes seen within a timeout (for event loops
+/// such as the TUI); iterating blocks until the next change.
+pub struct DeviceWatcher {
+    #[cfg(target_os = "linux")]
+    uevents: Option<uevent::UeventSocket>,
+    poll_interval: Duration,
+    scan: Scan,
+    devices: Vec<DeviceInfo>,
+    pending: VecDeque<DeviceEvent>,
+}
+
+/// Watch block devices, using uevents when possible and polling otherwise.
+pub fn watch() -> Result<DeviceWatcher> {
+    let mut watcher = DeviceWatcher::polling(DEFAULT_POLL_INTERVAL)?;
+    #[cfg(target_os = "linux")]
+    match uevent::UeventSocket::open() {
+        Ok(socket) => watcher.uevents = Some(socket),
+        Err(e) => warn!("Kernel uevents unavailable ({}); polling /sys/block", e),
+    }
+    Ok(watcher)
+}
+
+impl DeviceWatcher {
+    /// Watch by rescanning `/sys/block` every `interval`, without uevents.
+    pub fn polling(interval: Duration) -> Result<Self> {
+        Self::with_scan(interval, Box::new(get_storage_devices))
+    }
+
+    fn with_scan(poll_interval: Duration, mut scan: Scan) -> Result<Self> {
+        let devices = scan()?;
+        Ok(Self {
+            #[cfg(target_os = "linux")]
+            uevents: None,
+            poll_interval,
+            scan,
+            devices,
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// Devices as of the last scan.
+    pub fn devices(&self) -> &[DeviceInfo] {
+        &self.devices
+    }
+
+    pub fn backend(&self) -> WatchBackend {
+        #[cfg(target_os = "linux")]
+        if self.uevents.is_some() {
+            return WatchBackend::Uevents;
+        }
+        WatchBackend::Polling(self.poll_interval)
+    }
+
+    /// Wait up to `timeout` for devices to change; empty when nothing changed.
+    pub fn wait(&mut self, timeout: Duration) -> Result<Vec<DeviceEvent>> {
+        if !self.pending.is_empty() {
+            return Ok(self.pending.drain(..).collect());
+        }
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if !self.sleep_until_change(remaining)? {
+                return Ok(Vec::new());
+            }
+            let events = self.rescan()?;
+            if !events.is_empty() || Instant::now() >= deadline {
+                return Ok(events);
+            }
+        }
+    }
+
+    /// Block for up to `timeout`; false when there is no reason to rescan yet.
+    fn sleep_until_change(&mut self, timeout: Duration) -> Result<bool> {
+        #[cfg(target_os = "linux")]
+        if let Some(socket) = &self.uevents {
+            if !socket.wait_for_block_event(timeout)? {
+                return Ok(false);
+            }
+            let settle_until = Instant::now() + MAX_SETTLE;
+            while Instant::now() < settle_until && socket.wait_for_block_event(SETTLE)? {}
+            return Ok(true);
+        }
+        if timeout < self.poll_interval {
+            std::thread::sleep(timeout);
+            return Ok(false);
+        }
+        std::thread::sleep(self.poll_interval);
+        Ok(true)
+    }
+
+    fn rescan(&mut self) -> Result<Vec<DeviceEvent>> {
+        let devices = (self.scan)()?;
+        let events = diff_devices(&self.devices, &devices);
+        for event in &events {
+            debug!("Device {}: {}", event.kind(), event.device().device_name);
+        }
+        self.devices = devices;
+        Ok(events)
+    }
+}
+
+/// Blocks until the next change; ends only on a scan or socket error.
+impl Iterator for DeviceWatcher {
+    type Item = Result<DeviceEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(Ok(event));
+            }
+            match self.wait(Duration::from_secs(3600)) {
+                Ok(events) => self.pending.extend(events),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+/// Removed devices first, then additions and changes in the order of `new`.
+fn diff_devices(old: &[DeviceInfo], new: &[DeviceInfo]) -> Vec<DeviceEvent> {
+    let find = |devices: &[DeviceInfo], name: &str| {
+        devices
+            .iter()
+            .find(|device| device.device_name == name)
+            .cloned()
+    };
+    let mut events: Vec<DeviceEvent> = old
+        .iter()
+        .filter(|device| find(new, &device.device_name).is_none())
+        .cloned()
+        .map(DeviceEvent::Removed)
+        .collect();
+    for device in new {
+        match find(old, &device.device_name) {
+            None => events.push(DeviceEvent::Added(device.clone())),
+            Some(previous) if previous != *device => {
+                events.push(DeviceEvent::Changed(device.clone()))
+            }
+            Some(_) => {}
+        }
+    }
+    events
+}
+
+#[cfg(target_os = "linux")]
+mod uevent {
+    use log::debug;
+    use std::io;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::time::{Duration, Instant};
+
+    /// Multicast group of the kernel's own uevents (udev rebroadcasts on group 2).
+    const KERNEL_GROUP: u32 = 1;
+
+    pub(super) struct UeventSocket {
+        fd: OwnedFd,
+    }
+
+    impl UeventSocket {
+        pub(super) fn open() -> io::Result<Self> {
+            // SAFETY: plain socket(2); the descriptor is owned below.
+            let fd = unsafe {
+                libc::socket(
+                    libc::AF_NETLINK,
+                    libc::SOCK_DGRAM | libc::SOCK_CLOEXEC,
+                    libc::NETLINK_KOBJECT_UEVENT,
+                )
+            };
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            // SAFETY: fd is a fresh descriptor nobody else owns.
+            let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+            // SAFETY: sockaddr_nl is plain data; all-zero is a valid starting value.
+            let mut addr: libc::sockaddr_nl = unsafe { std::mem::zeroed() };
+            addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+            addr.nl_groups = KERNEL_GROUP;
+            // SAFETY: addr outlives the call and the length matches its type.
+            let rc = unsafe {
+                libc::bind(
+                    fd.as_raw_fd(),
+                    &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+                    std::mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+                )
+            };
+            if rc < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Self { fd })
+        }
+
+        /// Read uevents for up to `timeout`; true at the first one for a block device
+        /// (or when the kernel dropped events, since one of them may have been).
+        pub(super) fn wait_for_block_event(&self, timeout: Duration) -> io::Result<bool> {
+            let deadline = Instant::now() + timeout;
+            let mut buf = vec![0u8; 8192];
+            loop {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                let mut pollfd = libc::pollfd {
+                    fd: self.fd.as_raw_fd(),
+                    events: libc::POLLIN,
+                    revents: 0,
+                };
+                let millis = i32::try_from(remaining.as_millis()).unwrap_or(i32::MAX);
+                // SAFETY: one valid pollfd.
+                let ready = unsafe { libc::poll(&mut pollfd, 1, millis) };
+                if ready < 0 {
+                    let e = io::Error::last_os_error();
+                    if e.kind() == io::ErrorKind::Interrupted {
+                        continue;
+                    }
+                    return Err(e);
+                }
+                if ready == 0 {
+                    return Ok(false);
+                }
+                // SAFETY: buf is valid for buf.len() bytes.
+                let read = unsafe {
+                    libc::recv(
+                        self.fd.as_raw_fd(),
+                        buf.as_mut_ptr() as *mut libc::c_void,
+                        buf.len(),
+                        libc::MSG_DONTWAIT,
+                    )
+                };
+                if read < 0 {
+                    let e = io::Error::last_os_error();
+                    match e.raw_os_error() {
+                        Some(libc::ENOBUFS) => return Ok(true),
+                        Some(libc::EAGAIN) | Some(libc::EINTR) => continue,
+                        _ => return Err(e),
+                    }
+                }
+                if let Some(event) = parse_uevent(&buf[..read as usize]) {
+                    if event.subsystem == "block" {
+                        debug!("uevent {} {}", event.action, event.devname);
+                        return Ok(true);
+                    }
+                }
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    pub(super) struct Uevent {
+        pub action: String,
+        pub subsystem: String,
+        pub devname: String,
+    }
+
+    /// Parse a kernel uevent: `action@devpath` then NUL-separated `KEY=value` pairs.
+    pub(super) fn parse_uevent(message: &[u8]) -> Option<Uevent> {
+        let mut fields = message.split(|&b| b == 0).map(String::from_utf8_lossy);
+        let header = fields.next()?;
+        if !header.contains('@') {
+            // libudev-format messages (not sent to the kernel group) have no header.
+            return None;
+        }
+        let mut event = Uevent {
+            action: String::new(),
+            subsystem: String::new(),
+            devname: String::new(),
+        };
+        for field in fields {
+            if let Some((key, value)) = field.split_once('=') {
+                match key {
+                    "ACTION" => event.action = value.to_string(),
+                    "SUBSYSTEM" => event.subsystem = value.to_string(),
+                    "DEVNAME" => event.devname = value.to_string(),
+                    _ => {}
+                }
+            }
+        }
+        (!event.action.is_empty()).then_some(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    fn device(name: &str, size: u64) -> DeviceInfo {
+        DeviceInfo {
+            device_name: name.to_string(),
+            vendor_name: "SanDisk".to_string(),
+            model_name: "Ultra".to_string(),
+            removable: 1,
+            size,
+            size_bytes: size * 512,
+            serial: None,
+            wwn: None,
+            transport: None,
+            usb: None,
+            read_only: false,
+            logical_sector_size: 512,
+            physical_sector_size: 512,
+            by_id: Vec::new(),
+            partitions: Vec::new(),
+            partition_table: None,
+        }
+    }
+
+    #[test]
+    fn diff_reports_added_removed_and_changed_devices() {
+        let old = vec![device("/dev/sda", 100), device("/dev/sdb", 0)];
+        let new = vec![device("/dev/sdb", 64), device("/dev/sdc", 32)];
+        assert_eq!(
+            diff_devices(&old, &new),
+            vec![
+                DeviceEvent::Removed(device("/dev/sda", 100)),
+                DeviceEvent::Changed(device("/dev/sdb", 64)),
+                DeviceEvent::Added(device("/dev/sdc", 32)),
+            ]
+        );
+        assert!(diff_devices(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn polling_watcher_reports_changes_between_scans() {
+        let scans = Arc::new(Mutex::new(vec![
+            vec![device("/dev/sda", 100), device("/dev/sdb", 64)],
+            vec![device("/dev/sda", 100)],
+        ]));
+        let next = Arc::clone(&scans);
+        let mut watcher = DeviceWatcher::with_scan(
+            Duration::from_millis(1),
+            Box::new(move || Ok(next.lock().unwrap().pop().unwrap_or_default())),
+        )
+        .unwrap();
+        assert_eq!(watcher.devices().len(), 1);
+        assert_eq!(
+            watcher.backend(),
+            WatchBackend::Polling(Duration::from_millis(1))
+        );
+
+        let added = watcher.next().unwrap().unwrap();
+        assert_eq!(added, DeviceEvent::Added(device("/dev/sdb", 64)));
+        assert_eq!(watcher.devices().len(), 2);
+        // Scans now come back empty: both devices disappear at once.
+        assert_eq!(watcher.wait(Duration::from_secs(1)).unwrap().len(), 2);
+        assert!(watcher.wait(Duration::from_millis(5)).unwrap().is_empty());
+        assert!(scans.lock().unwrap().is_empty());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn parses_kernel_uevents() {
+        let message = b"add@/devices/pci0000:00/usb1/1-1/host6/target6:0:0/6:0:0:0/block/sdb\0\
+ACTION=add\0DEVPATH=/devices/pci0000:00/usb1/1-1/host6/target6:0:0/6:0:0:0/block/sdb\0\
+SUBSYSTEM=block\0MAJOR=8\0MINOR=16\0DEVNAME=sdb\0DEVTYPE=disk\0SEQNUM=4242\0";
+        assert_eq!(
+            uevent::parse_uevent(message),
+            Some(uevent::Uevent {
+                action: "add".to_string(),
+                subsystem: "block".to_string(),
+                devname: "sdb".to_string(),
+            })
+        );
+        assert_eq!(
+            uevent::parse_uevent(b"libudev\0\xfe\xed\xca\xfe\0ACTION=add\0"),
+            None
+        );
+    }
+}
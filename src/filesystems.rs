@@ -0,0 +1,188 @@
+//! Filesystem detection from superblocks: type and volume label of a partition.
+
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Seek, SeekFrom};
+
+mod ext;
+mod fat;
+pub use ext::ExtFs;
+pub use fat::FatFs;
+
+/// Bytes read from the start of a filesystem; covers the FAT boot sector, the ext
+/// and swap superblocks and the XFS superblock.
+const HEAD_SIZE: usize = 4096;
+
+const EXT_SUPERBLOCK: usize = 1024;
+const EXT_MAGIC: [u8; 2] = [0x53, 0xEF];
+const EXT_COMPAT_HAS_JOURNAL: u32 = 0x4;
+/// `extents`, `64bit` and `flex_bg`: features ext3 never has.
+const EXT_INCOMPAT_EXT4: u32 = 0x40 | 0x80 | 0x200;
+
+/// ISO 9660 primary volume descriptor (sector 16 of 2048 bytes).
+pub const ISO9660_PRIMARY_DESCRIPTOR: u64 = 0x8000;
+
+const BTRFS_SUPERBLOCK: u64 = 0x10000;
+const BTRFS_MAGIC: &[u8; 8] = b"_BHRfS_M";
+
+/// A detected filesystem.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Filesystem {
+    /// `FAT32`, `ext4`, `swap`, `NTFS`, …
+    pub kind: String,
+    pub label: Option<String>,
+}
+
+impl Filesystem {
+    fn new(kind: &str, label: Option<String>) -> Self {
+        Self {
+            kind: kind.to_string(),
+            label,
+        }
+    }
+}
+
+/// Probe the filesystem starting `offset` bytes into `reader`; `None` when nothing
+/// is recognised or the area cannot be read.
+pub fn probe_filesystem<R: Read + Seek>(reader: &mut R, offset: u64) -> Option<Filesystem> {
+    let mut head = vec![0u8; HEAD_SIZE];
+    read_at(reader, offset, &mut head)?;
+    probe_head(&head)
+        .or_else(|| {
+            let mut descriptor = vec![0u8; 2048];
+            read_at(reader, offset + ISO9660_PRIMARY_DESCRIPTOR, &mut descriptor)?;
+            (descriptor[0] == 1 && &descriptor[1..6] == b"CD001")
+                .then(|| Filesystem::new("ISO9660", text_field(&descriptor[40..72])))
+        })
+        .or_else(|| {
+            let mut btrfs = vec![0u8; 0x1000];
+            read_at(reader, offset + BTRFS_SUPERBLOCK, &mut btrfs)?;
+            (&btrfs[0x40..0x48] == BTRFS_MAGIC)
+                .then(|| Filesystem::new("btrfs", text_field(&btrfs[0x12B..0x22B])))
+        })
+}
+
+fn read_at<R: Read + Seek>(reader: &mut R, offset: u64, buf: &mut [u8]) -> Option<()> {
+    reader.seek(SeekFrom::Start(offset)).ok()?;
+    reader.read_exact(buf).ok()
+}
+
+fn probe_head(head: &[u8]) -> Option<Filesystem> {
+    let ext = &head[EXT_SUPERBLOCK..];
+    if ext[56..58] == EXT_MAGIC {
+        let compat = u32::from_le_bytes(ext[92..96].try_into().ok()?);
+        let incompat = u32::from_le_bytes(ext[96..100].try_into().ok()?);
+        let kind = if incompat & EXT_INCOMPAT_EXT4 != 0 {
+            "ext4"
+        } else if compat & EXT_COMPAT_HAS_JOURNAL != 0 {
+            "ext3"
+        } else {
+            "ext2"
+        };
+        return Some(Filesystem::new(kind, text_field(&ext[120..136])));
+    }
+    if &head[HEAD_SIZE - 10..] == b"SWAPSPACE2" {
+        return Some(Filesystem::new("swap", text_field(&ext[28..44])));
+    }
+    if &head[..4] == b"XFSB" {
+        return Some(Filesystem::new("XFS", text_field(&head[108..120])));
+    }
+    match &head[3..11] {
+        b"NTFS    " => return Some(Filesystem::new("NTFS", None)),
+        b"EXFAT   " => return Some(Filesystem::new("exFAT", None)),
+        _ => {}
+    }
+    if head[510..512] != [0x55, 0xAA] {
+        return None;
+    }
+    if &head[82..90] == b"FAT32   " {
+        return Some(Filesystem::new("FAT32", fat_label(&head[71..82])));
+    }
+    match &head[54..62] {
+        b"FAT12   " => Some(Filesystem::new("FAT12", fat_label(&head[43..54]))),
+        b"FAT16   " => Some(Filesystem::new("FAT16", fat_label(&head[43..54]))),
+        b"FAT     " => Some(Filesystem::new("FAT", fat_label(&head[43..54]))),
+        _ => None,
+    }
+}
+
+/// FAT boot-sector label; `NO NAME` is the "no label" placeholder.
+fn fat_label(bytes: &[u8]) -> Option<String> {
+    text_field(bytes).filter(|label| label != "NO NAME")
+}
+
+/// NUL- or space-padded text, `None` when empty.
+fn text_field(bytes: &[u8]) -> Option<String> {
+    let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    let text = String::from_utf8_lossy(&bytes[..end]).trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+#[cfg(test)]
+pub(crate) mod test_images {
+    pub use super::ext::test_images::ExtImage;
+    pub use super::fat::test_images::fat16_with_files;
+
+    /// FAT32 boot sector with the given label.
+    pub fn fat32(label: &str) -> Vec<u8> {
+        let mut head = vec![0u8; 4096];
+        head[3..11].copy_from_slice(b"MSDOS5.0");
+        head[71..82].copy_from_slice(format!("{label:<11}").as_bytes());
+        head[82..90].copy_from_slice(b"FAT32   ");
+        head[510..512].copy_from_slice(&[0x55, 0xAA]);
+        head
+    }
+
+    /// ext4 superblock (extents feature) with the given label.
+    pub fn ext4(label: &str) -> Vec<u8> {
+        let mut head = vec![0u8; 4096];
+        head[1024 + 56..1024 + 58].copy_from_slice(&[0x53, 0xEF]);
+        head[1024 + 96..1024 + 100].copy_from_slice(&0x40u32.to_le_bytes());
+        head[1024 + 120..1024 + 120 + label.len()].copy_from_slice(label.as_bytes());
+        head
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_images::{ext4, fat32};
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn probes_fat_ext_and_swap() {
+        assert_eq!(
+            probe_head(&fat32("BOOT")),
+            Some(Filesystem::new("FAT32", Some("BOOT".to_string())))
+        );
+        assert_eq!(
+            probe_head(&fat32("NO NAME")),
+            Some(Filesystem::new("FAT32", None))
+        );
+        assert_eq!(
+            probe_head(&ext4("rootfs")),
+            Some(Filesystem::new("ext4", Some("rootfs".to_string())))
+        );
+
+        let mut ext3 = ext4("");
+        ext3[1024 + 92] = EXT_COMPAT_HAS_JOURNAL as u8;
+        ext3[1024 + 96] = 0;
+        assert_eq!(probe_head(&ext3), Some(Filesystem::new("ext3", None)));
+
+        let mut swap = vec![0u8; HEAD_SIZE];
+        swap[HEAD_SIZE - 10..].copy_from_slice(b"SWAPSPACE2");
+        assert_eq!(probe_head(&swap), Some(Filesystem::new("swap", None)));
+        assert_eq!(probe_head(&vec![0u8; HEAD_SIZE]), None);
+    }
+
+    #[test]
+    fn probe_reads_at_offset_and_tolerates_short_input() {
+        let mut image = vec![0u8; 8192];
+        image[4096..].copy_from_slice(&ext4("data"));
+        let mut reader = Cursor::new(image);
+        assert_eq!(
+            probe_filesystem(&mut reader, 4096).map(|fs| fs.kind),
+            Some("ext4".to_string())
+        );
+        assert_eq!(probe_filesystem(&mut reader, 6144), None);
+    }
+}
@@ -0,0 +1,464 @@
+//! Read-only file access on ext2/3/4: enough to fetch small files such as
+//! `/etc/os-release` from an image, following symlinks.
+//!
+//! Supports extent-mapped and block-mapped (direct and indirect) files and linear
+//! directory scans (hashed directories are still readable linearly). Inline data is
+//! read from the inode only.
+
+use anyhow::{bail, Context, Result};
+use std::io::{Read, Seek, SeekFrom};
+
+const SUPERBLOCK: u64 = 1024;
+const ROOT_INODE: u32 = 2;
+const INCOMPAT_64BIT: u32 = 0x80;
+const INODE_FLAG_EXTENTS: u32 = 0x80000;
+const INODE_FLAG_INLINE_DATA: u32 = 0x1000_0000;
+const EXTENT_MAGIC: u16 = 0xF30A;
+const MODE_TYPE: u16 = 0xF000;
+const MODE_DIR: u16 = 0x4000;
+const MODE_SYMLINK: u16 = 0xA000;
+/// Largest file or directory read into memory.
+const MAX_FILE_BYTES: u64 = 16 << 20;
+const MAX_SYMLINKS: usize = 16;
+
+/// An ext2/3/4 filesystem starting `offset` bytes into `reader`.
+pub struct ExtFs<'a, R> {
+    reader: &'a mut R,
+    offset: u64,
+    block_size: u64,
+    inodes_per_group: u32,
+    inode_size: u64,
+    desc_size: u64,
+    first_data_block: u64,
+}
+
+struct Inode {
+    mode: u16,
+    size: u64,
+    flags: u32,
+    block: [u8; 60],
+}
+
+impl<'a, R: Read + Seek> ExtFs<'a, R> {
+    pub fn open(reader: &'a mut R, offset: u64) -> Result<Self> {
+        let mut sb = [0u8; 1024];
+        read_at(reader, offset + SUPERBLOCK, &mut sb).context("Failed to read the superblock")?;
+        if sb[56..58] != [0x53, 0xEF] {
+            bail!("No ext2/3/4 superblock");
+        }
+        let log_block_size = u32_le(&sb[24..]);
+        if log_block_size > 6 {
+            bail!("Unsupported ext block size");
+        }
+        let rev_level = u32_le(&sb[76..]);
+        let inode_size = if rev_level == 0 {
+            128
+        } else {
+            u16_le(&sb[88..]) as u64
+        };
+        let desc_size = if u32_le(&sb[96..]) & INCOMPAT_64BIT != 0 {
+            (u16_le(&sb[254..]) as u64).max(32)
+        } else {
+            32
+        };
+        let inodes_per_group = u32_le(&sb[40..]);
+        if inodes_per_group == 0 || inode_size < 128 {
+            bail!("Corrupt ext superblock");
+        }
+        Ok(Self {
+            reader,
+            offset,
+            block_size: 1024 << log_block_size,
+            inodes_per_group,
+            inode_size,
+            desc_size,
+            first_data_block: u32_le(&sb[20..]) as u64,
+        })
+    }
+
+    /// Contents of the regular file at absolute `path`; `None` when it does not exist.
+    pub fn read_file(&mut self, path: &str) -> Result<Option<Vec<u8>>> {
+        let Some(number) = self.lookup(path)? else {
+            return Ok(None);
+        };
+        let inode = self.inode(number)?;
+        if inode.mode & MODE_TYPE == MODE_DIR {
+            bail!("{} is a directory", path);
+        }
+        self.contents(&inode).map(Some)
+    }
+
+    /// Inode number of `path`, following symlinks in every component.
+    fn lookup(&mut self, path: &str) -> Result<Option<u32>> {
+        let mut pending: Vec<String> = components(path).rev().collect();
+        let mut stack = vec![ROOT_INODE];
+        let mut symlinks = 0;
+        while let Some(name) = pending.pop() {
+            let dir = *stack.last().expect("root stays on the stack");
+            match name.as_str() {
+                "." => continue,
+                ".." => {
+                    if stack.len() > 1 {
+                        stack.pop();
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+            let Some(number) = self.find_entry(dir, &name)? else {
+                return Ok(None);
+            };
+            let inode = self.inode(number)?;
+            if inode.mode & MODE_TYPE != MODE_SYMLINK {
+                stack.push(number);
+                continue;
+            }
+            symlinks += 1;
+            if symlinks > MAX_SYMLINKS {
+                bail!("Too many levels of symbolic links in {}", path);
+            }
+            let target = String::from_utf8_lossy(&self.contents(&inode)?).into_owned();
+            if target.starts_with('/') {
+                stack.truncate(1);
+            }
+            pending.extend(components(&target).rev());
+        }
+        Ok(stack.last().copied())
+    }
+
+    fn find_entry(&mut self, dir: u32, name: &str) -> Result<Option<u32>> {
+        let inode = self.inode(dir)?;
+        if inode.mode & MODE_TYPE != MODE_DIR {
+            return Ok(None);
+        }
+        let data = self.contents(&inode)?;
+        // Inline directories start with the parent's inode number.
+        let entries = if inode.flags & INODE_FLAG_INLINE_DATA != 0 {
+            data.get(4..).unwrap_or_default()
+        } else {
+            &data[..]
+        };
+        let mut at = 0;
+        while at + 8 <= entries.len() {
+            let number = u32_le(&entries[at..]);
+            let rec_len = u16_le(&entries[at + 4..]) as usize;
+            let name_len = entries[at + 6] as usize;
+            if rec_len < 8 {
+                break;
+            }
+            if number != 0 && entries.get(at + 8..at + 8 + name_len) == Some(name.as_bytes()) {
+                return Ok(Some(number));
+            }
+            at += rec_len;
+        }
+        Ok(None)
+    }
+
+    fn inode(&mut self, number: u32) -> Result<Inode> {
+        if number == 0 {
+            bail!("Invalid inode number 0");
+        }
+        let group = ((number - 1) / self.inodes_per_group) as u64;
+        let index = ((number - 1) % self.inodes_per_group) as u64;
+        let mut desc = vec![0u8; self.desc_size as usize];
+        let table = (self.first_data_block + 1) * self.block_size + group * self.desc_size;
+        self.read_fs(table, &mut desc)?;
+        let mut table_block = u32_le(&desc[8..]) as u64;
+        if self.desc_size >= 64 {
+            table_block |= (u32_le(&desc[40..]) as u64) << 32;
+        }
+
+        let mut raw = vec![0u8; 128];
+        self.read_fs(
+            table_block * self.block_size + index * self.inode_size,
+            &mut raw,
+        )?;
+        let mut block = [0u8; 60];
+        block.copy_from_slice(&raw[40..100]);
+        Ok(Inode {
+            mode: u16_le(&raw[0..]),
+            size: u32_le(&raw[4..]) as u64 | (u32_le(&raw[108..]) as u64) << 32,
+            flags: u32_le(&raw[32..]),
+            block,
+        })
+    }
+
+    fn contents(&mut self, inode: &Inode) -> Result<Vec<u8>> {
+        if inode.size > MAX_FILE_BYTES {
+            bail!("File too large to inspect ({} bytes)", inode.size);
+        }
+        let size = inode.size as usize;
+        let fast_symlink = inode.mode & MODE_TYPE == MODE_SYMLINK
+            && inode.size < 60
+            && inode.flags & INODE_FLAG_EXTENTS == 0;
+        if fast_symlink || inode.flags & INODE_FLAG_INLINE_DATA != 0 {
+            return Ok(inode.block[..size.min(60)].to_vec());
+        }
+
+        let blocks = inode.size.div_ceil(self.block_size);
+        let mut runs = Vec::new();
+        if inode.flags & INODE_FLAG_EXTENTS != 0 {
+            self.extent_runs(&inode.block, blocks, 0, &mut runs)?;
+        } else {
+            self.mapped_runs(&inode.block, blocks, &mut runs)?;
+        }
+
+        // Holes and unwritten extents read as zeros.
+        let mut data = vec![0u8; size];
+        for (logical, physical, len) in runs {
+            let start = logical * self.block_size;
+            if start >= inode.size {
+                continue;
+            }
+            let end = ((logical + len) * self.block_size).min(inode.size);
+            self.read_fs(
+                physical * self.block_size,
+                &mut data[start as usize..end as usize],
+            )?;
+        }
+        Ok(data)
+    }
+
+    /// `(logical, physical, blocks)` runs of an extent tree node.
+    fn extent_runs(
+        &mut self,
+        node: &[u8],
+        blocks: u64,
+        depth_seen: u16,
+        runs: &mut Vec<(u64, u64, u64)>,
+    ) -> Result<()> {
+        if u16_le(node) != EXTENT_MAGIC {
+            bail!("Corrupt extent header");
+        }
+        let entries = u16_le(&node[2..]) as usize;
+        let depth = u16_le(&node[6..]);
+        if depth_seen > 5 || 12 + entries * 12 > node.len() {
+            bail!("Corrupt extent tree");
+        }
+        for entry in node[12..12 + entries * 12].chunks_exact(12) {
+            let logical = u32_le(entry) as u64;
+            if logical >= blocks {
+                continue;
+            }
+            if depth == 0 {
+                let len = u16_le(&entry[4..]);
+                if len > 32768 {
+                    continue; // unwritten
+                }
+                let physical = (u16_le(&entry[6..]) as u64) << 32 | u32_le(&entry[8..]) as u64;
+                runs.push((logical, physical, len as u64));
+            } else {
+                let child = (u16_le(&entry[8..]) as u64) << 32 | u32_le(&entry[4..]) as u64;
+                let mut block = vec![0u8; self.block_size as usize];
+                self.read_fs(child * self.block_size, &mut block)?;
+                self.extent_runs(&block, blocks, depth_seen + 1, runs)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs of a block-mapped (ext2/3) file: 12 direct pointers, then single,
+    /// double and triple indirect blocks.
+    fn mapped_runs(
+        &mut self,
+        block: &[u8; 60],
+        blocks: u64,
+        runs: &mut Vec<(u64, u64, u64)>,
+    ) -> Result<()> {
+        let pointers: Vec<u64> = block.chunks_exact(4).map(|p| u32_le(p) as u64).collect();
+        let mut logical = 0;
+        for &physical in &pointers[..12] {
+            if logical >= blocks {
+                return Ok(());
+            }
+            if physical != 0 {
+                runs.push((logical, physical, 1));
+            }
+            logical += 1;
+        }
+        for (level, &physical) in pointers[12..].iter().enumerate() {
+            self.indirect_runs(physical, level as u32 + 1, blocks, &mut logical, runs)?;
+        }
+        Ok(())
+    }
+
+    fn indirect_runs(
+        &mut self,
+        physical: u64,
+        level: u32,
+        blocks: u64,
+        logical: &mut u64,
+        runs: &mut Vec<(u64, u64, u64)>,
+    ) -> Result<()> {
+        let per_block = self.block_size / 4;
+        if *logical >= blocks {
+            return Ok(());
+        }
+        if physical == 0 {
+            *logical += per_block.pow(level);
+            return Ok(());
+        }
+        let mut table = vec![0u8; self.block_size as usize];
+        self.read_fs(physical * self.block_size, &mut table)?;
+        for pointer in table.chunks_exact(4).map(|p| u32_le(p) as u64) {
+            if level == 1 {
+                if *logical >= blocks {
+                    break;
+                }
+                if pointer != 0 {
+                    runs.push((*logical, pointer, 1));
+                }
+                *logical += 1;
+            } else {
+                self.indirect_runs(pointer, level - 1, blocks, logical, runs)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn read_fs(&mut self, at: u64, buf: &mut [u8]) -> Result<()> {
+        read_at(self.reader, self.offset + at, buf)
+    }
+}
+
+fn components(path: &str) -> impl DoubleEndedIterator<Item = String> + '_ {
+    path.split('/')
+        .filter(|part| !part.is_empty())
+        .map(str::to_string)
+}
+
+fn read_at<R: Read + Seek>(reader: &mut R, at: u64, buf: &mut [u8]) -> Result<()> {
+    reader.seek(SeekFrom::Start(at))?;
+    reader
+        .read_exact(buf)
+        .with_context(|| format!("Failed to read {} bytes at offset {}", buf.len(), at))
+}
+
+fn u16_le(bytes: &[u8]) -> u16 {
+    u16::from_le_bytes([bytes[0], bytes[1]])
+}
+
+fn u32_le(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes(bytes[..4].try_into().expect("4 bytes"))
+}
+
+#[cfg(test)]
+pub(crate) mod test_images {
+    //! A tiny hand-built ext4 image: 1 KiB blocks, one group, extent-mapped files.
+
+    const BLOCK: usize = 1024;
+    const INODE_TABLE: usize = 5;
+    const INODE_SIZE: usize = 256;
+
+    pub struct ExtImage {
+        pub data: Vec<u8>,
+        next_block: usize,
+        next_inode: u32,
+    }
+
+    impl ExtImage {
+        pub fn new() -> Self {
+            let mut data = vec![0u8; 64 * BLOCK];
+            let sb = &mut data[1024..2048];
+            sb[20..24].copy_from_slice(&1u32.to_le_bytes()); // first data block
+            sb[40..44].copy_from_slice(&32u32.to_le_bytes()); // inodes per group
+            sb[56..58].copy_from_slice(&[0x53, 0xEF]);
+            sb[76..80].copy_from_slice(&1u32.to_le_bytes()); // dynamic revision
+            sb[88..90].copy_from_slice(&(INODE_SIZE as u16).to_le_bytes());
+            sb[96..100].copy_from_slice(&0x40u32.to_le_bytes()); // extents
+            let desc = 2 * BLOCK;
+            data[desc + 8..desc + 12].copy_from_slice(&(INODE_TABLE as u32).to_le_bytes());
+            let mut image = Self {
+                data,
+                next_block: INODE_TABLE + 32 * INODE_SIZE / BLOCK,
+                next_inode: 11,
+            };
+            image.write_inode(2, 0x41ED, &[]);
+            image
+        }
+
+        /// Add a directory entry in `dir` for a new file, directory or symlink.
+        pub fn add(&mut self, dir: u32, name: &str, mode: u16, contents: &[u8]) -> u32 {
+            let number = self.next_inode;
+            self.next_inode += 1;
+            self.write_inode(number, mode, contents);
+            let mut entries = self.dir_entries(dir);
+            let mut entry = vec![0u8; 8 + name.len().next_multiple_of(4)];
+            entry[..4].copy_from_slice(&number.to_le_bytes());
+            let rec_len = entry.len() as u16;
+            entry[4..6].copy_from_slice(&rec_len.to_le_bytes());
+            entry[6] = name.len() as u8;
+            entry[8..8 + name.len()].copy_from_slice(name.as_bytes());
+            entries.extend(entry);
+            self.write_inode(dir, 0x41ED, &entries);
+            number
+        }
+
+        fn dir_entries(&self, dir: u32) -> Vec<u8> {
+            let at = self.inode_at(dir);
+            let size = u32::from_le_bytes(self.data[at + 4..at + 8].try_into().unwrap()) as usize;
+            let block = u32::from_le_bytes(self.data[at + 60..at + 64].try_into().unwrap());
+            let start = block as usize * BLOCK;
+            self.data[start..start + size].to_vec()
+        }
+
+        fn inode_at(&self, number: u32) -> usize {
+            INODE_TABLE * BLOCK + (number as usize - 1) * INODE_SIZE
+        }
+
+        /// Write `contents` to fresh blocks (one extent) and point the inode at them;
+        /// short symlinks are stored in the inode.
+        fn write_inode(&mut self, number: u32, mode: u16, contents: &[u8]) {
+            let at = self.inode_at(number);
+            let mut inode = vec![0u8; INODE_SIZE];
+            inode[..2].copy_from_slice(&mode.to_le_bytes());
+            inode[4..8].copy_from_slice(&(contents.len() as u32).to_le_bytes());
+            if mode & 0xF000 == 0xA000 && contents.len() < 60 {
+                inode[40..40 + contents.len()].copy_from_slice(contents);
+            } else {
+                let blocks = contents.len().div_ceil(BLOCK).max(1);
+                let first = self.next_block;
+                self.next_block += blocks;
+                self.data[first * BLOCK..][..contents.len()].copy_from_slice(contents);
+                inode[32..36].copy_from_slice(&0x80000u32.to_le_bytes());
+                let header = &mut inode[40..52];
+                header[..2].copy_from_slice(&0xF30Au16.to_le_bytes());
+                header[2..4].copy_from_slice(&1u16.to_le_bytes());
+                header[4..6].copy_from_slice(&4u16.to_le_bytes());
+                let extent = &mut inode[52..64];
+                extent[4..6].copy_from_slice(&(blocks as u16).to_le_bytes());
+                extent[8..12].copy_from_slice(&(first as u32).to_le_bytes());
+            }
+            self.data[at..at + INODE_SIZE].copy_from_slice(&inode);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_images::ExtImage;
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn reads_files_through_relative_and_absolute_symlinks() {
+        let mut image = ExtImage::new();
+        let usr = image.add(2, "usr", 0x41ED, &[]);
+        let lib = image.add(usr, "lib", 0x41ED, &[]);
+        image.add(lib, "os-release", 0x81A4, b"NAME=\"Debian GNU/Linux\"\n");
+        let etc = image.add(2, "etc", 0x41ED, &[]);
+        image.add(etc, "os-release", 0xA1FF, b"../usr/lib/os-release");
+        image.add(etc, "issue", 0xA1FF, b"/etc/os-release");
+
+        let mut reader = Cursor::new(image.data);
+        let mut fs = ExtFs::open(&mut reader, 0).unwrap();
+        let expected = b"NAME=\"Debian GNU/Linux\"\n".to_vec();
+        assert_eq!(
+            fs.read_file("/etc/os-release").unwrap(),
+            Some(expected.clone())
+        );
+        assert_eq!(fs.read_file("/etc/issue").unwrap(), Some(expected));
+        assert_eq!(fs.read_file("/etc/missing").unwrap(), None);
+        assert!(fs.read_file("/usr/lib").is_err());
+    }
+}
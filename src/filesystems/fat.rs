@@ -0,0 +1,272 @@
+//! Read-only access to files in the root directory of a FAT12/16/32 filesystem,
+//! by their 8.3 name (`ISSUE.TXT`).
+
+use anyhow::{bail, Context, Result};
+use std::io::{Read, Seek, SeekFrom};
+
+const DIR_ENTRY_SIZE: usize = 32;
+const ATTR_LONG_NAME: u8 = 0x0F;
+const ATTR_VOLUME_ID: u8 = 0x08;
+const ATTR_DIRECTORY: u8 = 0x10;
+/// Largest file read into memory.
+const MAX_FILE_BYTES: u64 = 1 << 20;
+/// Longest cluster chain followed, as a guard against loops.
+const MAX_CHAIN: usize = 1 << 16;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum FatType {
+    Fat12,
+    Fat16,
+    Fat32,
+}
+
+/// A FAT filesystem starting `offset` bytes into `reader`.
+pub struct FatFs<'a, R> {
+    reader: &'a mut R,
+    offset: u64,
+    fat_type: FatType,
+    bytes_per_sector: u64,
+    cluster_bytes: u64,
+    fat_start: u64,
+    /// Fixed root directory (FAT12/16): byte offset and size.
+    root_dir: (u64, u64),
+    root_cluster: u32,
+    data_start: u64,
+}
+
+impl<'a, R: Read + Seek> FatFs<'a, R> {
+    pub fn open(reader: &'a mut R, offset: u64) -> Result<Self> {
+        let mut bpb = [0u8; 512];
+        reader.seek(SeekFrom::Start(offset))?;
+        reader
+            .read_exact(&mut bpb)
+            .context("Failed to read the FAT boot sector")?;
+        let bytes_per_sector = u16_le(&bpb[11..]) as u64;
+        let sectors_per_cluster = bpb[13] as u64;
+        let reserved = u16_le(&bpb[14..]) as u64;
+        let fats = bpb[16] as u64;
+        let root_entries = u16_le(&bpb[17..]) as u64;
+        let total_sectors = match u16_le(&bpb[19..]) {
+            0 => u32_le(&bpb[32..]) as u64,
+            n => n as u64,
+        };
+        let fat_sectors = match u16_le(&bpb[22..]) {
+            0 => u32_le(&bpb[36..]) as u64,
+            n => n as u64,
+        };
+        if !bytes_per_sector.is_power_of_two()
+            || !(512..=4096).contains(&bytes_per_sector)
+            || sectors_per_cluster == 0
+            || fats == 0
+        {
+            bail!("No FAT boot sector");
+        }
+
+        let root_dir_sectors = (root_entries * DIR_ENTRY_SIZE as u64).div_ceil(bytes_per_sector);
+        let data_sector = reserved + fats * fat_sectors + root_dir_sectors;
+        let clusters = total_sectors.saturating_sub(data_sector) / sectors_per_cluster;
+        let fat_type = if root_entries == 0 {
+            FatType::Fat32
+        } else if clusters < 4085 {
+            FatType::Fat12
+        } else {
+            FatType::Fat16
+        };
+        Ok(Self {
+            reader,
+            offset,
+            fat_type,
+            bytes_per_sector,
+            cluster_bytes: sectors_per_cluster * bytes_per_sector,
+            fat_start: reserved * bytes_per_sector,
+            root_dir: (
+                (reserved + fats * fat_sectors) * bytes_per_sector,
+                root_dir_sectors * bytes_per_sector,
+            ),
+            root_cluster: u32_le(&bpb[44..]),
+            data_start: data_sector * bytes_per_sector,
+        })
+    }
+
+    /// Contents of the root-directory file with 8.3 `name`; `None` when absent.
+    pub fn read_root_file(&mut self, name: &str) -> Result<Option<Vec<u8>>> {
+        let root = match self.fat_type {
+            FatType::Fat32 => self.read_chain(self.root_cluster, None)?,
+            _ => {
+                let (at, len) = self.root_dir;
+                let mut root = vec![0u8; len as usize];
+                self.read_fs(at, &mut root)?;
+                root
+            }
+        };
+        let wanted = short_name(name);
+        for entry in root.chunks_exact(DIR_ENTRY_SIZE) {
+            match entry[0] {
+                0x00 => break,
+                0xE5 => continue,
+                _ => {}
+            }
+            let attr = entry[11];
+            if attr == ATTR_LONG_NAME || attr & (ATTR_VOLUME_ID | ATTR_DIRECTORY) != 0 {
+                continue;
+            }
+            if entry[..11].eq_ignore_ascii_case(&wanted) {
+                let cluster = (u16_le(&entry[20..]) as u32) << 16 | u16_le(&entry[26..]) as u32;
+                let size = u32_le(&entry[28..]) as u64;
+                if size > MAX_FILE_BYTES {
+                    bail!("{} is too large to inspect ({} bytes)", name, size);
+                }
+                return self.read_chain(cluster, Some(size)).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Data of the cluster chain from `first`, cut to `size` when given.
+    fn read_chain(&mut self, first: u32, size: Option<u64>) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+        let mut cluster = first;
+        for _ in 0..MAX_CHAIN {
+            if cluster < 2 || size.is_some_and(|size| data.len() as u64 >= size) {
+                break;
+            }
+            let mut chunk = vec![0u8; self.cluster_bytes as usize];
+            self.read_fs(
+                self.data_start + (cluster as u64 - 2) * self.cluster_bytes,
+                &mut chunk,
+            )?;
+            data.extend(chunk);
+            if size.is_none() && data.len() as u64 > MAX_FILE_BYTES {
+                bail!("Directory too large to inspect");
+            }
+            cluster = match self.next_cluster(cluster)? {
+                Some(next) => next,
+                None => break,
+            };
+        }
+        if let Some(size) = size {
+            data.truncate(size as usize);
+        }
+        Ok(data)
+    }
+
+    /// FAT entry for `cluster`; `None` at the end of the chain.
+    fn next_cluster(&mut self, cluster: u32) -> Result<Option<u32>> {
+        let (at, width) = match self.fat_type {
+            FatType::Fat12 => (cluster as u64 * 3 / 2, 2),
+            FatType::Fat16 => (cluster as u64 * 2, 2),
+            FatType::Fat32 => (cluster as u64 * 4, 4),
+        };
+        let mut raw = [0u8; 4];
+        self.read_fs(self.fat_start + at, &mut raw[..width])?;
+        let (next, end) = match self.fat_type {
+            FatType::Fat12 => {
+                let pair = u16_le(&raw) as u32;
+                let next = if cluster.is_multiple_of(2) {
+                    pair & 0xFFF
+                } else {
+                    pair >> 4
+                };
+                (next, 0xFF7)
+            }
+            FatType::Fat16 => (u16_le(&raw) as u32, 0xFFF7),
+            FatType::Fat32 => (u32_le(&raw) & 0x0FFF_FFFF, 0x0FFF_FFF7),
+        };
+        Ok((2..end).contains(&next).then_some(next))
+    }
+
+    fn read_fs(&mut self, at: u64, buf: &mut [u8]) -> Result<()> {
+        self.reader.seek(SeekFrom::Start(self.offset + at))?;
+        self.reader.read_exact(buf).with_context(|| {
+            format!(
+                "Failed to read the FAT filesystem ({}-byte sectors) at offset {}",
+                self.bytes_per_sector, at
+            )
+        })
+    }
+}
+
+/// `issue.txt` → `ISSUE   TXT`.
+fn short_name(name: &str) -> [u8; 11] {
+    let mut short = [b' '; 11];
+    let (base, ext) = name.rsplit_once('.').unwrap_or((name, ""));
+    for (slot, byte) in short[..8].iter_mut().zip(base.bytes()) {
+        *slot = byte.to_ascii_uppercase();
+    }
+    for (slot, byte) in short[8..].iter_mut().zip(ext.bytes()) {
+        *slot = byte.to_ascii_uppercase();
+    }
+    short
+}
+
+fn u16_le(bytes: &[u8]) -> u16 {
+    u16::from_le_bytes([bytes[0], bytes[1]])
+}
+
+fn u32_le(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes(bytes[..4].try_into().expect("4 bytes"))
+}
+
+#[cfg(test)]
+pub(crate) mod test_images {
+    /// FAT16 volume (512-byte sectors and clusters, 16 root entries) whose root
+    /// holds `files`, each stored in consecutive clusters.
+    pub fn fat16_with_files(files: &[(&str, &[u8])]) -> Vec<u8> {
+        const SECTOR: usize = 512;
+        let total_sectors = 8192usize;
+        let mut image = vec![0u8; total_sectors * SECTOR];
+        image[11..13].copy_from_slice(&(SECTOR as u16).to_le_bytes());
+        image[13] = 1; // sectors per cluster
+        image[14..16].copy_from_slice(&1u16.to_le_bytes()); // reserved
+        image[16] = 1; // FATs
+        image[17..19].copy_from_slice(&16u16.to_le_bytes()); // root entries
+        image[19..21].copy_from_slice(&(total_sectors as u16).to_le_bytes());
+        image[22..24].copy_from_slice(&32u16.to_le_bytes()); // FAT sectors
+        image[54..62].copy_from_slice(b"FAT16   ");
+        image[510..512].copy_from_slice(&[0x55, 0xAA]);
+
+        let fat = SECTOR;
+        let root = (1 + 32) * SECTOR;
+        let data = root + SECTOR;
+        let mut cluster = 2usize;
+        for (index, (name, contents)) in files.iter().enumerate() {
+            let entry = &mut image[root + index * 32..][..32];
+            entry[..11].copy_from_slice(&super::short_name(name));
+            entry[26..28].copy_from_slice(&(cluster as u16).to_le_bytes());
+            entry[28..32].copy_from_slice(&(contents.len() as u32).to_le_bytes());
+            let clusters = contents.len().div_ceil(SECTOR).max(1);
+            image[data + (cluster - 2) * SECTOR..][..contents.len()].copy_from_slice(contents);
+            for n in cluster..cluster + clusters {
+                let next: u16 = if n + 1 == cluster + clusters {
+                    0xFFFF
+                } else {
+                    n as u16 + 1
+                };
+                image[fat + n * 2..fat + n * 2 + 2].copy_from_slice(&next.to_le_bytes());
+            }
+            cluster += clusters;
+        }
+        image
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_images::fat16_with_files;
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn reads_root_files_across_clusters() {
+        let long = vec![b'x'; 1300];
+        let image = fat16_with_files(&[("config.txt", b"arm_64bit=1\n"), ("issue.txt", &long)]);
+        let mut reader = Cursor::new(image);
+        let mut fs = FatFs::open(&mut reader, 0).unwrap();
+        assert_eq!(
+            fs.read_root_file("CONFIG.TXT").unwrap(),
+            Some(b"arm_64bit=1\n".to_vec())
+        );
+        assert_eq!(fs.read_root_file("issue.txt").unwrap(), Some(long));
+        assert_eq!(fs.read_root_file("cmdline.txt").unwrap(), None);
+    }
+}
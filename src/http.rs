@@ -0,0 +1,457 @@
+//! HTTP(S) downloads for flashing straight from `http://` / `https://` URLs.
+//!
+//! [`HttpReader`] streams the body of a GET response fetched with `ureq`. When the
+//! connection drops or stalls it reconnects with `Range: bytes=<offset>-` (guarded by
+//! `If-Range` when the server sent a validator), so the reader above it never sees
+//! the interruption.
+
+use anyhow::{bail, Result};
+use log::{info, warn};
+use std::io::{self, Read};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+const MAX_REDIRECTS: u32 = 10;
+/// Consecutive failed reads / reconnects before the download is abandoned.
+const MAX_RESUME_ATTEMPTS: u32 = 5;
+/// Delay before reconnect attempt N is `RESUME_RETRY_DELAY * N`.
+const RESUME_RETRY_DELAY: Duration = Duration::from_secs(1);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+/// A read that stalls this long counts as a dropped connection.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// True for `http://` and `https://` image sources.
+pub fn is_url(source: &str) -> bool {
+    let scheme = source.get(..8).unwrap_or(source).to_ascii_lowercase();
+    scheme.starts_with("http://") || scheme.starts_with("https://")
+}
+
+/// Last path segment of a URL, without query or fragment (e.g. `os.img.xz`).
+pub fn url_file_name(url: &str) -> &str {
+    let end = url.find(['?', '#']).unwrap_or(url.len());
+    let path = &url[..end];
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+/// Start offset of a `Content-Range: bytes <start>-<end>/<total>` header.
+fn content_range_start(value: &str) -> Option<u64> {
+    let range = value.trim().strip_prefix("bytes")?.trim_start();
+    range.split('-').next()?.trim().parse().ok()
+}
+
+/// Strong `ETag`, or `Last-Modified`, usable as an `If-Range` validator.
+fn resume_validator(response: &ureq::Response) -> Option<String> {
+    response
+        .header("etag")
+        .filter(|etag| !etag.starts_with("W/"))
+        .or_else(|| response.header("last-modified"))
+        .map(str::to_string)
+}
+
+/// Streaming body of an HTTP(S) GET that resumes with Range requests after a drop.
+pub struct HttpReader {
+    agent: ureq::Agent,
+    /// URL after redirects; resumes go straight there.
+    url: String,
+    body: Option<Box<dyn Read + Send + Sync>>,
+    /// Offset of the next body byte.
+    position: u64,
+    content_length: Option<u64>,
+    validator: Option<String>,
+    received: Arc<AtomicU64>,
+}
+
+impl HttpReader {
+    /// Connect, follow redirects and read the response head. Fails on any status but 200.
+    pub fn open(url: &str) -> Result<Self> {
+        let agent = ureq::AgentBuilder::new()
+            .timeout_connect(CONNECT_TIMEOUT)
+            .timeout_read(READ_TIMEOUT)
+            .timeout_write(READ_TIMEOUT)
+            .redirects(MAX_REDIRECTS)
+            // A resume must not reuse the connection that just failed.
+            .max_idle_connections(0)
+            .user_agent(concat!("litho/", env!("CARGO_PKG_VERSION")))
+            .build();
+        let response = match agent.get(url).call() {
+            Ok(response) => response,
+            Err(ureq::Error::Status(status, response)) => {
+                bail!("HTTP {} {} for {}", status, response.status_text(), url)
+            }
+            Err(error) => bail!("Failed to download {}: {}", url, error),
+        };
+        if response.status() != 200 {
+            bail!(
+                "HTTP {} {} for {}",
+                response.status(),
+                response.status_text(),
+                url
+            );
+        }
+        let final_url = response.get_url().to_string();
+        if final_url != url {
+            info!("Redirected from {} to {}", url, final_url);
+        }
+        let content_length = if response.header("transfer-encoding").is_some() {
+            None
+        } else {
+            response
+                .header("content-length")
+                .and_then(|length| length.parse().ok())
+        };
+        Ok(Self {
+            agent,
+            url: final_url,
+            validator: resume_validator(&response),
+            body: Some(response.into_reader()),
+            position: 0,
+            content_length,
+            received: Arc::new(AtomicU64::new(0)),
+        })
+    }
+
+    /// Body length from `Content-Length`, when the server sent one.
+    pub fn content_length(&self) -> Option<u64> {
+        self.content_length
+    }
+
+    /// `ETag` or `Last-Modified` of the response, identifying this version of the file.
+    pub fn validator(&self) -> Option<&str> {
+        self.validator.as_deref()
+    }
+
+    /// Shared count of body bytes received so far, for download progress.
+    pub fn received(&self) -> Arc<AtomicU64> {
+        self.received.clone()
+    }
+
+    fn read_body(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let body = self
+            .body
+            .as_mut()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotConnected, "not connected"))?;
+        body.read(buf)
+    }
+
+    /// Reconnect and continue at `position`. `InvalidData` errors are final.
+    fn resume(&mut self) -> io::Result<()> {
+        self.body = None;
+        let mut request = self
+            .agent
+            .get(&self.url)
+            .set("Range", &format!("bytes={}-", self.position));
+        if let Some(validator) = &self.validator {
+            request = request.set("If-Range", validator);
+        }
+        let response = match request.call() {
+            Ok(response) => response,
+            Err(ureq::Error::Status(status @ (408 | 429 | 500..=599), response)) => {
+                return Err(io::Error::other(format!(
+                    "HTTP {} {} for {}",
+                    status,
+                    response.status_text(),
+                    self.url
+                )));
+            }
+            Err(ureq::Error::Status(status, response)) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "HTTP {} {} for {}",
+                        status,
+                        response.status_text(),
+                        self.url
+                    ),
+                ));
+            }
+            Err(error) => return Err(io::Error::other(error.to_string())),
+        };
+        match response.status() {
+            206 => {
+                let start = response
+                    .header("content-range")
+                    .and_then(content_range_start);
+                if start != Some(self.position) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "Server resumed {} at {:?} instead of byte {}",
+                            self.url, start, self.position
+                        ),
+                    ));
+                }
+                self.body = Some(response.into_reader());
+            }
+            200 if self.validator.is_some() => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{} changed on the server during the download", self.url),
+                ));
+            }
+            200 => {
+                info!(
+                    "{} does not support range requests; skipping {} bytes",
+                    self.url, self.position
+                );
+                self.body = Some(response.into_reader());
+                self.discard_prefix()?;
+            }
+            status => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "HTTP {} {} for {}",
+                        status,
+                        response.status_text(),
+                        self.url
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// After a full (non-range) response on resume, drop the bytes already delivered.
+    fn discard_prefix(&mut self) -> io::Result<()> {
+        let mut buffer = vec![0u8; 64 * 1024];
+        let mut left = self.position;
+        while left > 0 {
+            let max = buffer
+                .len()
+                .min(usize::try_from(left).unwrap_or(usize::MAX));
+            let read = self.read_body(&mut buffer[..max])?;
+            if read == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "connection closed while skipping to the resume offset",
+                ));
+            }
+            left -= read as u64;
+        }
+        Ok(())
+    }
+}
+
+impl Read for HttpReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let mut attempts = 0;
+        loop {
+            let error = match self.read_body(buf) {
+                Ok(0)
+                    if self
+                        .content_length
+                        .is_some_and(|length| self.position < length) =>
+                {
+                    io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "connection closed before the end of the body",
+                    )
+                }
+                Ok(0) => return Ok(0),
+                Ok(read) => {
+                    self.position += read as u64;
+                    self.received.fetch_add(read as u64, Ordering::Relaxed);
+                    return Ok(read);
+                }
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) if error.kind() == io::ErrorKind::InvalidData => return Err(error),
+                Err(error) => error,
+            };
+            attempts += 1;
+            if attempts > MAX_RESUME_ATTEMPTS {
+                return Err(io::Error::new(
+                    error.kind(),
+                    format!(
+                        "Download of {} failed at byte {}: {}",
+                        self.url, self.position, error
+                    ),
+                ));
+            }
+            warn!(
+                "Download interrupted at byte {} ({}); resuming (attempt {}/{})",
+                self.position, error, attempts, MAX_RESUME_ATTEMPTS
+            );
+            thread::sleep(RESUME_RETRY_DELAY * attempts);
+            if let Err(error) = self.resume() {
+                if error.kind() == io::ErrorKind::InvalidData {
+                    return Err(error);
+                }
+                warn!("Resume failed: {}", error);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::OperationProgress;
+    use crate::range::IoRange;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use tempfile::NamedTempFile;
+
+    /// Local stand-in server: answers connection N with `handler(N, request_head)` and
+    /// closes it. Joining the handle returns the request heads.
+    fn serve<H>(connections: usize, handler: H) -> (String, thread::JoinHandle<Vec<String>>)
+    where
+        H: Fn(usize, &str) -> Vec<u8> + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let mut requests = Vec::new();
+            for index in 0..connections {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut head = String::new();
+                loop {
+                    let mut line = String::new();
+                    if reader.read_line(&mut line).unwrap() == 0 || line == "\r\n" {
+                        break;
+                    }
+                    head.push_str(&line);
+                }
+                let _ = stream.write_all(&handler(index, &head));
+                requests.push(head);
+            }
+            requests
+        });
+        (base, handle)
+    }
+
+    fn response(head: &str, body: &[u8]) -> Vec<u8> {
+        let mut bytes = format!("{}\r\n\r\n", head).into_bytes();
+        bytes.extend_from_slice(body);
+        bytes
+    }
+
+    fn pattern(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 239) as u8).collect()
+    }
+
+    #[test]
+    fn recognises_urls_and_file_names() {
+        assert_eq!(
+            url_file_name("https://example.com/a/b.img.xz?x=1#top"),
+            "b.img.xz"
+        );
+        assert!(is_url("HTTPS://example.com/") && is_url("http://x"));
+        assert!(!is_url("/tmp/http.img"));
+        assert_eq!(content_range_start("bytes 40000-99999/100000"), Some(40000));
+    }
+
+    #[test]
+    fn resumes_with_a_range_request_after_a_drop() {
+        let data = pattern(100_000);
+        let served = data.clone();
+        let (base, server) = serve(2, move |index, _| {
+            match index {
+            0 => response(
+                "HTTP/1.1 200 OK\r\nContent-Length: 100000\r\nETag: \"v1\"",
+                &served[..40_000],
+            ),
+            _ => response(
+                "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 40000-99999/100000\r\nContent-Length: 60000",
+                &served[40_000..],
+            ),
+        }
+        });
+
+        let mut reader = HttpReader::open(&format!("{}/disk.img", base)).unwrap();
+        assert_eq!(reader.content_length(), Some(100_000));
+        let mut body = Vec::new();
+        reader.read_to_end(&mut body).unwrap();
+
+        assert_eq!(body, data);
+        assert_eq!(reader.received().load(Ordering::Relaxed), 100_000);
+        let requests = server.join().unwrap();
+        assert!(requests[1].contains("Range: bytes=40000-\r\n"));
+        assert!(requests[1].contains("If-Range: \"v1\"\r\n"));
+    }
+
+    #[test]
+    fn follows_redirects_and_decodes_chunks() {
+        let (base, server) = serve(2, |index, _| match index {
+            0 => response(
+                "HTTP/1.1 302 Found\r\nLocation: files/img.bin\r\nContent-Length: 0",
+                b"",
+            ),
+            _ => response(
+                "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked",
+                b"5\r\nhello\r\n6;ext=1\r\n world\r\n0\r\n\r\n",
+            ),
+        });
+
+        let mut reader = HttpReader::open(&format!("{}/latest/", base)).unwrap();
+        assert_eq!(reader.content_length(), None);
+        let mut body = String::new();
+        reader.read_to_string(&mut body).unwrap();
+
+        assert_eq!(body, "hello world");
+        assert!(server.join().unwrap()[1].starts_with("GET /latest/files/img.bin "));
+    }
+
+    #[test]
+    fn refuses_to_resume_a_changed_image() {
+        let data = pattern(10_000);
+        let (base, server) = serve(2, move |index, _| {
+            let head = "HTTP/1.1 200 OK\r\nContent-Length: 10000\r\nETag: \"v2\"";
+            match index {
+                0 => response(head, &data[..4_000]),
+                _ => response(head, &data),
+            }
+        });
+
+        let mut reader = HttpReader::open(&format!("{}/disk.img", base)).unwrap();
+        let error = reader.read_to_end(&mut Vec::new()).unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("changed on the server"));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn flash_url_streams_an_xz_download_to_the_device() {
+        let image = pattern(300_000);
+        let compressed = lzma::compress(&image, 6).unwrap();
+        let length = compressed.len();
+        let (base, server) = serve(1, move |_, _| {
+            response(
+                &format!("HTTP/1.1 200 OK\r\nContent-Length: {}", length),
+                &compressed,
+            )
+        });
+        let device = NamedTempFile::new().unwrap();
+        device.as_file().set_len(512 * 1024).unwrap();
+        let mut last_download = None;
+
+        crate::flash(
+            format!("{}/os.img.xz?mirror=1", base),
+            device.path().to_str().unwrap().to_string(),
+            4096,
+            IoRange::default(),
+            false,
+            true,
+            None,
+            None,
+            Some(|event: OperationProgress| {
+                if event.bytes_downloaded.is_some() {
+                    last_download = event.bytes_downloaded.zip(event.download_total);
+                }
+            }),
+            None,
+        )
+        .unwrap();
+
+        let written = std::fs::read(device.path()).unwrap();
+        assert_eq!(&written[..image.len()], &image[..]);
+        assert_eq!(last_download, Some((length as u64, length as u64)));
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,591 @@
+//! What an image file holds before it is flashed: container or compression format,
+//! uncompressed size, partition table, filesystems, ISO 9660 details and the
+//! operating system on it.
+
+use crate::cache::ImageCache;
+use crate::filesystems::{probe_filesystem, ExtFs, FatFs, Filesystem, ISO9660_PRIMARY_DESCRIPTOR};
+use crate::http;
+use crate::partitions::{crc32, read_partition_table, PartitionTable};
+use crate::progress::OperationProgress;
+use anyhow::{bail, Context, Result};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use tempfile::NamedTempFile;
+
+/// ISO 9660 volume descriptors are read up to this many sectors past the first.
+const MAX_ISO_DESCRIPTORS: u64 = 32;
+/// Largest xz index read into memory.
+const MAX_XZ_INDEX_BYTES: u64 = 64 << 20;
+const XZ_HEADER_MAGIC: [u8; 6] = [0xFD, b'7', b'z', b'X', b'Z', 0x00];
+
+/// Container or compression format, from the file's magic bytes.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    /// Uncompressed disk image (including plain ISO 9660 images).
+    Raw,
+    Xz,
+    Gzip,
+    Bzip2,
+    Zstd,
+    Zip,
+    Qcow2,
+    Vhdx,
+    Vmdk,
+}
+
+impl fmt::Display for ImageFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Raw => "raw",
+            Self::Xz => "xz",
+            Self::Gzip => "gzip",
+            Self::Bzip2 => "bzip2",
+            Self::Zstd => "zstd",
+            Self::Zip => "zip",
+            Self::Qcow2 => "qcow2",
+            Self::Vhdx => "vhdx",
+            Self::Vmdk => "vmdk",
+        })
+    }
+}
+
+impl ImageFormat {
+    /// Detect the format from the first bytes of a file.
+    pub fn detect(magic: &[u8]) -> Self {
+        let starts = |prefix: &[u8]| magic.starts_with(prefix);
+        if starts(&XZ_HEADER_MAGIC) {
+            Self::Xz
+        } else if starts(&[0x1F, 0x8B]) {
+            Self::Gzip
+        } else if starts(b"BZh") {
+            Self::Bzip2
+        } else if starts(&[0x28, 0xB5, 0x2F, 0xFD]) {
+            Self::Zstd
+        } else if starts(b"PK\x03\x04") {
+            Self::Zip
+        } else if starts(b"QFI\xFB") {
+            Self::Qcow2
+        } else if starts(b"vhdxfile") {
+            Self::Vhdx
+        } else if starts(b"KDMV") {
+            Self::Vmdk
+        } else {
+            Self::Raw
+        }
+    }
+
+    /// Whether [`inspect_image`] can look inside images of this format.
+    pub fn is_readable(self) -> bool {
+        matches!(self, Self::Raw | Self::Xz)
+    }
+}
+
+/// ISO 9660 details of an image.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct IsoInfo {
+    pub volume_id: Option<String>,
+    /// Has an El Torito boot record (bootable from optical media).
+    pub el_torito: bool,
+    /// Also carries an MBR or GPT, so it boots when written to a USB stick.
+    pub hybrid: bool,
+}
+
+/// Operating system identified from `/etc/os-release` (ext2/3/4) or `issue.txt`
+/// (FAT boot partition).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct OsRelease {
+    pub pretty_name: String,
+    /// `ID` from os-release (`debian`, `ubuntu`, …).
+    pub id: Option<String>,
+    pub version_id: Option<String>,
+    /// Where it was read: `partition 2: /etc/os-release`.
+    pub source: String,
+}
+
+/// Everything [`inspect_image`] found out about an image file.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ImageReport {
+    pub path: String,
+    pub format: ImageFormat,
+    /// Size of the file as stored.
+    pub file_size: u64,
+    /// Size of the raw disk image, after decompression, when known.
+    pub image_size: Option<u64>,
+    pub partition_table: Option<PartitionTable>,
+    /// Filesystem spanning the whole image, when it has no partition table.
+    pub filesystem: Option<Filesystem>,
+    pub iso9660: Option<IsoInfo>,
+    pub os_release: Option<OsRelease>,
+    /// Parts that could not be inspected, and why.
+    pub notes: Vec<String>,
+}
+
+/// Inspect a local image file.
+///
+/// Raw images are read in place. `.xz` images are decompressed first, into the
+/// image cache when one is given (so a following flash reuses it) or else into a
+/// temporary file; their uncompressed size comes from the xz index either way.
+/// Other compressed formats are only identified and sized.
+pub fn inspect_image(path: &str, cache: Option<&ImageCache>) -> Result<ImageReport> {
+    if http::is_url(path) {
+        bail!("Only local image files can be inspected: {}", path);
+    }
+    let mut file = File::open(path).with_context(|| format!("Image file not found: {}", path))?;
+    let file_size = file
+        .metadata()
+        .with_context(|| format!("Failed to read metadata of {}", path))?
+        .len();
+    let mut magic = Vec::with_capacity(8);
+    (&mut file)
+        .take(8)
+        .read_to_end(&mut magic)
+        .with_context(|| format!("Failed to read {}", path))?;
+
+    let format = ImageFormat::detect(&magic);
+    let mut report = ImageReport {
+        path: path.to_string(),
+        format,
+        file_size,
+        image_size: None,
+        partition_table: None,
+        filesystem: None,
+        iso9660: None,
+        os_release: None,
+        notes: Vec::new(),
+    };
+    info!("Inspecting {} ({} image)", path, format);
+
+    match format {
+        ImageFormat::Raw => {
+            report.image_size = Some(file_size);
+            inspect_disk(&mut file, &mut report);
+        }
+        ImageFormat::Xz => {
+            match xz_uncompressed_size(&mut file, file_size) {
+                Ok(size) => report.image_size = Some(size),
+                Err(e) => report.notes.push(format!("xz index unreadable: {e:#}")),
+            }
+            inspect_xz(path, cache, &mut report)?;
+        }
+        ImageFormat::Gzip => {
+            let mut isize = [0u8; 4];
+            file.seek(SeekFrom::End(-4))?;
+            file.read_exact(&mut isize)
+                .with_context(|| format!("Failed to read the gzip trailer of {}", path))?;
+            let isize = u32::from_le_bytes(isize) as u64;
+            report.image_size = Some(isize);
+            // A disk image hardly ever compresses to more than its own size.
+            if file_size >= 1 << 32 || isize < file_size {
+                report
+                    .notes
+                    .push("gzip records the size modulo 4 GiB".to_string());
+            }
+            report.notes.push(not_inspected(format));
+        }
+        ImageFormat::Qcow2 => {
+            let mut size = [0u8; 8];
+            file.seek(SeekFrom::Start(24))?;
+            file.read_exact(&mut size)
+                .with_context(|| format!("Failed to read the qcow2 header of {}", path))?;
+            report.image_size = Some(u64::from_be_bytes(size));
+            report.notes.push(not_inspected(format));
+        }
+        _ => report.notes.push(not_inspected(format)),
+    }
+    Ok(report)
+}
+
+fn not_inspected(format: ImageFormat) -> String {
+    format!("Contents not inspected: litho cannot read {format} images (only raw and xz)")
+}
+
+/// Decompress an xz image (into the cache, or a temporary file) and inspect it.
+fn inspect_xz(path: &str, cache: Option<&ImageCache>, report: &mut ImageReport) -> Result<()> {
+    let mut no_progress = None::<fn(OperationProgress)>;
+    if let Some(cache) = cache {
+        let entry = crate::cached_xz_image(cache, path, true, &mut no_progress, None)?;
+        let raw_path = cache.image_path(&entry);
+        let mut raw = File::open(&raw_path)
+            .with_context(|| format!("Failed to open cached image {}", raw_path.display()))?;
+        inspect_disk(&mut raw, report);
+        return Ok(());
+    }
+
+    let temp = NamedTempFile::new().context("Failed to create temporary file")?;
+    let temp_path = temp
+        .path()
+        .to_str()
+        .context("Failed to convert path to string")?
+        .to_string();
+    crate::decompress_img(path.to_string(), temp_path, true, &mut no_progress, None)?;
+    let mut raw = temp
+        .reopen()
+        .context("Failed to reopen the decompressed image")?;
+    inspect_disk(&mut raw, report);
+    Ok(())
+}
+
+/// Fill in the table, filesystems, ISO details and OS of a raw disk image.
+fn inspect_disk<R: Read + Seek>(reader: &mut R, report: &mut ImageReport) {
+    report.iso9660 = read_iso_info(reader);
+    let whole = probe_filesystem(reader, 0);
+    let table = match read_partition_table(reader) {
+        Ok(table) => table,
+        Err(e) => {
+            report
+                .notes
+                .push(format!("Partition table unreadable: {e:#}"));
+            None
+        }
+    };
+    (report.partition_table, report.filesystem) = match (table, whole) {
+        // A filesystem boot sector (FAT superfloppy) also ends in 0x55AA; its
+        // "partition entries" are boot code.
+        (Some(_), Some(fs)) if fs.kind != "ISO9660" => (None, Some(fs)),
+        (Some(table), _) => (Some(table), None),
+        (None, whole) => (None, whole),
+    };
+    if let Some(iso) = &mut report.iso9660 {
+        iso.hybrid = report.partition_table.is_some();
+    }
+    report.os_release = guess_os(
+        reader,
+        report.partition_table.as_ref(),
+        report.filesystem.as_ref(),
+    );
+}
+
+/// Volume ID and El Torito record from the ISO 9660 volume descriptors.
+fn read_iso_info<R: Read + Seek>(reader: &mut R) -> Option<IsoInfo> {
+    let mut info: Option<IsoInfo> = None;
+    let mut el_torito = false;
+    let mut descriptor = [0u8; 2048];
+    for index in 0..MAX_ISO_DESCRIPTORS {
+        reader
+            .seek(SeekFrom::Start(ISO9660_PRIMARY_DESCRIPTOR + index * 2048))
+            .ok()?;
+        if reader.read_exact(&mut descriptor).is_err() || &descriptor[1..6] != b"CD001" {
+            break;
+        }
+        match descriptor[0] {
+            0 if descriptor[7..30].starts_with(b"EL TORITO SPECIFICATION") => el_torito = true,
+            1 => {
+                let volume_id = String::from_utf8_lossy(&descriptor[40..72])
+                    .trim()
+                    .to_string();
+                info = Some(IsoInfo {
+                    volume_id: (!volume_id.is_empty()).then_some(volume_id),
+                    el_torito: false,
+                    hybrid: false,
+                });
+            }
+            255 => break,
+            _ => {}
+        }
+    }
+    info.map(|info| IsoInfo { el_torito, ..info })
+}
+
+/// The operating system on a disk: `/etc/os-release` of the first ext2/3/4
+/// filesystem that has one, else `issue.txt` of a FAT boot partition.
+pub fn guess_os<R: Read + Seek>(
+    reader: &mut R,
+    table: Option<&PartitionTable>,
+    whole: Option<&Filesystem>,
+) -> Option<OsRelease> {
+    let mut candidates: Vec<(String, u64, &Filesystem)> = Vec::new();
+    if let Some(table) = table {
+        for partition in table.data_partitions() {
+            if let Some(fs) = &partition.filesystem {
+                candidates.push((
+                    format!("partition {}", partition.number),
+                    partition.first_lba * table.sector_size,
+                    fs,
+                ));
+            }
+        }
+    } else if let Some(fs) = whole {
+        candidates.push(("image".to_string(), 0, fs));
+    }
+
+    let ext = candidates
+        .iter()
+        .filter(|(_, _, fs)| fs.kind.starts_with("ext"))
+        .find_map(|(place, offset, _)| {
+            let mut ext = ExtFs::open(&mut *reader, *offset)
+                .map_err(|e| debug!("Skipping ext filesystem of {}: {:#}", place, e))
+                .ok()?;
+            ["/etc/os-release", "/usr/lib/os-release"]
+                .into_iter()
+                .find_map(|path| {
+                    let text = ext.read_file(path).ok()??;
+                    parse_os_release(&String::from_utf8_lossy(&text), format!("{place}: {path}"))
+                })
+        });
+    ext.or_else(|| {
+        candidates
+            .iter()
+            .filter(|(_, _, fs)| fs.kind.starts_with("FAT"))
+            .find_map(|(place, offset, _)| {
+                let mut fat = FatFs::open(&mut *reader, *offset).ok()?;
+                let text = fat.read_root_file("issue.txt").ok()??;
+                let first = String::from_utf8_lossy(&text)
+                    .lines()
+                    .map(str::trim)
+                    .find(|line| !line.is_empty())?
+                    .to_string();
+                Some(OsRelease {
+                    pretty_name: first,
+                    id: None,
+                    version_id: None,
+                    source: format!("{place}: issue.txt"),
+                })
+            })
+    })
+}
+
+/// `PRETTY_NAME` (or `NAME VERSION`), `ID` and `VERSION_ID` of an os-release file.
+fn parse_os_release(text: &str, source: String) -> Option<OsRelease> {
+    let field = |key: &str| {
+        text.lines().find_map(|line| {
+            let value = line.trim().strip_prefix(key)?.strip_prefix('=')?;
+            let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+            (!value.is_empty()).then(|| value.to_string())
+        })
+    };
+    let pretty_name = field("PRETTY_NAME").or_else(|| {
+        let name = field("NAME")?;
+        Some(match field("VERSION") {
+            Some(version) => format!("{name} {version}"),
+            None => name,
+        })
+    })?;
+    Some(OsRelease {
+        pretty_name,
+        id: field("ID"),
+        version_id: field("VERSION_ID"),
+        source,
+    })
+}
+
+/// Uncompressed size of an xz file from the index of each stream, without
+/// decompressing; handles concatenated streams and stream padding.
+fn xz_uncompressed_size<R: Read + Seek>(reader: &mut R, file_size: u64) -> Result<u64> {
+    let mut end = file_size;
+    let mut total = 0u64;
+    while end > 0 {
+        if end < 32 || !end.is_multiple_of(4) {
+            bail!("Truncated xz stream");
+        }
+        let mut footer = [0u8; 12];
+        read_at(reader, end - 12, &mut footer)?;
+        if footer[8..] == [0; 4] {
+            end -= 4; // stream padding
+            continue;
+        }
+        if &footer[10..] != b"YZ" {
+            bail!("No xz stream footer");
+        }
+        let index_size = (u32::from_le_bytes(footer[4..8].try_into()?) as u64 + 1) * 4;
+        if index_size > MAX_XZ_INDEX_BYTES {
+            bail!("xz index too large");
+        }
+        let index_start = (end - 12)
+            .checked_sub(index_size)
+            .context("Truncated xz index")?;
+        let mut index = vec![0u8; index_size as usize];
+        read_at(reader, index_start, &mut index)?;
+        let (body, stored_crc) = index.split_at(index.len() - 4);
+        if body[0] != 0 || crc32(body).to_le_bytes() != stored_crc {
+            bail!("Corrupt xz index");
+        }
+
+        let mut at = 1;
+        let records = varint(body, &mut at)?;
+        let mut blocks_size = 0u64;
+        for _ in 0..records {
+            let unpadded = varint(body, &mut at)?;
+            let uncompressed = varint(body, &mut at)?;
+            blocks_size = blocks_size.saturating_add(unpadded.next_multiple_of(4));
+            total = total.saturating_add(uncompressed);
+        }
+
+        let stream_start = index_start
+            .checked_sub(blocks_size + 12)
+            .context("Truncated xz stream")?;
+        let mut header = [0u8; 6];
+        read_at(reader, stream_start, &mut header)?;
+        if header != XZ_HEADER_MAGIC {
+            bail!("No xz stream header at offset {}", stream_start);
+        }
+        end = stream_start;
+    }
+    Ok(total)
+}
+
+/// xz multibyte integer (7 bits per byte, little-endian, at most 9 bytes).
+fn varint(bytes: &[u8], at: &mut usize) -> Result<u64> {
+    let mut value = 0u64;
+    for shift in 0..9 {
+        let byte = *bytes.get(*at).context("Truncated xz index")?;
+        *at += 1;
+        value |= ((byte & 0x7F) as u64) << (shift * 7);
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    bail!("Corrupt xz index integer")
+}
+
+fn read_at<R: Read + Seek>(reader: &mut R, at: u64, buf: &mut [u8]) -> Result<()> {
+    reader.seek(SeekFrom::Start(at))?;
+    reader
+        .read_exact(buf)
+        .with_context(|| format!("Failed to read {} bytes at offset {}", buf.len(), at))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystems::test_images as fs_images;
+    use crate::partitions::test_images::mbr_disk;
+    use std::io::{Cursor, Write};
+
+    /// MBR disk: FAT16 boot partition with `issue.txt`, ext4 root with os-release.
+    fn pi_image() -> Vec<u8> {
+        let mut disk = mbr_disk(20480, &[(0x0E, 2048, 8192), (0x83, 10240, 10240)]);
+        let fat = fs_images::fat16_with_files(&[("issue.txt", b"Raspberry Pi reference\n")]);
+        disk[2048 * 512..][..fat.len()].copy_from_slice(&fat);
+
+        let mut ext = fs_images::ExtImage::new();
+        let etc = ext.add(2, "etc", 0x41ED, &[]);
+        ext.add(
+            etc,
+            "os-release",
+            0x81A4,
+            b"PRETTY_NAME=\"Debian GNU/Linux 12 (bookworm)\"\nID=debian\nVERSION_ID=\"12\"\n",
+        );
+        disk[10240 * 512..][..ext.data.len()].copy_from_slice(&ext.data);
+        disk
+    }
+
+    fn write_temp(contents: &[u8]) -> NamedTempFile {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(contents).unwrap();
+        file
+    }
+
+    #[test]
+    fn raw_and_xz_images_report_table_filesystems_and_os() {
+        let disk = pi_image();
+        let raw = write_temp(&disk);
+        let report = inspect_image(raw.path().to_str().unwrap(), None).unwrap();
+        assert_eq!(report.format, ImageFormat::Raw);
+        assert_eq!(report.image_size, Some(disk.len() as u64));
+        assert_eq!(
+            report.partition_table.as_ref().unwrap().summary(),
+            "2 partitions: FAT16 4M, ext4 5M"
+        );
+        assert_eq!(
+            report.os_release,
+            Some(OsRelease {
+                pretty_name: "Debian GNU/Linux 12 (bookworm)".to_string(),
+                id: Some("debian".to_string()),
+                version_id: Some("12".to_string()),
+                source: "partition 2: /etc/os-release".to_string(),
+            })
+        );
+        assert!(report.notes.is_empty());
+
+        let xz = write_temp(&lzma::compress(&disk, 6).unwrap());
+        let report = inspect_image(xz.path().to_str().unwrap(), None).unwrap();
+        assert_eq!(report.format, ImageFormat::Xz);
+        assert_eq!(report.image_size, Some(disk.len() as u64));
+        assert_eq!(report.partition_table.unwrap().partitions.len(), 2);
+        assert!(report.os_release.is_some());
+    }
+
+    #[test]
+    fn fat_issue_txt_is_the_fallback_os_guess() {
+        let mut disk = pi_image();
+        disk[10240 * 512..].fill(0);
+        let mut reader = Cursor::new(disk);
+        let table = read_partition_table(&mut reader).unwrap().unwrap();
+        let os = guess_os(&mut reader, Some(&table), None).unwrap();
+        assert_eq!(os.pretty_name, "Raspberry Pi reference");
+        assert_eq!(os.source, "partition 1: issue.txt");
+    }
+
+    #[test]
+    fn xz_size_covers_concatenated_streams_and_padding() {
+        let first = vec![7u8; 300_000];
+        let second = vec![1u8; 12345];
+        let mut xz = lzma::compress(&first, 6).unwrap();
+        xz.extend([0u8; 8]);
+        xz.extend(lzma::compress(&second, 6).unwrap());
+        let size = xz_uncompressed_size(&mut Cursor::new(&xz), xz.len() as u64).unwrap();
+        assert_eq!(size, 312_345);
+
+        xz.truncate(xz.len() - 12);
+        assert!(xz_uncompressed_size(&mut Cursor::new(&xz), xz.len() as u64).is_err());
+    }
+
+    #[test]
+    fn iso_volume_id_el_torito_and_hybrid() {
+        let mut iso = vec![0u8; 0xA000];
+        let descriptor = |iso: &mut Vec<u8>, sector: usize, kind: u8| {
+            let at = sector * 2048;
+            iso[at] = kind;
+            iso[at + 1..at + 6].copy_from_slice(b"CD001");
+            at
+        };
+        let at = descriptor(&mut iso, 16, 1);
+        iso[at + 40..at + 72]
+            .copy_from_slice(format!("{:<32}", "Ubuntu 24.04 LTS amd64").as_bytes());
+        let at = descriptor(&mut iso, 17, 0);
+        iso[at + 7..at + 30].copy_from_slice(b"EL TORITO SPECIFICATION");
+        descriptor(&mut iso, 18, 255);
+
+        let mut report = ImageReport {
+            path: String::new(),
+            format: ImageFormat::Raw,
+            file_size: 0,
+            image_size: None,
+            partition_table: None,
+            filesystem: None,
+            iso9660: None,
+            os_release: None,
+            notes: Vec::new(),
+        };
+        inspect_disk(&mut Cursor::new(&iso), &mut report);
+        assert_eq!(
+            report.iso9660,
+            Some(IsoInfo {
+                volume_id: Some("Ubuntu 24.04 LTS amd64".to_string()),
+                el_torito: true,
+                hybrid: false,
+            })
+        );
+        assert_eq!(report.filesystem.as_ref().unwrap().kind, "ISO9660");
+
+        iso[..512].copy_from_slice(&mbr_disk(1, &[(0x00, 0, 0), (0xEF, 64, 32)]));
+        inspect_disk(&mut Cursor::new(&iso), &mut report);
+        assert!(report.iso9660.unwrap().hybrid);
+        assert!(report.partition_table.is_some());
+    }
+
+    #[test]
+    fn formats_from_magic() {
+        assert_eq!(ImageFormat::detect(&XZ_HEADER_MAGIC), ImageFormat::Xz);
+        assert_eq!(ImageFormat::detect(&[0x1F, 0x8B, 8]), ImageFormat::Gzip);
+        assert_eq!(
+            ImageFormat::detect(b"QFI\xFB\0\0\0\x03"),
+            ImageFormat::Qcow2
+        );
+        assert_eq!(ImageFormat::detect(&[0u8; 8]), ImageFormat::Raw);
+    }
+}
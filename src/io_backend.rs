@@ -3,9 +3,14 @@
 //! - **Default (`simulated-io`)** — safe for development and `cargo test`; no block writes.
 //! - **Release (`real-io`)** — `cargo build --no-default-features --features real-io`.
 
-use crate::progress::OperationProgress;
+use crate::cache::ImageCache;
+use crate::platform::DiscardMethod;
+use crate::progress::{OperationControl, OperationProgress};
+use crate::range::IoRange;
+use crate::rescue::RescueSummary;
+use crate::wipe::WipeMethod;
 use anyhow::Result;
-use std::sync::atomic::AtomicBool;
+use std::io::{Read, Write};
 
 #[cfg(all(feature = "real-io", feature = "simulated-io"))]
 compile_error!("Features `real-io` and `simulated-io` are mutually exclusive. Build real I/O with: cargo build --no-default-features --features real-io");
@@ -21,14 +26,18 @@ use crate::cli_simulate;
 /// True when flash/clone use the simulator instead of `liblitho::flash` / `clone`.
 pub const USES_SIMULATED_IO: bool = cfg!(not(feature = "real-io"));
 
+#[allow(clippy::too_many_arguments)]
 pub fn flash_io<F>(
     image: &str,
     device: &str,
     block_size: usize,
+    range: IoRange,
     silent: bool,
     verify: bool,
+    discard: Option<DiscardMethod>,
+    cache: Option<&ImageCache>,
     progress: Option<F>,
-    cancel: Option<&AtomicBool>,
+    control: Option<&OperationControl>,
 ) -> Result<()>
 where
     F: FnMut(OperationProgress),
@@ -39,53 +48,236 @@ where
             image.to_string(),
             device.to_string(),
             block_size,
+            range,
             silent,
             verify,
+            discard,
+            cache,
             progress,
-            cancel,
+            control,
         )
     }
 
     #[cfg(not(feature = "real-io"))]
     {
         cli_simulate::simulate_flash(
-            image,
-            device,
+            image, device, block_size, range, silent, verify, discard, cache, progress, control,
+        )
+    }
+}
+
+pub fn clone_io<F>(
+    device: &str,
+    file: &str,
+    block_size: usize,
+    range: IoRange,
+    silent: bool,
+    progress: Option<F>,
+    control: Option<&OperationControl>,
+) -> Result<()>
+where
+    F: FnMut(OperationProgress),
+{
+    #[cfg(feature = "real-io")]
+    {
+        crate::clone(
+            device.to_string(),
+            file.to_string(),
+            block_size,
+            range,
+            silent,
+            progress,
+            control,
+        )
+    }
+
+    #[cfg(not(feature = "real-io"))]
+    {
+        cli_simulate::simulate_clone(device, file, block_size, range, silent, progress, control)
+    }
+}
+
+/// Flash from a stream such as stdin; `size` is the input length when known.
+#[allow(clippy::too_many_arguments)]
+pub fn flash_reader_io<R, F>(
+    reader: R,
+    size: Option<u64>,
+    device: &str,
+    block_size: usize,
+    range: IoRange,
+    silent: bool,
+    verify: bool,
+    discard: Option<DiscardMethod>,
+    progress: Option<F>,
+    control: Option<&OperationControl>,
+) -> Result<()>
+where
+    R: Read,
+    F: FnMut(OperationProgress),
+{
+    #[cfg(feature = "real-io")]
+    {
+        crate::flash_from_reader(
+            reader,
+            size,
+            device.to_string(),
             block_size,
+            range,
             silent,
             verify,
+            discard,
             progress,
-            cancel,
+            control,
+        )
+    }
+
+    #[cfg(not(feature = "real-io"))]
+    {
+        cli_simulate::simulate_flash_reader(
+            reader, size, device, block_size, range, silent, verify, discard, progress, control,
         )
     }
 }
 
-pub fn clone_io<F>(
+/// Clone into a stream such as stdout.
+pub fn clone_writer_io<W, F>(
+    device: &str,
+    writer: W,
+    block_size: usize,
+    range: IoRange,
+    silent: bool,
+    progress: Option<F>,
+    control: Option<&OperationControl>,
+) -> Result<()>
+where
+    W: Write,
+    F: FnMut(OperationProgress),
+{
+    #[cfg(feature = "real-io")]
+    {
+        crate::clone_to_writer(
+            device.to_string(),
+            writer,
+            block_size,
+            range,
+            silent,
+            progress,
+            control,
+        )
+    }
+
+    #[cfg(not(feature = "real-io"))]
+    {
+        cli_simulate::simulate_clone_writer(
+            device, writer, block_size, range, silent, progress, control,
+        )
+    }
+}
+
+/// Clone only through the end of the last partition (GPT backup rebuilt at the new end).
+pub fn clone_used_io<F>(
     device: &str,
     file: &str,
     block_size: usize,
     silent: bool,
     progress: Option<F>,
-    cancel: Option<&AtomicBool>,
+    control: Option<&OperationControl>,
 ) -> Result<()>
 where
     F: FnMut(OperationProgress),
 {
     #[cfg(feature = "real-io")]
     {
-        crate::clone(
+        crate::clone_used(
+            device.to_string(),
+            file.to_string(),
+            block_size,
+            silent,
+            progress,
+            control,
+        )
+    }
+
+    #[cfg(not(feature = "real-io"))]
+    {
+        cli_simulate::simulate_clone_used(device, file, block_size, silent, progress, control)
+    }
+}
+
+/// ddrescue-style clone that skips unreadable areas and records them in `mapfile`.
+pub fn rescue_clone_io<F>(
+    device: &str,
+    file: &str,
+    mapfile: &str,
+    block_size: usize,
+    silent: bool,
+    progress: Option<F>,
+    control: Option<&OperationControl>,
+) -> Result<RescueSummary>
+where
+    F: FnMut(OperationProgress),
+{
+    #[cfg(feature = "real-io")]
+    {
+        crate::rescue::rescue_clone(
             device.to_string(),
             file.to_string(),
+            mapfile.to_string(),
+            block_size,
+            silent,
+            progress,
+            control,
+        )
+    }
+
+    #[cfg(not(feature = "real-io"))]
+    {
+        cli_simulate::simulate_rescue(device, file, mapfile, block_size, silent, progress, control)
+    }
+}
+
+/// Erase `device` with `method`, optionally reading the wiped area back.
+pub fn wipe_io<F>(
+    device: &str,
+    method: WipeMethod,
+    block_size: usize,
+    silent: bool,
+    verify: bool,
+    progress: Option<F>,
+    control: Option<&OperationControl>,
+) -> Result<()>
+where
+    F: FnMut(OperationProgress),
+{
+    #[cfg(feature = "real-io")]
+    {
+        crate::wipe::wipe(
+            device.to_string(),
+            method,
             block_size,
             silent,
+            verify,
             progress,
-            cancel,
+            control,
         )
     }
 
     #[cfg(not(feature = "real-io"))]
     {
-        cli_simulate::simulate_clone(device, file, block_size, silent, progress, cancel)
+        cli_simulate::simulate_wipe(device, method, block_size, silent, verify, progress, control)
+    }
+}
+
+/// Sync, unmount and power off the device after an operation (see [`crate::devices::eject`]).
+pub fn eject_io(device: &str) -> Result<Vec<String>, String> {
+    #[cfg(feature = "real-io")]
+    {
+        crate::devices::eject(device)
+    }
+
+    #[cfg(not(feature = "real-io"))]
+    {
+        Ok(cli_simulate::simulate_eject(device))
     }
 }
 
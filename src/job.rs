@@ -0,0 +1,252 @@
+//! Async flash and clone jobs (feature `async`).
+//!
+//! [`flash`] and [`clone`] run [`flash_io`] / [`clone_io`] on tokio's blocking pool
+//! and return a [`Job`] right away. The job exposes the operation's events as a
+//! [`ProgressStream`] (a `futures_core::Stream` of [`OperationProgress`]),
+//! `cancel` / `pause` / `resume`, and the final result as a future: await the job
+//! itself, or take the pieces apart with [`Job::into_parts`].
+//!
+//! Jobs must be started from within a tokio runtime. Dropping a job does not stop
+//! the operation; call [`Job::cancel`] first.
+
+use crate::cache::ImageCache;
+use crate::io_backend::{clone_io, flash_io};
+use crate::platform::DiscardMethod;
+use crate::progress::{OperationControl, OperationProgress};
+use crate::range::IoRange;
+use anyhow::Result;
+use futures_core::Stream;
+use std::future::{Future, IntoFuture};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+use tokio::task::JoinHandle;
+
+/// A running flash, clone or other blocking operation.
+pub struct Job {
+    progress: ProgressStream,
+    control: Arc<OperationControl>,
+    result: JobResult,
+}
+
+/// Progress events of a [`Job`], in order. Ends when the operation returns; the
+/// outcome (including cancellation) comes from the job's result.
+pub struct ProgressStream {
+    events: UnboundedReceiver<OperationProgress>,
+}
+
+/// Final result of a [`Job`]. A panic in the operation becomes an error.
+pub struct JobResult {
+    handle: JoinHandle<Result<()>>,
+}
+
+/// Flash `image` (file, `.xz` or URL) to `device` in the background, as
+/// [`flash_io`] with progress on the job's stream.
+#[allow(clippy::too_many_arguments)]
+pub fn flash(
+    image: String,
+    device: String,
+    block_size: usize,
+    range: IoRange,
+    verify: bool,
+    discard: Option<DiscardMethod>,
+    cache: Option<ImageCache>,
+) -> Job {
+    Job::spawn(move |progress, control| {
+        flash_io(
+            &image,
+            &device,
+            block_size,
+            range,
+            false,
+            verify,
+            discard,
+            cache.as_ref(),
+            Some(progress),
+            Some(control),
+        )
+    })
+}
+
+/// Clone `device` into `output` in the background, as [`clone_io`] with progress
+/// on the job's stream.
+pub fn clone(device: String, output: String, block_size: usize, range: IoRange) -> Job {
+    Job::spawn(move |progress, control| {
+        clone_io(
+            &device,
+            &output,
+            block_size,
+            range,
+            false,
+            Some(progress),
+            Some(control),
+        )
+    })
+}
+
+impl Job {
+    /// Run any blocking operation as a job: `task` gets the progress callback and
+    /// the control token to pass to a library function (e.g. [`crate::flash_from_reader`]
+    /// or [`crate::copy::flash_device`]).
+    pub fn spawn<T>(task: T) -> Self
+    where
+        T: FnOnce(&mut dyn FnMut(OperationProgress), &OperationControl) -> Result<()>
+            + Send
+            + 'static,
+    {
+        let (sender, events) = mpsc::unbounded_channel();
+        let control = Arc::new(OperationControl::new());
+        let task_control = Arc::clone(&control);
+        let handle = tokio::task::spawn_blocking(move || {
+            // A dropped stream only means nobody is listening; keep going.
+            let mut progress = |event: OperationProgress| {
+                let _ = sender.send(event);
+            };
+            task(&mut progress, &task_control)
+        });
+        Self {
+            progress: ProgressStream { events },
+            control,
+            result: JobResult { handle },
+        }
+    }
+
+    /// The job's progress events.
+    pub fn progress(&mut self) -> &mut ProgressStream {
+        &mut self.progress
+    }
+
+    /// Stop at the next block boundary; the result is then an
+    /// [`OperationCancelled`](crate::progress::OperationCancelled) error.
+    pub fn cancel(&self) {
+        self.control.cancel();
+    }
+
+    /// Block the operation at the next safe point until [`resume`](Self::resume).
+    pub fn pause(&self) {
+        self.control.pause();
+    }
+
+    pub fn resume(&self) {
+        self.control.resume();
+    }
+
+    /// The job's control token, for cancelling from another task or thread.
+    pub fn control(&self) -> Arc<OperationControl> {
+        Arc::clone(&self.control)
+    }
+
+    /// Split into the progress stream, the control token and the result future.
+    pub fn into_parts(self) -> (ProgressStream, Arc<OperationControl>, JobResult) {
+        (self.progress, self.control, self.result)
+    }
+}
+
+impl IntoFuture for Job {
+    type Output = Result<()>;
+    type IntoFuture = JobResult;
+
+    fn into_future(self) -> JobResult {
+        self.result
+    }
+}
+
+impl ProgressStream {
+    /// The next event, or `None` once the operation has returned.
+    pub async fn recv(&mut self) -> Option<OperationProgress> {
+        self.events.recv().await
+    }
+}
+
+impl Stream for ProgressStream {
+    type Item = OperationProgress;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.events.poll_recv(cx)
+    }
+}
+
+impl Future for JobResult {
+    type Output = Result<()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.handle)
+            .poll(cx)
+            .map(|joined| match joined {
+                Ok(result) => result,
+                Err(error) => Err(anyhow::anyhow!("Operation task failed: {}", error)),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::copy::flash_device;
+    use crate::platform::MemoryDevice;
+    use crate::progress::{is_operation_cancelled, OperationPhase};
+
+    #[tokio::test]
+    async fn job_streams_progress_and_resolves() {
+        let device = MemoryDevice::new(64 * 1024);
+        let target = device.clone();
+        let image: Vec<u8> = (0..32 * 1024u32).map(|i| (i % 251) as u8).collect();
+        let source = image.clone();
+
+        let mut job = Job::spawn(move |progress, control| {
+            flash_device(
+                source.as_slice(),
+                Some(source.len() as u64),
+                &mut target.writer(),
+                Some(&mut target.reader()),
+                4096,
+                IoRange::default(),
+                false,
+                Some(progress),
+                Some(control),
+            )
+            .map(|_| ())
+        });
+
+        let mut phases = Vec::new();
+        while let Some(event) = job.progress().recv().await {
+            phases.push(event.phase);
+        }
+        job.await.unwrap();
+
+        assert_eq!(phases.first(), Some(&OperationPhase::Writing));
+        assert_eq!(phases.last(), Some(&OperationPhase::Complete));
+        assert_eq!(&device.contents()[..image.len()], image.as_slice());
+    }
+
+    #[tokio::test]
+    async fn cancelled_job_resolves_to_operation_cancelled() {
+        let device = MemoryDevice::new(1024 * 1024);
+        let job = Job::spawn(move |progress, control| {
+            // Start writing only once cancelled, so the first block boundary stops it.
+            while !control.is_cancelled() {
+                std::thread::sleep(std::time::Duration::from_millis(1));
+            }
+            flash_device(
+                std::io::repeat(1),
+                None,
+                &mut device.writer(),
+                None,
+                512,
+                IoRange::default(),
+                false,
+                Some(progress),
+                Some(control),
+            )
+            .map(|_| ())
+        });
+        job.cancel();
+        let (mut progress, control, result) = job.into_parts();
+
+        assert!(control.is_cancelled());
+        let error = result.await.unwrap_err();
+        assert!(is_operation_cancelled(&error));
+        assert!(progress.recv().await.is_none());
+    }
+}
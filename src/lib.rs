@@ -1,31 +1,48 @@
+pub mod cache;
 pub mod cancel;
+pub mod copy;
 pub mod devices;
+pub mod filesystems;
+pub mod http;
+pub mod inspect;
 pub mod io_backend;
+pub mod partitions;
 pub mod platform;
 pub mod progress;
+pub mod range;
+pub mod rescue;
+pub mod wipe;
 
 #[cfg(not(feature = "real-io"))]
 pub mod cli_simulate;
 
+#[cfg(feature = "async")]
+pub mod job;
+
 use anyhow::{Context, Result};
+use cache::{CacheEntry, ImageCache};
 use log::{debug, info, warn};
 use lzma::reader::LzmaReader;
-use platform::PlatformDevice;
+use platform::{DeviceWriter, DiscardMethod, PlatformDevice};
 use progress::{
-    check_cancel, emit_progress, OperationCancelled, OperationPhase, OperationProgress,
+    check_cancel, emit_progress, OperationCancelled, OperationControl, OperationPhase,
+    OperationProgress,
 };
-use std::sync::atomic::AtomicBool;
+use range::IoRange;
 use sha2::{Digest, Sha256};
 use std::fs::File;
 use std::io::BufWriter;
-use std::io::{BufReader, Read, Write};
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
 use tempfile::NamedTempFile;
 
 /// Calculate the checksum of the data read from the given reader
 fn calculate_checksum<R: Read>(
     reader: &mut R,
     size: usize,
-    cancel: Option<&AtomicBool>,
+    control: Option<&OperationControl>,
 ) -> Result<String> {
     let mut hasher = Sha256::new();
     let chunk = 65536usize;
@@ -33,7 +50,7 @@ fn calculate_checksum<R: Read>(
     let mut remaining = size;
 
     while remaining > 0 {
-        check_cancel(cancel)?;
+        check_cancel(control)?;
         let to_read = remaining.min(buffer.len());
         let bytes_read = reader
             .read(&mut buffer[..to_read])
@@ -49,21 +66,27 @@ fn calculate_checksum<R: Read>(
     Ok(format!("{:x}", hasher.finalize()))
 }
 
+/// Clone the device at the given path to an image file.
+///
+/// `range.skip` / `range.count` select the part of the device to read and
+/// `range.seek` is the offset in the output file where the copy starts.
+#[allow(clippy::too_many_arguments)]
 pub fn clone<F>(
     device_path: String,
     output_path: String,
     block_size: usize,
+    range: IoRange,
     silent: bool,
     mut progress: Option<F>,
-    cancel: Option<&AtomicBool>,
+    control: Option<&OperationControl>,
 ) -> Result<()>
 where
     F: FnMut(OperationProgress),
 {
     if !silent {
         info!(
-            "Cloning device: {} to output: {} with block_size: {}",
-            device_path, output_path, block_size
+            "Cloning device: {} to output: {} with block_size: {} ({})",
+            device_path, output_path, block_size, range
         );
     }
 
@@ -74,65 +97,77 @@ where
             .with_message(format!("Opening {}", device_path)),
     );
 
-    let mut device_reader = PlatformDevice::new_clone_reader(&device_path)?;
-
-    let total_bytes = device_reader
-        .device_size()
-        .ok()
-        .filter(|s| *s > 0)
-        .or_else(|| devices::device_size_bytes(&device_path));
-
-    let output_file = File::create(&output_path)
-        .context(format!("Failed to create output file: {}", output_path))?;
-    let mut writer = BufWriter::new(output_file);
+    let (total_bytes_read, total_bytes) = copy_device_to_file(
+        &device_path,
+        &output_path,
+        block_size,
+        range,
+        silent,
+        &mut progress,
+        control,
+    )?;
 
-    let mut buffer = vec![0u8; block_size];
-    let mut total_bytes_read: u64 = 0;
-
-    let result = (|| -> Result<()> {
-        loop {
-            check_cancel(cancel)?;
-            let bytes_read = device_reader
-                .read(&mut buffer)
-                .context("Failed to read from device")?;
-            if bytes_read == 0 {
-                break;
-            }
-            writer
-                .write_all(&buffer[..bytes_read])
-                .context("Failed to write to output file")?;
-            total_bytes_read += bytes_read as u64;
-
-            let mut event = OperationProgress::new(OperationPhase::Writing)
-                .with_bytes(total_bytes_read, total_bytes);
-            if total_bytes.is_none() {
-                event = event.with_message(format!("{} bytes copied", total_bytes_read));
-            }
-            emit_progress(silent, &mut progress, event);
+    emit_progress(
+        silent,
+        &mut progress,
+        OperationProgress::new(OperationPhase::Complete)
+            .with_bytes(total_bytes_read, total_bytes)
+            .with_percentage(100.0)
+            .with_message("Clone completed"),
+    );
 
-            if !silent {
-                debug!("Read and written {} bytes", total_bytes_read);
-            }
-        }
-        Ok(())
-    })();
+    info!("Clone completed successfully");
+    Ok(())
+}
 
-    if let Err(error) = result {
-        drop(writer);
-        if error.downcast_ref::<OperationCancelled>().is_some() {
-            if let Err(remove_error) = std::fs::remove_file(&output_path) {
-                warn!(
-                    "Failed to remove incomplete clone output {}: {}",
-                    output_path, remove_error
-                );
-            }
-        }
-        return Err(error);
+/// Clone the device into any writer — stdout, a pipe, a compressor — instead of a file.
+///
+/// `range.skip` / `range.count` select the part of the device to read. A stream
+/// cannot seek, so a non-zero `range.seek` is rejected. The writer is flushed but
+/// not closed.
+#[allow(clippy::too_many_arguments)]
+pub fn clone_to_writer<W, F>(
+    device_path: String,
+    mut writer: W,
+    block_size: usize,
+    range: IoRange,
+    silent: bool,
+    mut progress: Option<F>,
+    control: Option<&OperationControl>,
+) -> Result<()>
+where
+    W: Write,
+    F: FnMut(OperationProgress),
+{
+    if range.seek > 0 {
+        anyhow::bail!("An output offset (seek) is not supported when cloning to a stream");
+    }
+    if !silent {
+        info!(
+            "Cloning device: {} to stream with block_size: {} ({})",
+            device_path, block_size, range
+        );
     }
 
-    writer
-        .flush()
-        .context("Failed to flush clone output file")?;
+    emit_progress(
+        silent,
+        &mut progress,
+        OperationProgress::new(OperationPhase::Preparing)
+            .with_message(format!("Opening {}", device_path)),
+    );
+
+    let (mut device_reader, total_bytes) = open_clone_source(&device_path, range)?;
+    let total_bytes_read = copy::read_device(
+        device_reader.as_mut(),
+        &mut writer,
+        block_size,
+        range,
+        total_bytes,
+        silent,
+        &mut progress,
+        control,
+    )?;
+    writer.flush().context("Failed to flush clone output")?;
 
     emit_progress(
         silent,
@@ -147,56 +182,541 @@ where
     Ok(())
 }
 
+/// Clone only up to the end of the last partition in the source's MBR / GPT.
+///
+/// For GPT sources the backup header and entry array are rebuilt at the new
+/// end of the image (and the primary header and protective MBR updated to
+/// match), so the output is a valid, smaller GPT disk image.
+pub fn clone_used<F>(
+    device_path: String,
+    output_path: String,
+    block_size: usize,
+    silent: bool,
+    mut progress: Option<F>,
+    control: Option<&OperationControl>,
+) -> Result<()>
+where
+    F: FnMut(OperationProgress),
+{
+    emit_progress(
+        silent,
+        &mut progress,
+        OperationProgress::new(OperationPhase::Preparing)
+            .with_message(format!("Reading partition table of {}", device_path)),
+    );
+
+    let mut device_reader = PlatformDevice::new_clone_reader(&device_path)?;
+    let table = partitions::read_partition_table(&mut device_reader)?.with_context(|| {
+        format!(
+            "No MBR or GPT partition table found on {}; use a full clone",
+            device_path
+        )
+    })?;
+    drop(device_reader);
+    if table.gpt.as_ref().is_some_and(|header| header.is_backup()) {
+        anyhow::bail!(
+            "The primary GPT of {} is corrupt; repair it or use a full clone",
+            device_path
+        );
+    }
+
+    let used_bytes = table.used_bytes();
+    if !silent {
+        info!(
+            "Cloning used extent of {}: {:?} table, {} partitions, {} bytes",
+            device_path,
+            table.kind,
+            table.partitions.len(),
+            used_bytes
+        );
+    }
+
+    let range = IoRange {
+        count: Some(used_bytes),
+        ..Default::default()
+    };
+    let (total_bytes_read, _) = copy_device_to_file(
+        &device_path,
+        &output_path,
+        block_size,
+        range,
+        silent,
+        &mut progress,
+        control,
+    )?;
+
+    if let Some(gpt) = table.relocated_gpt() {
+        write_relocated_gpt(&output_path, &gpt)
+            .context("Failed to write the relocated GPT backup header")?;
+    }
+
+    emit_progress(
+        silent,
+        &mut progress,
+        OperationProgress::new(OperationPhase::Complete)
+            .with_bytes(total_bytes_read, Some(used_bytes))
+            .with_percentage(100.0)
+            .with_message(format!(
+                "Clone completed — {} bytes in use ({} partitions)",
+                table.used_image_bytes(),
+                table.partitions.len()
+            )),
+    );
+
+    info!("Used-extent clone completed successfully");
+    Ok(())
+}
+
+/// Write the shrunk GPT (primary header, backup entries + header, protective MBR size).
+fn write_relocated_gpt(output_path: &str, gpt: &partitions::RelocatedGpt) -> Result<()> {
+    let mut output = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(output_path)
+        .context(format!("Failed to reopen clone output: {}", output_path))?;
+
+    output.seek(SeekFrom::Start(gpt.sector_size))?;
+    output.write_all(&gpt.primary_header)?;
+    output.seek(SeekFrom::Start(gpt.backup_entries_lba * gpt.sector_size))?;
+    output.write_all(&gpt.backup_entries)?;
+    output.seek(SeekFrom::Start(gpt.backup_lba * gpt.sector_size))?;
+    output.write_all(&gpt.backup_header)?;
+    output.set_len(gpt.total_sectors * gpt.sector_size)?;
+
+    // Protective MBR entry: size in sectors of everything after LBA 0.
+    let mut entry = [0u8; 16];
+    output.seek(SeekFrom::Start(446))?;
+    output.read_exact(&mut entry)?;
+    if entry[4] == 0xEE {
+        entry[12..16].copy_from_slice(&gpt.protective_mbr_sectors().to_le_bytes());
+        output.seek(SeekFrom::Start(446))?;
+        output.write_all(&entry)?;
+    }
+    output.sync_all()?;
+    Ok(())
+}
+
+/// Copy `range` of the device into `output_path`, returning bytes copied and the
+/// expected total. A cancelled copy removes the incomplete output.
+#[allow(clippy::too_many_arguments)]
+fn copy_device_to_file<F>(
+    device_path: &str,
+    output_path: &str,
+    block_size: usize,
+    range: IoRange,
+    silent: bool,
+    progress: &mut Option<F>,
+    control: Option<&OperationControl>,
+) -> Result<(u64, Option<u64>)>
+where
+    F: FnMut(OperationProgress),
+{
+    let (mut device_reader, total_bytes) = open_clone_source(device_path, range)?;
+
+    let mut output_file = File::create(output_path)
+        .context(format!("Failed to create output file: {}", output_path))?;
+    if range.seek > 0 {
+        output_file
+            .seek(SeekFrom::Start(range.seek))
+            .context(format!("Failed to seek output file to offset {}", range.seek))?;
+    }
+    let mut writer = BufWriter::new(output_file);
+
+    let result = copy::read_device(
+        device_reader.as_mut(),
+        &mut writer,
+        block_size,
+        range,
+        total_bytes,
+        silent,
+        progress,
+        control,
+    );
+
+    let total_bytes_read = match result {
+        Ok(copied) => copied,
+        Err(error) => {
+            drop(writer);
+            if error.downcast_ref::<OperationCancelled>().is_some() {
+                if let Err(remove_error) = std::fs::remove_file(output_path) {
+                    warn!(
+                        "Failed to remove incomplete clone output {}: {}",
+                        output_path, remove_error
+                    );
+                }
+            }
+            return Err(error);
+        }
+    };
+
+    writer
+        .flush()
+        .context("Failed to flush clone output file")?;
+
+    Ok((total_bytes_read, total_bytes))
+}
+
+/// Open the device for cloning, with the number of bytes the copy should produce
+/// (`None` when the device size is unknown and no count is set).
+fn open_clone_source(
+    device_path: &str,
+    range: IoRange,
+) -> Result<(Box<dyn platform::DeviceReader>, Option<u64>)> {
+    let device_reader = PlatformDevice::new_clone_reader(device_path)?;
+
+    let device_size = device_reader
+        .device_size()
+        .ok()
+        .filter(|s| *s > 0)
+        .or_else(|| devices::device_size_bytes(device_path));
+    let total_bytes = match device_size {
+        Some(size) => Some(range.copy_len(size)?),
+        None => range.count,
+    };
+
+    Ok((device_reader, total_bytes))
+}
+
 /// Flash the image at the given path to the device at the given path.
 ///
 /// When `verify` is false (default), the image is written and the operation
 /// completes without a post-write checksum pass.
+///
+/// `range.skip` / `range.count` select the part of the image to write and
+/// `range.seek` is the device offset where writing starts (e.g. a bootloader
+/// at 8 KiB). The range must fit the device.
+///
+/// With `discard`, the target is discarded (or zeroed) before writing: the whole
+/// device for a full flash, otherwise only the written range. Unsupported
+/// discards are reported in the Preparing phase and skipped.
+///
+/// `http://` and `https://` sources are streamed with [`flash_url`].
+///
+/// With `cache`, `.xz` files and downloads are decompressed into the image cache
+/// once and later flashes of the same source are written from there (see
+/// [`cache::ImageCache`]). Raw image files are always read in place.
+#[allow(clippy::too_many_arguments)]
 pub fn flash<F>(
     img_path: String,
     device_path: String,
     block_size: usize,
+    range: IoRange,
     silent: bool,
     verify: bool,
+    discard: Option<DiscardMethod>,
+    cache: Option<&ImageCache>,
     progress: Option<F>,
-    cancel: Option<&AtomicBool>,
+    control: Option<&OperationControl>,
 ) -> Result<()>
 where
     F: FnMut(OperationProgress),
 {
-    if img_path.ends_with(".xz") {
+    if http::is_url(&img_path) {
+        flash_url(
+            img_path,
+            device_path,
+            block_size,
+            range,
+            silent,
+            verify,
+            discard,
+            cache,
+            progress,
+            control,
+        )
+    } else if img_path.ends_with(".xz") {
         info!("Detected compressed image, calling flash_xz");
         flash_xz(
             img_path,
             device_path,
             block_size,
+            range,
             silent,
             verify,
+            discard,
+            cache,
             progress,
-            cancel,
+            control,
         )
     } else {
         flash_image(
             img_path,
             device_path,
             block_size,
+            range,
             silent,
             progress,
             false,
             verify,
-            cancel,
+            discard,
+            None,
+            control,
+        )
+    }
+}
+
+/// Flash an image streamed from an `http://` / `https://` URL. URLs ending in `.xz`
+/// are decompressed on the fly; nothing is stored on disk.
+///
+/// Writing events carry the download position (`bytes_downloaded` /
+/// `download_total`). A compressed download has no known write length, so its
+/// percentage follows the download. Dropped connections resume with Range requests.
+///
+/// With `cache` and a server that sends an `ETag` or `Last-Modified`, the download is
+/// stored (decompressed) in the image cache first; a later flash of the same URL with
+/// the same validator writes from the cache without downloading again.
+#[allow(clippy::too_many_arguments)]
+pub fn flash_url<F>(
+    url: String,
+    device_path: String,
+    block_size: usize,
+    range: IoRange,
+    silent: bool,
+    verify: bool,
+    discard: Option<DiscardMethod>,
+    cache: Option<&ImageCache>,
+    mut progress: Option<F>,
+    control: Option<&OperationControl>,
+) -> Result<()>
+where
+    F: FnMut(OperationProgress),
+{
+    emit_progress(
+        silent,
+        &mut progress,
+        OperationProgress::new(OperationPhase::Preparing)
+            .with_message(format!("Downloading {}", url)),
+    );
+
+    check_cancel(control)?;
+
+    let reader = http::HttpReader::open(&url)?;
+    let download_total = reader.content_length();
+    let downloaded = reader.received();
+    if !silent {
+        match download_total {
+            Some(total) => info!("Downloading {} ({} bytes)", url, total),
+            None => info!("Downloading {} (unknown size)", url),
+        }
+    }
+
+    let write_share = if verify { 90.0 } else { 100.0 };
+    let mut progress = progress.map(|mut callback| {
+        move |mut event: OperationProgress| {
+            let share = match event.phase {
+                OperationPhase::Writing => write_share,
+                OperationPhase::Decompressing => 100.0,
+                _ => return callback(event),
+            };
+            let bytes = downloaded.load(Ordering::Relaxed);
+            if let Some(total) = download_total.filter(|total| *total > 0) {
+                if event.percentage.is_none() {
+                    event = event.with_percentage(bytes as f64 / total as f64 * share);
+                }
+            }
+            callback(event.with_download(bytes, download_total))
+        }
+    });
+
+    let compressed = http::url_file_name(&url).ends_with(".xz");
+    let validator = reader.validator().map(str::to_string);
+    if let Some((cache, validator)) = cache.zip(validator) {
+        let stamp = format!(
+            "{}:{}",
+            download_total.map_or("-".to_string(), |total| total.to_string()),
+            validator
+        );
+        let entry = match cache.lookup_origin(&url, &stamp)? {
+            Some(entry) => entry,
+            None => {
+                emit_progress(
+                    silent,
+                    &mut progress,
+                    OperationProgress::new(OperationPhase::Decompressing)
+                        .with_message(format!("Downloading {} into the image cache", url)),
+                );
+                cache.insert(
+                    reader,
+                    compressed,
+                    &url,
+                    &stamp,
+                    silent,
+                    &mut progress,
+                    control,
+                )?
+            }
+        };
+        return flash_cached(
+            cache,
+            entry,
+            device_path,
+            block_size,
+            range,
+            silent,
+            verify,
+            discard,
+            progress,
+            control,
+        );
+    }
+
+    if compressed {
+        let decompressor =
+            LzmaReader::new_decompressor(reader).context("Failed to create LZMA decompressor")?;
+        flash_from_reader(
+            decompressor,
+            None,
+            device_path,
+            block_size,
+            range,
+            silent,
+            verify,
+            discard,
+            progress,
+            control,
+        )
+    } else {
+        flash_from_reader(
+            reader,
+            download_total,
+            device_path,
+            block_size,
+            range,
+            silent,
+            verify,
+            discard,
+            progress,
+            control,
         )
     }
 }
 
+/// Flash from any reader — stdin, a pipe, a download — instead of an image file.
+///
+/// `size` is the input length when known. Without it (and without `range.count`)
+/// progress reports bytes only, with no percentage, and the device size check is
+/// left to the write itself. `range.skip` bytes are read and dropped; `range.seek`
+/// and `discard` behave as in [`flash`], except that an unknown-length partial
+/// flash discards from `range.seek` to the end of the device.
+///
+/// With `verify`, the SHA-256 of the input is computed while writing and compared
+/// with a read-back of the written range.
+#[allow(clippy::too_many_arguments)]
+pub fn flash_from_reader<R, F>(
+    mut reader: R,
+    size: Option<u64>,
+    device_path: String,
+    block_size: usize,
+    range: IoRange,
+    silent: bool,
+    verify: bool,
+    discard: Option<DiscardMethod>,
+    mut progress: Option<F>,
+    control: Option<&OperationControl>,
+) -> Result<()>
+where
+    R: Read,
+    F: FnMut(OperationProgress),
+{
+    emit_progress(
+        silent,
+        &mut progress,
+        OperationProgress::new(OperationPhase::Preparing).with_message("Reading image from stream"),
+    );
+
+    check_cancel(control)?;
+
+    let write_size = match size {
+        Some(size) => Some(range.copy_len(size)?),
+        None => range.count,
+    };
+
+    let mut device_writer = PlatformDevice::new_writer(&device_path)?;
+    let device_size = device_writer
+        .device_size()
+        .ok()
+        .filter(|s| *s > 0)
+        .or_else(|| devices::device_size_bytes(&device_path));
+    if let (Some(write_size), Some(device_size)) = (write_size, device_size) {
+        range.check_fits(write_size, device_size)?;
+    }
+    if let Some(method) = discard {
+        let extent = match (device_size, write_size) {
+            (Some(device_size), _) if range.is_full() => Some((0, device_size)),
+            (_, Some(write_size)) => Some((range.seek, write_size)),
+            (Some(device_size), None) => Some((range.seek, device_size.saturating_sub(range.seek))),
+            (None, None) => None,
+        };
+        match extent {
+            Some((offset, len)) => wipe::discard_range(
+                device_writer.as_mut(),
+                &device_path,
+                method,
+                offset,
+                len,
+                wipe::DiscardMode::BeforeWrite,
+                silent,
+                &mut progress,
+                control,
+            )?,
+            None => warn!("Skipping discard: neither the input nor the device size is known"),
+        }
+    }
+    copy::skip_input(&mut reader, range.skip)?;
+
+    if !silent {
+        match write_size {
+            Some(write_size) => info!(
+                "Writing stream to the device... size: {} ({})",
+                write_size, range
+            ),
+            None => info!("Writing stream of unknown size to the device ({})", range),
+        }
+    }
+
+    let (count, source_checksum) = copy::write_image(
+        &mut reader,
+        device_writer.as_mut(),
+        block_size,
+        range.seek,
+        write_size,
+        verify,
+        verify,
+        silent,
+        &mut progress,
+        control,
+    )?;
+    let partitions = refresh_partitions(device_writer.as_mut(), &device_path, silent);
+
+    if let Some(checksum) = source_checksum.as_ref().filter(|_| !silent) {
+        info!("Source stream checksum: {}", checksum);
+    }
+
+    finish_flash(
+        &device_path,
+        range.seek,
+        count,
+        source_checksum,
+        partitions,
+        silent,
+        &mut progress,
+        control,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
 fn flash_image<F>(
     img_path: String,
     device_path: String,
     block_size: usize,
+    range: IoRange,
     silent: bool,
     mut progress: Option<F>,
     skip_prepare: bool,
     verify: bool,
-    cancel: Option<&AtomicBool>,
+    discard: Option<DiscardMethod>,
+    image_checksum: Option<String>,
+    control: Option<&OperationControl>,
 ) -> Result<()>
 where
     F: FnMut(OperationProgress),
@@ -210,7 +730,7 @@ where
         );
     }
 
-    check_cancel(cancel)?;
+    check_cancel(control)?;
 
     let mut img_file =
         File::open(&img_path).context(format!("Image file not found: {}", img_path))?;
@@ -218,9 +738,16 @@ where
         .metadata()
         .context("Failed to read image file metadata")?
         .len();
-    let file_size_usize = usize::try_from(file_size).context("File size too large")?;
-    let img_checksum = if verify {
-        let checksum = calculate_checksum(&mut img_file, file_size_usize, cancel)
+    let write_size = range.copy_len(file_size)?;
+    let write_size_usize = usize::try_from(write_size).context("File size too large")?;
+    let img_checksum = if let Some(checksum) = image_checksum.filter(|_| verify && range.is_full())
+    {
+        Some(checksum)
+    } else if verify {
+        img_file
+            .seek(SeekFrom::Start(range.skip))
+            .context("Failed to seek image file")?;
+        let checksum = calculate_checksum(&mut img_file, write_size_usize, control)
             .context("Failed to calculate image checksum")?;
         if !silent {
             info!("Source image checksum: {}", checksum);
@@ -231,188 +758,210 @@ where
     };
 
     let mut device_writer = PlatformDevice::new_writer(&device_path)?;
-
-    let img_file =
-        File::open(&img_path).context(format!("Failed to open image file: {}", img_path))?;
-
-    let mut reader = BufReader::new(img_file);
-    let mut buffer = vec![0u8; block_size];
-
-    if !silent {
-        info!("Writing image to the device... size: {}", file_size);
+    let device_size = device_writer
+        .device_size()
+        .ok()
+        .filter(|s| *s > 0)
+        .or_else(|| devices::device_size_bytes(&device_path));
+    if let Some(device_size) = device_size {
+        range.check_fits(write_size, device_size)?;
     }
-
-    let mut count: u64 = 0;
-    loop {
-        check_cancel(cancel)?;
-        let bytes_read = reader
-            .read(&mut buffer)
-            .context("Failed to read image file")?;
-        if bytes_read == 0 {
-            break;
-        }
-        device_writer
-            .write_all(&buffer[..bytes_read])
-            .context("Failed to write to device")?;
-        count += bytes_read as u64;
-        let write_pct = if verify {
-            (count as f64 / file_size as f64) * 90.0
-        } else {
-            (count as f64 / file_size as f64) * 100.0
+    if let Some(method) = discard {
+        let (offset, len) = match device_size {
+            Some(device_size) if range.is_full() => (0, device_size),
+            _ => (range.seek, write_size),
         };
-        emit_progress(
+        wipe::discard_range(
+            device_writer.as_mut(),
+            &device_path,
+            method,
+            offset,
+            len,
+            wipe::DiscardMode::BeforeWrite,
             silent,
             &mut progress,
-            OperationProgress::new(OperationPhase::Writing)
-                .with_bytes(count, Some(file_size))
-                .with_percentage(write_pct),
-        );
-
-        if !silent {
-            debug!(
-                "Written {}/{} : {:.1}%",
-                count,
-                file_size,
-                (count as f64 / file_size as f64) * 100.0
-            );
-        }
+            control,
+        )?;
     }
+    // Rewind the handle the checksum read from rather than reopening the path, so
+    // the bytes written are those of the file that was measured and hashed.
+    img_file
+        .seek(SeekFrom::Start(range.skip))
+        .context(format!("Failed to seek image file to offset {}", range.skip))?;
 
-    device_writer
-        .flush_and_sync()
-        .context("Failed to flush and sync device")?;
-
-    if !verify {
-        emit_progress(
-            silent,
-            &mut progress,
-            OperationProgress::new(OperationPhase::Complete)
-                .with_bytes(file_size, Some(file_size))
-                .with_percentage(100.0)
-                .with_message("Flash completed"),
+    if !silent {
+        info!(
+            "Writing image to the device... size: {} ({})",
+            write_size, range
         );
-        if !silent {
-            info!("Flash completed successfully");
-        }
-        return Ok(());
     }
 
-    let img_checksum = img_checksum.context("Missing source checksum")?;
-
-    emit_progress(
+    copy::write_image(
+        &mut BufReader::new(img_file),
+        device_writer.as_mut(),
+        block_size,
+        range.seek,
+        Some(write_size),
+        false,
+        verify,
         silent,
         &mut progress,
-        OperationProgress::new(OperationPhase::Verifying)
-            .with_percentage(90.0)
-            .with_message("Verifying checksum"),
-    );
+        control,
+    )?;
+    let partitions = refresh_partitions(device_writer.as_mut(), &device_path, silent);
 
-    let device_reader = PlatformDevice::new_verify_reader(&device_path)?;
-    let mut buffered_reader = BufReader::with_capacity(1024 * 1024, device_reader);
-    let mut verified: u64 = 0;
-    let verify_hasher = verify_checksum_with_progress(
-        &mut buffered_reader,
-        file_size_usize,
+    finish_flash(
+        &device_path,
+        range.seek,
+        write_size,
+        img_checksum,
+        partitions,
         silent,
         &mut progress,
-        &mut verified,
-        file_size,
-        cancel,
-    )?;
-    let device_checksum = format!("{:x}", verify_hasher.finalize());
-
-    if !silent {
-        info!("Device checksum: {}", device_checksum);
-    }
-
-    if img_checksum == device_checksum {
-        emit_progress(
-            silent,
-            &mut progress,
-            OperationProgress::new(OperationPhase::Complete)
-                .with_percentage(100.0)
-                .with_message("Checksums match"),
-        );
-        if !silent {
-            info!("Checksums match. Write operation successful.");
-        }
-        Ok(())
-    } else {
-        emit_progress(
-            silent,
-            &mut progress,
-            OperationProgress::new(OperationPhase::Failed).with_message("Checksums do not match"),
-        );
-        log::error!("Checksums do not match. Write operation may have failed.");
-        anyhow::bail!("Checksums do not match");
-    }
+        control,
+    )
 }
 
-fn verify_checksum_with_progress<F>(
-    reader: &mut dyn Read,
-    size: usize,
+/// Report completion of a flash of `written` bytes at `seek`. With `source_checksum`
+/// the device is reopened for reading and the written range compared first.
+#[allow(clippy::too_many_arguments)]
+fn finish_flash<F>(
+    device_path: &str,
+    seek: u64,
+    written: u64,
+    source_checksum: Option<String>,
+    partitions: Option<Vec<String>>,
     silent: bool,
     progress: &mut Option<F>,
-    verified: &mut u64,
-    file_size: u64,
-    cancel: Option<&AtomicBool>,
-) -> Result<Sha256>
+    control: Option<&OperationControl>,
+) -> Result<()>
 where
     F: FnMut(OperationProgress),
 {
-    let mut hasher = Sha256::new();
-    let mut buffer = vec![0u8; 65536];
-    let mut remaining = size;
+    match source_checksum {
+        Some(checksum) => {
+            let mut device_reader = PlatformDevice::new_verify_reader(device_path)?;
+            copy::finish_flash(
+                Some((device_reader.as_mut(), checksum)),
+                seek,
+                written,
+                partitions,
+                silent,
+                progress,
+                control,
+            )
+        }
+        None => copy::finish_flash(None, seek, written, partitions, silent, progress, control),
+    }
+}
 
-    while remaining > 0 {
-        check_cancel(cancel)?;
-        let to_read = remaining.min(buffer.len());
-        let bytes_read = reader
-            .read(&mut buffer[..to_read])
-            .with_context(|| {
-                format!(
-                    "Failed to read from device during verification ({} bytes remaining)",
-                    remaining
-                )
-            })?;
-        if bytes_read == 0 {
-            if remaining > 0 {
-                anyhow::bail!(
-                    "Unexpected end of device read during verification ({} bytes short)",
-                    remaining
+/// Attempts at `BLKRRPART` while the kernel still reports partitions busy.
+const REREAD_ATTEMPTS: u32 = 5;
+/// Delay before the first retry; later retries wait proportionally longer.
+const REREAD_RETRY_DELAY: Duration = Duration::from_millis(200);
+
+/// Re-read the target's partition table after a flash so the new partition nodes
+/// appear without a replug, then wait for udev. Failures are logged, not fatal.
+/// Returns the partitions the kernel now reports, or `None` if the re-read failed.
+fn refresh_partitions(
+    writer: &mut dyn DeviceWriter,
+    device_path: &str,
+    silent: bool,
+) -> Option<Vec<String>> {
+    refresh_partitions_with(
+        writer,
+        device_path,
+        silent,
+        &mut devices::settle_udev,
+        &mut std::thread::sleep,
+    )
+}
+
+/// [`refresh_partitions`] with the udev settle and the retry wait supplied by the caller.
+fn refresh_partitions_with(
+    writer: &mut dyn DeviceWriter,
+    device_path: &str,
+    silent: bool,
+    settle: &mut dyn FnMut(),
+    wait: &mut dyn FnMut(Duration),
+) -> Option<Vec<String>> {
+    if devices::is_partition_path(device_path) {
+        return None;
+    }
+    for attempt in 1..=REREAD_ATTEMPTS {
+        match writer.reread_partition_table() {
+            Ok(()) => {
+                settle();
+                let partitions = devices::disk_partitions(device_path);
+                if !silent {
+                    info!("Partitions on {}: {:?}", device_path, partitions);
+                }
+                return Some(partitions);
+            }
+            Err(error) if is_busy(&error) && attempt < REREAD_ATTEMPTS => {
+                debug!(
+                    "{} busy re-reading partitions (attempt {}/{})",
+                    device_path, attempt, REREAD_ATTEMPTS
                 );
+                wait(REREAD_RETRY_DELAY * attempt);
+            }
+            Err(error) => {
+                warn!(
+                    "Could not re-read the partition table of {}: {:#}",
+                    device_path, error
+                );
+                return None;
             }
-            break;
         }
-        hasher.update(&buffer[..bytes_read]);
-        remaining -= bytes_read;
-        *verified += bytes_read as u64;
-        let verify_pct = 90.0 + (*verified as f64 / file_size as f64) * 10.0;
-        emit_progress(
-            silent,
-            progress,
-            OperationProgress::new(OperationPhase::Verifying)
-                .with_bytes(*verified, Some(file_size))
-                .with_percentage(verify_pct.min(99.9)),
-        );
     }
+    None
+}
 
-    Ok(hasher)
+fn is_busy(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<std::io::Error>()
+        .and_then(std::io::Error::raw_os_error)
+        == Some(libc::EBUSY)
 }
 
 /// Flash the compressed file (only xz compression is supported) at the given path to the device at the given path
+///
+/// `range.skip` / `range.count` apply to the decompressed image.
+///
+/// With `cache`, the decompressed image is taken from (or stored in) the image cache
+/// instead of a temporary file.
+#[allow(clippy::too_many_arguments)]
 pub fn flash_xz<F>(
     img_path: String,
     device_path: String,
     block_size: usize,
+    range: IoRange,
     silent: bool,
     verify: bool,
+    discard: Option<DiscardMethod>,
+    cache: Option<&ImageCache>,
     mut progress: Option<F>,
-    cancel: Option<&AtomicBool>,
+    control: Option<&OperationControl>,
 ) -> Result<()>
 where
     F: FnMut(OperationProgress),
 {
+    if let Some(cache) = cache {
+        let entry = cached_xz_image(cache, &img_path, silent, &mut progress, control)?;
+        return flash_cached(
+            cache,
+            entry,
+            device_path,
+            block_size,
+            range,
+            silent,
+            verify,
+            discard,
+            progress,
+            control,
+        );
+    }
+
     let temp_file = NamedTempFile::new().context("Failed to create temporary file")?;
 
     let temp_file_str = temp_file
@@ -427,18 +976,21 @@ where
         temp_file_str.clone(),
         silent,
         &mut progress,
-        cancel,
+        control,
     )?;
 
     let result = flash_image(
         temp_file_str.clone(),
         device_path,
         block_size,
+        range,
         silent,
         progress,
         true,
         verify,
-        cancel,
+        discard,
+        None,
+        control,
     );
 
     debug!("Deleting temporary file");
@@ -451,13 +1003,96 @@ where
     Ok(())
 }
 
+/// Cache entry for a local `.xz` image: found by path and stamp, then by source hash,
+/// else decompressed into the cache.
+fn cached_xz_image<F>(
+    cache: &ImageCache,
+    img_path: &str,
+    silent: bool,
+    progress: &mut Option<F>,
+    control: Option<&OperationControl>,
+) -> Result<CacheEntry>
+where
+    F: FnMut(OperationProgress),
+{
+    let path = Path::new(img_path);
+    let origin = std::fs::canonicalize(path)
+        .context(format!("Image file not found: {}", img_path))?
+        .to_string_lossy()
+        .into_owned();
+    let stamp = cache::file_stamp(path)?;
+    if let Some(entry) = cache.lookup_origin(&origin, &stamp)? {
+        return Ok(entry);
+    }
+
+    emit_progress(
+        silent,
+        progress,
+        OperationProgress::new(OperationPhase::Preparing)
+            .with_message(format!("Hashing {}", img_path)),
+    );
+    let source_sha256 = cache::sha256_file(path, control)?;
+    if let Some(entry) = cache.lookup_source(&source_sha256, &origin, &stamp)? {
+        return Ok(entry);
+    }
+
+    emit_progress(
+        silent,
+        progress,
+        OperationProgress::new(OperationPhase::Decompressing)
+            .with_message(format!("Decompressing {} into the image cache", img_path)),
+    );
+    let source =
+        File::open(path).context(format!("Failed to open compressed file: {}", img_path))?;
+    cache.insert(source, true, &origin, &stamp, silent, progress, control)
+}
+
+/// Flash a cached raw image, reusing its stored checksum for verify.
+#[allow(clippy::too_many_arguments)]
+fn flash_cached<F>(
+    cache: &ImageCache,
+    entry: CacheEntry,
+    device_path: String,
+    block_size: usize,
+    range: IoRange,
+    silent: bool,
+    verify: bool,
+    discard: Option<DiscardMethod>,
+    mut progress: Option<F>,
+    control: Option<&OperationControl>,
+) -> Result<()>
+where
+    F: FnMut(OperationProgress),
+{
+    let image_path = cache.image_path(&entry);
+    emit_progress(
+        silent,
+        &mut progress,
+        OperationProgress::new(OperationPhase::Preparing)
+            .with_message(format!("Using cached image {}", image_path.display())),
+    );
+    flash_image(
+        image_path.to_string_lossy().into_owned(),
+        device_path,
+        block_size,
+        range,
+        silent,
+        progress,
+        true,
+        verify,
+        discard,
+        Some(entry.image_sha256),
+        control,
+    )
+}
+
 /// decompress the input image and write to the output file
 fn decompress_img<F>(
     compressed_file: String,
     decompressed_file: String,
     silent: bool,
     progress: &mut Option<F>,
-    cancel: Option<&AtomicBool>,
+    control: Option<&OperationControl>,
 ) -> Result<()>
 where
     F: FnMut(OperationProgress),
@@ -493,7 +1128,7 @@ where
     let mut decompressed: u64 = 0;
 
     loop {
-        check_cancel(cancel)?;
+        check_cancel(control)?;
         let bytes_read = decoder
             .read(&mut buffer)
             .context("Failed to read from compressed stream")?;
@@ -516,3 +1151,356 @@ where
     info!("Decompression completed");
     Ok(())
 }
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    fn write_temp(contents: &[u8]) -> NamedTempFile {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(contents).unwrap();
+        file
+    }
+
+    fn path(file: &NamedTempFile) -> String {
+        file.path().to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn flash_range_writes_at_seek_offset_and_verifies() {
+        let image: Vec<u8> = (0..4096u32).map(|i| (i % 251) as u8).collect();
+        let image_file = write_temp(&image);
+        let device_file = write_temp(&[0u8; 16 * 1024]);
+        let range = IoRange {
+            skip: 1024,
+            seek: 8 * 1024,
+            count: Some(2048),
+        };
+
+        flash_image::<fn(OperationProgress)>(
+            path(&image_file),
+            path(&device_file),
+            512,
+            range,
+            true,
+            None,
+            true,
+            true,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+        let device = std::fs::read(device_file.path()).unwrap();
+        assert!(device[..8 * 1024].iter().all(|b| *b == 0));
+        assert_eq!(&device[8 * 1024..10 * 1024], &image[1024..3072]);
+        assert!(device[10 * 1024..].iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn flash_range_refuses_writes_past_device_end() {
+        let image_file = write_temp(&[1u8; 4096]);
+        let device_file = write_temp(&[0u8; 8192]);
+        let range = IoRange {
+            seek: 6144,
+            ..Default::default()
+        };
+
+        let error = flash_image::<fn(OperationProgress)>(
+            path(&image_file),
+            path(&device_file),
+            512,
+            range,
+            true,
+            None,
+            true,
+            false,
+            None,
+            None,
+            None,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("do not fit"));
+        assert!(std::fs::read(device_file.path())
+            .unwrap()
+            .iter()
+            .all(|b| *b == 0));
+    }
+
+    #[derive(Default)]
+    struct RecordingWriter {
+        discards: Vec<(u64, u64, DiscardMethod)>,
+        busy_rereads: u32,
+        rereads: u32,
+    }
+
+    impl Write for RecordingWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Seek for RecordingWriter {
+        fn seek(&mut self, _pos: SeekFrom) -> std::io::Result<u64> {
+            Ok(0)
+        }
+    }
+
+    impl DeviceWriter for RecordingWriter {
+        fn open(_device_path: &str) -> Result<Self> {
+            Ok(Self::default())
+        }
+
+        fn flush_and_sync(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn device_size(&self) -> Result<u64> {
+            Ok(0)
+        }
+
+        fn discard(&mut self, offset: u64, len: u64, method: DiscardMethod) -> Result<()> {
+            self.discards.push((offset, len, method));
+            Ok(())
+        }
+
+        fn reread_partition_table(&mut self) -> Result<()> {
+            self.rereads += 1;
+            if self.rereads <= self.busy_rereads {
+                return Err(std::io::Error::from_raw_os_error(libc::EBUSY))
+                    .context("Failed to re-read the partition table");
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn discard_range_aligns_to_sectors_and_skips_unsupported() {
+        let mut writer = RecordingWriter::default();
+        wipe::discard_range::<fn(OperationProgress)>(
+            &mut writer,
+            "/nonexistent/litho-test",
+            DiscardMethod::ZeroOut,
+            100,
+            2000,
+            wipe::DiscardMode::BeforeWrite,
+            true,
+            &mut None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(writer.discards, vec![(512, 1536, DiscardMethod::ZeroOut)]);
+
+        let mut writer = RecordingWriter::default();
+        let mut messages = Vec::new();
+        wipe::discard_range(
+            &mut writer,
+            "/nonexistent/litho-test",
+            DiscardMethod::Discard,
+            0,
+            4096,
+            wipe::DiscardMode::BeforeWrite,
+            false,
+            &mut Some(|event: OperationProgress| messages.extend(event.message)),
+            None,
+        )
+        .unwrap();
+        assert!(writer.discards.is_empty());
+        assert!(messages[0].contains("not supported"));
+
+        let error = wipe::discard_range::<fn(OperationProgress)>(
+            &mut writer,
+            "/nonexistent/litho-test",
+            DiscardMethod::Discard,
+            0,
+            4096,
+            wipe::DiscardMode::Erase,
+            true,
+            &mut None,
+            None,
+        )
+        .unwrap_err();
+        assert!(writer.discards.is_empty());
+        assert!(error.to_string().contains("does not support discard"));
+    }
+
+    #[test]
+    fn refresh_partitions_retries_while_busy() {
+        let mut writer = RecordingWriter {
+            busy_rereads: 2,
+            ..Default::default()
+        };
+        let mut settles = 0;
+        let mut waits = Vec::new();
+        let partitions = refresh_partitions_with(
+            &mut writer,
+            "/dev/litho-test-disk",
+            true,
+            &mut || settles += 1,
+            &mut |delay| waits.push(delay),
+        );
+        assert_eq!(partitions, Some(Vec::new()));
+        assert_eq!(writer.rereads, 3);
+        assert_eq!(settles, 1);
+        assert_eq!(waits, [REREAD_RETRY_DELAY, REREAD_RETRY_DELAY * 2]);
+
+        let mut writer = RecordingWriter::default();
+        let partitions = refresh_partitions_with(
+            &mut writer,
+            "/dev/sdz1",
+            true,
+            &mut || panic!("settled for a partition target"),
+            &mut |_| panic!("waited for a partition target"),
+        );
+        assert_eq!(partitions, None);
+        assert_eq!(writer.rereads, 0);
+    }
+
+    #[test]
+    fn clone_used_stops_after_last_partition_and_rebuilds_gpt() {
+        let disk = partitions::test_images::gpt_disk(16384, &[(2048, 4095)]);
+        let device_file = write_temp(&disk);
+        let output = NamedTempFile::new().unwrap();
+
+        clone_used::<fn(OperationProgress)>(
+            path(&device_file),
+            path(&output),
+            4096,
+            true,
+            None,
+            None,
+        )
+        .unwrap();
+
+        let image = std::fs::read(output.path()).unwrap();
+        assert_eq!(image.len(), (4096 + 33) * 512);
+        assert_eq!(&image[1024..4096 * 512], &disk[1024..4096 * 512]);
+        let table = partitions::read_partition_table(&mut std::io::Cursor::new(&image))
+            .unwrap()
+            .unwrap();
+        assert_eq!(table.gpt.unwrap().backup_lba, 4096 + 32);
+        assert_eq!(u32::from_le_bytes(image[458..462].try_into().unwrap()), 4096 + 32);
+    }
+
+    #[test]
+    fn clone_used_requires_a_partition_table() {
+        let device_file = write_temp(&[0u8; 8192]);
+        let output = NamedTempFile::new().unwrap();
+        let error = clone_used::<fn(OperationProgress)>(
+            path(&device_file),
+            path(&output),
+            4096,
+            true,
+            None,
+            None,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("No MBR or GPT"));
+    }
+
+    #[test]
+    fn clone_range_copies_only_the_selected_bytes() {
+        let disk: Vec<u8> = (0..8192u32).map(|i| (i % 253) as u8).collect();
+        let device_file = write_temp(&disk);
+        let output = NamedTempFile::new().unwrap();
+        let range = IoRange {
+            skip: 512,
+            seek: 0,
+            count: Some(3000),
+        };
+        let mut last_total = None;
+
+        clone(
+            path(&device_file),
+            path(&output),
+            1024,
+            range,
+            false,
+            Some(|event: OperationProgress| last_total = event.bytes_total),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(std::fs::read(output.path()).unwrap(), &disk[512..3512]);
+        assert_eq!(last_total, Some(3000));
+    }
+
+    #[test]
+    fn flash_from_reader_of_unknown_length_reports_bytes_only() {
+        let image: Vec<u8> = (0..5000u32).map(|i| (i % 249) as u8).collect();
+        let device_file = write_temp(&[0u8; 8192]);
+        let range = IoRange {
+            skip: 100,
+            seek: 1024,
+            count: None,
+        };
+        let mut writing = Vec::new();
+
+        flash_from_reader(
+            image.as_slice(),
+            None,
+            path(&device_file),
+            1000,
+            range,
+            false,
+            true,
+            None,
+            Some(|event: OperationProgress| {
+                if event.phase == OperationPhase::Writing {
+                    writing.push(event);
+                }
+            }),
+            None,
+        )
+        .unwrap();
+
+        let device = std::fs::read(device_file.path()).unwrap();
+        assert_eq!(&device[1024..1024 + 4900], &image[100..]);
+        assert!(writing.iter().all(|event| event.percentage.is_none()));
+        assert_eq!(writing.last().unwrap().bytes_processed, 4900);
+    }
+
+    #[test]
+    fn clone_to_writer_streams_the_range() {
+        let disk: Vec<u8> = (0..4096u32).map(|i| (i % 241) as u8).collect();
+        let device_file = write_temp(&disk);
+        let mut output = Vec::new();
+        let range = IoRange {
+            skip: 1000,
+            seek: 0,
+            count: Some(1500),
+        };
+
+        clone_to_writer::<_, fn(OperationProgress)>(
+            path(&device_file),
+            &mut output,
+            512,
+            range,
+            true,
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(output, &disk[1000..2500]);
+
+        let seek = IoRange {
+            seek: 512,
+            ..Default::default()
+        };
+        assert!(clone_to_writer::<_, fn(OperationProgress)>(
+            path(&device_file),
+            Vec::new(),
+            512,
+            seek,
+            true,
+            None,
+            None,
+        )
+        .is_err());
+    }
+}
@@ -4,11 +4,22 @@ mod cli_output;
 use clap::{Parser, Subcommand};
 use cli_cancel::CANCEL_EXIT_CODE;
 use cli_output::{CliOutput, OutputMode};
-use liblitho::io_backend::{clone_io, flash_io};
-use liblitho::progress::is_operation_cancelled;
+use liblitho::cache::ImageCache;
+use liblitho::io_backend::{
+    clone_io, clone_used_io, clone_writer_io, eject_io, flash_io, flash_reader_io, rescue_clone_io,
+    wipe_io,
+};
+use liblitho::platform::DiscardMethod;
+use liblitho::progress::{is_operation_cancelled, OperationProgress};
+use liblitho::range::{parse_byte_size, IoRange};
+use liblitho::wipe::WipeMethod;
+use std::io::{self, BufWriter, IsTerminal};
 use std::path::PathBuf;
 use std::process::ExitCode;
 
+/// `--file` value for stdin (flash) or stdout (clone).
+const STDIO_PATH: &str = "-";
+
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
@@ -20,7 +31,7 @@ struct Cli {
     #[arg(long = "dry-run", global = true, default_value_t = false)]
     dry_run: bool,
 
-    /// Path watched for cooperative cancel requests (GUI sidecar / pkexec).
+    /// Path watched for cooperative cancel/pause/resume requests (GUI sidecar / pkexec).
     #[arg(long = "cancel-file", global = true)]
     cancel_file: Option<PathBuf>,
 
@@ -32,14 +43,18 @@ struct Cli {
 enum Commands {
     /// Read a block device into an image file.
     Clone {
-        /// Output image file.
+        /// Output image file, or - to write the image to stdout.
         #[arg(short, long)]
         file: String,
 
-        /// Source block device.
+        /// Source block device, or a partition such as /dev/sdb1.
         #[arg(short, long)]
         device: String,
 
+        /// Read only partition N of --device (e.g. 1 for /dev/sdb1 or /dev/mmcblk0p1).
+        #[arg(long = "partition", value_parser = clap::value_parser!(u32).range(1..), conflicts_with = "used")]
+        partition: Option<u32>,
+
         /// I/O buffer size in bytes.
         #[arg(short, long, default_value_t = 4096)]
         block_size: usize,
@@ -47,17 +62,56 @@ enum Commands {
         /// Suppress progress output.
         #[arg(short, long, default_value_t = false)]
         silent: bool,
+
+        /// Keep going past read errors: zero-fill unreadable sectors and retry them
+        /// one sector at a time (ddrescue-style).
+        #[arg(long = "rescue", default_value_t = false)]
+        rescue: bool,
+
+        /// GNU ddrescue-compatible mapfile for --rescue (default: <file>.map).
+        /// Re-running with the same mapfile only retries the bad areas.
+        #[arg(long = "mapfile", requires = "rescue")]
+        mapfile: Option<String>,
+
+        /// Skip this many bytes at the start of the device (suffixes K, M, G, s = 512-byte sectors).
+        #[arg(long = "skip", value_parser = parse_byte_size, conflicts_with = "rescue")]
+        skip: Option<u64>,
+
+        /// Start writing at this byte offset in the output file.
+        #[arg(long = "seek", value_parser = parse_byte_size, conflicts_with = "rescue")]
+        seek: Option<u64>,
+
+        /// Copy only this many bytes (e.g. 64M for the first 64 MiB).
+        #[arg(long = "count", value_parser = parse_byte_size, conflicts_with = "rescue")]
+        count: Option<u64>,
+
+        /// Stop after the last partition in the MBR / GPT (GPT backup header is
+        /// rebuilt at the end of the smaller image).
+        #[arg(long = "used", conflicts_with_all = ["rescue", "skip", "seek", "count"])]
+        used: bool,
+
+        /// Unmount (and swapoff) every partition of the target first, listing each step.
+        #[arg(long = "unmount", default_value_t = false)]
+        unmount: bool,
+
+        /// When done, sync, unmount any auto-mounted partitions and power the device off.
+        #[arg(long = "eject", default_value_t = false)]
+        eject: bool,
     },
     /// Write an image file to a block device.
     Flash {
-        /// Image file to write.
+        /// Image file to write, or - to read the image from stdin.
         #[arg(short, long)]
         file: String,
 
-        /// Target block device.
+        /// Target block device, or a partition such as /dev/sdb2.
         #[arg(short, long)]
         device: String,
 
+        /// Write only partition N of --device; the image must fit the partition.
+        #[arg(long = "partition", value_parser = clap::value_parser!(u32).range(1..))]
+        partition: Option<u32>,
+
         /// I/O buffer size in bytes.
         #[arg(short, long, default_value_t = 4096)]
         block_size: usize,
@@ -69,30 +123,185 @@ enum Commands {
         /// After writing, read the device back and compare SHA-256 checksums.
         #[arg(long = "verify", default_value_t = false)]
         verify: bool,
+
+        /// Skip this many bytes at the start of the image (suffixes K, M, G, s = 512-byte sectors).
+        #[arg(long = "skip", value_parser = parse_byte_size)]
+        skip: Option<u64>,
+
+        /// Start writing at this byte offset on the device (e.g. 8K for an Allwinner bootloader).
+        #[arg(long = "seek", value_parser = parse_byte_size)]
+        seek: Option<u64>,
+
+        /// Write only this many bytes of the image.
+        #[arg(long = "count", value_parser = parse_byte_size)]
+        count: Option<u64>,
+
+        /// Discard the target before writing: discard (TRIM), secure, or zeroout.
+        /// A full flash discards the whole device; a --seek/--count flash only its range.
+        #[arg(long = "discard", value_parser = str::parse::<DiscardMethod>, num_args = 0..=1, default_missing_value = "discard")]
+        discard: Option<DiscardMethod>,
+
+        /// Unmount (and swapoff) every partition of the target first, listing each step.
+        #[arg(long = "unmount", default_value_t = false)]
+        unmount: bool,
+
+        /// When done, sync, unmount any auto-mounted partitions and power the device off.
+        #[arg(long = "eject", default_value_t = false)]
+        eject: bool,
+
+        /// Decompress .xz files and downloads to a temporary file instead of the image cache.
+        #[arg(long = "no-cache", default_value_t = false)]
+        no_cache: bool,
+    },
+    /// Erase a block device (or a partition).
+    Wipe {
+        /// Block device to erase, or a partition such as /dev/sdb2.
+        #[arg(short, long)]
+        device: String,
+
+        /// quick (partition tables and signatures at both ends), zero, random, or discard.
+        #[arg(short, long, value_parser = str::parse::<WipeMethod>, default_value_t = WipeMethod::Quick)]
+        method: WipeMethod,
+
+        /// I/O buffer size in bytes.
+        #[arg(short, long, default_value_t = 1048576)]
+        block_size: usize,
+
+        /// Suppress progress output.
+        #[arg(short, long, default_value_t = false)]
+        silent: bool,
+
+        /// Read the wiped area back and check it (not available for discard).
+        #[arg(long = "verify", default_value_t = false)]
+        verify: bool,
+
+        /// Unmount (and swapoff) every partition of the target first, listing each step.
+        #[arg(long = "unmount", default_value_t = false)]
+        unmount: bool,
     },
     /// List storage devices or query one device.
     Query {
         /// Optional device path to query.
         #[arg(short, long)]
         device: Option<String>,
+
+        /// Keep running and print devices as they are added, removed or changed.
+        #[arg(long = "watch", default_value_t = false)]
+        watch: bool,
+
+        /// Print the device list or the single-device report as JSON.
+        #[arg(long = "json", default_value_t = false, conflicts_with = "watch")]
+        json: bool,
+    },
+    /// Show the partition table, filesystems and OS of a disk or image file.
+    Inspect {
+        /// Whole block device, or a /dev/disk/by-* link to one.
+        #[arg(short, long, required_unless_present = "file", conflicts_with = "file")]
+        device: Option<String>,
+
+        /// Image file (raw, .xz, .gz, ...) to inspect instead of a device.
+        #[arg(short, long)]
+        file: Option<String>,
+
+        /// Print the report as JSON.
+        #[arg(long = "json", default_value_t = false)]
+        json: bool,
+    },
+    /// Show or empty the decompressed image cache (~/.cache/litho/images).
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
     },
 }
 
+#[derive(Subcommand)]
+enum CacheAction {
+    /// List cached images, most recently used first.
+    List,
+    /// Remove every cached image.
+    Clear,
+}
+
 fn run(cli: Cli) -> ExitCode {
     let mut out = CliOutput::new(cli.output_mode);
+    if matches!(&cli.command, Commands::Clone { file, .. } if file == STDIO_PATH) {
+        out = out.on_stderr();
+    }
 
-    match cli.command {
+    let command = match resolve_partition_target(cli.command) {
+        Ok(command) => command,
+        Err(e) => {
+            out.error(&e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match command {
+        Commands::Clone {
+            file,
+            device,
+            block_size,
+            silent,
+            rescue: true,
+            mapfile,
+            unmount,
+            eject,
+            ..
+        } => {
+            let mapfile = mapfile.unwrap_or_else(|| format!("{file}.map"));
+            run_rescue(
+                &mut out,
+                &device,
+                &file,
+                &mapfile,
+                block_size,
+                silent,
+                unmount,
+                eject,
+                cli.dry_run,
+                cli.cancel_file.as_deref(),
+            )
+        }
+        Commands::Clone {
+            file,
+            device,
+            block_size,
+            silent,
+            used: true,
+            unmount,
+            eject,
+            ..
+        } => run_clone_used(
+            &mut out,
+            &device,
+            &file,
+            block_size,
+            silent,
+            unmount,
+            eject,
+            cli.dry_run,
+            cli.cancel_file.as_deref(),
+        ),
         Commands::Clone {
             file,
             device,
             block_size,
             silent,
+            skip,
+            seek,
+            count,
+            unmount,
+            eject,
+            ..
         } => run_clone(
             &mut out,
             &device,
             &file,
             block_size,
+            io_range(skip, seek, count),
             silent,
+            unmount,
+            eject,
             cli.dry_run,
             cli.cancel_file.as_deref(),
         ),
@@ -102,34 +311,219 @@ fn run(cli: Cli) -> ExitCode {
             block_size,
             silent,
             verify,
-        } => run_flash(
+            skip,
+            seek,
+            count,
+            discard,
+            unmount,
+            eject,
+            no_cache,
+            ..
+        } => {
+            let cache = if no_cache {
+                None
+            } else {
+                open_image_cache(&out)
+            };
+            run_flash(
+                &mut out,
+                &file,
+                &device,
+                block_size,
+                io_range(skip, seek, count),
+                silent,
+                verify,
+                discard,
+                cache.as_ref(),
+                unmount,
+                eject,
+                cli.dry_run,
+                cli.cancel_file.as_deref(),
+            )
+        }
+        Commands::Wipe {
+            device,
+            method,
+            block_size,
+            silent,
+            verify,
+            unmount,
+        } => run_wipe(
             &mut out,
-            &file,
             &device,
+            method,
             block_size,
             silent,
             verify,
+            unmount,
             cli.dry_run,
             cli.cancel_file.as_deref(),
         ),
-        Commands::Query { device } => run_query(&out, device.as_deref()),
+        Commands::Query {
+            device,
+            watch,
+            json,
+        } => {
+            if watch {
+                run_query_watch(&out, device.as_deref())
+            } else {
+                run_query(&out, device.as_deref(), json)
+            }
+        }
+        Commands::Inspect { device, file, json } => match (device, file) {
+            (Some(device), _) => run_inspect(&out, &device, json),
+            (None, Some(file)) => run_inspect_image(&out, &file, json),
+            (None, None) => unreachable!("clap requires --device or --file"),
+        },
+        Commands::Cache { action } => run_cache(&out, action),
     }
 }
 
+/// The default image cache; a misconfigured cache is a warning, not an error.
+fn open_image_cache(out: &CliOutput) -> Option<ImageCache> {
+    match ImageCache::open_default() {
+        Ok(cache) => cache,
+        Err(e) => {
+            out.warning(&format!("Image cache disabled: {e:#}"));
+            None
+        }
+    }
+}
+
+/// Replace `--device` with its partition path when `--partition N` is given.
+fn resolve_partition_target(mut command: Commands) -> Result<Commands, String> {
+    if let Commands::Clone {
+        device,
+        partition: Some(number),
+        ..
+    }
+    | Commands::Flash {
+        device,
+        partition: Some(number),
+        ..
+    } = &mut command
+    {
+        *device = liblitho::devices::partition_path(device, *number)?;
+    }
+    Ok(command)
+}
+
+/// With `--unmount`, list and release the target's mounts and swap, then run `validate`
+/// (which repeats the busy check). A dry run only lists what would be released.
+fn prepare_target(
+    out: &CliOutput,
+    device: &str,
+    unmount: bool,
+    dry_run: bool,
+    validate: fn(&str) -> Result<(), String>,
+) -> bool {
+    // Stable names stay in use throughout; show which node they point at today.
+    match liblitho::devices::resolve_device_link(device) {
+        Ok(resolved) if resolved != device.trim() => {
+            out.query_status(&format!("{device} -> {resolved}"));
+        }
+        Ok(_) => {}
+        Err(e) => {
+            out.error(&e);
+            return false;
+        }
+    }
+    if unmount {
+        let plan = match liblitho::devices::release_plan(device) {
+            Ok(plan) => plan,
+            Err(e) => {
+                out.error(&e);
+                return false;
+            }
+        };
+        if dry_run && !plan.is_empty() {
+            for action in &plan {
+                out.query_status(&format!("Would {action}"));
+            }
+            return true;
+        }
+        for action in &plan {
+            out.query_status(&format!("Will {action}"));
+        }
+        if let Err(e) = liblitho::devices::release_target(&plan) {
+            out.error(&e);
+            return false;
+        }
+    }
+    if let Err(e) = validate(device) {
+        out.error(&e);
+        return false;
+    }
+    true
+}
+
+/// `--eject` after a successful operation. Failures are warnings: the data is already
+/// written and synced.
+fn eject_target(out: &CliOutput, device: &str) {
+    match eject_io(device) {
+        Ok(steps) => {
+            for step in steps {
+                out.query_status(&format!("Eject: {step}"));
+            }
+        }
+        Err(e) => out.warning(&format!("Eject failed: {e}")),
+    }
+}
+
+fn io_range(skip: Option<u64>, seek: Option<u64>, count: Option<u64>) -> IoRange {
+    IoRange {
+        skip: skip.unwrap_or(0),
+        seek: seek.unwrap_or(0),
+        count,
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn run_flash(
     out: &mut CliOutput,
     file: &str,
     device: &str,
     block_size: usize,
+    range: IoRange,
     silent: bool,
     verify: bool,
+    discard: Option<DiscardMethod>,
+    cache: Option<&ImageCache>,
+    unmount: bool,
+    eject: bool,
     dry_run: bool,
     cancel_file: Option<&std::path::Path>,
 ) -> ExitCode {
-    if let Err(e) = liblitho::devices::validate_device_safe_for_io(device) {
+    let from_stdin = file == STDIO_PATH;
+    if from_stdin && io::stdin().is_terminal() {
+        out.error("Refusing to read an image from a terminal; pipe it into stdin");
+        return ExitCode::FAILURE;
+    }
+    // Ahead of --unmount: releasing a target that cannot be written gains nothing.
+    if let Err(e) = liblitho::devices::validate_device_writable(device) {
         out.error(&e);
         return ExitCode::FAILURE;
     }
+    if !prepare_target(
+        out,
+        device,
+        unmount,
+        dry_run,
+        liblitho::devices::validate_target_safe_for_io,
+    ) {
+        return ExitCode::FAILURE;
+    }
+
+    if !range.is_full() {
+        out.query_status(&format!("Range: {range}"));
+    }
+    if let Some(method) = discard {
+        out.query_status(&format!("Discard before writing: {method}"));
+    }
+    let cache = cache.filter(|_| file.ends_with(".xz") || liblitho::http::is_url(file));
+    if let Some(cache) = cache {
+        out.query_status(&format!("Image cache: {}", cache.dir().display()));
+    }
 
     if dry_run {
         out.dry_run_ok("flash", file, device, block_size);
@@ -138,24 +532,39 @@ fn run_flash(
 
     out.operation_start("Flashing", file, device, block_size);
 
-    let cancel = cli_cancel::prepare_operation_cancel();
-    cli_cancel::spawn_cancel_watchers(cancel.clone(), cancel_file.map(PathBuf::from));
-    let cancel_ref = Some(cancel.as_ref());
-    let result = if silent {
-        flash_io::<fn(liblitho::progress::OperationProgress)>(
-            file, device, block_size, true, verify, None, cancel_ref,
+    let control = cli_cancel::prepare_operation_control();
+    cli_cancel::spawn_control_watchers(
+        control.clone(),
+        cancel_file.map(PathBuf::from),
+        !from_stdin,
+    );
+    let control_ref = Some(control.as_ref());
+    let progress = (!silent).then_some(|event: OperationProgress| out.on_progress(&event));
+    let result = if from_stdin {
+        flash_reader_io(
+            io::stdin().lock(),
+            None,
+            device,
+            block_size,
+            range,
+            silent,
+            verify,
+            discard,
+            progress,
+            control_ref,
         )
     } else {
         flash_io(
             file,
             device,
             block_size,
-            false,
+            range,
+            silent,
             verify,
-            Some(|event| {
-                out.on_progress(&event);
-            }),
-            cancel_ref,
+            discard,
+            cache,
+            progress,
+            control_ref,
         )
     };
 
@@ -163,6 +572,9 @@ fn run_flash(
 
     match result {
         Ok(()) => {
+            if eject {
+                eject_target(out, device);
+            }
             out.done_ok("flash");
             ExitCode::SUCCESS
         }
@@ -177,20 +589,38 @@ fn run_flash(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn run_clone(
     out: &mut CliOutput,
     device: &str,
     file: &str,
     block_size: usize,
+    range: IoRange,
     silent: bool,
+    unmount: bool,
+    eject: bool,
     dry_run: bool,
     cancel_file: Option<&std::path::Path>,
 ) -> ExitCode {
-    if let Err(e) = liblitho::devices::validate_device_safe_for_io(device) {
-        out.error(&e);
+    let to_stdout = file == STDIO_PATH;
+    if to_stdout && io::stdout().is_terminal() {
+        out.error("Refusing to write image data to a terminal; redirect stdout");
+        return ExitCode::FAILURE;
+    }
+    if !prepare_target(
+        out,
+        device,
+        unmount,
+        dry_run,
+        liblitho::devices::validate_target_safe_for_io,
+    ) {
         return ExitCode::FAILURE;
     }
 
+    if !range.is_full() {
+        out.query_status(&format!("Range: {range}"));
+    }
+
     if dry_run {
         out.dry_run_ok("clone", device, file, block_size);
         return ExitCode::SUCCESS;
@@ -198,15 +628,99 @@ fn run_clone(
 
     out.operation_start("Cloning", device, file, block_size);
 
-    let cancel = cli_cancel::prepare_operation_cancel();
-    cli_cancel::spawn_cancel_watchers(cancel.clone(), cancel_file.map(PathBuf::from));
-    let cancel_ref = Some(cancel.as_ref());
-    let result = if silent {
-        clone_io::<fn(liblitho::progress::OperationProgress)>(
-            device, file, block_size, true, None, cancel_ref,
+    let control = cli_cancel::prepare_operation_control();
+    cli_cancel::spawn_control_watchers(control.clone(), cancel_file.map(PathBuf::from), true);
+    let control_ref = Some(control.as_ref());
+    let progress = (!silent).then_some(|event: OperationProgress| out.on_progress(&event));
+    let result = if to_stdout {
+        clone_writer_io(
+            device,
+            BufWriter::new(io::stdout().lock()),
+            block_size,
+            range,
+            silent,
+            progress,
+            control_ref,
         )
     } else {
         clone_io(
+            device,
+            file,
+            block_size,
+            range,
+            silent,
+            progress,
+            control_ref,
+        )
+    };
+
+    out.finish_progress_line();
+
+    match result {
+        Ok(()) => {
+            if eject {
+                eject_target(out, device);
+            }
+            out.done_ok("clone");
+            ExitCode::SUCCESS
+        }
+        Err(e) if is_operation_cancelled(&e) && to_stdout => {
+            out.cancelled("Clone cancelled — the output stream is incomplete.");
+            ExitCode::from(CANCEL_EXIT_CODE)
+        }
+        Err(e) if is_operation_cancelled(&e) => {
+            out.cancelled("Clone cancelled — incomplete output file removed.");
+            ExitCode::from(CANCEL_EXIT_CODE)
+        }
+        Err(e) => {
+            out.error(&e.to_string());
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run_clone_used(
+    out: &mut CliOutput,
+    device: &str,
+    file: &str,
+    block_size: usize,
+    silent: bool,
+    unmount: bool,
+    eject: bool,
+    dry_run: bool,
+    cancel_file: Option<&std::path::Path>,
+) -> ExitCode {
+    if file == STDIO_PATH {
+        out.error("--used rewrites the end of the image and needs an output file, not stdout");
+        return ExitCode::FAILURE;
+    }
+    if !prepare_target(
+        out,
+        device,
+        unmount,
+        dry_run,
+        liblitho::devices::validate_target_safe_for_io,
+    ) {
+        return ExitCode::FAILURE;
+    }
+
+    if dry_run {
+        out.dry_run_ok("clone (used extent)", device, file, block_size);
+        return ExitCode::SUCCESS;
+    }
+
+    out.operation_start("Cloning used extent", device, file, block_size);
+
+    let control = cli_cancel::prepare_operation_control();
+    cli_cancel::spawn_control_watchers(control.clone(), cancel_file.map(PathBuf::from), true);
+    let control_ref = Some(control.as_ref());
+    let result = if silent {
+        clone_used_io::<fn(liblitho::progress::OperationProgress)>(
+            device, file, block_size, true, None, control_ref,
+        )
+    } else {
+        clone_used_io(
             device,
             file,
             block_size,
@@ -214,7 +728,7 @@ fn run_clone(
             Some(|event| {
                 out.on_progress(&event);
             }),
-            cancel_ref,
+            control_ref,
         )
     };
 
@@ -222,6 +736,9 @@ fn run_clone(
 
     match result {
         Ok(()) => {
+            if eject {
+                eject_target(out, device);
+            }
             out.done_ok("clone");
             ExitCode::SUCCESS
         }
@@ -236,34 +753,244 @@ fn run_clone(
     }
 }
 
-fn run_query(out: &CliOutput, device: Option<&str>) -> ExitCode {
+#[allow(clippy::too_many_arguments)]
+fn run_wipe(
+    out: &mut CliOutput,
+    device: &str,
+    method: WipeMethod,
+    block_size: usize,
+    silent: bool,
+    verify: bool,
+    unmount: bool,
+    dry_run: bool,
+    cancel_file: Option<&std::path::Path>,
+) -> ExitCode {
+    if verify && !method.supports_verify() {
+        out.error(&format!(
+            "--verify is not available for a {method} wipe; use --method zero"
+        ));
+        return ExitCode::FAILURE;
+    }
+    if let Err(e) = liblitho::devices::validate_device_writable(device) {
+        out.error(&e);
+        return ExitCode::FAILURE;
+    }
+    if !prepare_target(
+        out,
+        device,
+        unmount,
+        dry_run,
+        liblitho::devices::validate_target_safe_for_io,
+    ) {
+        return ExitCode::FAILURE;
+    }
+
+    if dry_run {
+        out.dry_run_ok("wipe", &format!("[{method}]"), device, block_size);
+        return ExitCode::SUCCESS;
+    }
+
+    out.operation_start("Wiping", &format!("[{method}]"), device, block_size);
+
+    let control = cli_cancel::prepare_operation_control();
+    cli_cancel::spawn_control_watchers(control.clone(), cancel_file.map(PathBuf::from), true);
+    let control_ref = Some(control.as_ref());
+    let result = if silent {
+        wipe_io::<fn(liblitho::progress::OperationProgress)>(
+            device, method, block_size, true, verify, None, control_ref,
+        )
+    } else {
+        wipe_io(
+            device,
+            method,
+            block_size,
+            false,
+            verify,
+            Some(|event| {
+                out.on_progress(&event);
+            }),
+            control_ref,
+        )
+    };
+
+    out.finish_progress_line();
+
+    match result {
+        Ok(()) => {
+            out.done_ok("wipe");
+            ExitCode::SUCCESS
+        }
+        Err(e) if is_operation_cancelled(&e) => {
+            out.cancelled("Wipe cancelled — device may be partially erased.");
+            ExitCode::from(CANCEL_EXIT_CODE)
+        }
+        Err(e) => {
+            out.error(&e.to_string());
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run_rescue(
+    out: &mut CliOutput,
+    device: &str,
+    file: &str,
+    mapfile: &str,
+    block_size: usize,
+    silent: bool,
+    unmount: bool,
+    eject: bool,
+    dry_run: bool,
+    cancel_file: Option<&std::path::Path>,
+) -> ExitCode {
+    if file == STDIO_PATH {
+        out.error("--rescue retries bad areas in place and needs an output file, not stdout");
+        return ExitCode::FAILURE;
+    }
+    if !prepare_target(
+        out,
+        device,
+        unmount,
+        dry_run,
+        liblitho::devices::validate_target_safe_for_io,
+    ) {
+        return ExitCode::FAILURE;
+    }
+
+    if dry_run {
+        out.dry_run_ok("rescue-clone", device, file, block_size);
+        return ExitCode::SUCCESS;
+    }
+
+    out.operation_start("Rescuing", device, file, block_size);
+    out.query_status(&format!("Mapfile: {mapfile}"));
+
+    let control = cli_cancel::prepare_operation_control();
+    cli_cancel::spawn_control_watchers(control.clone(), cancel_file.map(PathBuf::from), true);
+    let control_ref = Some(control.as_ref());
+    let result = if silent {
+        rescue_clone_io::<fn(liblitho::progress::OperationProgress)>(
+            device, file, mapfile, block_size, true, None, control_ref,
+        )
+    } else {
+        rescue_clone_io(
+            device,
+            file,
+            mapfile,
+            block_size,
+            false,
+            Some(|event| {
+                out.on_progress(&event);
+            }),
+            control_ref,
+        )
+    };
+
+    out.finish_progress_line();
+
+    match result {
+        Ok(summary) => {
+            if summary.bytes_bad > 0 {
+                out.warning(&liblitho::rescue::rescue_summary_message(&summary));
+            }
+            if eject {
+                eject_target(out, device);
+            }
+            out.done_ok("rescue clone");
+            ExitCode::SUCCESS
+        }
+        Err(e) if is_operation_cancelled(&e) => {
+            out.cancelled("Rescue cancelled — output and mapfile kept; re-run to resume.");
+            ExitCode::from(CANCEL_EXIT_CODE)
+        }
+        Err(e) => {
+            out.error(&e.to_string());
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run_cache(out: &CliOutput, action: CacheAction) -> ExitCode {
+    // A disabled cache (LITHO_CACHE_MAX=0) can still be listed and cleared.
+    let cache = match ImageCache::open_default() {
+        Ok(Some(cache)) => cache,
+        Ok(None) => match ImageCache::default_dir() {
+            Ok(dir) => ImageCache::new(dir, 0),
+            Err(e) => {
+                out.error(&format!("{e:#}"));
+                return ExitCode::FAILURE;
+            }
+        },
+        Err(e) => {
+            out.error(&format!("{e:#}"));
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match action {
+        CacheAction::List => match cache.entries() {
+            Ok(entries) => {
+                for entry in &entries {
+                    out.cache_entry(entry);
+                }
+                let used: u64 = entries.iter().map(|entry| entry.size).sum();
+                out.query_status(&format!(
+                    "{} cached images, {} bytes of {} in {}",
+                    entries.len(),
+                    used,
+                    cache.max_bytes(),
+                    cache.dir().display()
+                ));
+                ExitCode::SUCCESS
+            }
+            Err(e) => {
+                out.error(&format!("{e:#}"));
+                ExitCode::FAILURE
+            }
+        },
+        CacheAction::Clear => match cache.clear() {
+            Ok((count, bytes)) => {
+                out.query_status(&format!(
+                    "Removed {count} cached images ({bytes} bytes) from {}",
+                    cache.dir().display()
+                ));
+                ExitCode::SUCCESS
+            }
+            Err(e) => {
+                out.error(&format!("{e:#}"));
+                ExitCode::FAILURE
+            }
+        },
+    }
+}
+
+fn run_query(out: &CliOutput, device: Option<&str>, json: bool) -> ExitCode {
     match device {
         Some(path) => {
-            out.query_status(&format!("Querying device: {path}"));
-            // Single-device lookup is not implemented yet; list all and let the user filter.
-            match liblitho::devices::get_storage_devices() {
-                Ok(devices) => {
-                    let mut found = false;
-                    for dev in devices {
-                        if device_path_matches(&dev.device_name, path) {
-                            out.query_device(&dev);
-                            found = true;
-                            break;
-                        }
-                    }
-                    if !found {
-                        out.error(&format!("Device not found: {path}"));
-                        return ExitCode::FAILURE;
-                    }
+            if !json {
+                out.query_status(&format!("Querying device: {path}"));
+            }
+            match liblitho::devices::query_device(path) {
+                Ok(report) if json => {
+                    out.json(&report);
+                    ExitCode::SUCCESS
+                }
+                Ok(report) => {
+                    out.device_report(&report);
                     ExitCode::SUCCESS
                 }
                 Err(e) => {
-                    out.error(&e.to_string());
+                    out.error(&format!("{e:#}"));
                     ExitCode::FAILURE
                 }
             }
         }
-        None => match liblitho::devices::get_storage_devices() {
+        None => match liblitho::devices::get_storage_devices_with_tables() {
+            Ok(devices) if json => {
+                out.json(&devices);
+                ExitCode::SUCCESS
+            }
             Ok(devices) => {
                 if devices.is_empty() {
                     out.query_status("No storage devices found");
@@ -282,6 +1009,88 @@ fn run_query(out: &CliOutput, device: Option<&str>) -> ExitCode {
     }
 }
 
+fn run_inspect(out: &CliOutput, device: &str, json: bool) -> ExitCode {
+    match liblitho::devices::inspect_device(device) {
+        Ok(contents) if json => {
+            out.json(&contents);
+            ExitCode::SUCCESS
+        }
+        Ok(contents) => {
+            out.device_contents(&contents);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            out.error(&format!("{e:#}"));
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run_inspect_image(out: &CliOutput, file: &str, json: bool) -> ExitCode {
+    if !json {
+        out.query_status(&format!("Inspecting {file}..."));
+    }
+    let cache = open_image_cache(out);
+    match liblitho::inspect::inspect_image(file, cache.as_ref()) {
+        Ok(report) if json => {
+            out.json(&report);
+            ExitCode::SUCCESS
+        }
+        Ok(report) => {
+            out.image_report(&report);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            out.error(&format!("{e:#}"));
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Print the current devices, then every change until interrupted.
+fn run_query_watch(out: &CliOutput, device: Option<&str>) -> ExitCode {
+    let mut watcher = match liblitho::devices::watch() {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            out.error(&format!("{e:#}"));
+            return ExitCode::FAILURE;
+        }
+    };
+    let device = match device
+        .map(liblitho::devices::resolve_device_link)
+        .transpose()
+    {
+        Ok(device) => device,
+        Err(e) => {
+            out.error(&e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let wanted = |dev: &liblitho::devices::DeviceInfo| {
+        device
+            .as_deref()
+            .is_none_or(|path| device_path_matches(&dev.device_name, path))
+    };
+    for dev in watcher.devices().iter().filter(|dev| wanted(dev)) {
+        out.query_device(dev);
+    }
+    out.query_status(&format!(
+        "Watching for device changes ({}); Ctrl-C to stop",
+        watcher.backend()
+    ));
+    for event in &mut watcher {
+        match event {
+            Ok(event) if wanted(event.device()) => out.device_event(&event),
+            Ok(_) => {}
+            Err(e) => {
+                out.error(&format!("{e:#}"));
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    ExitCode::SUCCESS
+}
+
 fn device_path_matches(device_name: &str, query_path: &str) -> bool {
     query_path.trim() == device_name.trim()
 }
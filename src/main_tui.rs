@@ -15,7 +15,7 @@ async fn main() -> Result<(), io::Error> {
         log_file: cli.log_file.clone(),
         log_level: Some(cli.log_level.clone()),
     })
-    .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+    .map_err(io::Error::other)?;
 
     let launch: LaunchParams = cli.into();
     if let Err(err) = run_tui(launch).await {
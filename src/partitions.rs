@@ -0,0 +1,945 @@
+//! MBR / GPT partition table parsing and GPT relocation for used-extent clones.
+
+use crate::filesystems::{probe_filesystem, Filesystem};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::{Read, Seek, SeekFrom};
+
+/// Sector size used by MBR tables (LBA units in the partition entries).
+pub const MBR_SECTOR_SIZE: u64 = 512;
+
+const MBR_SIGNATURE: [u8; 2] = [0x55, 0xAA];
+const MBR_ENTRIES_OFFSET: usize = 446;
+const MBR_ENTRY_SIZE: usize = 16;
+const MBR_TYPE_GPT_PROTECTIVE: u8 = 0xEE;
+/// CHS, LBA and Linux extended partition containers.
+const MBR_TYPES_EXTENDED: [u8; 3] = [0x05, 0x0F, 0x85];
+/// Logical partitions followed before an EBR chain is treated as a loop.
+const MAX_LOGICAL_PARTITIONS: u32 = 128;
+
+const GPT_SIGNATURE: &[u8; 8] = b"EFI PART";
+const GPT_MIN_HEADER_SIZE: usize = 92;
+/// Logical sector sizes probed for the GPT header at LBA 1.
+const GPT_SECTOR_SIZES: [u64; 2] = [512, 4096];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PartitionTableKind {
+    Mbr,
+    Gpt,
+}
+
+impl fmt::Display for PartitionTableKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Mbr => "MBR",
+            Self::Gpt => "GPT",
+        })
+    }
+}
+
+/// One partition, in units of the table's logical sector size.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Partition {
+    /// 1-based partition number (`/dev/sdX<number>`); MBR logical partitions start at 5.
+    pub number: u32,
+    pub first_lba: u64,
+    /// Inclusive, as in GPT.
+    pub last_lba: u64,
+    /// MBR type byte (`0x83`) or GPT type GUID.
+    pub type_id: String,
+    pub name: Option<String>,
+    /// Filesystem found at the start of the partition.
+    #[serde(default)]
+    pub filesystem: Option<Filesystem>,
+}
+
+impl Partition {
+    pub fn sectors(&self) -> u64 {
+        self.last_lba.saturating_sub(self.first_lba) + 1
+    }
+
+    /// MBR extended container holding the logical partitions.
+    pub fn is_extended(&self) -> bool {
+        MBR_TYPES_EXTENDED
+            .iter()
+            .any(|kind| self.type_id == format!("0x{kind:02x}"))
+    }
+
+    /// Human name of the partition type (`Linux`, `EFI System`, …), when known.
+    pub fn type_name(&self) -> Option<&'static str> {
+        let name = match self.type_id.as_str() {
+            "0x01" => "FAT12",
+            "0x04" | "0x06" | "0x0e" => "FAT16",
+            "0x05" | "0x0f" | "0x85" => "Extended",
+            "0x07" => "NTFS/exFAT",
+            "0x0b" | "0x0c" => "FAT32",
+            "0x82" => "Linux swap",
+            "0x83" => "Linux",
+            "0x8e" => "Linux LVM",
+            "0xef" => "EFI System",
+            "0xfd" => "Linux RAID",
+            "C12A7328-F81F-11D2-BA4B-00A0C93EC93B" => "EFI System",
+            "21686148-6449-6E6F-744E-656564454649" => "BIOS boot",
+            "EBD0A0A2-B9E5-4433-87C0-68B6B72699C7" => "Microsoft basic data",
+            "E3C9E316-0B5C-4DB8-817D-F92DF00215AE" => "Microsoft reserved",
+            "0FC63DAF-8483-4772-8E79-3D69D8477DE4" => "Linux filesystem",
+            "4F68BCE3-E8CD-4DB1-96E7-FBCAF984B709" => "Linux root (x86-64)",
+            "B921B045-1DF0-41C3-AF44-4C6F280D3FAE" => "Linux root (ARM64)",
+            "0657FD6D-A4AB-43C4-84E5-0933C84B4F4F" => "Linux swap",
+            "E6D6D379-F507-44C2-A23C-238F2A3DF928" => "Linux LVM",
+            "A19D880F-05FC-4D3B-A006-743F0F84911E" => "Linux RAID",
+            "BC13C2FF-59E6-4262-A352-B275FD6F7172" => "Linux extended boot",
+            _ => return None,
+        };
+        Some(name)
+    }
+
+    /// `FAT32 boot` style description: filesystem (or type) and label (or GPT name).
+    pub fn description(&self) -> String {
+        let kind = self
+            .filesystem
+            .as_ref()
+            .map(|fs| fs.kind.clone())
+            .or_else(|| self.type_name().map(str::to_string))
+            .unwrap_or_else(|| self.type_id.clone());
+        let label = self
+            .filesystem
+            .as_ref()
+            .and_then(|fs| fs.label.as_deref())
+            .or(self.name.as_deref());
+        match label {
+            Some(label) => format!("{kind} {label}"),
+            None => kind,
+        }
+    }
+}
+
+/// GPT header fields needed to relocate the backup header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GptHeader {
+    /// Raw header bytes (`header_size` long) as read from disk.
+    pub raw: Vec<u8>,
+    pub current_lba: u64,
+    pub backup_lba: u64,
+    pub first_usable_lba: u64,
+    pub last_usable_lba: u64,
+    pub entries_lba: u64,
+    pub num_entries: u32,
+    pub entry_size: u32,
+    pub entries_crc32: u32,
+}
+
+impl GptHeader {
+    /// True for a backup header (stored at the end of the disk).
+    pub fn is_backup(&self) -> bool {
+        self.current_lba > self.backup_lba
+    }
+
+    /// Number of sectors occupied by the partition entry array.
+    pub fn entries_sectors(&self, sector_size: u64) -> u64 {
+        (self.num_entries as u64 * self.entry_size as u64).div_ceil(sector_size)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PartitionTable {
+    pub kind: PartitionTableKind,
+    pub sector_size: u64,
+    pub partitions: Vec<Partition>,
+    #[serde(skip)]
+    pub gpt: Option<GptHeader>,
+    /// Raw GPT partition entry array (empty for MBR).
+    #[serde(skip)]
+    pub gpt_entries: Vec<u8>,
+    /// Problems that did not prevent reading the table, such as a corrupt GPT
+    /// backup (or primary, when the backup was used instead).
+    #[serde(default)]
+    pub warnings: Vec<String>,
+}
+
+impl PartitionTable {
+    /// Partitions that hold data, without MBR extended containers.
+    pub fn data_partitions(&self) -> impl Iterator<Item = &Partition> {
+        self.partitions.iter().filter(|p| !p.is_extended())
+    }
+
+    pub fn partition_bytes(&self, partition: &Partition) -> u64 {
+        partition.sectors() * self.sector_size
+    }
+
+    /// One-line overview, e.g. `2 partitions: FAT32 boot 256M, ext4 rootfs 7.2G`.
+    pub fn summary(&self) -> String {
+        let parts: Vec<String> = self
+            .data_partitions()
+            .map(|p| {
+                format!(
+                    "{} {}",
+                    p.description(),
+                    format_size(self.partition_bytes(p))
+                )
+            })
+            .collect();
+        match parts.len() {
+            0 => format!("{} table, no partitions", self.kind),
+            1 => format!("1 partition: {}", parts[0]),
+            n => format!("{n} partitions: {}", parts.join(", ")),
+        }
+    }
+
+    /// Last sector used by any partition (or by the table itself when empty).
+    pub fn last_used_lba(&self) -> u64 {
+        let table_end = match &self.gpt {
+            Some(header) => header.first_usable_lba.saturating_sub(1),
+            None => 0,
+        };
+        self.partitions
+            .iter()
+            .map(|p| p.last_lba)
+            .fold(table_end, u64::max)
+    }
+
+    /// Bytes from the start of the disk through the end of the last partition.
+    pub fn used_bytes(&self) -> u64 {
+        (self.last_used_lba() + 1) * self.sector_size
+    }
+
+    /// Size of an image that holds the used extent plus a relocated GPT backup
+    /// (entries + header); equal to [`Self::used_bytes`] for MBR.
+    pub fn used_image_bytes(&self) -> u64 {
+        match &self.gpt {
+            Some(header) => {
+                let backup_sectors = header.entries_sectors(self.sector_size) + 1;
+                self.used_bytes()
+                    .saturating_add(backup_sectors * self.sector_size)
+            }
+            None => self.used_bytes(),
+        }
+    }
+
+    /// GPT structures for an image shrunk to `used_image_bytes()`.
+    pub fn relocated_gpt(&self) -> Option<RelocatedGpt> {
+        let header = self.gpt.as_ref()?;
+        let sector_size = self.sector_size;
+        let total_sectors = self.used_image_bytes() / sector_size;
+        let backup_lba = total_sectors - 1;
+        let backup_entries_lba = backup_lba - header.entries_sectors(sector_size);
+        let last_usable_lba = backup_entries_lba - 1;
+
+        let primary = gpt_header_bytes(
+            &header.raw,
+            header.current_lba,
+            backup_lba,
+            last_usable_lba,
+            header.entries_lba,
+        );
+        let backup = gpt_header_bytes(
+            &header.raw,
+            backup_lba,
+            header.current_lba,
+            last_usable_lba,
+            backup_entries_lba,
+        );
+        let mut backup_entries = self.gpt_entries.clone();
+        backup_entries.resize((header.entries_sectors(sector_size) * sector_size) as usize, 0);
+
+        Some(RelocatedGpt {
+            sector_size,
+            total_sectors,
+            primary_header: primary,
+            backup_entries_lba,
+            backup_entries,
+            backup_lba,
+            backup_header: backup,
+        })
+    }
+}
+
+/// Primary header, backup entries and backup header for a shrunk GPT image.
+#[derive(Debug, Clone)]
+pub struct RelocatedGpt {
+    pub sector_size: u64,
+    pub total_sectors: u64,
+    pub primary_header: Vec<u8>,
+    pub backup_entries_lba: u64,
+    pub backup_entries: Vec<u8>,
+    pub backup_lba: u64,
+    pub backup_header: Vec<u8>,
+}
+
+impl RelocatedGpt {
+    /// Protective MBR size field for the shrunk image (capped at 32 bits).
+    pub fn protective_mbr_sectors(&self) -> u32 {
+        let sectors = self.total_sectors - 1;
+        u32::try_from(sectors).unwrap_or(u32::MAX)
+    }
+}
+
+/// Read the partition table from the start of a disk or image, with the filesystem
+/// found in each partition.
+///
+/// GPT takes precedence over the protective (or hybrid) MBR. When the primary GPT
+/// fails its checksums the backup at the end of the disk is used instead; a bad
+/// primary or backup is reported in [`PartitionTable::warnings`].
+pub fn read_partition_table<R: Read + Seek>(reader: &mut R) -> Result<Option<PartitionTable>> {
+    let mut mbr = [0u8; 512];
+    reader
+        .seek(SeekFrom::Start(0))
+        .context("Failed to seek to the partition table")?;
+    reader
+        .read_exact(&mut mbr)
+        .context("Failed to read the master boot record")?;
+    if mbr[510..512] != MBR_SIGNATURE {
+        return Ok(None);
+    }
+
+    let entries = parse_mbr_entries(&mbr);
+    let mut table = if entries
+        .iter()
+        .any(|(_, kind, _, _)| *kind == MBR_TYPE_GPT_PROTECTIVE)
+    {
+        read_gpt(reader)?
+    } else {
+        read_mbr(reader, entries)
+    };
+
+    let sector_size = table.sector_size;
+    for partition in table.partitions.iter_mut().filter(|p| !p.is_extended()) {
+        partition.filesystem = probe_filesystem(reader, partition.first_lba * sector_size);
+    }
+    Ok(Some(table))
+}
+
+/// Non-empty entries of an MBR or EBR as `(number, type, start_lba, sectors)`.
+fn parse_mbr_entries(mbr: &[u8; 512]) -> Vec<(u32, u8, u64, u64)> {
+    (0..4)
+        .filter_map(|index| {
+            let entry = &mbr[MBR_ENTRIES_OFFSET + index * MBR_ENTRY_SIZE..][..MBR_ENTRY_SIZE];
+            let kind = entry[4];
+            let start = u32_le(&entry[8..12]) as u64;
+            let sectors = u32_le(&entry[12..16]) as u64;
+            (kind != 0 && sectors > 0).then_some((index as u32 + 1, kind, start, sectors))
+        })
+        .collect()
+}
+
+fn mbr_partition(number: u32, kind: u8, start: u64, sectors: u64) -> Partition {
+    Partition {
+        number,
+        first_lba: start,
+        last_lba: start + sectors - 1,
+        type_id: format!("0x{kind:02x}"),
+        name: None,
+        filesystem: None,
+    }
+}
+
+/// Primary partitions plus the logical partitions of an extended partition.
+fn read_mbr<R: Read + Seek>(reader: &mut R, entries: Vec<(u32, u8, u64, u64)>) -> PartitionTable {
+    let mut partitions: Vec<Partition> = entries
+        .iter()
+        .map(|&(number, kind, start, sectors)| mbr_partition(number, kind, start, sectors))
+        .collect();
+    let mut warnings = Vec::new();
+    if let Some(&(_, _, extended_start, _)) = entries
+        .iter()
+        .find(|(_, kind, _, _)| MBR_TYPES_EXTENDED.contains(kind))
+    {
+        if let Err(e) = read_logical_partitions(reader, extended_start, &mut partitions) {
+            warnings.push(format!("Extended partition chain is broken: {e:#}"));
+        }
+    }
+    PartitionTable {
+        kind: PartitionTableKind::Mbr,
+        sector_size: MBR_SECTOR_SIZE,
+        partitions,
+        gpt: None,
+        gpt_entries: Vec::new(),
+        warnings,
+    }
+}
+
+/// Follow the EBR chain of the extended partition at `extended_start`, appending
+/// logical partitions numbered from 5 as Linux does.
+///
+/// In each EBR the first entry is the logical partition, relative to the EBR, and
+/// the second links to the next EBR, relative to the extended partition.
+fn read_logical_partitions<R: Read + Seek>(
+    reader: &mut R,
+    extended_start: u64,
+    partitions: &mut Vec<Partition>,
+) -> Result<()> {
+    let mut ebr_lba = extended_start;
+    let mut visited = Vec::new();
+    let mut number = 5;
+    while number < 5 + MAX_LOGICAL_PARTITIONS {
+        if visited.contains(&ebr_lba) {
+            bail!("EBR at LBA {} links back into the chain", ebr_lba);
+        }
+        visited.push(ebr_lba);
+
+        let mut ebr = [0u8; 512];
+        reader
+            .seek(SeekFrom::Start(ebr_lba * MBR_SECTOR_SIZE))
+            .context("Failed to seek to an extended boot record")?;
+        reader
+            .read_exact(&mut ebr)
+            .with_context(|| format!("Failed to read the EBR at LBA {ebr_lba}"))?;
+        if ebr[510..512] != MBR_SIGNATURE {
+            bail!("No EBR signature at LBA {}", ebr_lba);
+        }
+
+        let mut next = None;
+        for (slot, kind, start, sectors) in parse_mbr_entries(&ebr) {
+            match slot {
+                1 => {
+                    partitions.push(mbr_partition(number, kind, ebr_lba + start, sectors));
+                    number += 1;
+                }
+                2 if MBR_TYPES_EXTENDED.contains(&kind) => next = Some(extended_start + start),
+                _ => {}
+            }
+        }
+        match next {
+            Some(lba) => ebr_lba = lba,
+            None => return Ok(()),
+        }
+    }
+    bail!("More than {} logical partitions", MAX_LOGICAL_PARTITIONS)
+}
+
+/// GPT at the first sector size with a header at LBA 1, falling back to the backup
+/// header in the last sector when the primary is corrupt or missing.
+fn read_gpt<R: Read + Seek>(reader: &mut R) -> Result<PartitionTable> {
+    let mut primary_error = None;
+    for sector_size in GPT_SECTOR_SIZES {
+        match read_gpt_header(reader, sector_size, 1) {
+            Ok(Some((header, entries))) => {
+                let warnings = check_backup_gpt(reader, sector_size, &header)
+                    .err()
+                    .map(|e| format!("Backup GPT is unusable: {e:#}"))
+                    .into_iter()
+                    .collect();
+                return Ok(gpt_table(sector_size, header, entries, warnings));
+            }
+            Ok(None) => {}
+            Err(e) => {
+                primary_error = Some((sector_size, e));
+                break;
+            }
+        }
+    }
+
+    let sector_sizes = match &primary_error {
+        Some((sector_size, _)) => vec![*sector_size],
+        None => GPT_SECTOR_SIZES.to_vec(),
+    };
+    for sector_size in sector_sizes {
+        let backup =
+            last_lba(reader, sector_size).and_then(|lba| read_gpt_header(reader, sector_size, lba));
+        if let Ok(Some((header, entries))) = backup {
+            let reason = match &primary_error {
+                Some((_, e)) => format!("{e:#}"),
+                None => "no header at LBA 1".to_string(),
+            };
+            let warning = format!("Primary GPT is unusable ({reason}); using the backup");
+            return Ok(gpt_table(sector_size, header, entries, vec![warning]));
+        }
+    }
+    match primary_error {
+        Some((_, e)) => Err(e.context("Neither the primary nor the backup GPT is usable")),
+        None => bail!("Protective MBR found but no valid GPT header"),
+    }
+}
+
+/// Last sector of the disk or image.
+fn last_lba<R: Seek>(reader: &mut R, sector_size: u64) -> Result<u64> {
+    let size = reader
+        .seek(SeekFrom::End(0))
+        .context("Failed to find the end of the disk")?;
+    match size / sector_size {
+        0..=2 => bail!("Disk too small for a GPT"),
+        sectors => Ok(sectors - 1),
+    }
+}
+
+/// CRC-checked GPT header and entry array at `lba`; `None` without a signature there.
+fn read_gpt_header<R: Read + Seek>(
+    reader: &mut R,
+    sector_size: u64,
+    lba: u64,
+) -> Result<Option<(GptHeader, Vec<u8>)>> {
+    let mut sector = vec![0u8; sector_size as usize];
+    reader
+        .seek(SeekFrom::Start(lba_offset(lba, sector_size)?))
+        .context("Failed to seek to the GPT header")?;
+    if reader.read_exact(&mut sector).is_err() || &sector[..8] != GPT_SIGNATURE {
+        return Ok(None);
+    }
+
+    let header_size = u32_le(&sector[12..16]) as usize;
+    if !(GPT_MIN_HEADER_SIZE..=sector.len()).contains(&header_size) {
+        bail!("Invalid GPT header size: {}", header_size);
+    }
+    let raw = sector[..header_size].to_vec();
+    let stored_crc = u32_le(&raw[16..20]);
+    if header_crc32(&raw) != stored_crc {
+        bail!("GPT header checksum mismatch");
+    }
+
+    let header = GptHeader {
+        current_lba: u64_le(&raw[24..32]),
+        backup_lba: u64_le(&raw[32..40]),
+        first_usable_lba: u64_le(&raw[40..48]),
+        last_usable_lba: u64_le(&raw[48..56]),
+        entries_lba: u64_le(&raw[72..80]),
+        num_entries: u32_le(&raw[80..84]),
+        entry_size: u32_le(&raw[84..88]),
+        entries_crc32: u32_le(&raw[88..92]),
+        raw,
+    };
+    // 128 × 2^n bytes per entry, no larger than a sector (UEFI spec 5.3.2).
+    let entry_size_ok = header.entry_size >= 128
+        && header.entry_size.is_power_of_two()
+        && header.entry_size as u64 <= sector_size;
+    if !entry_size_ok || header.num_entries == 0 || header.num_entries > 1024 {
+        bail!(
+            "Unsupported GPT entry layout ({} entries of {} bytes)",
+            header.num_entries,
+            header.entry_size
+        );
+    }
+
+    let mut entries = vec![0u8; header.num_entries as usize * header.entry_size as usize];
+    reader
+        .seek(SeekFrom::Start(lba_offset(header.entries_lba, sector_size)?))
+        .context("Failed to seek to the GPT partition entries")?;
+    reader
+        .read_exact(&mut entries)
+        .context("Failed to read the GPT partition entries")?;
+    if crc32(&entries) != header.entries_crc32 {
+        bail!("GPT partition entry checksum mismatch");
+    }
+    Ok(Some((header, entries)))
+}
+
+/// Byte offset of `lba`, or an error when a corrupt table points past any disk.
+fn lba_offset(lba: u64, sector_size: u64) -> Result<u64> {
+    lba.checked_mul(sector_size)
+        .with_context(|| format!("LBA {lba} is beyond the end of any disk"))
+}
+
+/// Check that the backup GPT named by `primary` is intact and describes the same
+/// partitions.
+fn check_backup_gpt<R: Read + Seek>(
+    reader: &mut R,
+    sector_size: u64,
+    primary: &GptHeader,
+) -> Result<()> {
+    let (backup, _) = read_gpt_header(reader, sector_size, primary.backup_lba)?
+        .with_context(|| format!("no header at LBA {}", primary.backup_lba))?;
+    if backup.current_lba != primary.backup_lba || backup.backup_lba != primary.current_lba {
+        bail!("header locations do not match the primary");
+    }
+    if backup.entries_crc32 != primary.entries_crc32 {
+        bail!("partition entries differ from the primary");
+    }
+    Ok(())
+}
+
+fn gpt_table(
+    sector_size: u64,
+    header: GptHeader,
+    entries: Vec<u8>,
+    mut warnings: Vec<String>,
+) -> PartitionTable {
+    let mut partitions = Vec::new();
+    for (index, entry) in entries.chunks_exact(header.entry_size as usize).enumerate() {
+        if entry[..16].iter().all(|b| *b == 0) {
+            continue;
+        }
+        let partition = Partition {
+            number: index as u32 + 1,
+            first_lba: u64_le(&entry[32..40]),
+            last_lba: u64_le(&entry[40..48]),
+            type_id: format_guid(&entry[..16]),
+            name: utf16_name(&entry[56..128]),
+            filesystem: None,
+        };
+        // Later byte arithmetic on the partition relies on its end being addressable.
+        let end = partition
+            .last_lba
+            .checked_add(1)
+            .and_then(|sectors| lba_offset(sectors, sector_size).ok());
+        if partition.first_lba > partition.last_lba || end.is_none() {
+            warnings.push(format!(
+                "Ignoring GPT partition {} with invalid range {}..{}",
+                partition.number, partition.first_lba, partition.last_lba
+            ));
+            continue;
+        }
+        partitions.push(partition);
+    }
+
+    PartitionTable {
+        kind: PartitionTableKind::Gpt,
+        sector_size,
+        partitions,
+        gpt: Some(header),
+        gpt_entries: entries,
+        warnings,
+    }
+}
+
+/// Copy of `raw` with new location fields and a recomputed header CRC.
+fn gpt_header_bytes(
+    raw: &[u8],
+    current_lba: u64,
+    backup_lba: u64,
+    last_usable_lba: u64,
+    entries_lba: u64,
+) -> Vec<u8> {
+    let mut header = raw.to_vec();
+    header[24..32].copy_from_slice(&current_lba.to_le_bytes());
+    header[32..40].copy_from_slice(&backup_lba.to_le_bytes());
+    header[48..56].copy_from_slice(&last_usable_lba.to_le_bytes());
+    header[72..80].copy_from_slice(&entries_lba.to_le_bytes());
+    let crc = header_crc32(&header);
+    header[16..20].copy_from_slice(&crc.to_le_bytes());
+    header
+}
+
+/// CRC of a GPT header with its own CRC field treated as zero.
+fn header_crc32(header: &[u8]) -> u32 {
+    let mut copy = header.to_vec();
+    copy[16..20].fill(0);
+    crc32(&copy)
+}
+
+/// CRC-32 (IEEE 802.3), as used by GPT.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+/// Compact binary size as `lsblk` prints it: `256M`, `7.2G`.
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["B", "K", "M", "G", "T", "P"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    let text = format!("{value:.1}");
+    let text = text.strip_suffix(".0").unwrap_or(&text);
+    format!("{text}{}", UNITS[unit])
+}
+
+fn u32_le(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes(bytes[..4].try_into().expect("4 bytes"))
+}
+
+fn u64_le(bytes: &[u8]) -> u64 {
+    u64::from_le_bytes(bytes[..8].try_into().expect("8 bytes"))
+}
+
+/// GUID in its canonical mixed-endian text form.
+fn format_guid(bytes: &[u8]) -> String {
+    format!(
+        "{:08X}-{:04X}-{:04X}-{:02X}{:02X}-{:02X}{:02X}{:02X}{:02X}{:02X}{:02X}",
+        u32_le(&bytes[0..4]),
+        u16::from_le_bytes([bytes[4], bytes[5]]),
+        u16::from_le_bytes([bytes[6], bytes[7]]),
+        bytes[8],
+        bytes[9],
+        bytes[10],
+        bytes[11],
+        bytes[12],
+        bytes[13],
+        bytes[14],
+        bytes[15]
+    )
+}
+
+fn utf16_name(bytes: &[u8]) -> Option<String> {
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .take_while(|unit| *unit != 0)
+        .collect();
+    let name = String::from_utf16_lossy(&units);
+    (!name.is_empty()).then_some(name)
+}
+
+#[cfg(test)]
+pub(crate) mod test_images {
+    use super::*;
+
+    /// MBR disk image with the given `(type, start_lba, sectors)` primaries.
+    pub fn mbr_disk(total_sectors: u64, parts: &[(u8, u32, u32)]) -> Vec<u8> {
+        let mut disk = vec![0u8; (total_sectors * 512) as usize];
+        for (index, (kind, start, sectors)) in parts.iter().enumerate() {
+            let entry = &mut disk[MBR_ENTRIES_OFFSET + index * MBR_ENTRY_SIZE..][..MBR_ENTRY_SIZE];
+            entry[4] = *kind;
+            entry[8..12].copy_from_slice(&start.to_le_bytes());
+            entry[12..16].copy_from_slice(&sectors.to_le_bytes());
+        }
+        disk[510..512].copy_from_slice(&MBR_SIGNATURE);
+        disk
+    }
+
+    /// 512-byte-sector GPT disk with primary and backup tables and the given
+    /// `(first_lba, last_lba)` partitions.
+    pub fn gpt_disk(total_sectors: u64, parts: &[(u64, u64)]) -> Vec<u8> {
+        let num_entries = 128u32;
+        let entry_size = 128u32;
+        let entries_sectors = (num_entries * entry_size) as u64 / 512;
+        let mut disk = mbr_disk(total_sectors, &[(0xEE, 1, (total_sectors - 1) as u32)]);
+
+        let mut entries = vec![0u8; (num_entries * entry_size) as usize];
+        for (index, (first, last)) in parts.iter().enumerate() {
+            let entry = &mut entries[index * entry_size as usize..][..entry_size as usize];
+            entry[0] = 0xAF; // any non-zero type GUID
+            entry[16] = index as u8 + 1;
+            entry[32..40].copy_from_slice(&first.to_le_bytes());
+            entry[40..48].copy_from_slice(&last.to_le_bytes());
+        }
+
+        let mut raw = vec![0u8; GPT_MIN_HEADER_SIZE];
+        raw[..8].copy_from_slice(GPT_SIGNATURE);
+        raw[8..12].copy_from_slice(&0x0001_0000u32.to_le_bytes());
+        raw[12..16].copy_from_slice(&(GPT_MIN_HEADER_SIZE as u32).to_le_bytes());
+        raw[40..48].copy_from_slice(&(2 + entries_sectors).to_le_bytes());
+        raw[80..84].copy_from_slice(&num_entries.to_le_bytes());
+        raw[84..88].copy_from_slice(&entry_size.to_le_bytes());
+        raw[88..92].copy_from_slice(&crc32(&entries).to_le_bytes());
+
+        let backup_lba = total_sectors - 1;
+        let backup_entries_lba = backup_lba - entries_sectors;
+        let primary = gpt_header_bytes(&raw, 1, backup_lba, backup_entries_lba - 1, 2);
+        let backup = gpt_header_bytes(
+            &raw,
+            backup_lba,
+            1,
+            backup_entries_lba - 1,
+            backup_entries_lba,
+        );
+
+        disk[512..512 + primary.len()].copy_from_slice(&primary);
+        disk[1024..1024 + entries.len()].copy_from_slice(&entries);
+        let at = (backup_entries_lba * 512) as usize;
+        disk[at..at + entries.len()].copy_from_slice(&entries);
+        let at = (backup_lba * 512) as usize;
+        disk[at..at + backup.len()].copy_from_slice(&backup);
+        disk
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_images::{gpt_disk, mbr_disk};
+    use super::*;
+    use crate::filesystems::test_images::{ext4, fat32};
+    use std::io::Cursor;
+
+    #[test]
+    fn crc32_matches_reference_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn mbr_used_extent_ends_at_last_partition() {
+        let disk = mbr_disk(4096, &[(0x0C, 2048, 512), (0x83, 2560, 1000)]);
+        let table = read_partition_table(&mut Cursor::new(disk)).unwrap().unwrap();
+        assert_eq!(table.kind, PartitionTableKind::Mbr);
+        assert_eq!(table.partitions.len(), 2);
+        assert_eq!(table.used_bytes(), 3560 * 512);
+        assert_eq!(table.used_image_bytes(), table.used_bytes());
+    }
+
+    #[test]
+    fn mbr_extended_chain_yields_logical_partitions_and_filesystems() {
+        let mut disk = mbr_disk(8192, &[(0x0C, 2048, 1024), (0x05, 4096, 4096)]);
+        let ebr = |disk: &mut Vec<u8>, lba: usize, parts: &[(u8, u32, u32)]| {
+            disk[lba * 512..][..512].copy_from_slice(&mbr_disk(1, parts));
+        };
+        ebr(&mut disk, 4096, &[(0x83, 2048, 1000), (0x05, 3072, 1000)]);
+        ebr(&mut disk, 7168, &[(0x82, 1, 500)]);
+        disk[2048 * 512..][..4096].copy_from_slice(&fat32("BOOT"));
+        disk[6144 * 512..][..4096].copy_from_slice(&ext4("rootfs"));
+
+        let table = read_partition_table(&mut Cursor::new(disk))
+            .unwrap()
+            .unwrap();
+        let layout: Vec<(u32, u64, u64)> = table
+            .partitions
+            .iter()
+            .map(|p| (p.number, p.first_lba, p.last_lba))
+            .collect();
+        assert_eq!(
+            layout,
+            vec![
+                (1, 2048, 3071),
+                (2, 4096, 8191),
+                (5, 6144, 7143),
+                (6, 7169, 7668)
+            ]
+        );
+        assert!(table.partitions[1].is_extended());
+        assert!(table.warnings.is_empty());
+        assert_eq!(
+            table.summary(),
+            "3 partitions: FAT32 BOOT 512K, ext4 rootfs 500K, Linux swap 250K"
+        );
+    }
+
+    #[test]
+    fn gpt_falls_back_to_the_backup_and_reports_bad_copies() {
+        let disk = gpt_disk(65536, &[(2048, 4095), (4096, 10239)]);
+        let intact = read_partition_table(&mut Cursor::new(&disk))
+            .unwrap()
+            .unwrap();
+        assert!(intact.warnings.is_empty());
+        assert!(!intact.gpt.as_ref().unwrap().is_backup());
+
+        let mut bad_primary = disk.clone();
+        bad_primary[512 + 40] ^= 1;
+        let table = read_partition_table(&mut Cursor::new(&bad_primary))
+            .unwrap()
+            .unwrap();
+        assert!(table.gpt.as_ref().unwrap().is_backup());
+        assert_eq!(table.partitions, intact.partitions);
+        assert!(table.warnings[0].starts_with("Primary GPT is unusable (GPT header checksum"));
+
+        let mut bad_backup = disk.clone();
+        let at = 65535 * 512;
+        bad_backup[at..at + 512].fill(0);
+        let table = read_partition_table(&mut Cursor::new(&bad_backup))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            table.warnings,
+            vec!["Backup GPT is unusable: no header at LBA 65535"]
+        );
+
+        bad_primary[at + 40] ^= 1;
+        assert!(read_partition_table(&mut Cursor::new(&bad_primary)).is_err());
+    }
+
+    /// `disk` with `bytes` written at `offset` into the primary GPT header and the
+    /// header checksum updated to match.
+    fn patch_primary_header(disk: &[u8], offset: usize, bytes: &[u8]) -> Vec<u8> {
+        let mut disk = disk.to_vec();
+        let header = &mut disk[512..512 + GPT_MIN_HEADER_SIZE];
+        header[offset..offset + bytes.len()].copy_from_slice(bytes);
+        let crc = header_crc32(header);
+        header[16..20].copy_from_slice(&crc.to_le_bytes());
+        disk
+    }
+
+    #[test]
+    fn oversized_gpt_entries_are_rejected() {
+        let disk = gpt_disk(65536, &[(2048, 4095)]);
+        let crafted = patch_primary_header(&disk, 84, &0xFFFF_FF80u32.to_le_bytes());
+        let table = read_partition_table(&mut Cursor::new(&crafted))
+            .unwrap()
+            .unwrap();
+        assert!(table.gpt.as_ref().unwrap().is_backup());
+        assert!(table.warnings[0].contains("Unsupported GPT entry layout"));
+
+        let crafted = patch_primary_header(&disk, 84, &384u32.to_le_bytes());
+        let table = read_partition_table(&mut Cursor::new(&crafted))
+            .unwrap()
+            .unwrap();
+        assert!(table.warnings[0].contains("Unsupported GPT entry layout"));
+    }
+
+    #[test]
+    fn gpt_entries_beyond_any_disk_are_rejected() {
+        let disk = gpt_disk(65536, &[(2048, 4095)]);
+        let crafted = patch_primary_header(&disk, 72, &(u64::MAX / 2).to_le_bytes());
+        let table = read_partition_table(&mut Cursor::new(&crafted))
+            .unwrap()
+            .unwrap();
+        assert!(table.gpt.as_ref().unwrap().is_backup());
+        assert!(table.warnings[0].contains("is beyond the end of any disk"));
+    }
+
+    #[test]
+    fn corrupt_gpt_locations_become_warnings() {
+        let disk = gpt_disk(65536, &[(2048, 4095), (4096, 10239)]);
+        let crafted = patch_primary_header(&disk, 32, &u64::MAX.to_le_bytes());
+        let table = read_partition_table(&mut Cursor::new(&crafted))
+            .unwrap()
+            .unwrap();
+        assert!(!table.gpt.as_ref().unwrap().is_backup());
+        assert_eq!(
+            table.warnings,
+            vec![format!(
+                "Backup GPT is unusable: LBA {} is beyond the end of any disk",
+                u64::MAX
+            )]
+        );
+
+        let huge = gpt_disk(65536, &[(2048, 4095), (u64::MAX - 1, u64::MAX)]);
+        let reversed = gpt_disk(65536, &[(2048, 4095), (10239, 4096)]);
+        for disk in [huge, reversed] {
+            let table = read_partition_table(&mut Cursor::new(disk))
+                .unwrap()
+                .unwrap();
+            assert_eq!(table.partitions.len(), 1);
+            assert!(table.warnings[0].starts_with("Ignoring GPT partition 2"));
+            assert_eq!(table.used_bytes(), 4096 * 512);
+        }
+    }
+
+    #[test]
+    fn missing_signature_means_no_table() {
+        let disk = vec![0u8; 4096];
+        assert!(read_partition_table(&mut Cursor::new(disk))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn gpt_relocation_produces_a_valid_smaller_table() {
+        let disk = gpt_disk(65536, &[(2048, 4095), (4096, 10239)]);
+        let table = read_partition_table(&mut Cursor::new(&disk))
+            .unwrap()
+            .unwrap();
+        assert_eq!(table.kind, PartitionTableKind::Gpt);
+        assert_eq!(table.used_bytes(), 10240 * 512);
+        assert_eq!(table.used_image_bytes(), (10240 + 33) * 512);
+
+        let relocated = table.relocated_gpt().unwrap();
+        let mut image = disk[..table.used_image_bytes() as usize].to_vec();
+        image[512..512 + relocated.primary_header.len()]
+            .copy_from_slice(&relocated.primary_header);
+        let at = (relocated.backup_entries_lba * 512) as usize;
+        image[at..at + relocated.backup_entries.len()].copy_from_slice(&relocated.backup_entries);
+        let at = (relocated.backup_lba * 512) as usize;
+        image[at..at + relocated.backup_header.len()].copy_from_slice(&relocated.backup_header);
+
+        let shrunk = read_partition_table(&mut Cursor::new(&image))
+            .unwrap()
+            .unwrap();
+        let header = shrunk.gpt.as_ref().unwrap();
+        assert_eq!(header.backup_lba, 10240 + 32);
+        assert_eq!(header.last_usable_lba, 10239);
+        assert_eq!(shrunk.partitions, table.partitions);
+
+        let backup = &image[(relocated.backup_lba * 512) as usize..][..GPT_MIN_HEADER_SIZE];
+        assert_eq!(u64_le(&backup[24..32]), 10240 + 32);
+        assert_eq!(u64_le(&backup[72..80]), 10240);
+        assert_eq!(header_crc32(backup), u32_le(&backup[16..20]));
+    }
+}
@@ -0,0 +1,110 @@
+use super::{DeviceReader, DeviceWriter};
+use anyhow::{Context, Result};
+use log::debug;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+
+/// Regular file read as a device (disk images, loop-back tests). Works on every platform.
+pub struct FileDeviceReader {
+    file: File,
+}
+
+impl DeviceReader for FileDeviceReader {
+    fn open(device_path: &str) -> Result<Self> {
+        debug!("Opening file device for reading: {}", device_path);
+        let file = File::open(device_path).context(format!(
+            "Failed to open file device for reading: {}",
+            device_path
+        ))?;
+        Ok(Self { file })
+    }
+
+    fn device_size(&self) -> Result<u64> {
+        Ok(self
+            .file
+            .metadata()
+            .context("Failed to get file metadata")?
+            .len())
+    }
+}
+
+impl Read for FileDeviceReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.file.read(buf)
+    }
+}
+
+impl Seek for FileDeviceReader {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
+
+/// Regular file written as a device. Like a block device it keeps its size: the file
+/// must exist, is not truncated, and writes past its end are refused.
+pub struct FileDeviceWriter {
+    file: File,
+    size: u64,
+}
+
+impl FileDeviceWriter {
+    /// Create (or truncate) `path` as a zero-filled device of `size` bytes.
+    pub fn create(path: &str, size: u64) -> Result<Self> {
+        let file = File::create(path).context(format!("Failed to create file device: {}", path))?;
+        file.set_len(size)
+            .context(format!("Failed to size file device: {}", path))?;
+        Self::open(path)
+    }
+}
+
+impl DeviceWriter for FileDeviceWriter {
+    fn open(device_path: &str) -> Result<Self> {
+        debug!("Opening file device for writing: {}", device_path);
+        let file = OpenOptions::new()
+            .write(true)
+            .open(device_path)
+            .context(format!(
+                "Failed to open file device for writing: {}",
+                device_path
+            ))?;
+        let size = file
+            .metadata()
+            .context("Failed to get file metadata")?
+            .len();
+        Ok(Self { file, size })
+    }
+
+    fn flush_and_sync(&mut self) -> Result<()> {
+        self.file.flush().context("Failed to flush file device")?;
+        self.file.sync_all().context("Failed to sync file device")?;
+        Ok(())
+    }
+
+    fn device_size(&self) -> Result<u64> {
+        Ok(self.size)
+    }
+}
+
+impl Write for FileDeviceWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let position = self.file.stream_position()?;
+        let room = usize::try_from(self.size.saturating_sub(position)).unwrap_or(usize::MAX);
+        if room == 0 && !buf.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::WriteZero,
+                "No space left on the file device",
+            ));
+        }
+        self.file.write(&buf[..buf.len().min(room)])
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Seek for FileDeviceWriter {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
@@ -1,9 +1,9 @@
-use super::{DeviceReader, DeviceWriter};
+use super::{DeviceReader, DeviceWriter, DiscardMethod};
 use anyhow::{Context, Result};
 use libc::{O_DIRECT, O_DSYNC, O_SYNC};
 use log::debug;
 use std::fs::{File, OpenOptions};
-use std::io::{Read, Write};
+use std::io::{Read, Seek, SeekFrom, Write};
 use std::os::unix::fs::OpenOptionsExt;
 use std::os::unix::io::AsRawFd;
 
@@ -50,6 +50,12 @@ impl Read for LinuxBufferedDeviceReader {
     }
 }
 
+impl Seek for LinuxBufferedDeviceReader {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
+
 impl DeviceReader for LinuxDeviceReader {
     fn open(device_path: &str) -> Result<Self> {
         debug!("Opening Linux device for reading: {}", device_path);
@@ -79,6 +85,19 @@ impl Read for LinuxDeviceReader {
     }
 }
 
+impl Seek for LinuxDeviceReader {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
+
+// <linux/fs.h>: _IO(0x12, 94), _IO(0x12, 95), _IO(0x12, 119), _IO(0x12, 125), _IO(0x12, 127)
+const BLKROGET: libc::c_ulong = 0x125E;
+const BLKRRPART: libc::c_ulong = 0x125F;
+const BLKDISCARD: libc::c_ulong = 0x1277;
+const BLKSECDISCARD: libc::c_ulong = 0x127D;
+const BLKZEROOUT: libc::c_ulong = 0x127F;
+
 pub struct LinuxDeviceWriter {
     file: File,
 }
@@ -92,10 +111,24 @@ impl DeviceWriter for LinuxDeviceWriter {
             .custom_flags(O_SYNC)
             .custom_flags(O_DSYNC)
             .open(device_path)
+            .map_err(|e| {
+                if e.raw_os_error() == Some(libc::EROFS) {
+                    anyhow::anyhow!("device is write-protected")
+                } else {
+                    anyhow::Error::new(e)
+                }
+            })
             .context(format!(
                 "Failed to open device for writing: {}",
                 device_path
             ))?;
+        // The kernel lets a read-only disk be opened for writing and fails the first write.
+        if read_only_flag(&file) == Some(true) {
+            anyhow::bail!(
+                "Failed to open device for writing: {}: device is write-protected",
+                device_path
+            );
+        }
         Ok(Self { file })
     }
 
@@ -119,6 +152,34 @@ impl DeviceWriter for LinuxDeviceWriter {
             .context("Failed to get device metadata")?;
         Ok(metadata.len())
     }
+
+    fn discard(&mut self, offset: u64, len: u64, method: DiscardMethod) -> Result<()> {
+        let request = match method {
+            DiscardMethod::Discard => BLKDISCARD,
+            DiscardMethod::Secure => BLKSECDISCARD,
+            DiscardMethod::ZeroOut => BLKZEROOUT,
+        };
+        let range: [u64; 2] = [offset, len];
+        // SAFETY: the request takes a pointer to two u64 values (start, length).
+        let rc = unsafe { libc::ioctl(self.file.as_raw_fd(), request as _, range.as_ptr()) };
+        if rc != 0 {
+            return Err(std::io::Error::last_os_error())
+                .context(format!("{} of {} bytes at {} failed", method, len, offset));
+        }
+        debug!("{} {} bytes at offset {}", method, len, offset);
+        Ok(())
+    }
+
+    fn reread_partition_table(&mut self) -> Result<()> {
+        // SAFETY: BLKRRPART takes no argument.
+        let rc = unsafe { libc::ioctl(self.file.as_raw_fd(), BLKRRPART as _) };
+        if rc != 0 {
+            return Err(std::io::Error::last_os_error())
+                .context("Failed to re-read the partition table");
+        }
+        debug!("Partition table re-read");
+        Ok(())
+    }
 }
 
 impl Write for LinuxDeviceWriter {
@@ -130,3 +191,22 @@ impl Write for LinuxDeviceWriter {
         self.file.flush()
     }
 }
+
+impl Seek for LinuxDeviceWriter {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
+
+/// `BLKROGET`: whether the kernel refuses writes to the block device at `device_path`
+/// (lock switch on an SD card, `blockdev --setro`). `None` when it is not a block device.
+pub fn block_device_read_only(device_path: &str) -> Option<bool> {
+    read_only_flag(&File::open(device_path).ok()?)
+}
+
+fn read_only_flag(file: &File) -> Option<bool> {
+    let mut read_only: libc::c_int = 0;
+    // SAFETY: BLKROGET stores one int through the pointer.
+    let rc = unsafe { libc::ioctl(file.as_raw_fd(), BLKROGET as _, &mut read_only) };
+    (rc == 0).then_some(read_only != 0)
+}
@@ -2,7 +2,7 @@ use super::{DeviceReader, DeviceWriter};
 use anyhow::{Context, Result};
 use log::debug;
 use std::fs::{File, OpenOptions};
-use std::io::{Read, Write};
+use std::io::{Read, Seek, SeekFrom, Write};
 use std::os::unix::io::AsRawFd;
 
 pub struct MacDeviceReader {
@@ -43,6 +43,12 @@ impl Read for MacDeviceReader {
     }
 }
 
+impl Seek for MacDeviceReader {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
+
 pub struct MacDeviceWriter {
     file: File,
 }
@@ -99,3 +105,9 @@ impl Write for MacDeviceWriter {
         self.file.flush()
     }
 }
+
+impl Seek for MacDeviceWriter {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
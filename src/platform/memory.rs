@@ -0,0 +1,225 @@
+use super::{DeviceReader, DeviceWriter, DiscardMethod};
+use anyhow::Result;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Fixed-size device held in memory, for tests and dry runs of the copy loops.
+///
+/// Readers and writers share the same bytes, so a flash followed by a verify read
+/// sees what was written. Writes past the end fail like a full block device.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryDevice {
+    state: Arc<Mutex<MemoryState>>,
+}
+
+#[derive(Debug, Default)]
+struct MemoryState {
+    data: Vec<u8>,
+    syncs: u32,
+    discards: Vec<(u64, u64, DiscardMethod)>,
+}
+
+impl MemoryDevice {
+    /// A zero-filled device of `size` bytes.
+    pub fn new(size: usize) -> Self {
+        Self::from_bytes(vec![0u8; size])
+    }
+
+    /// A device with the given contents; its size is the length of `data`.
+    pub fn from_bytes(data: Vec<u8>) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(MemoryState {
+                data,
+                ..Default::default()
+            })),
+        }
+    }
+
+    /// A reader positioned at the start of the device.
+    pub fn reader(&self) -> MemoryDeviceReader {
+        MemoryDeviceReader {
+            device: self.clone(),
+            position: 0,
+        }
+    }
+
+    /// A writer positioned at the start of the device.
+    pub fn writer(&self) -> MemoryDeviceWriter {
+        MemoryDeviceWriter {
+            device: self.clone(),
+            position: 0,
+        }
+    }
+
+    /// Copy of the current contents.
+    pub fn contents(&self) -> Vec<u8> {
+        self.lock().data.clone()
+    }
+
+    /// Number of `flush_and_sync` calls on any writer.
+    pub fn syncs(&self) -> u32 {
+        self.lock().syncs
+    }
+
+    /// Discards issued by any writer, as `(offset, len, method)`.
+    pub fn discards(&self) -> Vec<(u64, u64, DiscardMethod)> {
+        self.lock().discards.clone()
+    }
+
+    fn size(&self) -> u64 {
+        self.lock().data.len() as u64
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MemoryState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+pub struct MemoryDeviceReader {
+    device: MemoryDevice,
+    position: u64,
+}
+
+impl DeviceReader for MemoryDeviceReader {
+    fn open(device_path: &str) -> Result<Self> {
+        anyhow::bail!(
+            "In-memory devices are created with MemoryDevice::new, not opened by path ({})",
+            device_path
+        )
+    }
+
+    fn device_size(&self) -> Result<u64> {
+        Ok(self.device.size())
+    }
+}
+
+impl Read for MemoryDeviceReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let state = self.device.lock();
+        let start = usize::try_from(self.position)
+            .unwrap_or(usize::MAX)
+            .min(state.data.len());
+        let read = buf.len().min(state.data.len() - start);
+        buf[..read].copy_from_slice(&state.data[start..start + read]);
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl Seek for MemoryDeviceReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.position = seek_position(self.position, self.device.size(), pos)?;
+        Ok(self.position)
+    }
+}
+
+pub struct MemoryDeviceWriter {
+    device: MemoryDevice,
+    position: u64,
+}
+
+impl DeviceWriter for MemoryDeviceWriter {
+    fn open(device_path: &str) -> Result<Self> {
+        anyhow::bail!(
+            "In-memory devices are created with MemoryDevice::new, not opened by path ({})",
+            device_path
+        )
+    }
+
+    fn flush_and_sync(&mut self) -> Result<()> {
+        self.device.lock().syncs += 1;
+        Ok(())
+    }
+
+    fn device_size(&self) -> Result<u64> {
+        Ok(self.device.size())
+    }
+
+    fn discard(&mut self, offset: u64, len: u64, method: DiscardMethod) -> Result<()> {
+        let mut state = self.device.lock();
+        let size = state.data.len() as u64;
+        if offset.checked_add(len).is_none_or(|end| end > size) {
+            anyhow::bail!(
+                "{} of {} bytes at {} is past the end of the device",
+                method,
+                len,
+                offset
+            );
+        }
+        // Discarded blocks read back as zeros on most devices; make that certain here.
+        state.data[offset as usize..(offset + len) as usize].fill(0);
+        state.discards.push((offset, len, method));
+        Ok(())
+    }
+}
+
+impl Write for MemoryDeviceWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut state = self.device.lock();
+        let start = usize::try_from(self.position)
+            .unwrap_or(usize::MAX)
+            .min(state.data.len());
+        let written = buf.len().min(state.data.len() - start);
+        if written == 0 && !buf.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::WriteZero,
+                "No space left on the in-memory device",
+            ));
+        }
+        state.data[start..start + written].copy_from_slice(&buf[..written]);
+        self.position += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for MemoryDeviceWriter {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.position = seek_position(self.position, self.device.size(), pos)?;
+        Ok(self.position)
+    }
+}
+
+fn seek_position(current: u64, size: u64, pos: SeekFrom) -> io::Result<u64> {
+    let target = match pos {
+        SeekFrom::Start(offset) => Some(offset),
+        SeekFrom::End(delta) => size.checked_add_signed(delta),
+        SeekFrom::Current(delta) => current.checked_add_signed(delta),
+    };
+    target.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Seek before the start of the device",
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writers_and_readers_share_contents() {
+        let device = MemoryDevice::new(8);
+        let mut writer = device.writer();
+        writer.seek(SeekFrom::Start(6)).unwrap();
+        assert!(writer.write_all(&[1, 2, 3]).is_err());
+        assert_eq!(device.contents(), vec![0, 0, 0, 0, 0, 0, 1, 2]);
+
+        let mut reader = device.reader();
+        reader.seek(SeekFrom::End(-3)).unwrap();
+        let mut tail = Vec::new();
+        reader.read_to_end(&mut tail).unwrap();
+        assert_eq!(tail, vec![0, 1, 2]);
+
+        writer.discard(4, 4, DiscardMethod::ZeroOut).unwrap();
+        assert_eq!(device.contents(), vec![0; 8]);
+        assert!(writer.discard(4, 8, DiscardMethod::Discard).is_err());
+        assert_eq!(device.discards(), vec![(4, 4, DiscardMethod::ZeroOut)]);
+    }
+}
@@ -1,5 +1,10 @@
 use anyhow::Result;
-use std::io::{Read, Write};
+use std::fmt;
+use std::io::{Read, Seek, Write};
+use std::str::FromStr;
+
+mod file;
+mod memory;
 
 #[cfg(target_os = "linux")]
 mod linux;
@@ -8,8 +13,11 @@ mod macos;
 #[cfg(target_os = "windows")]
 mod windows;
 
+pub use file::{FileDeviceReader, FileDeviceWriter};
+pub use memory::{MemoryDevice, MemoryDeviceReader, MemoryDeviceWriter};
+
 /// Trait for reading from a device in a platform-specific way
-pub trait DeviceReader: Read {
+pub trait DeviceReader: Read + Seek {
     /// Open a device for reading
     fn open(device_path: &str) -> Result<Self>
     where
@@ -19,8 +27,44 @@ pub trait DeviceReader: Read {
     fn device_size(&self) -> Result<u64>;
 }
 
+/// How to release a range of the target before writing it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiscardMethod {
+    /// `BLKDISCARD` — TRIM / UNMAP; contents afterwards are unspecified.
+    Discard,
+    /// `BLKSECDISCARD` — discard and erase every copy of the data (eMMC secure trim).
+    Secure,
+    /// `BLKZEROOUT` — zero the range, offloaded to the device when supported.
+    ZeroOut,
+}
+
+impl fmt::Display for DiscardMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Discard => "discard",
+            Self::Secure => "secure",
+            Self::ZeroOut => "zeroout",
+        })
+    }
+}
+
+impl FromStr for DiscardMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "discard" | "trim" => Ok(Self::Discard),
+            "secure" => Ok(Self::Secure),
+            "zeroout" | "zero" => Ok(Self::ZeroOut),
+            other => Err(format!(
+                "Unknown discard method {other:?} (expected discard, secure or zeroout)"
+            )),
+        }
+    }
+}
+
 /// Trait for writing to a device in a platform-specific way
-pub trait DeviceWriter: Write {
+pub trait DeviceWriter: Write + Seek {
     /// Open a device for writing
     fn open(device_path: &str) -> Result<Self>
     where
@@ -31,6 +75,18 @@ pub trait DeviceWriter: Write {
 
     /// Get the size of the device in bytes
     fn device_size(&self) -> Result<u64>;
+
+    /// Discard (or zero) `len` bytes at `offset`; both must be 512-byte aligned.
+    fn discard(&mut self, offset: u64, len: u64, method: DiscardMethod) -> Result<()> {
+        let _ = (offset, len);
+        anyhow::bail!("{} is not supported on this platform", method)
+    }
+
+    /// Ask the kernel to re-read the partition table; errors with `EBUSY` while
+    /// partitions are in use.
+    fn reread_partition_table(&mut self) -> Result<()> {
+        anyhow::bail!("Re-reading the partition table is not supported on this platform")
+    }
 }
 
 /// Platform-specific device implementation factory
@@ -103,4 +159,18 @@ impl PlatformDevice {
             anyhow::bail!("Unsupported platform")
         }
     }
+
+    /// Whether the kernel marks the block device read-only (`BLKROGET` on Linux);
+    /// `None` when that cannot be told, e.g. for image files or on other platforms.
+    pub fn is_read_only(device_path: &str) -> Option<bool> {
+        #[cfg(target_os = "linux")]
+        {
+            linux::block_device_read_only(device_path)
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = device_path;
+            None
+        }
+    }
 }
@@ -2,7 +2,7 @@ use super::{DeviceReader, DeviceWriter};
 use anyhow::{Context, Result};
 use log::debug;
 use std::fs::{File, OpenOptions};
-use std::io::{Read, Write};
+use std::io::{Read, Seek, SeekFrom, Write};
 
 #[cfg(target_os = "windows")]
 use std::os::windows::fs::OpenOptionsExt;
@@ -59,6 +59,12 @@ impl Read for WindowsDeviceReader {
     }
 }
 
+impl Seek for WindowsDeviceReader {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
+
 pub struct WindowsDeviceWriter {
     file: File,
 }
@@ -124,3 +130,9 @@ impl Write for WindowsDeviceWriter {
         self.file.flush()
     }
 }
+
+impl Seek for WindowsDeviceWriter {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
@@ -1,5 +1,7 @@
 use serde::Serialize;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 #[serde(rename_all = "snake_case")]
@@ -25,13 +27,86 @@ impl std::fmt::Display for OperationCancelled {
 
 impl std::error::Error for OperationCancelled {}
 
-/// Best-effort cooperative cancel check for block I/O loops.
-pub fn check_cancel(cancel: Option<&AtomicBool>) -> Result<(), OperationCancelled> {
-    if cancel.is_some_and(|flag| flag.load(Ordering::Relaxed)) {
-        Err(OperationCancelled)
-    } else {
-        Ok(())
+/// How often a paused operation re-checks the cancel flag while blocked.
+const PAUSE_POLL: Duration = Duration::from_millis(50);
+
+/// Cancel/pause token shared between a running flash/clone and its controller.
+///
+/// Copy loops call [`check_cancel`] between blocks; while paused the call blocks
+/// there, so no read or write is left half-done.
+#[derive(Debug, Default)]
+pub struct OperationControl {
+    cancelled: Arc<AtomicBool>,
+    paused: Mutex<bool>,
+    resumed: Condvar,
+}
+
+impl OperationControl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Token whose cancel state is `flag`, for callers that still signal cancel by
+    /// setting an `AtomicBool` (the `Option<&AtomicBool>` parameter these functions
+    /// took before pause/resume). Setting the flag cancels; [`Self::cancel`] sets it.
+    pub fn from_flag(flag: Arc<AtomicBool>) -> Self {
+        Self {
+            cancelled: flag,
+            ..Self::default()
+        }
+    }
+
+    /// Request cancel. Only touches an atomic, so it is safe from signal handlers;
+    /// a paused operation notices within [`PAUSE_POLL`].
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn pause(&self) {
+        *self.paused.lock().unwrap_or_else(|e| e.into_inner()) = true;
+    }
+
+    pub fn resume(&self) {
+        *self.paused.lock().unwrap_or_else(|e| e.into_inner()) = false;
+        self.resumed.notify_all();
     }
+
+    pub fn is_paused(&self) -> bool {
+        *self.paused.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Clear cancel and pause state before reusing the token for a new operation.
+    pub fn reset(&self) {
+        self.cancelled.store(false, Ordering::Relaxed);
+        self.resume();
+    }
+
+    /// Safe point for I/O loops: blocks while paused, errors once cancelled.
+    pub fn checkpoint(&self) -> Result<(), OperationCancelled> {
+        let mut paused = self.paused.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            if self.is_cancelled() {
+                return Err(OperationCancelled);
+            }
+            if !*paused {
+                return Ok(());
+            }
+            paused = self
+                .resumed
+                .wait_timeout(paused, PAUSE_POLL)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+    }
+}
+
+/// Best-effort cooperative cancel check for block I/O loops (blocks while paused).
+pub fn check_cancel(control: Option<&OperationControl>) -> Result<(), OperationCancelled> {
+    control.map_or(Ok(()), OperationControl::checkpoint)
 }
 
 pub fn is_operation_cancelled(err: &anyhow::Error) -> bool {
@@ -46,6 +121,44 @@ pub fn is_stdin_cancel_line(line: &str) -> bool {
     line.trim().eq_ignore_ascii_case(STDIN_CANCEL_LINE)
 }
 
+/// Stdin / cancel-file line that pauses a running operation at the next safe point.
+pub const STDIN_PAUSE_LINE: &str = "pause";
+
+/// Stdin / cancel-file line that resumes a paused operation.
+pub const STDIN_RESUME_LINE: &str = "resume";
+
+/// Control request parsed from a stdin or cancel-file line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlCommand {
+    Cancel,
+    Pause,
+    Resume,
+}
+
+impl ControlCommand {
+    /// Apply this request to a shared control token.
+    pub fn apply(self, control: &OperationControl) {
+        match self {
+            ControlCommand::Cancel => control.cancel(),
+            ControlCommand::Pause => control.pause(),
+            ControlCommand::Resume => control.resume(),
+        }
+    }
+}
+
+pub fn parse_control_line(line: &str) -> Option<ControlCommand> {
+    let line = line.trim();
+    if line.eq_ignore_ascii_case(STDIN_CANCEL_LINE) {
+        Some(ControlCommand::Cancel)
+    } else if line.eq_ignore_ascii_case(STDIN_PAUSE_LINE) {
+        Some(ControlCommand::Pause)
+    } else if line.eq_ignore_ascii_case(STDIN_RESUME_LINE) {
+        Some(ControlCommand::Resume)
+    } else {
+        None
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct OperationProgress {
     pub phase: OperationPhase,
@@ -53,6 +166,14 @@ pub struct OperationProgress {
     pub bytes_total: Option<u64>,
     pub percentage: Option<f64>,
     pub message: Option<String>,
+    /// Failed reads so far (rescue clone only).
+    pub read_errors: Option<u64>,
+    /// Partitions the kernel sees on the target after a flash (Complete only).
+    pub partitions: Option<Vec<String>>,
+    /// Bytes downloaded so far when flashing from a URL.
+    pub bytes_downloaded: Option<u64>,
+    /// Download size from `Content-Length`, when the server sent one.
+    pub download_total: Option<u64>,
 }
 
 impl OperationProgress {
@@ -63,6 +184,10 @@ impl OperationProgress {
             bytes_total: None,
             percentage: None,
             message: None,
+            read_errors: None,
+            partitions: None,
+            bytes_downloaded: None,
+            download_total: None,
         }
     }
 
@@ -88,6 +213,22 @@ impl OperationProgress {
         self.message = Some(message.into());
         self
     }
+
+    pub fn with_read_errors(mut self, read_errors: u64) -> Self {
+        self.read_errors = Some(read_errors);
+        self
+    }
+
+    pub fn with_partitions(mut self, partitions: Vec<String>) -> Self {
+        self.partitions = Some(partitions);
+        self
+    }
+
+    pub fn with_download(mut self, downloaded: u64, total: Option<u64>) -> Self {
+        self.bytes_downloaded = Some(downloaded);
+        self.download_total = total;
+        self
+    }
 }
 
 pub(crate) fn emit_progress<F>(silent: bool, progress: &mut Option<F>, event: OperationProgress)
@@ -105,6 +246,8 @@ where
 #[cfg(test)]
 mod tests {
     use super::{is_stdin_cancel_line, STDIN_CANCEL_LINE, *};
+    use std::sync::Arc;
+    use std::thread;
 
     #[test]
     fn percentage_from_bytes() {
@@ -114,15 +257,61 @@ mod tests {
 
     #[test]
     fn check_cancel_passes_when_flag_clear() {
-        let flag = AtomicBool::new(false);
-        assert!(check_cancel(Some(&flag)).is_ok());
+        let control = OperationControl::new();
+        assert!(check_cancel(Some(&control)).is_ok());
         assert!(check_cancel(None).is_ok());
     }
 
     #[test]
     fn check_cancel_errors_when_flag_set() {
-        let flag = AtomicBool::new(true);
-        assert_eq!(check_cancel(Some(&flag)), Err(OperationCancelled));
+        let control = OperationControl::new();
+        control.cancel();
+        assert_eq!(check_cancel(Some(&control)), Err(OperationCancelled));
+    }
+
+    #[test]
+    fn from_flag_follows_the_callers_flag() {
+        let flag = Arc::new(AtomicBool::new(false));
+        let control = OperationControl::from_flag(flag.clone());
+        assert!(check_cancel(Some(&control)).is_ok());
+        flag.store(true, Ordering::Relaxed);
+        assert_eq!(check_cancel(Some(&control)), Err(OperationCancelled));
+        control.reset();
+        assert!(!flag.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn check_cancel_blocks_until_resumed() {
+        let control = Arc::new(OperationControl::new());
+        control.pause();
+        let worker = {
+            let control = control.clone();
+            thread::spawn(move || check_cancel(Some(&control)))
+        };
+        thread::sleep(Duration::from_millis(120));
+        assert!(!worker.is_finished());
+        control.resume();
+        assert_eq!(worker.join().unwrap(), Ok(()));
+    }
+
+    #[test]
+    fn cancel_wakes_paused_operation() {
+        let control = Arc::new(OperationControl::new());
+        control.pause();
+        let worker = {
+            let control = control.clone();
+            thread::spawn(move || check_cancel(Some(&control)))
+        };
+        control.cancel();
+        assert_eq!(worker.join().unwrap(), Err(OperationCancelled));
+    }
+
+    #[test]
+    fn control_lines_parse_case_insensitive() {
+        assert_eq!(parse_control_line("PAUSE\n"), Some(ControlCommand::Pause));
+        assert_eq!(parse_control_line(" resume "), Some(ControlCommand::Resume));
+        assert_eq!(parse_control_line("cancel"), Some(ControlCommand::Cancel));
+        assert_eq!(parse_control_line("stop"), None);
     }
 
     #[test]
@@ -0,0 +1,149 @@
+//! dd-style byte ranges for partial flash and clone (`seek` / `skip` / `count`).
+
+use anyhow::{bail, Result};
+use std::fmt;
+
+/// Which bytes of the source to copy and where they land in the destination.
+///
+/// - `skip` — bytes to skip at the start of the source (image for flash, device for clone)
+/// - `seek` — byte offset in the destination where writing starts
+/// - `count` — bytes to copy; `None` copies to the end of the source
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IoRange {
+    pub skip: u64,
+    pub seek: u64,
+    pub count: Option<u64>,
+}
+
+impl IoRange {
+    pub fn is_full(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Bytes to copy from a source of `source_size` bytes, checking `skip` / `count`.
+    pub fn copy_len(&self, source_size: u64) -> Result<u64> {
+        if self.skip > source_size {
+            bail!(
+                "Skip offset {} is past the end of the source ({} bytes)",
+                self.skip,
+                source_size
+            );
+        }
+        let available = source_size - self.skip;
+        let len = match self.count {
+            Some(count) if count > available => bail!(
+                "Count of {} bytes at skip {} exceeds the source ({} bytes)",
+                count,
+                self.skip,
+                source_size
+            ),
+            Some(count) => count,
+            None => available,
+        };
+        if len == 0 {
+            bail!("Nothing to copy: the selected range is empty ({})", self);
+        }
+        Ok(len)
+    }
+
+    /// Refuse ranges that would write past the end of a destination of `dest_size` bytes.
+    pub fn check_fits(&self, len: u64, dest_size: u64) -> Result<()> {
+        let end = self.seek.saturating_add(len);
+        if end > dest_size {
+            bail!(
+                "{} bytes at offset {} do not fit the target ({} bytes, {} too many)",
+                len,
+                self.seek,
+                dest_size,
+                end - dest_size
+            );
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for IoRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "skip={} seek={}", self.skip, self.seek)?;
+        match self.count {
+            Some(count) => write!(f, " count={count}"),
+            None => write!(f, " count=all"),
+        }
+    }
+}
+
+/// Parse a byte size such as `8192`, `8K`, `32KiB`, `16M` or `1G` (binary multiples).
+pub fn parse_byte_size(input: &str) -> Result<u64, String> {
+    let trimmed = input.trim();
+    let split = trimmed
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(trimmed.len());
+    let (digits, suffix) = trimmed.split_at(split);
+    if digits.is_empty() {
+        return Err(format!("Invalid size: {input}"));
+    }
+    let value: u64 = digits
+        .parse()
+        .map_err(|e| format!("Invalid size {input}: {e}"))?;
+    let multiplier: u64 = match suffix.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1 << 10,
+        "m" | "mb" | "mib" => 1 << 20,
+        "g" | "gb" | "gib" => 1 << 30,
+        "t" | "tb" | "tib" => 1 << 40,
+        "s" => 512,
+        other => return Err(format!("Unknown size suffix {other:?} in {input}")),
+    };
+    value
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("Size too large: {input}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_byte_size_accepts_binary_suffixes() {
+        assert_eq!(parse_byte_size("8192").unwrap(), 8192);
+        assert_eq!(parse_byte_size("8K").unwrap(), 8192);
+        assert_eq!(parse_byte_size("32KiB").unwrap(), 32 * 1024);
+        assert_eq!(parse_byte_size("16m").unwrap(), 16 * 1024 * 1024);
+        assert_eq!(parse_byte_size("2s").unwrap(), 1024);
+        assert!(parse_byte_size("MiB").is_err());
+        assert!(parse_byte_size("12x").is_err());
+    }
+
+    #[test]
+    fn copy_len_honours_skip_and_count() {
+        let range = IoRange {
+            skip: 100,
+            ..Default::default()
+        };
+        assert_eq!(range.copy_len(1000).unwrap(), 900);
+        let range = IoRange {
+            skip: 100,
+            count: Some(50),
+            ..Default::default()
+        };
+        assert_eq!(range.copy_len(1000).unwrap(), 50);
+        assert!(range.copy_len(120).is_err());
+        assert!(IoRange::default().copy_len(0).is_err());
+        assert!(IoRange {
+            skip: 2000,
+            ..Default::default()
+        }
+        .copy_len(1000)
+        .is_err());
+    }
+
+    #[test]
+    fn check_fits_accounts_for_seek() {
+        let range = IoRange {
+            seek: 8 * 1024,
+            ..Default::default()
+        };
+        assert!(range.check_fits(1024, 16 * 1024).is_ok());
+        assert!(range.check_fits(9 * 1024, 16 * 1024).is_err());
+    }
+}
@@ -0,0 +1,1037 @@
+//! ddrescue-style clone of failing media.
+//!
+//! The first pass copies at the caller's block size and skips any block that fails to
+//! read (zero-filled in the output). The second pass retries failed blocks in smaller
+//! chunks, and the third retries what is still unreadable one sector at a time. The
+//! source is read with direct I/O so one bad sector does not fail a whole page-cache
+//! page around it. Progress is recorded in a GNU ddrescue-compatible mapfile, so a
+//! later run with the same mapfile only touches the areas that are still bad.
+
+use crate::platform::PlatformDevice;
+use crate::progress::{
+    check_cancel, emit_progress, OperationControl, OperationPhase, OperationProgress,
+};
+use anyhow::{bail, Context, Result};
+use log::{debug, info, warn};
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Sector size used when the device does not report its logical block size.
+pub const SECTOR_SIZE: u64 = 512;
+
+/// The trimming pass retries failed blocks in chunks of `block_size / TRIM_DIVISOR`.
+const TRIM_DIVISOR: u64 = 16;
+
+/// Direct I/O buffers are aligned to at least a page.
+const BUFFER_ALIGN: u64 = 4096;
+
+/// Save the mapfile at least this often during a long error-free copy.
+const MAPFILE_SAVE_INTERVAL: u64 = 64 * 1024 * 1024;
+
+/// Save the mapfile at least this often while reads keep failing.
+const MAPFILE_SAVE_PERIOD: Duration = Duration::from_secs(5);
+
+/// Per-block status characters from the GNU ddrescue mapfile format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockStatus {
+    /// `?` — not read yet.
+    NonTried,
+    /// `*` — failed as part of a large block; not yet retried.
+    NonTrimmed,
+    /// `/` — trimmed but not yet scraped sector by sector.
+    NonScraped,
+    /// `-` — at least one sector here failed a sector-sized read.
+    BadSector,
+    /// `+` — copied successfully.
+    Finished,
+}
+
+impl BlockStatus {
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn symbol(self) -> char {
+        match self {
+            BlockStatus::NonTried => '?',
+            BlockStatus::NonTrimmed => '*',
+            BlockStatus::NonScraped => '/',
+            BlockStatus::BadSector => '-',
+            BlockStatus::Finished => '+',
+        }
+    }
+
+    pub fn from_symbol(symbol: char) -> Option<Self> {
+        match symbol {
+            '?' => Some(BlockStatus::NonTried),
+            '*' => Some(BlockStatus::NonTrimmed),
+            '/' => Some(BlockStatus::NonScraped),
+            '-' => Some(BlockStatus::BadSector),
+            '+' => Some(BlockStatus::Finished),
+            _ => None,
+        }
+    }
+}
+
+/// Current pass recorded in the mapfile status line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RescuePass {
+    /// `?` — copying non-tried areas.
+    Copying,
+    /// `*` — retrying failed blocks in smaller chunks.
+    Trimming,
+    /// `/` — retrying failed areas sector by sector.
+    Scraping,
+    /// `+` — nothing left to do.
+    Finished,
+}
+
+impl RescuePass {
+    fn symbol(self) -> char {
+        match self {
+            RescuePass::Copying => '?',
+            RescuePass::Trimming => '*',
+            RescuePass::Scraping => '/',
+            RescuePass::Finished => '+',
+        }
+    }
+
+    fn from_symbol(symbol: char) -> Option<Self> {
+        match symbol {
+            '?' => Some(RescuePass::Copying),
+            '*' => Some(RescuePass::Trimming),
+            '/' | '-' => Some(RescuePass::Scraping),
+            '+' | 'F' | 'G' => Some(RescuePass::Finished),
+            _ => None,
+        }
+    }
+
+    fn number(self) -> u32 {
+        match self {
+            RescuePass::Copying => 1,
+            RescuePass::Trimming => 2,
+            RescuePass::Scraping | RescuePass::Finished => 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MapBlock {
+    pub pos: u64,
+    pub size: u64,
+    pub status: BlockStatus,
+}
+
+/// In-memory GNU ddrescue mapfile covering `[0, size)` of the source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mapfile {
+    pub current_pos: u64,
+    pub pass: RescuePass,
+    /// Sorted, contiguous and with adjacent blocks of the same status merged.
+    blocks: Vec<MapBlock>,
+    /// Bytes per status, indexed by [`BlockStatus::index`]; kept up to date by
+    /// [`Mapfile::set_status`] so progress updates need not walk the map.
+    totals: [u64; 5],
+}
+
+impl Mapfile {
+    /// Fresh mapfile with the whole source marked non-tried.
+    pub fn new(size: u64) -> Self {
+        let blocks = if size == 0 {
+            Vec::new()
+        } else {
+            vec![MapBlock {
+                pos: 0,
+                size,
+                status: BlockStatus::NonTried,
+            }]
+        };
+        Self::with_blocks(0, RescuePass::Copying, blocks)
+    }
+
+    fn with_blocks(current_pos: u64, pass: RescuePass, blocks: Vec<MapBlock>) -> Self {
+        let mut map = Self {
+            current_pos,
+            pass,
+            blocks,
+            totals: [0; 5],
+        };
+        map.merge_adjacent();
+        for block in &map.blocks {
+            map.totals[block.status.index()] += block.size;
+        }
+        map
+    }
+
+    /// Parse ddrescue mapfile text (comments start with `#`).
+    pub fn parse(contents: &str) -> Result<Self> {
+        let mut status_line: Option<(u64, RescuePass)> = None;
+        let mut blocks: Vec<MapBlock> = Vec::new();
+
+        for (index, raw) in contents.lines().enumerate() {
+            let line = raw.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let line_no = index + 1;
+
+            if status_line.is_none() {
+                if fields.len() < 2 {
+                    bail!("Mapfile line {line_no}: expected current position and status");
+                }
+                let pos = parse_number(fields[0])
+                    .with_context(|| format!("Mapfile line {line_no}: bad position"))?;
+                let pass = single_char(fields[1])
+                    .and_then(RescuePass::from_symbol)
+                    .with_context(|| format!("Mapfile line {line_no}: bad status {}", fields[1]))?;
+                status_line = Some((pos, pass));
+                continue;
+            }
+
+            if fields.len() < 3 {
+                bail!("Mapfile line {line_no}: expected pos, size and status");
+            }
+            let pos = parse_number(fields[0])
+                .with_context(|| format!("Mapfile line {line_no}: bad block position"))?;
+            let size = parse_number(fields[1])
+                .with_context(|| format!("Mapfile line {line_no}: bad block size"))?;
+            let status = single_char(fields[2])
+                .and_then(BlockStatus::from_symbol)
+                .with_context(|| {
+                    format!("Mapfile line {line_no}: bad block status {}", fields[2])
+                })?;
+            let expected = blocks.last().map_or(0, |b| b.pos + b.size);
+            if pos != expected {
+                bail!("Mapfile line {line_no}: block at {pos:#x} does not follow {expected:#x}");
+            }
+            if size > 0 {
+                blocks.push(MapBlock { pos, size, status });
+            }
+        }
+
+        let (current_pos, pass) = status_line.context("Mapfile has no status line")?;
+        Ok(Self::with_blocks(current_pos, pass, blocks))
+    }
+
+    /// The blocks in order, covering `[0, extent)`.
+    pub fn blocks(&self) -> &[MapBlock] {
+        &self.blocks
+    }
+
+    /// Total bytes described by the mapfile.
+    pub fn extent(&self) -> u64 {
+        self.blocks.last().map_or(0, |b| b.pos + b.size)
+    }
+
+    /// Bytes currently in `status`.
+    pub fn bytes_with(&self, status: BlockStatus) -> u64 {
+        self.totals[status.index()]
+    }
+
+    /// Number of separate bad areas (`-` blocks), as ddrescue reports them.
+    pub fn bad_areas(&self) -> u64 {
+        self.blocks
+            .iter()
+            .filter(|b| b.status == BlockStatus::BadSector)
+            .count() as u64
+    }
+
+    /// `(pos, size)` of every block whose status matches `wanted`.
+    pub fn areas(&self, wanted: impl Fn(BlockStatus) -> bool) -> Vec<(u64, u64)> {
+        self.blocks
+            .iter()
+            .filter(|b| wanted(b.status))
+            .map(|b| (b.pos, b.size))
+            .collect()
+    }
+
+    /// Mark `[pos, pos + size)` with `status`, splitting the blocks at its ends and
+    /// merging with the neighbours; only the blocks around the range are touched.
+    pub fn set_status(&mut self, pos: u64, size: u64, status: BlockStatus) {
+        let end = pos.saturating_add(size).min(self.extent());
+        if pos >= end {
+            return;
+        }
+        let first = self.blocks.partition_point(|b| b.pos + b.size <= pos);
+        let last = self.blocks.partition_point(|b| b.pos < end);
+        let head = self.blocks[first];
+        let tail = self.blocks[last - 1];
+
+        let mut pieces = Vec::with_capacity(3);
+        if head.pos < pos {
+            pieces.push(MapBlock {
+                pos: head.pos,
+                size: pos - head.pos,
+                status: head.status,
+            });
+        }
+        pieces.push(MapBlock {
+            pos,
+            size: end - pos,
+            status,
+        });
+        if tail.pos + tail.size > end {
+            pieces.push(MapBlock {
+                pos: end,
+                size: tail.pos + tail.size - end,
+                status: tail.status,
+            });
+        }
+
+        for block in &self.blocks[first..last] {
+            self.totals[block.status.index()] -= block.size;
+        }
+        for piece in &pieces {
+            self.totals[piece.status.index()] += piece.size;
+        }
+        let inserted = pieces.len();
+        self.blocks.splice(first..last, pieces);
+
+        // Merge the new pieces with each other and with the blocks on either side.
+        let mut index = first.saturating_sub(1);
+        let mut stop = (first + inserted + 1).min(self.blocks.len());
+        while index + 1 < stop {
+            let next = self.blocks[index + 1];
+            let block = &mut self.blocks[index];
+            if block.status == next.status && block.pos + block.size == next.pos {
+                block.size += next.size;
+                self.blocks.remove(index + 1);
+                stop -= 1;
+            } else {
+                index += 1;
+            }
+        }
+    }
+
+    fn merge_adjacent(&mut self) {
+        let mut merged: Vec<MapBlock> = Vec::with_capacity(self.blocks.len());
+        for block in self.blocks.drain(..) {
+            match merged.last_mut() {
+                Some(last) if last.status == block.status && last.pos + last.size == block.pos => {
+                    last.size += block.size;
+                }
+                _ => merged.push(block),
+            }
+        }
+        self.blocks = merged;
+    }
+
+    /// Load `path` when it exists (and matches `size`), otherwise start a new map.
+    pub fn load_or_new(path: &Path, size: u64) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::new(size));
+        }
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read mapfile: {}", path.display()))?;
+        let map = Self::parse(&contents)
+            .with_context(|| format!("Invalid mapfile: {}", path.display()))?;
+        if map.extent() != size {
+            bail!(
+                "Mapfile {} covers {} bytes but the source has {} bytes",
+                path.display(),
+                map.extent(),
+                size
+            );
+        }
+        Ok(map)
+    }
+
+    /// Write the mapfile atomically (temp file + rename).
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let tmp = path.with_extension("map.tmp");
+        fs::write(&tmp, self.to_string())
+            .with_context(|| format!("Failed to write mapfile: {}", tmp.display()))?;
+        fs::rename(&tmp, path)
+            .with_context(|| format!("Failed to replace mapfile: {}", path.display()))?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for Mapfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# Mapfile. Created by litho {}", env!("CARGO_PKG_VERSION"))?;
+        writeln!(f, "# current_pos  current_status  current_pass")?;
+        writeln!(
+            f,
+            "{:#010x}     {}               {}",
+            self.current_pos,
+            self.pass.symbol(),
+            self.pass.number()
+        )?;
+        writeln!(f, "#      pos        size  status")?;
+        for block in &self.blocks {
+            writeln!(
+                f,
+                "{:#010x}  {:#010x}  {}",
+                block.pos,
+                block.size,
+                block.status.symbol()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_number(field: &str) -> Option<u64> {
+    if let Some(hex) = field
+        .strip_prefix("0x")
+        .or_else(|| field.strip_prefix("0X"))
+    {
+        u64::from_str_radix(hex, 16).ok()
+    } else {
+        field.parse().ok()
+    }
+}
+
+fn single_char(field: &str) -> Option<char> {
+    let mut chars = field.chars();
+    let c = chars.next()?;
+    chars.next().is_none().then_some(c)
+}
+
+/// Outcome of a rescue run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RescueSummary {
+    pub bytes_total: u64,
+    pub bytes_rescued: u64,
+    pub bytes_bad: u64,
+    pub bad_areas: u64,
+    /// Failed reads during this run (block and sector retries).
+    pub read_errors: u64,
+}
+
+impl RescueSummary {
+    fn from_map(map: &Mapfile, read_errors: u64) -> Self {
+        Self {
+            bytes_total: map.extent(),
+            bytes_rescued: map.bytes_with(BlockStatus::Finished),
+            bytes_bad: map.extent() - map.bytes_with(BlockStatus::Finished),
+            bad_areas: map.bad_areas(),
+            read_errors,
+        }
+    }
+}
+
+/// Rescue `device_path` into `output_path`, tracking progress in `mapfile_path`.
+///
+/// The output file is not truncated, so re-running with the same mapfile resumes and
+/// retries only the areas still marked bad. Cancel keeps both files for that reason.
+pub fn rescue_clone<F>(
+    device_path: String,
+    output_path: String,
+    mapfile_path: String,
+    block_size: usize,
+    silent: bool,
+    mut progress: Option<F>,
+    control: Option<&OperationControl>,
+) -> Result<RescueSummary>
+where
+    F: FnMut(OperationProgress),
+{
+    if !silent {
+        info!(
+            "Rescuing device: {} to output: {} (mapfile: {}, block_size: {})",
+            device_path, output_path, mapfile_path, block_size
+        );
+    }
+
+    emit_progress(
+        silent,
+        &mut progress,
+        OperationProgress::new(OperationPhase::Preparing)
+            .with_message(format!("Opening {}", device_path)),
+    );
+
+    // Direct reads, so a failing sector does not take its page-cache neighbours with it.
+    let mut device_reader = PlatformDevice::new_reader(&device_path).or_else(|e| {
+        warn!("Direct I/O unavailable on {device_path} ({e:#}); retries may cover whole pages");
+        PlatformDevice::new_clone_reader(&device_path)
+    })?;
+    let sector_size = crate::devices::logical_sector_size(&device_path)
+        .filter(|size| size.is_power_of_two())
+        .unwrap_or(SECTOR_SIZE);
+    let size = match device_reader
+        .device_size()
+        .ok()
+        .filter(|s| *s > 0)
+        .or_else(|| crate::devices::device_size_bytes(&device_path))
+    {
+        Some(size) => size,
+        None => device_reader
+            .seek(SeekFrom::End(0))
+            .context("Failed to determine device size")?,
+    };
+
+    let mapfile = Path::new(&mapfile_path);
+    let mut map = Mapfile::load_or_new(mapfile, size)?;
+    if map.pass == RescuePass::Finished && map.bytes_with(BlockStatus::Finished) == size {
+        info!("Mapfile reports a complete rescue; nothing to do");
+        let summary = RescueSummary::from_map(&map, 0);
+        report_complete(&summary, size, silent, &mut progress);
+        return Ok(summary);
+    }
+
+    let mut output = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&output_path)
+        .context(format!("Failed to open output file: {}", output_path))?;
+    let output_len = output
+        .metadata()
+        .context("Failed to read output file metadata")?
+        .len();
+    if output_len < size {
+        output
+            .set_len(size)
+            .context("Failed to extend output file")?;
+    }
+
+    let mut save = |map: &Mapfile| map.save(mapfile);
+    let result = rescue_copy(
+        &mut device_reader,
+        &mut output,
+        &mut map,
+        block_size,
+        sector_size,
+        silent,
+        &mut progress,
+        control,
+        &mut save,
+    );
+
+    if let Err(save_error) = map.save(mapfile) {
+        warn!("Failed to save mapfile after rescue: {save_error}");
+    }
+    let read_errors = result?;
+
+    output.flush().context("Failed to flush rescue output")?;
+    output
+        .sync_all()
+        .context("Failed to sync rescue output")?;
+
+    let summary = RescueSummary::from_map(&map, read_errors);
+    report_complete(&summary, size, silent, &mut progress);
+    Ok(summary)
+}
+
+fn report_complete<F>(summary: &RescueSummary, size: u64, silent: bool, progress: &mut Option<F>)
+where
+    F: FnMut(OperationProgress),
+{
+    emit_progress(
+        silent,
+        progress,
+        OperationProgress::new(OperationPhase::Complete)
+            .with_bytes(summary.bytes_rescued, Some(size))
+            .with_percentage(100.0)
+            .with_read_errors(summary.read_errors)
+            .with_message(rescue_summary_message(summary)),
+    );
+    info!("{}", rescue_summary_message(summary));
+}
+
+pub fn rescue_summary_message(summary: &RescueSummary) -> String {
+    if summary.bytes_bad == 0 {
+        "Rescue completed: all data recovered".to_string()
+    } else {
+        format!(
+            "Rescue completed: {} bytes unreadable in {} bad area(s)",
+            summary.bytes_bad, summary.bad_areas
+        )
+    }
+}
+
+/// Core three-pass copy over any seekable source and sink; returns the read error count.
+///
+/// Reads are multiples of `sector_size` at `sector_size`-aligned offsets into a
+/// page-aligned buffer, as direct I/O requires.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn rescue_copy<R, W, F, S>(
+    reader: &mut R,
+    writer: &mut W,
+    map: &mut Mapfile,
+    block_size: usize,
+    sector_size: u64,
+    silent: bool,
+    progress: &mut Option<F>,
+    control: Option<&OperationControl>,
+    save: &mut S,
+) -> Result<u64>
+where
+    R: Read + Seek + ?Sized,
+    W: Write + Seek + ?Sized,
+    F: FnMut(OperationProgress),
+    S: FnMut(&Mapfile) -> Result<()>,
+{
+    let sector_size = sector_size.max(1);
+    let block_size = (block_size as u64).max(sector_size) / sector_size * sector_size;
+    let trim_size = (block_size / TRIM_DIVISOR / sector_size * sector_size).max(sector_size);
+    let mut buffer = AlignedBuffer::new(block_size as usize, BUFFER_ALIGN.max(sector_size));
+    let mut state = PassState {
+        read_errors: 0,
+        since_save: 0,
+        last_save: Instant::now(),
+    };
+
+    // Pass 1: copy everything not tried yet, skipping blocks that fail.
+    map.pass = RescuePass::Copying;
+    let areas = map.areas(|s| s == BlockStatus::NonTried);
+    copy_areas(
+        reader,
+        writer,
+        map,
+        buffer.as_mut_slice(),
+        &areas,
+        block_size,
+        BlockStatus::NonTrimmed,
+        silent,
+        progress,
+        control,
+        save,
+        &mut state,
+    )?;
+
+    // Pass 2: retry failed blocks in smaller chunks, so that only the chunks that
+    // still fail are read sector by sector.
+    if trim_size > sector_size {
+        map.pass = RescuePass::Trimming;
+        let areas = map.areas(|s| s == BlockStatus::NonTrimmed);
+        if !areas.is_empty() {
+            info!(
+                "Retrying {} failed area(s) in {trim_size}-byte chunks",
+                areas.len()
+            );
+        }
+        copy_areas(
+            reader,
+            writer,
+            map,
+            buffer.as_mut_slice(),
+            &areas,
+            trim_size,
+            BlockStatus::NonScraped,
+            silent,
+            progress,
+            control,
+            save,
+            &mut state,
+        )?;
+    }
+
+    // Pass 3: retry every unfinished area one sector at a time.
+    map.pass = RescuePass::Scraping;
+    let areas = map.areas(|s| {
+        matches!(
+            s,
+            BlockStatus::NonTrimmed | BlockStatus::NonScraped | BlockStatus::BadSector
+        )
+    });
+    if !areas.is_empty() {
+        info!(
+            "Retrying {} unreadable area(s) sector by sector",
+            areas.len()
+        );
+    }
+    copy_areas(
+        reader,
+        writer,
+        map,
+        buffer.as_mut_slice(),
+        &areas,
+        sector_size,
+        BlockStatus::BadSector,
+        silent,
+        progress,
+        control,
+        save,
+        &mut state,
+    )?;
+
+    map.pass = RescuePass::Finished;
+    map.current_pos = map.extent();
+    Ok(state.read_errors)
+}
+
+struct PassState {
+    read_errors: u64,
+    /// Bytes rescued since the mapfile was last saved.
+    since_save: u64,
+    last_save: Instant,
+}
+
+/// Read `areas` in `chunk`-sized pieces, marking each piece finished or `failed`
+/// (zero-filled in the output).
+#[allow(clippy::too_many_arguments)]
+fn copy_areas<R, W, F, S>(
+    reader: &mut R,
+    writer: &mut W,
+    map: &mut Mapfile,
+    buffer: &mut [u8],
+    areas: &[(u64, u64)],
+    chunk: u64,
+    failed: BlockStatus,
+    silent: bool,
+    progress: &mut Option<F>,
+    control: Option<&OperationControl>,
+    save: &mut S,
+    state: &mut PassState,
+) -> Result<()>
+where
+    R: Read + Seek + ?Sized,
+    W: Write + Seek + ?Sized,
+    F: FnMut(OperationProgress),
+    S: FnMut(&Mapfile) -> Result<()>,
+{
+    for &(area_pos, area_size) in areas {
+        let area_end = area_pos + area_size;
+        let mut pos = area_pos;
+        while pos < area_end {
+            check_cancel(control)?;
+            let len = (area_end - pos).min(chunk);
+            let piece = &mut buffer[..len as usize];
+            map.current_pos = pos;
+            match read_at(reader, pos, piece) {
+                Ok(()) => {
+                    write_at(writer, pos, piece)?;
+                    map.set_status(pos, len, BlockStatus::Finished);
+                    state.since_save += len;
+                }
+                Err(error) => {
+                    debug!("Read error at {pos:#x} (+{len}): {error}");
+                    state.read_errors += 1;
+                    piece.fill(0);
+                    write_at(writer, pos, piece)?;
+                    map.set_status(pos, len, failed);
+                }
+            }
+            pos += len;
+            emit_rescue_progress(silent, progress, map, state.read_errors);
+            if state.since_save >= MAPFILE_SAVE_INTERVAL
+                || state.last_save.elapsed() >= MAPFILE_SAVE_PERIOD
+            {
+                save(map)?;
+                state.since_save = 0;
+                state.last_save = Instant::now();
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Zeroed buffer whose usable part starts on an `align` boundary, as `O_DIRECT` needs.
+struct AlignedBuffer {
+    storage: Vec<u8>,
+    offset: usize,
+    len: usize,
+}
+
+impl AlignedBuffer {
+    fn new(len: usize, align: u64) -> Self {
+        let align = align as usize;
+        let storage = vec![0u8; len + align];
+        let offset = storage.as_ptr().align_offset(align);
+        Self {
+            storage,
+            offset,
+            len,
+        }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        &mut self.storage[self.offset..self.offset + self.len]
+    }
+}
+
+fn emit_rescue_progress<F>(
+    silent: bool,
+    progress: &mut Option<F>,
+    map: &Mapfile,
+    read_errors: u64,
+) where
+    F: FnMut(OperationProgress),
+{
+    let total = map.extent();
+    let pending = map.bytes_with(BlockStatus::NonTried)
+        + map.bytes_with(BlockStatus::NonTrimmed)
+        + map.bytes_with(BlockStatus::NonScraped);
+    let pass = match map.pass {
+        RescuePass::Copying => "Pass 1: copying",
+        RescuePass::Trimming => "Pass 2: retrying failed blocks",
+        RescuePass::Scraping | RescuePass::Finished => "Pass 3: retrying bad sectors",
+    };
+    emit_progress(
+        silent,
+        progress,
+        OperationProgress::new(OperationPhase::Writing)
+            .with_bytes(total - pending, Some(total))
+            .with_read_errors(read_errors)
+            .with_message(format!(
+                "{pass} — {read_errors} read error(s), {} bytes unreadable",
+                map.bytes_with(BlockStatus::BadSector) + map.bytes_with(BlockStatus::NonTrimmed)
+            )),
+    );
+}
+
+/// Read exactly `buf.len()` bytes at `pos`; a short read counts as an error.
+fn read_at<R: Read + Seek + ?Sized>(reader: &mut R, pos: u64, buf: &mut [u8]) -> std::io::Result<()> {
+    reader.seek(SeekFrom::Start(pos))?;
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => {
+                return Err(std::io::Error::new(
+                    ErrorKind::UnexpectedEof,
+                    format!("short read at {:#x}", pos + filled as u64),
+                ))
+            }
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+fn write_at<W: Write + Seek + ?Sized>(writer: &mut W, pos: u64, buf: &[u8]) -> Result<()> {
+    writer
+        .seek(SeekFrom::Start(pos))
+        .context("Failed to seek in rescue output")?;
+    writer
+        .write_all(buf)
+        .context("Failed to write to rescue output")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// In-memory disk whose listed byte ranges fail to read.
+    struct FlakyDisk {
+        data: Vec<u8>,
+        bad: Vec<(u64, u64)>,
+        pos: u64,
+        reads: Vec<(u64, usize)>,
+    }
+
+    impl Read for FlakyDisk {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let start = self.pos;
+            let len = buf.len().min(self.data.len().saturating_sub(start as usize));
+            let end = start + len as u64;
+            self.reads.push((start, len));
+            if self.bad.iter().any(|&(b, e)| start < e && end > b) {
+                return Err(std::io::Error::other("I/O error"));
+            }
+            buf[..len].copy_from_slice(&self.data[start as usize..end as usize]);
+            self.pos = end;
+            Ok(len)
+        }
+    }
+
+    impl Seek for FlakyDisk {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+            self.pos = match pos {
+                SeekFrom::Start(p) => p,
+                SeekFrom::End(d) => (self.data.len() as i64 + d) as u64,
+                SeekFrom::Current(d) => (self.pos as i64 + d) as u64,
+            };
+            Ok(self.pos)
+        }
+    }
+
+    fn disk(bad: Vec<(u64, u64)>) -> FlakyDisk {
+        FlakyDisk {
+            data: (0..16 * 1024).map(|i| (i % 251) as u8 + 1).collect(),
+            bad,
+            pos: 0,
+            reads: Vec::new(),
+        }
+    }
+
+    fn run(
+        disk: &mut FlakyDisk,
+        output: &mut Cursor<Vec<u8>>,
+        map: &mut Mapfile,
+    ) -> Result<u64> {
+        run_with(disk, output, map, 4096, 512)
+    }
+
+    fn run_with(
+        disk: &mut FlakyDisk,
+        output: &mut Cursor<Vec<u8>>,
+        map: &mut Mapfile,
+        block_size: usize,
+        sector_size: u64,
+    ) -> Result<u64> {
+        rescue_copy(
+            disk,
+            output,
+            map,
+            block_size,
+            sector_size,
+            true,
+            &mut None::<fn(OperationProgress)>,
+            None,
+            &mut |_: &Mapfile| Ok(()),
+        )
+    }
+
+    #[test]
+    fn clean_disk_copies_everything() {
+        let mut source = disk(Vec::new());
+        let mut output = Cursor::new(vec![0u8; source.data.len()]);
+        let mut map = Mapfile::new(source.data.len() as u64);
+        let errors = run(&mut source, &mut output, &mut map).unwrap();
+        assert_eq!(errors, 0);
+        assert_eq!(output.get_ref(), &source.data);
+        assert_eq!(map.blocks.len(), 1);
+        assert_eq!(map.blocks[0].status, BlockStatus::Finished);
+    }
+
+    #[test]
+    fn bad_sectors_are_zero_filled_and_mapped() {
+        let mut source = disk(vec![(5000, 5100)]);
+        let mut output = Cursor::new(vec![0xAAu8; source.data.len()]);
+        let mut map = Mapfile::new(source.data.len() as u64);
+        let errors = run(&mut source, &mut output, &mut map).unwrap();
+
+        // One failed 4 KiB block plus one failed sector retry.
+        assert_eq!(errors, 2);
+        assert_eq!(
+            map.areas(|s| s == BlockStatus::BadSector),
+            vec![(4608, 512)]
+        );
+        let out = output.get_ref();
+        assert!(out[4608..5120].iter().all(|&b| b == 0));
+        assert_eq!(&out[..4608], &source.data[..4608]);
+        assert_eq!(&out[5120..], &source.data[5120..]);
+    }
+
+    #[test]
+    fn trimming_pass_narrows_failed_blocks_before_scraping() {
+        let mut source = disk(vec![(5000, 5100)]);
+        let mut output = Cursor::new(vec![0xAAu8; source.data.len()]);
+        let mut map = Mapfile::new(source.data.len() as u64);
+        let errors = run_with(&mut source, &mut output, &mut map, 16 * 1024, 512).unwrap();
+
+        // 16 KiB block, then 1 KiB chunks, then only the two sectors of the bad chunk.
+        assert_eq!(errors, 3);
+        assert_eq!(source.reads.len(), 1 + 16 + 2);
+        assert_eq!(&source.reads[17..], &[(4096, 512), (4608, 512)]);
+        assert_eq!(
+            map.areas(|s| s == BlockStatus::BadSector),
+            vec![(4608, 512)]
+        );
+    }
+
+    #[test]
+    fn retries_use_the_device_sector_size() {
+        let mut source = disk(vec![(5000, 5100)]);
+        let mut output = Cursor::new(vec![0u8; source.data.len()]);
+        let mut map = Mapfile::new(source.data.len() as u64);
+        run_with(&mut source, &mut output, &mut map, 4096, 4096).unwrap();
+        assert_eq!(
+            map.areas(|s| s == BlockStatus::BadSector),
+            vec![(4096, 4096)]
+        );
+        assert!(source
+            .reads
+            .iter()
+            .all(|&(pos, len)| pos % 4096 == 0 && len == 4096));
+    }
+
+    #[test]
+    fn rerun_only_retries_bad_areas() {
+        let mut source = disk(vec![(5000, 5100)]);
+        let mut output = Cursor::new(vec![0u8; source.data.len()]);
+        let mut map = Mapfile::new(source.data.len() as u64);
+        run(&mut source, &mut output, &mut map).unwrap();
+
+        let mut map = Mapfile::parse(&map.to_string()).unwrap();
+        let mut healed = disk(Vec::new());
+        let errors = run(&mut healed, &mut output, &mut map).unwrap();
+
+        assert_eq!(errors, 0);
+        assert_eq!(healed.reads, vec![(4608, 512)]);
+        assert_eq!(output.get_ref(), &healed.data);
+        assert_eq!(map.bytes_with(BlockStatus::Finished), map.extent());
+    }
+
+    #[test]
+    fn finished_mapfile_skips_the_rescue() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("disk.img");
+        fs::write(&source, vec![0x5Au8; 8192]).unwrap();
+        let mapfile = dir.path().join("disk.map");
+        fs::write(&mapfile, "0x00002000 +\n0x00000000 0x00002000 +\n").unwrap();
+        let output = dir.path().join("rescued.img");
+        let mut phases = Vec::new();
+
+        let summary = rescue_clone(
+            source.display().to_string(),
+            output.display().to_string(),
+            mapfile.display().to_string(),
+            4096,
+            false,
+            Some(|event: OperationProgress| phases.push(event.phase)),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(summary.bytes_rescued, 8192);
+        assert_eq!(phases.last(), Some(&OperationPhase::Complete));
+        assert!(!output.exists());
+    }
+
+    #[test]
+    fn mapfile_round_trips_ddrescue_format() {
+        let text = "\
+# Mapfile. Created by GNU ddrescue version 1.27
+# current_pos  current_status  current_pass
+0x00001200     /               3
+#      pos        size  status
+0x00000000  0x00001000  +
+0x00001000  0x00000200  -
+0x00001200  0x00000E00  *
+";
+        let map = Mapfile::parse(text).unwrap();
+        assert_eq!(map.current_pos, 0x1200);
+        assert_eq!(map.pass, RescuePass::Scraping);
+        assert_eq!(map.extent(), 0x2000);
+        assert_eq!(map.bad_areas(), 1);
+        assert_eq!(Mapfile::parse(&map.to_string()).unwrap(), map);
+    }
+
+    #[test]
+    fn mapfile_rejects_gaps() {
+        let text = "0x0 ?\n0x0 0x100 +\n0x200 0x100 +\n";
+        assert!(Mapfile::parse(text).is_err());
+    }
+
+    #[test]
+    fn set_status_splits_and_merges() {
+        let mut map = Mapfile::new(4096);
+        map.set_status(1024, 512, BlockStatus::BadSector);
+        assert_eq!(map.blocks.len(), 3);
+        assert_eq!(map.bytes_with(BlockStatus::BadSector), 512);
+        map.set_status(1536, 512, BlockStatus::BadSector);
+        assert_eq!(
+            map.areas(|s| s == BlockStatus::BadSector),
+            vec![(1024, 1024)]
+        );
+        map.set_status(512, 3072, BlockStatus::Finished);
+        assert_eq!(map.blocks.len(), 3);
+        assert_eq!(map.bytes_with(BlockStatus::Finished), 3072);
+        assert_eq!(map.bytes_with(BlockStatus::NonTried), 1024);
+        map.set_status(0, 4096, BlockStatus::NonTried);
+        assert_eq!(map.blocks.len(), 1);
+        assert_eq!(map.bytes_with(BlockStatus::BadSector), 0);
+    }
+}
@@ -1,10 +1,15 @@
 use crate::tui::helpers::{
     default_device_index, device_display_name, device_label, device_path, file_basename,
+    next_wipe_method, wipe_method_description,
+};
+use crate::tui::hotplug::{
+    carry_partition_tables, inserted_removable, reselect_device_index, spawn_device_watcher,
+    was_removed, DeviceUpdate,
 };
 use crate::tui::launch::{launch_prefilled, LaunchParams};
 use crate::tui::layout::{terminal_too_small, MIN_COLS, MIN_ROWS};
 use crate::tui::operation::spawn_operation;
-use liblitho::io_backend::{complete_suffix, in_progress_suffix};
+use liblitho::io_backend::{complete_suffix, eject_io, in_progress_suffix};
 use crate::tui::privilege::{is_running_as_root, polkit_agent_available, relaunch_elevated};
 use crate::tui::ui::{
     render_device_picker_dialog, render_file_picker_hint, render_output_filename_dialog, ui,
@@ -16,14 +21,14 @@ use crossterm::{
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use fpicker::{FileExplorer, Theme};
-use liblitho::devices::DeviceInfo;
-use liblitho::progress::{OperationPhase, OperationProgress};
-use log::{error, info};
+use liblitho::devices::{DeviceInfo, ReleaseAction};
+use liblitho::progress::{OperationControl, OperationPhase, OperationProgress};
+use liblitho::wipe::WipeMethod;
+use log::{error, info, warn};
 use ratatui::{backend::CrosstermBackend, layout::Rect, Terminal};
 use std::io::{self, IsTerminal, Stdout};
 use std::path::Path;
 use std::sync::{
-    atomic::{AtomicBool, Ordering},
     mpsc::{self, Receiver, TryRecvError},
     Arc,
 };
@@ -34,6 +39,7 @@ type TuiTerminal = Terminal<CrosstermBackend<Stdout>>;
 pub enum Operation {
     Clone,
     Flash,
+    Wipe,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -50,6 +56,7 @@ pub enum InputFocus {
 pub enum StatusState {
     Ready,
     InProgress,
+    Paused,
     Complete,
     Cancelled,
     Error,
@@ -60,6 +67,8 @@ pub enum Dialog {
     None,
     NonRemovableConfirm { target_index: usize },
     ElevationConfirm,
+    WipeConfirm,
+    UnmountConfirm,
 }
 
 pub struct App {
@@ -74,13 +83,25 @@ pub struct App {
     pub status_detail: String,
     pub dialog: Dialog,
     pub progress_rx: Receiver<OperationProgress>,
-    pub operation_cancel: Arc<AtomicBool>,
+    pub operation_control: Arc<OperationControl>,
     pub is_root: bool,
     pub polkit_available: bool,
     pub auto_start_pending: bool,
     pub terminal_warn_logged: bool,
-    /// When true, flash operations read the device back and compare checksums.
+    /// When true, flash operations read the device back and compare checksums
+    /// (wipe operations read the erased area back).
     pub verify_checksum: bool,
+    pub wipe_method: WipeMethod,
+    /// Mounts and swap to release once [`Dialog::UnmountConfirm`] is accepted.
+    pub pending_release: Vec<ReleaseAction>,
+    /// Hotplug updates; `None` when monitoring is unavailable.
+    pub device_rx: Option<Receiver<DeviceUpdate>>,
+    /// Removable devices plugged in since launch and not yet selected.
+    pub new_devices: Vec<String>,
+    /// Device the running (or last) operation writes to or reads from.
+    pub operation_target: Option<String>,
+    /// Why the running operation was cancelled, when not by the user.
+    pub cancel_reason: Option<String>,
 }
 
 impl App {
@@ -89,15 +110,16 @@ impl App {
         let mut selected_device_index = default_device_index(&devices);
 
         let operation = match launch.mode.as_deref() {
-            Some(mode) if mode == "clone" => Operation::Clone,
+            Some("clone") => Operation::Clone,
+            Some("wipe") => Operation::Wipe,
             _ => Operation::Flash,
         };
 
         if let Some(ref wanted_device) = launch.device {
-            if let Some(idx) = devices
-                .iter()
-                .position(|d| device_path(d) == *wanted_device)
-            {
+            // `--device` may be a stable /dev/disk/by-* name; the list holds /dev nodes.
+            let wanted_device = liblitho::devices::resolve_device_link(wanted_device)
+                .unwrap_or_else(|_| wanted_device.clone());
+            if let Some(idx) = devices.iter().position(|d| device_path(d) == wanted_device) {
                 selected_device_index = idx;
             }
         }
@@ -113,6 +135,7 @@ impl App {
         };
 
         let image_file = launch.image.unwrap_or_default();
+        let wipe_method = launch.wipe_method.unwrap_or_default();
         let (_tx, progress_rx) = mpsc::channel();
 
         App {
@@ -127,12 +150,18 @@ impl App {
             status_detail: String::from("Waiting for operation..."),
             dialog: Dialog::None,
             progress_rx,
-            operation_cancel: Arc::new(AtomicBool::new(false)),
+            operation_control: Arc::new(OperationControl::new()),
             is_root,
             polkit_available,
             auto_start_pending,
             terminal_warn_logged: false,
             verify_checksum: false,
+            wipe_method,
+            pending_release: Vec::new(),
+            device_rx: spawn_device_watcher(),
+            new_devices: Vec::new(),
+            operation_target: None,
+            cancel_reason: None,
         }
     }
 
@@ -189,8 +218,17 @@ impl App {
         }
     }
 
+    /// Whether the verify checkbox applies to the current mode (flash, or a verifiable wipe).
+    pub fn verify_applies(&self) -> bool {
+        match self.operation {
+            Operation::Flash => true,
+            Operation::Clone => false,
+            Operation::Wipe => self.wipe_method.supports_verify(),
+        }
+    }
+
     fn next_after_file(&self) -> InputFocus {
-        if self.operation == Operation::Flash {
+        if self.verify_applies() {
             InputFocus::Verify
         } else {
             InputFocus::Start
@@ -198,32 +236,69 @@ impl App {
     }
 
     fn prev_before_start(&self) -> InputFocus {
-        if self.operation == Operation::Flash {
+        if self.verify_applies() {
             InputFocus::Verify
         } else {
             InputFocus::File
         }
     }
 
+    /// Flash and wipe write to the target; clone only reads it.
+    fn writes_device(&self) -> bool {
+        self.operation != Operation::Clone
+    }
+
     pub fn set_operation(&mut self, op: Operation) {
         self.operation = op;
-        if op == Operation::Clone && self.focus == InputFocus::Verify {
+        if !self.verify_applies() && self.focus == InputFocus::Verify {
             self.focus = InputFocus::File;
         }
     }
 
+    /// Step the mode cards left (`-1`) or right (`+1`), wrapping around.
+    pub fn cycle_operation(&mut self, step: isize) {
+        const ORDER: [Operation; 3] = [Operation::Flash, Operation::Clone, Operation::Wipe];
+        let index = ORDER.iter().position(|op| *op == self.operation).unwrap_or(0);
+        let next = (index as isize + step).rem_euclid(ORDER.len() as isize) as usize;
+        self.set_operation(ORDER[next]);
+    }
+
     pub fn toggle_verify_checksum(&mut self) {
-        if self.operation == Operation::Flash && !self.is_running {
+        if self.verify_applies() && !self.is_running {
             self.verify_checksum = !self.verify_checksum;
         }
     }
 
+    pub fn cycle_wipe_method(&mut self) {
+        if self.operation != Operation::Wipe || self.is_running {
+            return;
+        }
+        self.wipe_method = next_wipe_method(self.wipe_method);
+        self.set_status(
+            StatusState::Ready,
+            format!(
+                "Wipe method: {} — {}",
+                self.wipe_method,
+                wipe_method_description(self.wipe_method)
+            ),
+        );
+    }
+
     pub fn try_select_device(&mut self, index: usize) {
         if index >= self.devices.len() {
             return;
         }
 
         let device = &self.devices[index];
+        self.new_devices.retain(|path| *path != device.device_name);
+        if device.read_only && self.writes_device() {
+            let message = format!(
+                "{}: device is write-protected; pick another target or clone it instead",
+                device_path(device)
+            );
+            self.set_status(StatusState::Error, message);
+            return;
+        }
         if device.removable == 1 {
             self.selected_device_index = index;
             self.set_status(
@@ -276,11 +351,15 @@ impl App {
             let mode = match self.operation {
                 Operation::Flash => "flash",
                 Operation::Clone => "clone",
+                Operation::Wipe => "wipe",
             };
             let device = self.selected_device().map(device_path).unwrap_or_default();
-            let image = self.image_file.clone();
+            let (image, method) = match self.operation {
+                Operation::Wipe => (String::new(), Some(self.wipe_method.to_string())),
+                _ => (self.image_file.clone(), None),
+            };
 
-            if device.is_empty() || image.is_empty() {
+            if device.is_empty() || (image.is_empty() && method.is_none()) {
                 self.set_status(
                     StatusState::Error,
                     String::from("Select a device and file before elevating privileges."),
@@ -302,7 +381,7 @@ impl App {
             );
             teardown_terminal(terminal);
 
-            match relaunch_elevated(mode, &device, &image) {
+            match relaunch_elevated(mode, &device, &image, method.as_deref()) {
                 Ok(()) => {
                     // Non-Unix: elevated child was spawned; this process must exit.
                     #[cfg(not(unix))]
@@ -323,11 +402,9 @@ impl App {
     }
 
     pub fn refresh_devices(&mut self) {
-        match liblitho::devices::get_storage_devices() {
+        match liblitho::devices::get_storage_devices_with_tables() {
             Ok(devs) => {
-                self.devices = devs;
-                self.selected_device_index =
-                    default_device_index(&self.devices).min(self.devices.len().saturating_sub(1));
+                self.replace_devices(devs);
                 self.set_status(StatusState::Ready, String::from("Devices refreshed"));
             }
             Err(e) => {
@@ -339,8 +416,65 @@ impl App {
         }
     }
 
+    /// Apply hotplug updates from the watcher thread.
+    pub fn check_devices(&mut self) {
+        let updates: Vec<DeviceUpdate> = match &self.device_rx {
+            Some(rx) => rx.try_iter().collect(),
+            None => return,
+        };
+        for update in updates {
+            self.apply_device_update(update);
+        }
+    }
+
+    fn apply_device_update(&mut self, mut update: DeviceUpdate) {
+        let selected = self.selected_device().map(device_path);
+        carry_partition_tables(
+            &self.devices,
+            &mut update,
+            liblitho::devices::read_device_table,
+        );
+        self.replace_devices(update.devices);
+
+        if self.is_running {
+            if let Some(target) = self
+                .operation_target
+                .clone()
+                .filter(|target| was_removed(&update.events, target))
+            {
+                warn!("Target {target} was removed during the operation");
+                self.cancel_reason = Some(format!("{target} was removed — operation cancelled."));
+                self.cancel_operation();
+            }
+            return;
+        }
+
+        let inserted = inserted_removable(&update.events);
+        if let Some(device) = inserted
+            .last()
+            .and_then(|path| self.devices.iter().find(|d| d.device_name == *path))
+        {
+            let label = device_label(device);
+            self.new_devices.extend(inserted);
+            self.set_status(StatusState::Ready, format!("Inserted: {label}"));
+        } else if let Some(path) = selected.filter(|path| was_removed(&update.events, path)) {
+            self.set_status(StatusState::Ready, format!("{path} was removed"));
+        }
+    }
+
+    /// Swap in a new device list, keeping the selected device when it is still there.
+    fn replace_devices(&mut self, devices: Vec<DeviceInfo>) {
+        let selected = self.selected_device().map(device_path);
+        self.devices = devices;
+        self.selected_device_index = reselect_device_index(selected.as_deref(), &self.devices)
+            .min(self.devices.len().saturating_sub(1));
+        let devices = &self.devices;
+        self.new_devices
+            .retain(|path| devices.iter().any(|d| d.device_name == *path));
+    }
+
     pub fn start_operation(&mut self) {
-        if self.image_file.is_empty() {
+        if self.operation != Operation::Wipe && self.image_file.is_empty() {
             self.set_status(
                 StatusState::Error,
                 String::from("Select a source file before starting."),
@@ -355,6 +489,16 @@ impl App {
             return;
         }
 
+        // Ahead of elevation and unmounting: a write-protected target would fail anyway.
+        if self.writes_device() {
+            if let Some(device) = self.selected_device() {
+                if let Err(e) = liblitho::devices::validate_device_writable(&device_path(device)) {
+                    self.set_status(StatusState::Error, e);
+                    return;
+                }
+            }
+        }
+
         if !self.is_root {
             if self.dialog == Dialog::None {
                 self.dialog = Dialog::ElevationConfirm;
@@ -371,32 +515,93 @@ impl App {
             return;
         }
 
+        match liblitho::devices::release_plan(&device_path) {
+            Ok(plan) if !plan.is_empty() => {
+                self.auto_start_pending = false;
+                self.pending_release = plan;
+                self.dialog = Dialog::UnmountConfirm;
+                return;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                self.set_status(StatusState::Error, e);
+                return;
+            }
+        }
+
         let known: Vec<&str> = self.devices.iter().map(|d| d.device_name.as_str()).collect();
         if let Err(e) = liblitho::devices::validate_listed_block_device(&device_path, &known) {
             self.set_status(StatusState::Error, e);
             return;
         }
 
+        if self.operation == Operation::Wipe {
+            // Wiping has no source file to double-check, so always ask first.
+            self.auto_start_pending = false;
+            self.dialog = Dialog::WipeConfirm;
+            return;
+        }
+
+        self.begin_operation(device_path);
+    }
+
+    /// Unmount / swapoff the target after confirmation, then start again (which repeats
+    /// the busy check).
+    pub fn confirm_unmount(&mut self, accept: bool) {
+        self.dialog = Dialog::None;
+        let plan = std::mem::take(&mut self.pending_release);
+        if !accept {
+            self.set_status(
+                StatusState::Ready,
+                String::from("Target is still in use; operation not started."),
+            );
+            return;
+        }
+        for action in &plan {
+            info!("Releasing target: {action}");
+        }
+        if let Err(e) = liblitho::devices::release_target(&plan) {
+            error!("{e}");
+            self.set_status(StatusState::Error, e);
+            return;
+        }
+        self.start_operation();
+    }
+
+    pub fn confirm_wipe(&mut self, accept: bool) {
+        self.dialog = Dialog::None;
+        if !accept {
+            self.set_status(StatusState::Ready, String::from("Wipe not started."));
+            return;
+        }
+        let device_path = self.selected_device().map(device_path).unwrap_or_default();
+        self.begin_operation(device_path);
+    }
+
+    fn begin_operation(&mut self, device_path: String) {
         let device_name = self
             .selected_device()
             .map(device_display_name)
             .unwrap_or_else(|| "device".to_string());
 
-        let verb = match self.operation {
-            Operation::Flash => "Writing",
-            Operation::Clone => "Cloning",
+        let detail = match self.operation {
+            Operation::Flash => format!("Writing to {device_name}..."),
+            Operation::Clone => format!("Cloning to {device_name}..."),
+            Operation::Wipe => format!("Wiping {device_name} ({})...", self.wipe_method),
         };
 
         self.is_running = true;
         self.progress = 0.0;
         self.auto_start_pending = false;
+        self.operation_target = Some(device_path.clone());
+        self.cancel_reason = None;
         self.set_status(
             StatusState::InProgress,
-            format!("{verb} to {device_name}...{suffix}", suffix = in_progress_suffix()),
+            format!("{detail}{suffix}", suffix = in_progress_suffix()),
         );
 
-        let cancel = Arc::new(AtomicBool::new(false));
-        self.operation_cancel = cancel.clone();
+        let control = Arc::new(OperationControl::new());
+        self.operation_control = control.clone();
 
         let (tx, rx) = mpsc::channel();
         self.progress_rx = rx;
@@ -414,18 +619,48 @@ impl App {
             match op {
                 Operation::Flash => "flash",
                 Operation::Clone => "clone",
+                Operation::Wipe => "wipe",
             },
             device_path,
             image_path,
             block_size
         );
 
-        let verify = self.operation == Operation::Flash && self.verify_checksum;
-        spawn_operation(op, device_path, image_path, block_size, verify, cancel, tx);
+        let verify = self.verify_applies() && self.verify_checksum;
+        spawn_operation(
+            op,
+            device_path,
+            image_path,
+            self.wipe_method,
+            block_size,
+            verify,
+            control,
+            tx,
+        );
+    }
+
+    /// Pause a running operation at its next block boundary, or resume it.
+    pub fn toggle_pause(&mut self) {
+        if !self.is_running || self.operation_control.is_cancelled() {
+            return;
+        }
+        if self.operation_control.is_paused() {
+            self.operation_control.resume();
+            info!("Operation resumed");
+            self.set_status(StatusState::InProgress, String::from("Resuming..."));
+        } else {
+            self.operation_control.pause();
+            info!("Operation pause requested");
+            self.set_status(
+                StatusState::Paused,
+                String::from("Paused — press p to resume, c to cancel."),
+            );
+        }
     }
 
     pub fn cancel_operation(&mut self) {
-        self.operation_cancel.store(true, Ordering::Relaxed);
+        // A paused worker notices cancel on its own; no resume needed.
+        self.operation_control.cancel();
         info!("Operation cancel requested");
         self.set_status(
             StatusState::InProgress,
@@ -437,7 +672,7 @@ impl App {
         loop {
             match self.progress_rx.try_recv() {
                 Ok(progress) => {
-                    if self.operation_cancel.load(Ordering::Relaxed)
+                    if self.operation_control.is_cancelled()
                         && progress.phase != OperationPhase::Cancelled
                     {
                         continue;
@@ -448,11 +683,12 @@ impl App {
                 Err(TryRecvError::Disconnected) => {
                     if self.is_running {
                         self.is_running = false;
-                        if self.operation_cancel.load(Ordering::Relaxed) {
-                            self.set_status(
-                                StatusState::Cancelled,
-                                String::from("Operation cancelled by user."),
-                            );
+                        if self.operation_control.is_cancelled() {
+                            let message = self
+                                .cancel_reason
+                                .take()
+                                .unwrap_or_else(|| String::from("Operation cancelled by user."));
+                            self.set_status(StatusState::Cancelled, message);
                         } else {
                             self.set_status(
                                 StatusState::Error,
@@ -467,7 +703,7 @@ impl App {
     }
 
     fn apply_progress(&mut self, progress: OperationProgress) {
-        if self.operation_cancel.load(Ordering::Relaxed)
+        if self.operation_control.is_cancelled()
             && progress.phase != OperationPhase::Cancelled
         {
             return;
@@ -477,6 +713,18 @@ impl App {
             self.progress = pct.clamp(0.0, 100.0);
         }
 
+        // Events queued before the pause took effect must not clobber the paused status.
+        let in_flight = matches!(
+            progress.phase,
+            OperationPhase::Preparing
+                | OperationPhase::Decompressing
+                | OperationPhase::Writing
+                | OperationPhase::Verifying
+        );
+        if in_flight && self.operation_control.is_paused() {
+            return;
+        }
+
         if let Some(ref message) = progress.message {
             self.status_detail = message.clone();
         } else {
@@ -500,10 +748,14 @@ impl App {
                 let verb = match self.operation {
                     Operation::Flash => "flashed",
                     Operation::Clone => "cloned",
+                    Operation::Wipe => "wiped",
                 };
                 self.set_status(
                     StatusState::Complete,
-                    format!("Successfully {verb} {device_name}{suffix}", suffix = complete_suffix()),
+                    format!(
+                        "Successfully {verb} {device_name}{suffix} — press e to eject",
+                        suffix = complete_suffix()
+                    ),
                 );
             }
             OperationPhase::Failed => {
@@ -517,16 +769,43 @@ impl App {
             }
             OperationPhase::Cancelled => {
                 self.is_running = false;
+                let message = self.cancel_reason.take().or(progress.message);
                 self.set_status(
                     StatusState::Cancelled,
-                    progress
-                        .message
-                        .unwrap_or_else(|| String::from("Operation cancelled by user.")),
+                    message.unwrap_or_else(|| String::from("Operation cancelled by user.")),
                 );
             }
         }
     }
 
+    /// Post-completion action: sync, unmount and power off the device just used.
+    pub fn eject_device(&mut self) {
+        if self.is_running || self.status_state != StatusState::Complete {
+            return;
+        }
+        let Some(device) = self.selected_device() else {
+            return;
+        };
+        let path = device_path(device);
+        let name = device_display_name(device);
+        info!("Ejecting {path}");
+        match eject_io(&path) {
+            Ok(steps) => {
+                info!("Eject steps: {steps:?}");
+                // Ready, not Complete: the refreshed list may select another device.
+                self.refresh_devices();
+                self.set_status(
+                    StatusState::Ready,
+                    format!("Ejected {name} — safe to remove{}", complete_suffix()),
+                );
+            }
+            Err(e) => {
+                error!("Eject failed: {e}");
+                self.set_status(StatusState::Error, format!("Eject failed: {e}"));
+            }
+        }
+    }
+
     pub fn open_file_picker<B: ratatui::backend::Backend>(
         &mut self,
         terminal: &mut Terminal<B>,
@@ -534,6 +813,10 @@ impl App {
         match self.operation {
             Operation::Flash => self.open_flash_file_picker(terminal),
             Operation::Clone => self.open_clone_output_picker(terminal),
+            Operation::Wipe => {
+                self.cycle_wipe_method();
+                Ok(())
+            }
         }
     }
 
@@ -647,11 +930,9 @@ impl App {
                         filename.pop();
                         error = None;
                     }
-                    KeyCode::Char(c) => {
-                        if !c.is_control() {
-                            filename.push(c);
-                            error = None;
-                        }
+                    KeyCode::Char(c) if !c.is_control() => {
+                        filename.push(c);
+                        error = None;
                     }
                     _ => {}
                 }
@@ -684,6 +965,11 @@ impl App {
                 render_device_picker_dialog(f, self, list_index);
             })?;
 
+            self.check_devices();
+            list_index = list_index.min(self.devices.len().saturating_sub(1));
+            if !event::poll(std::time::Duration::from_millis(100))? {
+                continue;
+            }
             let event = read()?;
             if let Event::Key(key) = event {
                 match key.code {
@@ -691,10 +977,8 @@ impl App {
                     KeyCode::Up => {
                         list_index = list_index.saturating_sub(1);
                     }
-                    KeyCode::Down => {
-                        if list_index + 1 < self.devices.len() {
-                            list_index += 1;
-                        }
+                    KeyCode::Down if list_index + 1 < self.devices.len() => {
+                        list_index += 1;
                     }
                     KeyCode::Char('r') => {
                         self.refresh_devices();
@@ -717,7 +1001,7 @@ fn load_storage_devices(refresh_for_prefill: bool) -> Vec<DeviceInfo> {
     if refresh_for_prefill {
         info!("Refreshing device list for --device pre-fill");
     }
-    liblitho::devices::get_storage_devices().unwrap_or_else(|e| {
+    liblitho::devices::get_storage_devices_with_tables().unwrap_or_else(|e| {
         if refresh_for_prefill {
             log::warn!("Failed to load devices for pre-fill: {e}");
         }
@@ -787,9 +1071,7 @@ fn check_tty() -> io::Result<()> {
 }
 
 fn init_terminal() -> io::Result<TuiTerminal> {
-    if let Err(e) = check_tty() {
-        return Err(e);
-    }
+    check_tty()?;
 
     if let Err(e) = enable_raw_mode() {
         error!("Failed to enable raw mode: {e:?}");
@@ -946,6 +1228,30 @@ pub async fn run_app(terminal: &mut TuiTerminal, app: &mut App) -> io::Result<()
                                 }
                                 _ => {}
                             },
+                            Dialog::UnmountConfirm => match key.code {
+                                KeyCode::Char('y') | KeyCode::Char('Y') => {
+                                    app.confirm_unmount(true);
+                                }
+                                KeyCode::Char('n')
+                                | KeyCode::Char('N')
+                                | KeyCode::Esc
+                                | KeyCode::Enter => {
+                                    app.confirm_unmount(false);
+                                }
+                                _ => {}
+                            },
+                            Dialog::WipeConfirm => match key.code {
+                                KeyCode::Char('y') | KeyCode::Char('Y') => {
+                                    app.confirm_wipe(true);
+                                }
+                                KeyCode::Char('n')
+                                | KeyCode::Char('N')
+                                | KeyCode::Esc
+                                | KeyCode::Enter => {
+                                    app.confirm_wipe(false);
+                                }
+                                _ => {}
+                            },
                             Dialog::None => {}
                         }
                         continue;
@@ -955,12 +1261,21 @@ pub async fn run_app(terminal: &mut TuiTerminal, app: &mut App) -> io::Result<()
                         KeyCode::Char('q') => return Ok(()),
                         KeyCode::Tab => app.next_focus(),
                         KeyCode::BackTab => app.prev_focus(),
-                        KeyCode::Left | KeyCode::Char('1') if app.focus == InputFocus::Mode => {
+                        KeyCode::Char('1') if app.focus == InputFocus::Mode => {
                             app.set_operation(Operation::Flash);
                         }
-                        KeyCode::Right | KeyCode::Char('2') if app.focus == InputFocus::Mode => {
+                        KeyCode::Char('2') if app.focus == InputFocus::Mode => {
                             app.set_operation(Operation::Clone);
                         }
+                        KeyCode::Char('3') if app.focus == InputFocus::Mode => {
+                            app.set_operation(Operation::Wipe);
+                        }
+                        KeyCode::Left if app.focus == InputFocus::Mode => {
+                            app.cycle_operation(-1);
+                        }
+                        KeyCode::Right if app.focus == InputFocus::Mode => {
+                            app.cycle_operation(1);
+                        }
                         KeyCode::Char('d') if app.focus == InputFocus::Device => {
                             app.open_device_picker(terminal)?;
                         }
@@ -984,6 +1299,10 @@ pub async fn run_app(terminal: &mut TuiTerminal, app: &mut App) -> io::Result<()
                             InputFocus::Cancel if app.is_running => app.cancel_operation(),
                             _ => {}
                         },
+                        KeyCode::Char('p') if app.is_running => app.toggle_pause(),
+                        KeyCode::Char('e') if app.status_state == StatusState::Complete => {
+                            app.eject_device();
+                        }
                         KeyCode::Char('c') if app.is_running => app.cancel_operation(),
                         KeyCode::Esc if app.is_running => app.cancel_operation(),
                         _ => {}
@@ -997,5 +1316,82 @@ pub async fn run_app(terminal: &mut TuiTerminal, app: &mut App) -> io::Result<()
         }
 
         app.check_progress();
+        app.check_devices();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tui::helpers::test_devices::device;
+    use liblitho::devices::DeviceEvent;
+    use std::sync::mpsc::Sender;
+
+    /// App flashing `target`, without a device watcher; returns the progress sender
+    /// the worker would hold.
+    fn running_app(target: &str, devices: Vec<DeviceInfo>) -> (App, Sender<OperationProgress>) {
+        let (tx, progress_rx) = mpsc::channel();
+        let app = App {
+            image_file: String::from("/tmp/os.img"),
+            devices,
+            selected_device_index: 0,
+            operation: Operation::Flash,
+            focus: InputFocus::Cancel,
+            is_running: true,
+            progress: 0.0,
+            status_state: StatusState::InProgress,
+            status_detail: String::from("Writing..."),
+            dialog: Dialog::None,
+            progress_rx,
+            operation_control: Arc::new(OperationControl::new()),
+            is_root: true,
+            polkit_available: true,
+            auto_start_pending: false,
+            terminal_warn_logged: false,
+            verify_checksum: false,
+            wipe_method: WipeMethod::default(),
+            pending_release: Vec::new(),
+            device_rx: None,
+            new_devices: Vec::new(),
+            operation_target: Some(target.to_string()),
+            cancel_reason: None,
+        };
+        (app, tx)
+    }
+
+    #[test]
+    fn removing_the_target_cancels_the_running_operation() {
+        let devices = vec![device("/dev/sda", 1), device("/dev/sdX", 1)];
+        let (mut app, tx) = running_app("/dev/sdX", devices);
+        app.apply_device_update(DeviceUpdate {
+            devices: vec![device("/dev/sda", 1)],
+            events: vec![DeviceEvent::Removed(device("/dev/sdX", 1))],
+        });
+
+        assert!(app.operation_control.is_cancelled());
+        assert!(app.status_state == StatusState::InProgress);
+
+        // The worker stops and drops its sender; the reason replaces "cancelled by user".
+        drop(tx);
+        app.check_progress();
+        assert!(!app.is_running);
+        assert!(app.status_state == StatusState::Cancelled);
+        assert_eq!(
+            app.status_detail,
+            "/dev/sdX was removed — operation cancelled."
+        );
+    }
+
+    #[test]
+    fn removing_another_device_leaves_the_operation_running() {
+        let devices = vec![device("/dev/sdX", 1), device("/dev/sdY", 1)];
+        let (mut app, _tx) = running_app("/dev/sdX", devices);
+        app.apply_device_update(DeviceUpdate {
+            devices: vec![device("/dev/sdX", 1)],
+            events: vec![DeviceEvent::Removed(device("/dev/sdY", 1))],
+        });
+
+        assert!(!app.operation_control.is_cancelled());
+        assert!(app.cancel_reason.is_none());
     }
 }
@@ -1,5 +1,6 @@
 use crate::tui::app::Operation;
 use liblitho::devices::DeviceInfo;
+use liblitho::wipe::WipeMethod;
 use ratatui::{
     style::{Color, Modifier, Style},
     text::{Line, Span},
@@ -10,11 +11,18 @@ use std::path::Path;
 const TEXT: Color = Color::Rgb(228, 228, 231);
 const MUTED: Color = Color::Rgb(113, 113, 122);
 const ACCENT: Color = Color::Rgb(96, 165, 250);
+const CYAN: Color = Color::Rgb(34, 211, 238);
 const EMERALD: Color = Color::Rgb(52, 211, 153);
 const AMBER: Color = Color::Rgb(251, 191, 36);
+const RED: Color = Color::Rgb(248, 113, 113);
 
+/// First removable device that can be written to, else the first removable one.
 pub fn default_device_index(devices: &[DeviceInfo]) -> usize {
-    devices.iter().position(|d| d.removable == 1).unwrap_or(0)
+    devices
+        .iter()
+        .position(|d| d.removable == 1 && !d.read_only)
+        .or_else(|| devices.iter().position(|d| d.removable == 1))
+        .unwrap_or(0)
 }
 
 pub fn device_path(device: &DeviceInfo) -> String {
@@ -38,15 +46,67 @@ pub fn device_label(device: &DeviceInfo) -> String {
     } else {
         "(Fixed)"
     };
+    let serial = device
+        .serial
+        .as_deref()
+        .map(|serial| format!(" SN {serial}"))
+        .unwrap_or_default();
     format!(
-        "{} • {} {}",
+        "{}{} • {} {}",
         device_display_name(device),
+        serial,
         format_size(device.size),
         removable
     )
 }
 
-pub fn device_list_entry(device: &DeviceInfo, selected: bool) -> ListItem<'static> {
+/// Transport, serial and USB IDs, to tell identical sticks apart (`usb • SN 4C53… • 0781:5581`).
+pub fn device_identity(device: &DeviceInfo) -> String {
+    let mut parts = Vec::new();
+    if let Some(transport) = device.transport {
+        parts.push(transport.to_string());
+    }
+    if let Some(serial) = &device.serial {
+        parts.push(format!("SN {serial}"));
+    }
+    if let Some(usb) = &device.usb {
+        parts.push(format!("{}:{}", usb.vendor_id, usb.product_id));
+    }
+    if device.read_only {
+        parts.push("read-only".to_string());
+    }
+    parts.join(" • ")
+}
+
+/// What is on the disk (`2 partitions: FAT32 boot 256M, ext4 rootfs 7.2G`), from its
+/// partition table or, when the disk could not be read, udev's partition probe.
+pub fn device_contents(device: &DeviceInfo) -> String {
+    if let Some(table) = &device.partition_table {
+        return table.summary();
+    }
+    let parts: Vec<String> = device
+        .partitions
+        .iter()
+        .map(|partition| {
+            let mut part = partition
+                .fs_type
+                .clone()
+                .unwrap_or_else(|| "unknown".to_string());
+            if let Some(label) = &partition.label {
+                part.push_str(&format!(" {label}"));
+            }
+            format!("{part} {}", format_size(partition.size_bytes / 512))
+        })
+        .collect();
+    match parts.len() {
+        0 => "No partition table found".to_string(),
+        1 => format!("1 partition: {}", parts[0]),
+        n => format!("{n} partitions: {}", parts.join(", ")),
+    }
+}
+
+/// One picker row; `new` tags a removable device plugged in since launch.
+pub fn device_list_entry(device: &DeviceInfo, selected: bool, new: bool) -> ListItem<'static> {
     let path = device_path(device);
     let removable = if device.removable == 1 {
         Span::styled(" Removable", Style::default().fg(EMERALD))
@@ -70,6 +130,22 @@ pub fn device_list_entry(device: &DeviceInfo, selected: bool) -> ListItem<'stati
         ),
         Span::styled(format_size(device.size), Style::default().fg(MUTED)),
         removable,
+        if device.read_only {
+            Span::styled(
+                " WRITE-PROTECTED",
+                Style::default().fg(RED).add_modifier(Modifier::BOLD),
+            )
+        } else {
+            Span::raw("")
+        },
+        if new {
+            Span::styled(
+                " NEW",
+                Style::default().fg(CYAN).add_modifier(Modifier::BOLD),
+            )
+        } else {
+            Span::raw("")
+        },
     ]))
 }
 
@@ -114,6 +190,7 @@ pub fn file_path_hint(path: &str, operation: Operation, max_chars: usize) -> Str
             .parent()
             .map(|parent| truncate_end(&parent.display().to_string(), max_chars))
             .unwrap_or_default(),
+        Operation::Wipe => String::new(),
     }
 }
 
@@ -121,12 +198,90 @@ pub fn file_section_label(operation: Operation) -> &'static str {
     match operation {
         Operation::Flash => "SOURCE FILE",
         Operation::Clone => "OUTPUT FILE",
+        Operation::Wipe => "WIPE METHOD",
+    }
+}
+
+pub fn wipe_method_description(method: WipeMethod) -> &'static str {
+    match method {
+        WipeMethod::Quick => "Partition tables and signatures at both ends",
+        WipeMethod::Zero => "Overwrite the whole device with zeros",
+        WipeMethod::Random => "Overwrite the whole device with random data",
+        WipeMethod::Discard => "TRIM every block (SSD, eMMC, SD)",
+    }
+}
+
+/// The wipe method after `current` in [`WipeMethod::ALL`], wrapping around.
+pub fn next_wipe_method(current: WipeMethod) -> WipeMethod {
+    let all = WipeMethod::ALL;
+    let index = all.iter().position(|m| *m == current).unwrap_or(0);
+    all[(index + 1) % all.len()]
+}
+
+pub fn format_size(size: u64) -> String {
+    const SECTOR_SIZE: u64 = 512;
+    let bytes = size * SECTOR_SIZE;
+
+    if bytes >= 1_000_000_000_000 {
+        format!("{:.2} TB", bytes as f64 / 1_000_000_000_000.0)
+    } else if bytes >= 1_000_000_000 {
+        format!("{:.2} GB", bytes as f64 / 1_000_000_000.0)
+    } else if bytes >= 1_000_000 {
+        format!("{:.2} MB", bytes as f64 / 1_000_000.0)
+    } else if bytes >= 1_000 {
+        format!("{:.2} KB", bytes as f64 / 1_000.0)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test_devices {
+    use super::*;
+
+    /// Empty disk at `name` with no partitions, for the TUI tests.
+    pub fn device(name: &str, removable: u8) -> DeviceInfo {
+        DeviceInfo {
+            device_name: name.to_string(),
+            vendor_name: String::new(),
+            model_name: String::new(),
+            removable,
+            size: 0,
+            size_bytes: 0,
+            serial: None,
+            wwn: None,
+            transport: None,
+            usb: None,
+            read_only: false,
+            logical_sector_size: 512,
+            physical_sector_size: 512,
+            by_id: Vec::new(),
+            partitions: Vec::new(),
+            partition_table: None,
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::test_devices::device;
     use super::*;
+    use liblitho::devices::PartitionInfo;
+
+    #[test]
+    fn device_contents_falls_back_to_udev_partitions() {
+        let mut device = device("/dev/sdb", 1);
+        device.partitions.push(PartitionInfo {
+            path: "/dev/sdb1".to_string(),
+            number: 1,
+            size_bytes: 268435456,
+            fs_type: Some("vfat".to_string()),
+            label: Some("boot".to_string()),
+            uuid: None,
+            mountpoint: None,
+        });
+        assert_eq!(device_contents(&device), "1 partition: vfat boot 268.44 MB");
+    }
 
     #[test]
     fn truncate_end_short_string_unchanged() {
@@ -149,21 +304,27 @@ mod tests {
         let hint = file_path_hint("/home/user/image.img", Operation::Flash, 40);
         assert_eq!(hint, "/home/user/image.img");
     }
-}
 
-pub fn format_size(size: u64) -> String {
-    const SECTOR_SIZE: u64 = 512;
-    let bytes = size * SECTOR_SIZE;
+    #[test]
+    fn next_wipe_method_cycles_through_all() {
+        let mut method = WipeMethod::Quick;
+        for expected in [
+            WipeMethod::Zero,
+            WipeMethod::Random,
+            WipeMethod::Discard,
+            WipeMethod::Quick,
+        ] {
+            method = next_wipe_method(method);
+            assert_eq!(method, expected);
+        }
+    }
 
-    if bytes >= 1_000_000_000_000 {
-        format!("{:.2} TB", bytes as f64 / 1_000_000_000_000.0)
-    } else if bytes >= 1_000_000_000 {
-        format!("{:.2} GB", bytes as f64 / 1_000_000_000.0)
-    } else if bytes >= 1_000_000 {
-        format!("{:.2} MB", bytes as f64 / 1_000_000.0)
-    } else if bytes >= 1_000 {
-        format!("{:.2} KB", bytes as f64 / 1_000.0)
-    } else {
-        format!("{} B", bytes)
+    #[test]
+    fn default_device_index_skips_write_protected_cards() {
+        let mut card = device("/dev/mmcblk0", 1);
+        card.read_only = true;
+        let devices = [device("/dev/nvme0n1", 0), card, device("/dev/sdb", 1)];
+        assert_eq!(default_device_index(&devices), 2);
+        assert_eq!(default_device_index(&devices[..2]), 1);
     }
 }
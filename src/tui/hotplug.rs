@@ -0,0 +1,161 @@
+//! Background hotplug monitoring for the device list.
+
+use crate::tui::helpers::default_device_index;
+use liblitho::devices::{watch, DeviceEvent, DeviceInfo};
+use log::{info, warn};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+
+/// Longest single wait on the watcher; the thread exits on the first update after
+/// the TUI has dropped its receiver.
+const WAIT: Duration = Duration::from_secs(1);
+
+/// The device list after a hotplug change, with the changes that led to it.
+pub struct DeviceUpdate {
+    pub devices: Vec<DeviceInfo>,
+    pub events: Vec<DeviceEvent>,
+}
+
+/// Watch devices on a background thread; `None` when monitoring is unavailable
+/// (the `r` key still refreshes by hand).
+pub fn spawn_device_watcher() -> Option<Receiver<DeviceUpdate>> {
+    let mut watcher = match watch() {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            warn!("Device hotplug monitoring disabled: {e:#}");
+            return None;
+        }
+    };
+    info!("Watching devices via {}", watcher.backend());
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || loop {
+        match watcher.wait(WAIT) {
+            Ok(events) if events.is_empty() => {}
+            Ok(events) => {
+                let update = DeviceUpdate {
+                    devices: watcher.devices().to_vec(),
+                    events,
+                };
+                if tx.send(update).is_err() {
+                    break;
+                }
+            }
+            Err(e) => {
+                warn!("Device hotplug monitoring stopped: {e:#}");
+                break;
+            }
+        }
+    });
+    Some(rx)
+}
+
+/// Index of `selected` in the new list, or the default pick when it is gone.
+pub fn reselect_device_index(selected: Option<&str>, devices: &[DeviceInfo]) -> usize {
+    selected
+        .and_then(|path| devices.iter().position(|d| d.device_name == path))
+        .unwrap_or_else(|| default_device_index(devices))
+}
+
+/// Keep the partition tables of `old` entries the events leave untouched, and read
+/// them with `read_table` only for added or changed disks.
+pub fn carry_partition_tables<R>(old: &[DeviceInfo], update: &mut DeviceUpdate, mut read_table: R)
+where
+    R: FnMut(&mut DeviceInfo),
+{
+    for device in &mut update.devices {
+        let changed = update.events.iter().any(|event| {
+            !matches!(event, DeviceEvent::Removed(_))
+                && event.device().device_name == device.device_name
+        });
+        match old.iter().find(|d| d.device_name == device.device_name) {
+            Some(previous) if !changed => device.partition_table = previous.partition_table.clone(),
+            _ => read_table(device),
+        }
+    }
+}
+
+/// Paths of removable devices that were plugged in.
+pub fn inserted_removable(events: &[DeviceEvent]) -> Vec<String> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            DeviceEvent::Added(device) if device.removable == 1 => Some(device.device_name.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// True when `path` is among the removed devices.
+pub fn was_removed(events: &[DeviceEvent], path: &str) -> bool {
+    events
+        .iter()
+        .any(|event| matches!(event, DeviceEvent::Removed(device) if device.device_name == path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tui::helpers::test_devices::device;
+    use liblitho::partitions::{PartitionTable, PartitionTableKind};
+
+    #[test]
+    fn reselect_keeps_selection_by_path() {
+        let devices = vec![
+            device("/dev/sda", 0),
+            device("/dev/sdb", 1),
+            device("/dev/sdc", 1),
+        ];
+        assert_eq!(reselect_device_index(Some("/dev/sdc"), &devices), 2);
+        assert_eq!(reselect_device_index(Some("/dev/sdd"), &devices), 1);
+        assert_eq!(reselect_device_index(None, &devices[..1]), 0);
+    }
+
+    #[test]
+    fn inserted_and_removed_devices_from_events() {
+        let events = vec![
+            DeviceEvent::Removed(device("/dev/sdb", 1)),
+            DeviceEvent::Added(device("/dev/sdc", 1)),
+            DeviceEvent::Added(device("/dev/nvme1n1", 0)),
+            DeviceEvent::Changed(device("/dev/sdd", 1)),
+        ];
+        assert_eq!(inserted_removable(&events), vec!["/dev/sdc".to_string()]);
+        assert!(was_removed(&events, "/dev/sdb"));
+        assert!(!was_removed(&events, "/dev/sdd"));
+    }
+
+    #[test]
+    fn partition_tables_are_read_only_for_new_or_changed_disks() {
+        let table = PartitionTable {
+            kind: PartitionTableKind::Mbr,
+            sector_size: 512,
+            partitions: Vec::new(),
+            gpt: None,
+            gpt_entries: Vec::new(),
+            warnings: Vec::new(),
+        };
+        let mut old = vec![device("/dev/sda", 0), device("/dev/sdb", 1)];
+        for device in &mut old {
+            device.partition_table = Some(table.clone());
+        }
+        let mut update = DeviceUpdate {
+            devices: vec![
+                device("/dev/sda", 0),
+                device("/dev/sdb", 1),
+                device("/dev/sdc", 1),
+            ],
+            events: vec![
+                DeviceEvent::Changed(device("/dev/sdb", 1)),
+                DeviceEvent::Added(device("/dev/sdc", 1)),
+            ],
+        };
+
+        let mut read = Vec::new();
+        carry_partition_tables(&old, &mut update, |device| {
+            read.push(device.device_name.clone())
+        });
+        assert_eq!(read, ["/dev/sdb", "/dev/sdc"]);
+        assert_eq!(update.devices[0].partition_table, Some(table));
+        assert_eq!(update.devices[1].partition_table, None);
+    }
+}
@@ -1,10 +1,11 @@
 use clap::Parser;
+use liblitho::wipe::WipeMethod;
 use std::path::PathBuf;
 
 #[derive(Parser, Debug, Clone)]
 #[command(name = "litho-tui", about = "Interactive terminal UI for litho")]
 pub struct TuiCli {
-    /// flash or clone
+    /// flash, clone or wipe
     #[arg(short, long)]
     pub mode: Option<String>,
 
@@ -20,6 +21,10 @@ pub struct TuiCli {
     #[arg(short, long)]
     pub file: Option<String>,
 
+    /// wipe method for --mode wipe: quick, zero, random or discard
+    #[arg(long, value_parser = str::parse::<WipeMethod>)]
+    pub method: Option<WipeMethod>,
+
     /// begin operation immediately (never set by pkexec re-launch)
     #[arg(long)]
     pub start: bool,
@@ -38,6 +43,7 @@ pub struct LaunchParams {
     pub mode: Option<String>,
     pub device: Option<String>,
     pub image: Option<String>,
+    pub wipe_method: Option<WipeMethod>,
     pub start: bool,
 }
 
@@ -49,6 +55,7 @@ impl From<TuiCli> for LaunchParams {
             mode,
             device: cli.device,
             image,
+            wipe_method: cli.method,
             start: cli.start,
         }
     }
@@ -58,6 +65,8 @@ pub fn normalize_mode(mode: &str) -> String {
     let lower = mode.to_lowercase();
     if lower == "clone" || lower == "backup" {
         "clone".to_string()
+    } else if lower == "wipe" || lower == "erase" {
+        "wipe".to_string()
     } else {
         "flash".to_string()
     }
@@ -79,6 +88,24 @@ mod tests {
         assert_eq!(normalize_mode("flash"), "flash");
     }
 
+    #[test]
+    fn tui_cli_parses_wipe_mode_and_method() {
+        let cli = TuiCli::try_parse_from([
+            "litho-tui",
+            "--mode",
+            "erase",
+            "--device",
+            "/dev/sdb",
+            "--method",
+            "zero",
+        ])
+        .expect("parse");
+        let launch: LaunchParams = cli.into();
+        assert_eq!(launch.mode.as_deref(), Some("wipe"));
+        assert_eq!(launch.wipe_method, Some(WipeMethod::Zero));
+        assert!(TuiCli::try_parse_from(["litho-tui", "--method", "shred"]).is_err());
+    }
+
     #[test]
     fn tui_cli_parses_launch_args() {
         let cli = TuiCli::try_parse_from([
@@ -37,8 +37,7 @@ pub fn compute_layout(area: Rect) -> UiLayout {
     let panel_width = area
         .width
         .saturating_sub(2)
-        .min(PANEL_WIDTH_FULL)
-        .max(MIN_COLS - 2);
+        .clamp(MIN_COLS - 2, PANEL_WIDTH_FULL);
 
     let main_card_height = if compact {
         area.height
@@ -47,8 +46,7 @@ pub fn compute_layout(area: Rect) -> UiLayout {
     } else {
         area.height
             .saturating_sub(HEADER_HEIGHT + FOOTER_HEIGHT + 4)
-            .min(52)
-            .max(20)
+            .clamp(20, 52)
     };
 
     let panel_height = HEADER_HEIGHT + main_card_height + FOOTER_HEIGHT + 1;
@@ -6,21 +6,12 @@ use std::path::{Path, PathBuf};
 /// Maximum log file size before rotation (5 MiB).
 const MAX_LOG_BYTES: u64 = 5 * 1024 * 1024;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct LogOptions {
     pub log_file: Option<PathBuf>,
     pub log_level: Option<String>,
 }
 
-impl Default for LogOptions {
-    fn default() -> Self {
-        Self {
-            log_file: None,
-            log_level: None,
-        }
-    }
-}
-
 fn default_log_path() -> PathBuf {
     dirs_fallback().join("litho").join("litho-tui.log")
 }
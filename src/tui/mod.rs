@@ -1,5 +1,6 @@
 pub mod app;
 pub mod helpers;
+pub mod hotplug;
 pub mod launch;
 pub mod layout;
 pub mod logging;
@@ -1,61 +1,90 @@
 use crate::tui::app::Operation;
-use liblitho::io_backend::{clone_io, flash_io, USES_SIMULATED_IO};
-use liblitho::progress::{is_operation_cancelled, OperationPhase, OperationProgress};
-use log::info;
-use std::sync::atomic::{AtomicBool, Ordering};
+use liblitho::cache::ImageCache;
+use liblitho::io_backend::{clone_io, flash_io, wipe_io, USES_SIMULATED_IO};
+use liblitho::progress::{
+    is_operation_cancelled, OperationControl, OperationPhase, OperationProgress,
+};
+use liblitho::range::IoRange;
+use liblitho::wipe::WipeMethod;
+use log::{info, warn};
 use std::sync::mpsc::Sender;
 use std::sync::Arc;
 
+#[allow(clippy::too_many_arguments)]
 pub fn spawn_operation(
     operation: Operation,
     device_path: String,
     image_path: String,
+    wipe_method: WipeMethod,
     block_size: usize,
     verify: bool,
-    cancel: Arc<AtomicBool>,
+    control: Arc<OperationControl>,
     tx: Sender<OperationProgress>,
 ) {
     tokio::task::spawn_blocking(move || {
         let op_name = match operation {
             Operation::Flash => "flash",
             Operation::Clone => "clone",
+            Operation::Wipe => "wipe",
         };
 
+        let source = match operation {
+            Operation::Wipe => format!("method={wipe_method}"),
+            _ => format!("image={image_path}"),
+        };
         if USES_SIMULATED_IO {
             info!(
-                "Starting simulated {op_name}: device={device_path}, image={image_path}, block_size={block_size}"
+                "Starting simulated {op_name}: device={device_path}, {source}, block_size={block_size}"
             );
         } else {
-            info!(
-                "Starting {op_name}: device={device_path}, image={image_path}, block_size={block_size}"
-            );
+            info!("Starting {op_name}: device={device_path}, {source}, block_size={block_size}");
         }
 
         let on_progress = |progress: OperationProgress| {
-            if cancel.load(Ordering::Relaxed) {
+            if control.is_cancelled() {
                 return;
             }
             let _ = tx.send(progress);
         };
 
-        let cancel_ref = Some(cancel.as_ref());
+        let cache = match ImageCache::open_default() {
+            Ok(cache) => cache,
+            Err(e) => {
+                warn!("Image cache disabled: {e:#}");
+                None
+            }
+        };
+        let control_ref = Some(control.as_ref());
         let result = match operation {
             Operation::Flash => flash_io(
                 &image_path,
                 &device_path,
                 block_size,
+                IoRange::default(),
                 false,
                 verify,
+                None,
+                cache.as_ref(),
                 Some(on_progress),
-                cancel_ref,
+                control_ref,
             ),
             Operation::Clone => clone_io(
                 &device_path,
                 &image_path,
                 block_size,
+                IoRange::default(),
+                false,
+                Some(on_progress),
+                control_ref,
+            ),
+            Operation::Wipe => wipe_io(
+                &device_path,
+                wipe_method,
+                block_size,
                 false,
+                verify,
                 Some(on_progress),
-                cancel_ref,
+                control_ref,
             ),
         };
 
@@ -68,6 +97,9 @@ pub fn spawn_operation(
                     Operation::Clone => {
                         "Clone cancelled — incomplete output file removed.".to_string()
                     }
+                    Operation::Wipe => {
+                        "Wipe cancelled — device may be partially erased.".to_string()
+                    }
                 };
                 info!("{op_name} cancelled");
                 let _ = tx.send(
@@ -89,27 +89,37 @@ fn preserve_terminal_env(cmd: &mut Command) {
     }
 }
 
-fn elevated_args(mode: &str, device: &str, image: &str) -> [String; 6] {
-    [
+fn elevated_args(mode: &str, device: &str, image: &str, method: Option<&str>) -> Vec<String> {
+    let mut args = vec![
         "--mode".to_string(),
         mode.to_string(),
         "--device".to_string(),
         device.to_string(),
-        "--image".to_string(),
-        image.to_string(),
-    ]
+    ];
+    if !image.is_empty() {
+        args.extend(["--image".to_string(), image.to_string()]);
+    }
+    if let Some(method) = method {
+        args.extend(["--method".to_string(), method.to_string()]);
+    }
+    args
 }
 
 /// Replace the current process with an elevated litho-tui instance.
 ///
 /// Never passes `--start`. On success this function does not return.
 #[cfg(unix)]
-pub fn relaunch_elevated(mode: &str, device: &str, image: &str) -> Result<(), String> {
+pub fn relaunch_elevated(
+    mode: &str,
+    device: &str,
+    image: &str,
+    method: Option<&str>,
+) -> Result<(), String> {
     use std::os::unix::process::CommandExt;
 
     let exe =
         std::env::current_exe().map_err(|e| format!("Could not resolve executable: {}", e))?;
-    let args = elevated_args(mode, device, image);
+    let args = elevated_args(mode, device, image, method);
 
     let mut cmd = if is_running_as_root() {
         let mut cmd = Command::new(&exe);
@@ -135,10 +145,15 @@ pub fn relaunch_elevated(mode: &str, device: &str, image: &str) -> Result<(), St
 
 /// Non-Unix fallback: spawn and let the caller exit.
 #[cfg(not(unix))]
-pub fn relaunch_elevated(mode: &str, device: &str, image: &str) -> Result<(), String> {
+pub fn relaunch_elevated(
+    mode: &str,
+    device: &str,
+    image: &str,
+    method: Option<&str>,
+) -> Result<(), String> {
     let exe =
         std::env::current_exe().map_err(|e| format!("Could not resolve executable: {}", e))?;
-    let args = elevated_args(mode, device, image);
+    let args = elevated_args(mode, device, image, method);
 
     let mut cmd = if is_running_as_root() {
         let mut cmd = Command::new(&exe);
@@ -172,4 +187,16 @@ mod tests {
             assert!(pkexec_on_path());
         }
     }
+
+    #[test]
+    fn elevated_args_omit_empty_image_and_pass_wipe_method() {
+        assert_eq!(
+            elevated_args("wipe", "/dev/sdb", "", Some("zero")),
+            ["--mode", "wipe", "--device", "/dev/sdb", "--method", "zero"]
+        );
+        assert_eq!(
+            elevated_args("flash", "/dev/sdb", "/tmp/os.img", None),
+            ["--mode", "flash", "--device", "/dev/sdb", "--image", "/tmp/os.img"]
+        );
+    }
 }
@@ -1,7 +1,7 @@
 use crate::tui::app::{App, Dialog, InputFocus, Operation, StatusState};
 use crate::tui::helpers::{
-    device_label, device_list_entry, device_path, file_basename, file_path_hint,
-    file_section_label, format_size, truncate_end,
+    device_contents, device_identity, device_label, device_list_entry, device_path, file_basename,
+    file_path_hint, file_section_label, format_size, truncate_end, wipe_method_description,
 };
 use crate::tui::layout::{
     centered_rect, compute_layout, main_card_constraints, MIN_COLS, MIN_ROWS, PANEL_WIDTH_FULL,
@@ -49,6 +49,8 @@ pub fn ui(f: &mut Frame, app: &App) {
             render_confirmation_dialog(f, app, target_index);
         }
         Dialog::ElevationConfirm => render_elevation_dialog(f),
+        Dialog::WipeConfirm => render_wipe_confirm_dialog(f, app),
+        Dialog::UnmountConfirm => render_unmount_confirm_dialog(f, app),
     }
 }
 
@@ -141,7 +143,7 @@ fn render_main_card(f: &mut Frame, app: &App, area: Rect, compact: bool) {
         area,
     );
 
-    let include_verify = app.operation == Operation::Flash;
+    let include_verify = app.verify_applies();
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .margin(1)
@@ -176,7 +178,10 @@ fn render_verify_option(f: &mut Frame, app: &App, area: Rect) {
     let focused = app.focus == InputFocus::Verify;
     let border = if focused { CYAN } else { BORDER };
     let marker = if app.verify_checksum { "[x]" } else { "[ ]" };
-    let label = format!("{marker} Verify checksum after write");
+    let label = match app.operation {
+        Operation::Wipe => format!("{marker} Read back and verify wiped area"),
+        _ => format!("{marker} Verify checksum after write"),
+    };
 
     let block = Block::default()
         .borders(Borders::ALL)
@@ -200,11 +205,16 @@ fn render_mode_cards(f: &mut Frame, app: &App, area: Rect) {
     let cols = Layout::default()
         .direction(Direction::Horizontal)
         .spacing(1)
-        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .constraints([
+            Constraint::Ratio(1, 3),
+            Constraint::Ratio(1, 3),
+            Constraint::Ratio(1, 3),
+        ])
         .split(area);
 
     let flash_active = app.operation == Operation::Flash;
     let clone_active = app.operation == Operation::Clone;
+    let wipe_active = app.operation == Operation::Wipe;
     let mode_focused = app.focus == InputFocus::Mode;
 
     render_mode_card(
@@ -214,7 +224,7 @@ fn render_mode_cards(f: &mut Frame, app: &App, area: Rect) {
         "Write image to device",
         flash_active,
         mode_focused,
-        "1/←",
+        "1",
     );
     render_mode_card(
         f,
@@ -223,7 +233,16 @@ fn render_mode_cards(f: &mut Frame, app: &App, area: Rect) {
         "Create image from device",
         clone_active,
         mode_focused,
-        "2/→",
+        "2",
+    );
+    render_mode_card(
+        f,
+        cols[2],
+        "Wipe Disk",
+        "Erase device",
+        wipe_active,
+        mode_focused,
+        "3",
     );
 }
 
@@ -239,7 +258,7 @@ fn render_mode_card(
     let border_color = if active {
         ACCENT
     } else if section_focused {
-        BORDER
+        MUTED
     } else {
         BORDER
     };
@@ -328,6 +347,18 @@ fn render_device_info(f: &mut Frame, app: &App, area: Rect) {
         )));
     }
 
+    // Last so the compact layout keeps the hint and drops this line first.
+    if let Some(identity) = app
+        .selected_device()
+        .map(device_identity)
+        .filter(|identity| !identity.is_empty())
+    {
+        lines.push(Line::from(Span::styled(
+            format!(" {}", identity),
+            Style::default().fg(MUTED),
+        )));
+    }
+
     f.render_widget(Paragraph::new(lines), area);
 }
 
@@ -349,7 +380,15 @@ fn render_file_select(f: &mut Frame, app: &App, area: Rect) {
     let max_name_chars = inner.width.saturating_sub(3) as usize;
     let max_hint_chars = inner.width as usize;
 
-    let (name, path_hint) = if app.image_file.is_empty() {
+    let (name, path_hint) = if app.operation == Operation::Wipe {
+        (
+            app.wipe_method.to_string(),
+            format!(
+                "{} · Enter/f to change",
+                wipe_method_description(app.wipe_method)
+            ),
+        )
+    } else if app.image_file.is_empty() {
         match app.operation {
             Operation::Flash => (
                 "No file selected".to_string(),
@@ -359,6 +398,7 @@ fn render_file_select(f: &mut Frame, app: &App, area: Rect) {
                 "No output file set".to_string(),
                 "Enter/f to choose save location and name".to_string(),
             ),
+            Operation::Wipe => unreachable!("wipe mode has no file"),
         }
     } else {
         (
@@ -367,9 +407,13 @@ fn render_file_select(f: &mut Frame, app: &App, area: Rect) {
         )
     };
 
+    let icon = match app.operation {
+        Operation::Wipe => "⌫ ",
+        _ => "📄 ",
+    };
     let lines = vec![
         Line::from(vec![
-            Span::styled("📄 ", Style::default().fg(ACCENT)),
+            Span::styled(icon, Style::default().fg(ACCENT)),
             Span::styled(
                 truncate_end(&name, max_name_chars),
                 focus_style(focused),
@@ -440,6 +484,7 @@ fn render_controls(f: &mut Frame, app: &App, area: Rect) {
     let start_label = match app.operation {
         Operation::Flash => "▶  START FLASH",
         Operation::Clone => "▶  START CLONE",
+        Operation::Wipe => "▶  START WIPE",
     };
 
     let start_focused = app.focus == InputFocus::Start;
@@ -500,7 +545,7 @@ fn render_footer(f: &mut Frame, area: Rect, show_shortcut_hints: bool) {
     if show_shortcut_hints {
         spans.push(Span::raw("   │   "));
         spans.push(Span::styled(
-            "Tab · q quit · Enter start",
+            "Tab · q quit · Enter start · p pause",
             Style::default().fg(MUTED),
         ));
     }
@@ -519,7 +564,7 @@ pub fn render_device_picker_dialog(f: &mut Frame, app: &App, list_index: usize)
 
     let max_list_rows = f.area().height.saturating_sub(8).max(3) as usize;
     let estimated_visible = app.devices.len().clamp(1, max_list_rows);
-    let dialog_height = (estimated_visible as u16 + 5).min(f.area().height.saturating_sub(2));
+    let dialog_height = (estimated_visible as u16 + 6).min(f.area().height.saturating_sub(2));
 
     let area = centered_rect(dialog_width, dialog_height, f.area());
     f.render_widget(Clear, area);
@@ -540,7 +585,11 @@ pub fn render_device_picker_dialog(f: &mut Frame, app: &App, list_index: usize)
 
     let chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Min(1), Constraint::Length(1)])
+        .constraints([
+            Constraint::Min(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
         .split(inner);
 
     let visible_count = chunks[0].height.max(1) as usize;
@@ -556,17 +605,32 @@ pub fn render_device_picker_dialog(f: &mut Frame, app: &App, list_index: usize)
         .enumerate()
         .skip(scroll)
         .take(visible_count)
-        .map(|(i, device)| device_list_entry(device, i == list_index))
+        .map(|(i, device)| {
+            device_list_entry(
+                device,
+                i == list_index,
+                app.new_devices.contains(&device.device_name),
+            )
+        })
         .collect();
 
     let list = List::new(items).style(Style::default().bg(CARD_BG));
     f.render_widget(list, chunks[0]);
 
+    // What would be overwritten on the highlighted disk.
+    if let Some(device) = app.devices.get(list_index) {
+        f.render_widget(
+            Paragraph::new(format!(" {}", device_contents(device)))
+                .style(Style::default().fg(TEXT)),
+            chunks[1],
+        );
+    }
+
     f.render_widget(
         Paragraph::new("↑↓ navigate · Enter select · r refresh · Esc cancel")
             .style(Style::default().fg(MUTED))
             .alignment(Alignment::Center),
-        chunks[1],
+        chunks[2],
     );
 }
 
@@ -576,6 +640,7 @@ pub fn render_file_picker_hint(f: &mut Frame, operation: Operation) {
         Operation::Clone => {
             "Navigate to output folder · Enter/n: name new file · →: open folder · Esc: cancel"
         }
+        Operation::Wipe => return,
     };
 
     let area = Rect {
@@ -649,7 +714,7 @@ fn render_confirmation_dialog(f: &mut Frame, app: &App, target_index: usize) {
     let device_name = app
         .devices
         .get(target_index)
-        .map(|d| device_label(d))
+        .map(device_label)
         .unwrap_or_else(|| "unknown device".to_string());
 
     let block = Block::default()
@@ -676,6 +741,87 @@ fn render_confirmation_dialog(f: &mut Frame, app: &App, target_index: usize) {
     f.render_widget(Paragraph::new(text).wrap(Wrap { trim: true }), inner);
 }
 
+fn render_unmount_confirm_dialog(f: &mut Frame, app: &App) {
+    let max_actions = f.area().height.saturating_sub(8).max(1) as usize;
+    let shown = app.pending_release.len().min(max_actions);
+    let area = centered_rect(62, shown as u16 + 6, f.area());
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(AMBER))
+        .style(Style::default().bg(CARD_BG))
+        .title(" Target in use ");
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let mut text = vec![Line::from(Span::styled(
+        "The target is mounted or used as swap. Release it first:",
+        Style::default().fg(TEXT),
+    ))];
+    text.extend(app.pending_release.iter().take(shown).map(|action| {
+        Line::from(Span::styled(
+            format!("  • {action}"),
+            Style::default().fg(AMBER),
+        ))
+    }));
+    if app.pending_release.len() > shown {
+        text.push(Line::from(Span::styled(
+            format!("  … and {} more", app.pending_release.len() - shown),
+            Style::default().fg(MUTED),
+        )));
+    }
+    text.push(Line::from(""));
+    text.push(Line::from(Span::styled(
+        "Unmount and continue?  [Y] yes   [N]/Esc no",
+        Style::default().fg(MUTED),
+    )));
+    f.render_widget(Paragraph::new(text), inner);
+}
+
+fn render_wipe_confirm_dialog(f: &mut Frame, app: &App) {
+    let area = centered_rect(58, 8, f.area());
+    f.render_widget(Clear, area);
+
+    let device_name = app
+        .selected_device()
+        .map(device_label)
+        .unwrap_or_else(|| "unknown device".to_string());
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(RED))
+        .style(Style::default().bg(CARD_BG))
+        .title(" Confirm wipe ");
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let text = vec![
+        Line::from(Span::styled(
+            "Erase all data on this device? This cannot be undone.",
+            Style::default().fg(TEXT),
+        )),
+        Line::from(Span::styled(device_name, Style::default().fg(RED))),
+        Line::from(Span::styled(
+            format!(
+                "Method: {} — {}",
+                app.wipe_method,
+                wipe_method_description(app.wipe_method)
+            ),
+            Style::default().fg(MUTED),
+        )),
+        Line::from(Span::styled(
+            "Wipe?  [Y] yes   [N]/Esc no",
+            Style::default().fg(MUTED),
+        )),
+    ];
+    f.render_widget(Paragraph::new(text).wrap(Wrap { trim: true }), inner);
+}
+
 pub fn render_elevation_dialog(f: &mut Frame) {
     let area = centered_rect(58, 9, f.area());
     f.render_widget(Clear, area);
@@ -692,7 +838,7 @@ pub fn render_elevation_dialog(f: &mut Frame) {
 
     let text = vec![
         Line::from(Span::styled(
-            "Flash and clone operations require root access.",
+            "Flash, clone and wipe operations require root access.",
             Style::default().fg(TEXT),
         )),
         Line::from(Span::styled(
@@ -738,6 +884,7 @@ fn status_color(state: StatusState) -> Color {
     match state {
         StatusState::Ready => EMERALD,
         StatusState::InProgress => AMBER,
+        StatusState::Paused => CYAN,
         StatusState::Complete => EMERALD,
         StatusState::Cancelled => ORANGE,
         StatusState::Error => RED,
@@ -748,6 +895,7 @@ fn status_label(state: StatusState) -> &'static str {
     match state {
         StatusState::Ready => "Ready",
         StatusState::InProgress => "IN PROGRESS",
+        StatusState::Paused => "PAUSED",
         StatusState::Complete => "COMPLETE",
         StatusState::Cancelled => "CANCELLED",
         StatusState::Error => "ERROR",
@@ -0,0 +1,526 @@
+//! Device erase: quick signature wipe, zero / random fill, or whole-device discard.
+
+use crate::devices;
+use crate::partitions;
+use crate::platform::{DeviceWriter, DiscardMethod, PlatformDevice};
+use crate::progress::{
+    check_cancel, emit_progress, OperationControl, OperationPhase, OperationProgress,
+};
+use anyhow::{bail, Context, Result};
+use log::{debug, info, warn};
+use std::fmt;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::str::FromStr;
+
+/// Bytes zeroed at the start and end of the device (and of each partition) by a quick wipe.
+///
+/// Covers MBR / GPT (primary and backup), and the superblocks and labels of common
+/// filesystems and RAID / LVM / ZFS metadata kept near either end.
+pub const QUICK_WIPE_BYTES: u64 = 1024 * 1024;
+
+/// Largest single discard request, so cancel is checked between requests.
+const DISCARD_CHUNK: u64 = 1024 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WipeMethod {
+    /// Zero the partition tables and filesystem signatures at both ends (and each partition start).
+    #[default]
+    Quick,
+    /// Overwrite the whole device with zeros.
+    Zero,
+    /// Overwrite the whole device with pseudo-random data.
+    Random,
+    /// `BLKDISCARD` the whole device (SSD / eMMC / SD); contents afterwards are unspecified.
+    Discard,
+}
+
+impl WipeMethod {
+    pub const ALL: [WipeMethod; 4] = [Self::Quick, Self::Zero, Self::Random, Self::Discard];
+
+    /// Discard gives no guarantee about what reads back, so it cannot be verified.
+    pub fn supports_verify(&self) -> bool {
+        *self != Self::Discard
+    }
+}
+
+impl fmt::Display for WipeMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Quick => "quick",
+            Self::Zero => "zero",
+            Self::Random => "random",
+            Self::Discard => "discard",
+        })
+    }
+}
+
+impl FromStr for WipeMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "quick" => Ok(Self::Quick),
+            "zero" | "zeros" => Ok(Self::Zero),
+            "random" => Ok(Self::Random),
+            "discard" | "trim" => Ok(Self::Discard),
+            other => Err(format!(
+                "Unknown wipe method {other:?} (expected quick, zero, random or discard)"
+            )),
+        }
+    }
+}
+
+/// Erase the device at `device_path` with `method`.
+///
+/// With `verify`, the wiped areas are read back and compared (zeros, or the
+/// regenerated random stream). Progress is reported as Writing (0–90% when
+/// verifying) then Verifying.
+pub fn wipe<F>(
+    device_path: String,
+    method: WipeMethod,
+    block_size: usize,
+    silent: bool,
+    verify: bool,
+    mut progress: Option<F>,
+    control: Option<&OperationControl>,
+) -> Result<()>
+where
+    F: FnMut(OperationProgress),
+{
+    if verify && !method.supports_verify() {
+        bail!("A {} wipe cannot be verified; use the zero method instead", method);
+    }
+    if !silent {
+        info!(
+            "Wiping device: {} (method: {}, block_size: {}, verify: {})",
+            device_path, method, block_size, verify
+        );
+    }
+
+    emit_progress(
+        silent,
+        &mut progress,
+        OperationProgress::new(OperationPhase::Preparing)
+            .with_message(format!("Opening {} for a {} wipe", device_path, method)),
+    );
+    check_cancel(control)?;
+
+    let partition_starts = if method == WipeMethod::Quick {
+        partition_starts(&device_path)
+    } else {
+        Vec::new()
+    };
+
+    let mut writer = PlatformDevice::new_writer(&device_path)?;
+    let device_size = writer
+        .device_size()
+        .ok()
+        .filter(|s| *s > 0)
+        .or_else(|| devices::device_size_bytes(&device_path))
+        .with_context(|| format!("Cannot determine the size of {}", device_path))?;
+
+    if method == WipeMethod::Discard {
+        discard_range(
+            writer.as_mut(),
+            &device_path,
+            DiscardMethod::Discard,
+            0,
+            device_size,
+            DiscardMode::Erase,
+            silent,
+            &mut progress,
+            control,
+        )?;
+        return finish(&device_path, method, device_size, silent, &mut progress);
+    }
+
+    let regions = match method {
+        WipeMethod::Quick => quick_regions(device_size, &partition_starts),
+        _ => vec![(0, device_size)],
+    };
+    let seed = (method == WipeMethod::Random).then(random_seed);
+    let total: u64 = regions.iter().map(|(_, len)| len).sum();
+    let write_share = if verify { 90.0 } else { 100.0 };
+
+    let mut buffer = vec![0u8; block_size];
+    let mut written = 0u64;
+    for &(offset, len) in &regions {
+        writer
+            .seek(SeekFrom::Start(offset))
+            .with_context(|| format!("Failed to seek device to offset {}", offset))?;
+        let mut done = 0u64;
+        while done < len {
+            check_cancel(control)?;
+            let n = (len - done).min(buffer.len() as u64) as usize;
+            fill_pattern(&mut buffer[..n], offset + done, seed);
+            writer
+                .write_all(&buffer[..n])
+                .with_context(|| format!("Failed to write at offset {}", offset + done))?;
+            done += n as u64;
+            written += n as u64;
+            emit_progress(
+                silent,
+                &mut progress,
+                OperationProgress::new(OperationPhase::Writing)
+                    .with_bytes(written, Some(total))
+                    .with_percentage(written as f64 / total as f64 * write_share),
+            );
+        }
+    }
+    writer
+        .flush_and_sync()
+        .context("Failed to flush and sync device")?;
+    drop(writer);
+    debug!("Wiped {} bytes in {} regions", written, regions.len());
+
+    if verify {
+        verify_regions(
+            &device_path,
+            &regions,
+            seed,
+            block_size,
+            silent,
+            &mut progress,
+            control,
+        )?;
+    }
+
+    finish(&device_path, method, total, silent, &mut progress)
+}
+
+fn finish<F>(
+    device_path: &str,
+    method: WipeMethod,
+    bytes: u64,
+    silent: bool,
+    progress: &mut Option<F>,
+) -> Result<()>
+where
+    F: FnMut(OperationProgress),
+{
+    emit_progress(
+        silent,
+        progress,
+        OperationProgress::new(OperationPhase::Complete)
+            .with_bytes(bytes, Some(bytes))
+            .with_percentage(100.0)
+            .with_message(format!("Wipe completed ({} method)", method)),
+    );
+    if !silent {
+        info!("{} wipe of {} completed", method, device_path);
+    }
+    Ok(())
+}
+
+/// Byte offsets of partitions in the current table, so their signatures are wiped too.
+fn partition_starts(device_path: &str) -> Vec<u64> {
+    let table = PlatformDevice::new_verify_reader(device_path)
+        .and_then(|mut reader| partitions::read_partition_table(&mut reader));
+    match table {
+        Ok(Some(table)) => table
+            .partitions
+            .iter()
+            .map(|p| p.first_lba * table.sector_size)
+            .collect(),
+        Ok(None) => Vec::new(),
+        Err(error) => {
+            warn!("Ignoring unreadable partition table on {}: {:#}", device_path, error);
+            Vec::new()
+        }
+    }
+}
+
+/// Sorted, merged `(offset, len)` regions for a quick wipe.
+fn quick_regions(device_size: u64, partition_starts: &[u64]) -> Vec<(u64, u64)> {
+    let mut regions: Vec<(u64, u64)> = std::iter::once(0)
+        .chain(partition_starts.iter().copied())
+        .chain(std::iter::once(
+            device_size.saturating_sub(QUICK_WIPE_BYTES),
+        ))
+        .filter(|start| *start < device_size)
+        .map(|start| (start, (start + QUICK_WIPE_BYTES).min(device_size)))
+        .collect();
+    regions.sort_unstable();
+
+    let mut merged: Vec<(u64, u64)> = Vec::new();
+    for (start, end) in regions {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+        .into_iter()
+        .map(|(start, end)| (start, end - start))
+        .collect()
+}
+
+/// How [`discard_range`] treats a device that cannot discard the range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DiscardMode {
+    /// Discard ahead of a write: failures are logged and skipped (the write
+    /// overwrites the data anyway) and progress is reported as Preparing.
+    BeforeWrite,
+    /// The discard is the erase itself: failures are errors and progress is
+    /// reported as Writing.
+    Erase,
+}
+
+/// Discard `len` bytes at `offset` in sector-aligned chunks, reporting support and
+/// progress. Cancellation always aborts; other failures follow `mode`.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn discard_range<F>(
+    writer: &mut dyn DeviceWriter,
+    device_path: &str,
+    method: DiscardMethod,
+    offset: u64,
+    len: u64,
+    mode: DiscardMode,
+    silent: bool,
+    progress: &mut Option<F>,
+    control: Option<&OperationControl>,
+) -> Result<()>
+where
+    F: FnMut(OperationProgress),
+{
+    let support = devices::discard_support(device_path);
+    let discard_ok = support.is_some_and(|s| s.supports_discard());
+    let mut method = match method {
+        DiscardMethod::ZeroOut => DiscardMethod::ZeroOut,
+        _ if discard_ok => method,
+        _ if mode == DiscardMode::Erase => bail!(
+            "{} does not support discard; use the zero or quick method",
+            device_path
+        ),
+        _ => {
+            warn!("{} does not support discard; skipping", device_path);
+            emit_progress(
+                silent,
+                progress,
+                OperationProgress::new(OperationPhase::Preparing)
+                    .with_message(format!("Discard not supported by {}; skipping", device_path)),
+            );
+            return Ok(());
+        }
+    };
+    let detail = match support {
+        Some(s) if method == DiscardMethod::ZeroOut && s.write_zeroes_max_bytes == 0 => {
+            "zeroout emulated by the kernel".to_string()
+        }
+        Some(_) if method == DiscardMethod::ZeroOut => "zeroout offloaded to device".to_string(),
+        Some(s) => format!("discard supported, max {} bytes per request", s.max_bytes),
+        None => "discard support unknown".to_string(),
+    };
+    emit_progress(
+        silent,
+        progress,
+        OperationProgress::new(OperationPhase::Preparing)
+            .with_message(format!("{} {} ({})", method, device_path, detail)),
+    );
+
+    // BLKDISCARD / BLKZEROOUT reject ranges that are not sector aligned.
+    let start = offset.div_ceil(512) * 512;
+    let end = (offset + len) / 512 * 512;
+    let total = end.saturating_sub(start);
+    let mut done = 0u64;
+    while done < total {
+        check_cancel(control)?;
+        let chunk = (total - done).min(DISCARD_CHUNK);
+        match writer.discard(start + done, chunk, method) {
+            Ok(()) => done += chunk,
+            Err(error) if method == DiscardMethod::Secure && discard_ok => {
+                warn!("Secure discard failed ({:#}); falling back to discard", error);
+                method = DiscardMethod::Discard;
+            }
+            Err(error) if mode == DiscardMode::Erase => return Err(error),
+            Err(error) => {
+                warn!("Skipping discard of {}: {:#}", device_path, error);
+                emit_progress(
+                    silent,
+                    progress,
+                    OperationProgress::new(OperationPhase::Preparing)
+                        .with_message(format!("Discard failed, continuing: {:#}", error)),
+                );
+                return Ok(());
+            }
+        }
+        let event = match mode {
+            DiscardMode::BeforeWrite => OperationProgress::new(OperationPhase::Preparing),
+            DiscardMode::Erase => OperationProgress::new(OperationPhase::Writing)
+                .with_percentage(done as f64 / total as f64 * 100.0),
+        };
+        emit_progress(
+            silent,
+            progress,
+            event
+                .with_bytes(done, Some(total))
+                .with_message(format!("{}: {} of {} bytes", method, done, total)),
+        );
+    }
+    Ok(())
+}
+
+fn verify_regions<F>(
+    device_path: &str,
+    regions: &[(u64, u64)],
+    seed: Option<u64>,
+    block_size: usize,
+    silent: bool,
+    progress: &mut Option<F>,
+    control: Option<&OperationControl>,
+) -> Result<()>
+where
+    F: FnMut(OperationProgress),
+{
+    emit_progress(
+        silent,
+        progress,
+        OperationProgress::new(OperationPhase::Verifying)
+            .with_percentage(90.0)
+            .with_message("Reading back the wiped area"),
+    );
+
+    let total: u64 = regions.iter().map(|(_, len)| len).sum();
+    let mut reader = PlatformDevice::new_verify_reader(device_path)?;
+    let mut actual = vec![0u8; block_size];
+    let mut expected = vec![0u8; block_size];
+    let mut verified = 0u64;
+    for &(offset, len) in regions {
+        reader
+            .seek(SeekFrom::Start(offset))
+            .with_context(|| format!("Failed to seek device to offset {}", offset))?;
+        let mut done = 0u64;
+        while done < len {
+            check_cancel(control)?;
+            let n = (len - done).min(actual.len() as u64) as usize;
+            reader
+                .read_exact(&mut actual[..n])
+                .with_context(|| format!("Failed to read back offset {}", offset + done))?;
+            fill_pattern(&mut expected[..n], offset + done, seed);
+            if let Some(index) = (0..n).find(|i| actual[*i] != expected[*i]) {
+                bail!(
+                    "Wipe verification failed at offset {}",
+                    offset + done + index as u64
+                );
+            }
+            done += n as u64;
+            verified += n as u64;
+            emit_progress(
+                silent,
+                progress,
+                OperationProgress::new(OperationPhase::Verifying)
+                    .with_bytes(verified, Some(total))
+                    .with_percentage((90.0 + verified as f64 / total as f64 * 10.0).min(99.9)),
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Fill `buf` with the wipe pattern for device offset `offset`: zeros, or a
+/// position-derived pseudo-random stream so verify can regenerate any chunk.
+fn fill_pattern(buf: &mut [u8], offset: u64, seed: Option<u64>) {
+    let Some(seed) = seed else {
+        buf.fill(0);
+        return;
+    };
+    let mut pos = 0usize;
+    while pos < buf.len() {
+        let absolute = offset + pos as u64;
+        let word = splitmix64(seed.wrapping_add((absolute / 8).wrapping_mul(0x9E37_79B9_7F4A_7C15)))
+            .to_le_bytes();
+        let start = (absolute % 8) as usize;
+        let n = (8 - start).min(buf.len() - pos);
+        buf[pos..pos + n].copy_from_slice(&word[start..start + n]);
+        pos += n;
+    }
+}
+
+fn splitmix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
+fn random_seed() -> u64 {
+    let mut bytes = [0u8; 8];
+    if std::fs::File::open("/dev/urandom")
+        .and_then(|mut f| f.read_exact(&mut bytes))
+        .is_ok()
+    {
+        return u64::from_le_bytes(bytes);
+    }
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0x5EED)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quick_regions_cover_both_ends_and_partition_starts() {
+        let mib = QUICK_WIPE_BYTES;
+        let regions = quick_regions(8 * mib, &[mib, 5 * mib]);
+        assert_eq!(regions, vec![(0, 2 * mib), (5 * mib, mib), (7 * mib, mib)]);
+        assert_eq!(quick_regions(mib / 2, &[]), vec![(0, mib / 2)]);
+    }
+
+    #[test]
+    fn random_pattern_is_reproducible_for_any_chunking() {
+        let mut whole = vec![0u8; 100];
+        fill_pattern(&mut whole, 3, Some(42));
+        let mut first = vec![0u8; 37];
+        let mut second = vec![0u8; 63];
+        fill_pattern(&mut first, 3, Some(42));
+        fill_pattern(&mut second, 40, Some(42));
+        assert_eq!(&whole[..37], &first[..]);
+        assert_eq!(&whole[37..], &second[..]);
+        assert!(whole.iter().any(|b| *b != 0));
+    }
+
+    #[test]
+    fn method_parses_and_rejects_unknown() {
+        assert_eq!("Random".parse::<WipeMethod>().unwrap(), WipeMethod::Random);
+        assert!("shred".parse::<WipeMethod>().is_err());
+        assert!(!WipeMethod::Discard.supports_verify());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn quick_wipe_zeroes_table_and_partition_starts_only() {
+        let mib = QUICK_WIPE_BYTES as usize;
+        let mut disk = partitions::test_images::mbr_disk(8192, &[(0x83, 2048, 4096)]);
+        disk[2 * mib..3 * mib].fill(0xAB);
+        disk[mib + 4096..mib + 8192].fill(0xCD);
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(&disk).unwrap();
+        let path = file.path().to_str().unwrap().to_string();
+
+        wipe::<fn(OperationProgress)>(path, WipeMethod::Quick, 4096, true, true, None, None)
+            .unwrap();
+
+        let wiped = std::fs::read(file.path()).unwrap();
+        assert!(wiped[..2 * mib].iter().all(|b| *b == 0));
+        assert!(wiped[2 * mib..3 * mib].iter().all(|b| *b == 0xAB));
+        assert!(wiped[3 * mib..].iter().all(|b| *b == 0));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn random_wipe_overwrites_everything_and_verifies() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(&[0xABu8; 64 * 1024]).unwrap();
+        let path = file.path().to_str().unwrap().to_string();
+
+        wipe::<fn(OperationProgress)>(path, WipeMethod::Random, 4096, true, true, None, None)
+            .unwrap();
+
+        let wiped = std::fs::read(file.path()).unwrap();
+        assert_eq!(wiped.len(), 64 * 1024);
+        assert!(wiped.chunks(512).all(|c| c.iter().any(|b| *b != 0xAB)));
+    }
+}